@@ -0,0 +1,226 @@
+use clap::ValueEnum;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The same event is reported at most this often, so a pattern matching a
+/// flood of lines doesn't bury the desktop in notifications
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Gap between the bells of a beep sequence
+const BELL_GAP: Duration = Duration::from_millis(250);
+
+/// Something worth hearing about while working in another window
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum AlertEvent {
+    /// Reading from the port failed (device unplugged or reset)
+    Disconnect,
+    /// The port was opened again after a disconnect
+    Reconnect,
+    /// An RX line matched an `--alert` pattern
+    Pattern,
+    /// A `--block` was captured
+    Block,
+    /// A `{sleep}`/`{wait}` sequence or the startup commands finished
+    SequenceDone,
+}
+
+impl AlertEvent {
+    pub fn title(self) -> &'static str {
+        match self {
+            AlertEvent::Disconnect => "Device disconnected",
+            AlertEvent::Reconnect => "Device reconnected",
+            AlertEvent::Pattern => "Alert pattern matched",
+            AlertEvent::Block => "Block captured",
+            AlertEvent::SequenceDone => "Sequence finished",
+        }
+    }
+}
+
+/// What to play for an event
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sound {
+    /// `bell` or `bell:3`: the terminal bell, N times
+    Bell(u32),
+    /// Anything else: a sound file played with the system player
+    File(PathBuf),
+}
+
+/// Parse `EVENT=SOUND` for `--sound`, e.g. `disconnect=bell:3` or
+/// `pattern=/usr/share/sounds/alarm.wav`
+pub fn parse_sound_rule(s: &str) -> Result<(AlertEvent, Sound), String> {
+    let (event, sound) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected EVENT=SOUND, got '{s}'"))?;
+    let event = AlertEvent::from_str(event, true).map_err(|_| {
+        format!("unknown event '{event}' (expected disconnect, reconnect, pattern, block or sequence-done)")
+    })?;
+    let sound = match sound.strip_prefix("bell") {
+        Some("") => Sound::Bell(1),
+        Some(count) if count.starts_with(':') => Sound::Bell(
+            count[1..]
+                .parse()
+                .map_err(|_| format!("bad bell count in '{sound}'"))?,
+        ),
+        _ if sound.is_empty() => return Err(format!("no sound given for '{s}'")),
+        _ => Sound::File(PathBuf::from(sound)),
+    };
+    Ok((event, sound))
+}
+
+/// Where significant events are reported besides the status line
+#[derive(Default)]
+pub struct Alerts {
+    /// Desktop notifications (`--notify`)
+    pub desktop: bool,
+    /// RX lines raising [`AlertEvent::Pattern`]
+    pub patterns: Vec<Regex>,
+    /// Sound per event (`--sound`)
+    pub sounds: HashMap<AlertEvent, Sound>,
+    last_fired: HashMap<AlertEvent, Instant>,
+}
+
+impl Alerts {
+    pub fn new(
+        desktop: bool,
+        patterns: Vec<Regex>,
+        sounds: impl IntoIterator<Item = (AlertEvent, Sound)>,
+    ) -> Self {
+        Self {
+            desktop,
+            patterns,
+            sounds: sounds.into_iter().collect(),
+            last_fired: HashMap::new(),
+        }
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        self.patterns.iter().any(|p| p.is_match(line))
+    }
+
+    /// Report `event` (rate limited per event)
+    pub fn fire(&mut self, event: AlertEvent, detail: &str) {
+        if !self.desktop && !self.sounds.contains_key(&event) {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(last) = self.last_fired.get(&event)
+            && now.duration_since(*last) < NOTIFY_INTERVAL
+        {
+            return;
+        }
+        self.last_fired.insert(event, now);
+        if self.desktop {
+            desktop_notification(event.title(), detail);
+        }
+        if let Some(sound) = self.sounds.get(&event) {
+            play_sound(sound.clone());
+        }
+    }
+}
+
+/// Ring the bell or play the file in the background
+fn play_sound(sound: Sound) {
+    let path = match sound {
+        Sound::Bell(count) => {
+            std::thread::spawn(move || {
+                for i in 0..count {
+                    if i > 0 {
+                        std::thread::sleep(BELL_GAP);
+                    }
+                    let mut out = std::io::stdout().lock();
+                    let _ = out.write_all(b"\x07");
+                    let _ = out.flush();
+                }
+            });
+            return;
+        }
+        Sound::File(path) => path,
+    };
+
+    #[cfg(target_os = "macos")]
+    let command = {
+        let mut c = Command::new("afplay");
+        c.arg(path);
+        c
+    };
+    #[cfg(windows)]
+    let command = {
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-Command"]).arg(format!(
+            "(New-Object Media.SoundPlayer '{}').PlaySync()",
+            path.display().to_string().replace('\'', "''")
+        ));
+        c
+    };
+    // PulseAudio/PipeWire first, plain ALSA as a fallback
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let command = {
+        let mut c = Command::new("sh");
+        c.args(["-c", r#"paplay "$1" 2>/dev/null || aplay -q "$1""#, "sh"])
+            .arg(path);
+        c
+    };
+
+    spawn_detached(command);
+}
+
+/// Show a desktop notification through the platform's own tool, in the
+/// background; failures (no notification daemon, ...) are ignored
+fn desktop_notification(title: &str, body: &str) {
+    let title = format!("sermonizer: {title}");
+    let body: String = body.chars().take(200).collect();
+
+    #[cfg(target_os = "macos")]
+    let command = {
+        let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut c = Command::new("osascript");
+        c.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"{}\"",
+            quote(&body),
+            quote(&title)
+        ));
+        c
+    };
+    #[cfg(windows)]
+    let command = {
+        let quote = |s: &str| s.replace('\'', "''");
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-Command"]).arg(format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; \
+             $n.Visible = $true; \
+             $n.ShowBalloonTip(5000, '{}', '{}', 'Info'); \
+             Start-Sleep -Seconds 6; $n.Dispose()",
+            quote(&title),
+            quote(&body)
+        ));
+        c
+    };
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let command = {
+        let mut c = Command::new("notify-send");
+        c.args(["-a", "sermonizer"]).arg(title).arg(body);
+        c
+    };
+
+    spawn_detached(command);
+}
+
+/// Run a helper with no stdio, ignoring failures
+fn spawn_detached(mut command: Command) {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // Reap the child in the background so it doesn't linger as a zombie
+    std::thread::spawn(move || {
+        if let Ok(mut child) = command.spawn() {
+            let _ = child.wait();
+        }
+    });
+}
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A login prompt showing up again this soon after sending the password
+/// means the credentials were rejected
+const RETRY_WINDOW: Duration = Duration::from_secs(10);
+
+/// `[profiles.<name>.auto_login]` in the config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoLoginSettings {
+    pub user: String,
+    /// Plain-text password (prefer `password_env`)
+    pub password: Option<String>,
+    /// Environment variable holding the password
+    pub password_env: Option<String>,
+    /// Regex for the login prompt (default `login:\s*$`)
+    pub login_prompt: Option<String>,
+    /// Regex for the password prompt (default `[Pp]assword:\s*$`)
+    pub password_prompt: Option<String>,
+    /// Ask (Ctrl+L) before logging in instead of doing it right away
+    pub confirm: bool,
+}
+
+/// What the UI should do about the text just received
+#[derive(Debug, PartialEq)]
+pub enum LoginAction {
+    SendUser(String),
+    SendPassword(String),
+    /// Login prompt seen, waiting for Ctrl+L
+    AskConfirm,
+    /// Prompt came back right after the password
+    Failed,
+}
+
+enum State {
+    Idle,
+    WaitingConfirm,
+    UserSent,
+    PasswordSent(Instant),
+    Failed,
+}
+
+/// Answers login/password prompts on embedded Linux consoles
+pub struct AutoLogin {
+    user: String,
+    password: Option<String>,
+    login_prompt: Regex,
+    password_prompt: Regex,
+    confirm: bool,
+    state: State,
+    /// The prompt on the current (partial) line was already handled
+    handled_line: bool,
+}
+
+impl AutoLogin {
+    pub fn new(settings: &AutoLoginSettings) -> Result<Self> {
+        let password = match (&settings.password, &settings.password_env) {
+            (Some(p), _) => Some(p.clone()),
+            (None, Some(var)) => Some(
+                std::env::var(var)
+                    .with_context(|| format!("auto_login.password_env: ${var} is not set"))?,
+            ),
+            (None, None) => None,
+        };
+        let regex = |pattern: &Option<String>, default: &str, what: &str| {
+            Regex::new(pattern.as_deref().unwrap_or(default))
+                .with_context(|| format!("Invalid auto_login.{what} regex"))
+        };
+        Ok(Self {
+            user: settings.user.clone(),
+            password,
+            login_prompt: regex(&settings.login_prompt, r"login:\s*$", "login_prompt")?,
+            password_prompt: regex(
+                &settings.password_prompt,
+                r"[Pp]assword:\s*$",
+                "password_prompt",
+            )?,
+            confirm: settings.confirm,
+            state: State::Idle,
+            handled_line: false,
+        })
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// A new line started; prompts on it may be answered again
+    pub fn on_new_line(&mut self) {
+        self.handled_line = false;
+    }
+
+    /// Look at the line currently being received (prompts have no line
+    /// ending, so this is usually the partial line)
+    pub fn on_text(&mut self, line: &str) -> Option<LoginAction> {
+        if self.handled_line {
+            return None;
+        }
+        if self.login_prompt.is_match(line) {
+            self.handled_line = true;
+            return match self.state {
+                State::Failed => None,
+                State::PasswordSent(at) if at.elapsed() < RETRY_WINDOW => {
+                    self.state = State::Failed;
+                    Some(LoginAction::Failed)
+                }
+                _ if self.confirm => {
+                    self.state = State::WaitingConfirm;
+                    Some(LoginAction::AskConfirm)
+                }
+                _ => Some(self.start()),
+            };
+        }
+        if matches!(self.state, State::UserSent) && self.password_prompt.is_match(line) {
+            self.handled_line = true;
+            if let Some(password) = self.password.clone() {
+                self.state = State::PasswordSent(Instant::now());
+                return Some(LoginAction::SendPassword(password));
+            }
+            self.state = State::Idle;
+        }
+        None
+    }
+
+    fn start(&mut self) -> LoginAction {
+        self.state = State::UserSent;
+        LoginAction::SendUser(self.user.clone())
+    }
+
+    /// Ctrl+L: log in now (after a confirm prompt, or to retry after a
+    /// failure)
+    pub fn confirm(&mut self) -> LoginAction {
+        self.start()
+    }
+}
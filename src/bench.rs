@@ -0,0 +1,55 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// `--bench` file: the ports of a test rig and how to bring each one up
+///
+/// ```toml
+/// [[port]]
+/// role = "dut"
+/// port = "/dev/ttyUSB0"
+/// profile = "esp32"
+/// log = "logs/dut.log"
+/// startup = ["version", "{sleep 500ms}status"]
+///
+/// [[port]]
+/// role = "gps"
+/// port = "/dev/ttyACM0"
+/// baud = 9600
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bench {
+    #[serde(rename = "port")]
+    pub ports: Vec<BenchPort>,
+}
+
+/// One port of the rig. Unset fields come from the port's profile, then
+/// the command line, as for a single port.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchPort {
+    /// What the port is to the rig ("dut", "gps"), shown in its tab
+    pub role: Option<String>,
+    pub port: String,
+    /// Profile from the config file for this port, in place of the
+    /// default one (or `--profile`)
+    pub profile: Option<String>,
+    pub baud: Option<u32>,
+    pub log: Option<PathBuf>,
+    pub tx_log: Option<PathBuf>,
+    /// Commands sent once connected, as with `--startup`
+    #[serde(default)]
+    pub startup: Vec<String>,
+}
+
+pub fn load_bench(path: &Path) -> Result<Bench> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read bench file: {}", path.display()))?;
+    let bench: Bench = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse bench file: {}", path.display()))?;
+    if bench.ports.is_empty() {
+        bail!("Bench file {} lists no [[port]]", path.display());
+    }
+    Ok(bench)
+}
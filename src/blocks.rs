@@ -0,0 +1,138 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A block still open after this many lines is closed anyway, so a missing
+/// end line can't swallow the rest of the session
+const MAX_BLOCK_LINES: usize = 500;
+
+/// Captured blocks kept for the session bundle
+const KEEP_BLOCKS: usize = 50;
+
+/// Groups the lines from one matching `start` through one matching `end`
+/// (a stack trace, a register dump) into a block named `name`
+#[derive(Debug, Clone)]
+pub struct BlockRule {
+    pub name: String,
+    pub start: Regex,
+    pub end: Regex,
+}
+
+/// A block as written in a profile: `blocks = { trace = { start = "..", end = ".." } }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockPatterns {
+    pub start: String,
+    pub end: String,
+}
+
+impl BlockRule {
+    pub fn new(name: &str, start: &str, end: &str) -> Result<Self> {
+        if name.is_empty() {
+            bail!("block name must not be empty");
+        }
+        Ok(BlockRule {
+            name: name.to_string(),
+            start: Regex::new(start)
+                .with_context(|| format!("Invalid start pattern for block '{name}'"))?,
+            end: Regex::new(end)
+                .with_context(|| format!("Invalid end pattern for block '{name}'"))?,
+        })
+    }
+}
+
+/// `--block NAME START END` values, three per rule
+pub fn block_rules(values: &[String]) -> Result<Vec<BlockRule>> {
+    values
+        .chunks(3)
+        .map(|rule| match rule {
+            [name, start, end] => BlockRule::new(name, start, end),
+            _ => bail!("--block needs NAME START END"),
+        })
+        .collect()
+}
+
+/// A finished block
+pub struct CapturedBlock {
+    pub name: String,
+    pub time: DateTime<Local>,
+    pub lines: Vec<String>,
+}
+
+struct OpenBlock {
+    rule: usize,
+    time: DateTime<Local>,
+    lines: Vec<String>,
+}
+
+/// Follows RX lines through the block rules
+#[derive(Default)]
+pub struct BlockTracker {
+    rules: Vec<BlockRule>,
+    open: Option<OpenBlock>,
+    captured: VecDeque<CapturedBlock>,
+}
+
+impl BlockTracker {
+    pub fn new(rules: Vec<BlockRule>) -> Self {
+        BlockTracker {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// Block names in rule order (the F4 filter cycles through them too)
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if !names.contains(&rule.name) {
+                names.push(rule.name.clone());
+            }
+        }
+        names
+    }
+
+    /// A complete RX line: returns the name of the block it belongs to, and
+    /// the block if this line finished it
+    pub fn on_line(&mut self, line: &str) -> (Option<String>, Option<&CapturedBlock>) {
+        if self.open.is_none() {
+            let Some(rule) = self.rules.iter().position(|r| r.start.is_match(line)) else {
+                return (None, None);
+            };
+            self.open = Some(OpenBlock {
+                rule,
+                time: Local::now(),
+                lines: vec![line.to_string()],
+            });
+            return (Some(self.rules[rule].name.clone()), None);
+        }
+
+        let Some(open) = self.open.as_mut() else {
+            return (None, None);
+        };
+        let rule = &self.rules[open.rule];
+        open.lines.push(line.to_string());
+        let name = rule.name.clone();
+        if !rule.end.is_match(line) && open.lines.len() < MAX_BLOCK_LINES {
+            return (Some(name), None);
+        }
+
+        let Some(open) = self.open.take() else {
+            return (Some(name), None);
+        };
+        if self.captured.len() == KEEP_BLOCKS {
+            self.captured.pop_front();
+        }
+        self.captured.push_back(CapturedBlock {
+            name: name.clone(),
+            time: open.time,
+            lines: open.lines,
+        });
+        (Some(name), self.captured.back())
+    }
+
+    pub fn captured(&self) -> impl Iterator<Item = &CapturedBlock> {
+        self.captured.iter()
+    }
+}
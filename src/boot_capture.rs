@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Writes the RX lines from connecting (and the reset that comes with it)
+/// up to the `--boot-done` line into their own file, with the time since
+/// the port was opened, so boot logs look the same whoever captured them
+pub struct BootCapture {
+    path: PathBuf,
+    file: BufWriter<File>,
+    done: Regex,
+    started: Instant,
+    lines: usize,
+}
+
+impl BootCapture {
+    pub fn create(path: &Path, done: Regex, port_name: &str) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create boot capture {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        writeln!(
+            file,
+            "# sermonizer boot capture: {port_name}, {} (until /{done}/)",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        )?;
+        Ok(BootCapture {
+            path: path.to_path_buf(),
+            file,
+            done,
+            started: Instant::now(),
+            lines: 0,
+        })
+    }
+
+    /// Record a complete RX line; returns the closing note once the boot
+    /// is done (the capture is finished then)
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn on_line(&mut self, line: &str) -> Option<String> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let _ = writeln!(self.file, "[{elapsed:10.3}] {line}");
+        self.lines += 1;
+        if !self.done.is_match(line) {
+            return None;
+        }
+        let _ = self.file.flush();
+        Some(format!(
+            "-- [boot] captured {} line(s) in {elapsed:.3}s to {} --",
+            self.lines,
+            self.path.display()
+        ))
+    }
+}
+
+impl Drop for BootCapture {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
@@ -0,0 +1,138 @@
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+/// A named point in the boot log, recognised by a regex
+#[derive(Debug, Clone)]
+pub struct Milestone {
+    pub name: String,
+    pub pattern: Regex,
+}
+
+/// Parse a `NAME=REGEX` milestone (clap value parser)
+pub fn parse_milestone(s: &str) -> Result<Milestone, String> {
+    let (name, pattern) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=REGEX, got '{s}'"))?;
+    if name.is_empty() {
+        return Err("milestone name must not be empty".to_string());
+    }
+    let pattern =
+        Regex::new(pattern).map_err(|e| format!("invalid regex for milestone '{name}': {e}"))?;
+    Ok(Milestone {
+        name: name.to_string(),
+        pattern,
+    })
+}
+
+fn format_elapsed(d: Duration) -> String {
+    format!("+{:.3} s", d.as_secs_f64())
+}
+
+/// Measures time from arming (manually or on reset) to the first RX line
+/// and to each milestone
+pub struct BootProfiler {
+    milestones: Vec<Milestone>,
+    armed_at: Option<Instant>,
+    first_rx: Option<Duration>,
+    hits: Vec<Option<Duration>>,
+    reported: bool,
+}
+
+impl BootProfiler {
+    pub fn new(milestones: Vec<Milestone>) -> Self {
+        let hits = vec![None; milestones.len()];
+        Self {
+            milestones,
+            armed_at: None,
+            first_rx: None,
+            hits,
+            reported: false,
+        }
+    }
+
+    pub fn has_milestones(&self) -> bool {
+        !self.milestones.is_empty()
+    }
+
+    /// Start (or restart) a measurement
+    pub fn arm(&mut self) {
+        self.armed_at = Some(Instant::now());
+        self.first_rx = None;
+        self.hits = vec![None; self.milestones.len()];
+        self.reported = false;
+    }
+
+    fn is_done(&self) -> bool {
+        self.hits.iter().all(Option::is_some)
+    }
+
+    /// Feed a received line; returns annotation lines for the output pane,
+    /// followed by the report once the last milestone was reached
+    pub fn on_line(&mut self, line: &str) -> Vec<String> {
+        let Some(armed_at) = self.armed_at else {
+            return Vec::new();
+        };
+        if self.reported {
+            return Vec::new();
+        }
+        let elapsed = armed_at.elapsed();
+        let mut notes = Vec::new();
+
+        if self.first_rx.is_none() {
+            self.first_rx = Some(elapsed);
+            notes.push(format!(
+                "-- [boot] first RX at {} --",
+                format_elapsed(elapsed)
+            ));
+        }
+        for (milestone, hit) in self.milestones.iter().zip(self.hits.iter_mut()) {
+            if hit.is_none() && milestone.pattern.is_match(line) {
+                *hit = Some(elapsed);
+                notes.push(format!(
+                    "-- [boot] {} at {} --",
+                    milestone.name,
+                    format_elapsed(elapsed)
+                ));
+            }
+        }
+
+        if self.is_done() {
+            self.reported = true;
+            notes.extend(self.report());
+        }
+        notes
+    }
+
+    /// Timing table: each milestone with its time since arming and since
+    /// the previous milestone
+    pub fn report(&self) -> Vec<String> {
+        if self.armed_at.is_none() {
+            return Vec::new();
+        }
+        let mut lines = vec!["Boot timing report:".to_string()];
+        if let Some(first) = self.first_rx {
+            lines.push(format!(
+                "  {:<20} {:>12}",
+                "first RX",
+                format_elapsed(first)
+            ));
+        }
+        let mut previous = Duration::ZERO;
+        for (milestone, hit) in self.milestones.iter().zip(&self.hits) {
+            match hit {
+                Some(t) => {
+                    let delta = t.saturating_sub(previous);
+                    lines.push(format!(
+                        "  {:<20} {:>12}  (+{:.3} s)",
+                        milestone.name,
+                        format_elapsed(*t),
+                        delta.as_secs_f64()
+                    ));
+                    previous = *t;
+                }
+                None => lines.push(format!("  {:<20} {:>12}", milestone.name, "not reached")),
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, Result, bail};
+use serialport::SerialPort;
+use std::io::{ErrorKind, Write};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::config::PortSettings;
+use crate::logging::LogWriter;
+use crate::port_lock::OnBusy;
+use crate::reconnect::{OpenOptions, open_port};
+use crate::serial_io::hex_bytes;
+
+/// A line without its newline is shown once a direction has been quiet this
+/// long
+const GAP: Duration = Duration::from_millis(50);
+
+/// Bytes per line of the hex display
+const HEX_WIDTH: usize = 16;
+
+/// The two sides of `sermonizer bridge`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    A,
+    B,
+}
+
+impl Side {
+    fn label(self) -> &'static str {
+        match self {
+            Side::A => "A>B",
+            Side::B => "B>A",
+        }
+    }
+}
+
+/// What `sermonizer bridge` connects and how it shows the traffic
+pub struct Bridge {
+    pub a: String,
+    pub b: String,
+    pub settings_a: PortSettings,
+    pub settings_b: PortSettings,
+    pub hex: bool,
+    pub log: Option<LogWriter>,
+}
+
+/// Copy everything `from` receives to `to`, and hand a copy to the display
+fn forward(
+    side: Side,
+    (mut from, from_name): (Box<dyn SerialPort>, String),
+    (mut to, to_name): (Box<dyn SerialPort>, String),
+    tx: mpsc::Sender<(Side, Result<Vec<u8>, String>)>,
+) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let result = match from.read(&mut buf) {
+            Ok(0) => continue,
+            Ok(n) => match to.write_all(&buf[..n]).and_then(|()| to.flush()) {
+                Ok(()) => Ok(buf[..n].to_vec()),
+                Err(e) => Err(format!("writing to {to_name} failed: {e}")),
+            },
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => {
+                continue;
+            }
+            Err(e) => Err(format!("reading from {from_name} failed: {e}")),
+        };
+        let failed = result.is_err();
+        if tx.send((side, result)).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Per direction: bytes not shown yet and when the last ones came
+struct Pending {
+    bytes: Vec<u8>,
+    last: Instant,
+}
+
+impl Pending {
+    fn new() -> Self {
+        Pending {
+            bytes: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+}
+
+impl Bridge {
+    fn show(&self, side: Side, text: &str) {
+        let line = format!(
+            "{} {} {text}",
+            chrono::Local::now().format("%H:%M:%S%.3f"),
+            side.label()
+        );
+        println!("{line}");
+        if let Some(log) = &self.log
+            && let Ok(mut w) = log.lock()
+        {
+            let _ = writeln!(w, "{line}");
+            let _ = w.flush();
+        }
+    }
+
+    /// Show the complete lines (or hex rows) of `pending`; with `all` the
+    /// rest too
+    fn flush(&self, side: Side, pending: &mut Pending, all: bool) {
+        if self.hex {
+            while pending.bytes.len() >= HEX_WIDTH || (all && !pending.bytes.is_empty()) {
+                let n = pending.bytes.len().min(HEX_WIDTH);
+                let row: Vec<u8> = pending.bytes.drain(..n).collect();
+                self.show(side, &hex_bytes(&row));
+            }
+            return;
+        }
+        while let Some(pos) = pending.bytes.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.bytes.drain(..=pos).collect();
+            self.show(side, &escape(&line));
+        }
+        if all && !pending.bytes.is_empty() {
+            let rest = std::mem::take(&mut pending.bytes);
+            self.show(side, &escape(&rest));
+        }
+    }
+}
+
+/// Line text with its line ending dropped and other control characters
+/// escaped
+fn escape(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line)
+        .chars()
+        .map(|c| {
+            if c.is_control() && c != '\t' {
+                format!("\\x{:02X}", c as u32)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// `sermonizer bridge`: forward bytes between two ports, showing what each
+/// side sends, until a port fails or the process is interrupted
+pub fn run_bridge(bridge: &Bridge) -> Result<()> {
+    let on_busy = OnBusy::at_start(false, None);
+    let (port_a, _) = open_port(
+        &bridge.a,
+        &bridge.settings_a,
+        OpenOptions::default(),
+        on_busy,
+    )?;
+    let (port_b, _) = open_port(
+        &bridge.b,
+        &bridge.settings_b,
+        OpenOptions::default(),
+        on_busy,
+    )?;
+    let clone = |port: &(dyn SerialPort + Send), name: &str| {
+        port.try_clone()
+            .with_context(|| format!("Failed to share serial port '{name}'"))
+    };
+    let (writer_a, writer_b) = (
+        clone(port_a.as_ref(), &bridge.a)?,
+        clone(port_b.as_ref(), &bridge.b)?,
+    );
+    println!(
+        "Bridging A = {} ({} {}) and B = {} ({} {}), Ctrl+C to stop",
+        bridge.a,
+        bridge.settings_a.baud,
+        bridge.settings_a.framing(),
+        bridge.b,
+        bridge.settings_b.baud,
+        bridge.settings_b.framing()
+    );
+
+    let (tx, rx) = mpsc::channel();
+    let tx_b = tx.clone();
+    let a: (Box<dyn SerialPort>, String) = (port_a, bridge.a.clone());
+    let b: (Box<dyn SerialPort>, String) = (port_b, bridge.b.clone());
+    let (to_a, to_b) = ((writer_a, a.1.clone()), (writer_b, b.1.clone()));
+    std::thread::spawn(move || forward(Side::A, a, to_b, tx));
+    std::thread::spawn(move || forward(Side::B, b, to_a, tx_b));
+
+    let mut pending = [(Side::A, Pending::new()), (Side::B, Pending::new())];
+    loop {
+        let received = rx.recv_timeout(GAP);
+        for (side, p) in &mut pending {
+            if let Ok((from, Ok(bytes))) = &received
+                && from == side
+            {
+                p.bytes.extend_from_slice(bytes);
+                p.last = Instant::now();
+                bridge.flush(*side, p, false);
+            } else if p.last.elapsed() >= GAP {
+                bridge.flush(*side, p, true);
+            }
+        }
+        match received {
+            Ok((_, Err(e))) => {
+                for (side, p) in &mut pending {
+                    bridge.flush(*side, p, true);
+                }
+                bail!("Bridge stopped: {e}");
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            _ => {}
+        }
+    }
+}
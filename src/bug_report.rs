@@ -0,0 +1,235 @@
+use regex::Regex;
+use serialport::SerialPortInfo;
+
+use crate::port_discovery::describe_port;
+use crate::profile::Profile;
+use crate::quirks::QuirkDb;
+
+/// Scrollback lines included in a report from the TUI
+pub const REPORT_LINES: usize = 50;
+
+/// What a hidden value is replaced with
+const REDACTED: &str = "<redacted>";
+
+/// Query parameters of an HTTP URL that carry credentials
+const SECRET_PARAMS: &[&str] = &["p", "password", "token", "api_key", "apikey"];
+
+/// OS, architecture and terminal, one `- key: value` per line
+pub fn environment() -> Vec<String> {
+    let mut lines = vec![
+        format!("- sermonizer: {}", env!("CARGO_PKG_VERSION")),
+        format!("- OS: {} {}", std::env::consts::OS, std::env::consts::ARCH),
+    ];
+    #[cfg(target_os = "linux")]
+    {
+        let release = std::fs::read_to_string("/etc/os-release")
+            .ok()
+            .and_then(|text| {
+                text.lines()
+                    .find_map(|l| l.strip_prefix("PRETTY_NAME="))
+                    .map(|v| v.trim_matches('"').to_string())
+            });
+        if let Some(release) = release {
+            lines.push(format!("- Distribution: {release}"));
+        }
+        if let Ok(kernel) = std::fs::read_to_string("/proc/sys/kernel/osrelease") {
+            lines.push(format!("- Kernel: {}", kernel.trim()));
+        }
+    }
+    for var in ["TERM", "TERM_PROGRAM", "TMUX"] {
+        if let Some(value) = std::env::var_os(var) {
+            let value = if var == "TMUX" {
+                "yes".into()
+            } else {
+                value.to_string_lossy().into_owned()
+            };
+            lines.push(format!("- {var}: {value}"));
+        }
+    }
+    lines
+}
+
+/// Kernel driver bound to a tty, e.g. `ftdi_sio` (Linux only)
+fn driver(port_name: &str) -> Option<String> {
+    let name = port_name.rsplit('/').next()?;
+    let link = std::fs::read_link(format!("/sys/class/tty/{name}/device/driver")).ok()?;
+    Some(link.file_name()?.to_string_lossy().into_owned())
+}
+
+/// USB details, quirk entry and driver of one port
+pub fn adapter_info(port_name: &str, ports: &[SerialPortInfo], quirks: &QuirkDb) -> Vec<String> {
+    let Some(info) = ports.iter().find(|p| p.port_name == port_name) else {
+        return vec![format!("- {port_name} (not enumerated)")];
+    };
+    let mut lines = vec![format!("- {}{}", info.port_name, describe_port(info))];
+    if let Some(quirk) = quirks.for_port(info) {
+        lines.push(format!("- Adapter: {}", quirk.name));
+    }
+    if let Some(driver) = driver(port_name) {
+        lines.push(format!("- Driver: {driver}"));
+    }
+    lines
+}
+
+/// `url` with the user info and credential query values replaced
+fn redact_url(url: &str) -> String {
+    let (base, query) = url.split_once('?').unwrap_or((url, ""));
+    let mut redacted = match base.split_once("://") {
+        Some((scheme, rest)) => {
+            let authority_end = rest.find('/').unwrap_or(rest.len());
+            match rest[..authority_end].rfind('@') {
+                Some(at) => format!("{scheme}://{REDACTED}{}", &rest[at..]),
+                None => base.to_string(),
+            }
+        }
+        None => base.to_string(),
+    };
+    if url.contains('?') {
+        let params: Vec<String> = query
+            .split('&')
+            .map(|param| match param.split_once('=') {
+                Some((key, _)) if SECRET_PARAMS.contains(&key.to_ascii_lowercase().as_str()) => {
+                    format!("{key}={REDACTED}")
+                }
+                _ => param.to_string(),
+            })
+            .collect();
+        redacted.push('?');
+        redacted.push_str(&params.join("&"));
+    }
+    redacted
+}
+
+/// The profile as it goes into a report: the auto-login password and the
+/// credentials in the `influx` URL are blanked before serializing, however
+/// they are spelled in the config file
+pub fn redact_profile(profile: &Profile) -> Profile {
+    let mut profile = profile.clone();
+    if let Some(password) = profile
+        .auto_login
+        .as_mut()
+        .and_then(|login| login.password.as_mut())
+    {
+        *password = REDACTED.to_string();
+    }
+    if let Some(influx) = profile.influx.as_mut() {
+        *influx = redact_url(influx);
+    }
+    profile
+}
+
+/// Hide things that shouldn't end up in a public issue: the given secrets,
+/// `password=...`-style values and the home directory
+pub fn redact(text: &str, secrets: &[&str]) -> String {
+    let mut text = text.to_string();
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        text = text.replace(secret, REDACTED);
+    }
+    let key_value =
+        Regex::new(r"(?i)\b(pass(word|wd)?|pwd|token|secret|api[_-]?key)(\s*[:=]\s*)\S+")
+            .expect("valid regex");
+    text = key_value
+        .replace_all(&text, format!("$1$3{REDACTED}"))
+        .into_owned();
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        let home = home.to_string_lossy();
+        if home.len() > 1 {
+            text = text.replace(home.as_ref(), "~");
+        }
+    }
+    text
+}
+
+/// Assemble the markdown report from titled sections; `output` (recent
+/// scrollback) goes into a code block
+pub fn render(sections: &[(&str, Vec<String>)], output: &[String]) -> String {
+    let mut md = String::from("### Environment\n\n");
+    md.push_str(&environment().join("\n"));
+    md.push('\n');
+    for (title, lines) in sections {
+        md.push_str(&format!("\n### {title}\n\n"));
+        md.push_str(&lines.join("\n"));
+        md.push('\n');
+    }
+    if !output.is_empty() {
+        md.push_str(&format!(
+            "\n### Last {} lines of output\n\n```text\n",
+            output.len()
+        ));
+        md.push_str(&output.join("\n"));
+        md.push_str("\n```\n");
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_login::AutoLoginSettings;
+
+    #[test]
+    fn profile_password_is_blanked_whatever_it_contains() {
+        let profile = Profile {
+            auto_login: Some(AutoLoginSettings {
+                user: "root".into(),
+                password: Some("correct horse \"battery\" staple".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let toml = toml::to_string_pretty(&redact_profile(&profile)).unwrap();
+        assert!(!toml.contains("horse"), "{toml}");
+        assert!(!toml.contains("staple"), "{toml}");
+        assert!(toml.contains("password = \"<redacted>\""), "{toml}");
+        assert!(toml.contains("user = \"root\""), "{toml}");
+        // The profile itself is left alone
+        let login = profile.auto_login.as_ref().unwrap();
+        assert_eq!(
+            login.password.as_deref(),
+            Some("correct horse \"battery\" staple")
+        );
+    }
+
+    #[test]
+    fn profile_without_password_stays_without() {
+        let profile = Profile {
+            auto_login: Some(AutoLoginSettings {
+                password_env: Some("BOARD_PASSWORD".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let redacted = redact_profile(&profile);
+        let login = redacted.auto_login.unwrap();
+        assert_eq!(login.password, None);
+        assert_eq!(login.password_env.as_deref(), Some("BOARD_PASSWORD"));
+    }
+
+    #[test]
+    fn influx_credentials_are_blanked() {
+        assert_eq!(
+            redact_url("http://admin:s3cret@db:8086/write?db=bench&u=admin&p=s3cret"),
+            "http://<redacted>@db:8086/write?db=bench&u=admin&p=<redacted>"
+        );
+        assert_eq!(
+            redact_url("http://db:8086/api/v2/write?org=o&bucket=b&token=abc"),
+            "http://db:8086/api/v2/write?org=o&bucket=b&token=<redacted>"
+        );
+        assert_eq!(
+            redact_url("http://db:8086/write?db=a@b"),
+            "http://db:8086/write?db=a@b"
+        );
+        assert_eq!(redact_url("metrics.lp"), "metrics.lp");
+    }
+
+    #[test]
+    fn text_redaction() {
+        let text = "login ok\npassword: hunter2\ntoken=abc123 sent pin 4711";
+        let redacted = redact(text, &["4711"]);
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("abc123"));
+        assert!(!redacted.contains("4711"));
+        assert!(redacted.contains("password: <redacted>"));
+        assert!(redacted.contains("login ok"));
+    }
+}
@@ -0,0 +1,184 @@
+/// What a `:hex`/`:crc16`... helper line works out: the line shown in the
+/// output and the value Ctrl+Y puts into the input line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub text: String,
+    pub value: String,
+}
+
+/// Helpers typed at the input line instead of being sent. Anything else
+/// starting with `:` (Intel HEX records, AT+ style commands) goes out as
+/// typed.
+const HELPERS: &[&str] = &[
+    "hex",
+    "dec",
+    "bin",
+    "crc16",
+    "crc16-ccitt",
+    "crc32",
+    "sum8",
+    "xor8",
+];
+
+/// `None` unless `line` is `:<helper> <args>`
+pub fn run(line: &str) -> Option<Result<Answer, String>> {
+    let rest = line.trim().strip_prefix(':')?;
+    let (name, args) = rest.split_once(' ').unwrap_or((rest, ""));
+    let name = name.to_ascii_lowercase();
+    if !HELPERS.contains(&name.as_str()) {
+        return None;
+    }
+    let args = args.trim();
+    Some(evaluate(&name, args).map(|(text, value)| Answer {
+        text: format!("= :{name} {args} -> {text}"),
+        value,
+    }))
+}
+
+fn evaluate(name: &str, args: &str) -> Result<(String, String), String> {
+    match name {
+        "hex" | "dec" | "bin" => {
+            let n = parse_number(args)?;
+            let value = match name {
+                "hex" => format!("0x{n:X}"),
+                "dec" => n.to_string(),
+                _ => format!("0b{n:b}"),
+            };
+            Ok((format!("{value} ({n} = 0x{n:X} = 0b{n:b})"), value))
+        }
+        _ => {
+            let bytes = parse_bytes(args)?;
+            let count = bytes.len();
+            let (text, value) = match name {
+                "crc16" => {
+                    let crc = crc16_modbus(&bytes);
+                    let [lo, hi] = crc.to_le_bytes();
+                    // Modbus sends the low byte first
+                    (
+                        format!("0x{crc:04X} (on the wire: {lo:02X} {hi:02X})"),
+                        format!("{lo:02X} {hi:02X}"),
+                    )
+                }
+                "crc16-ccitt" => {
+                    let crc = crc16_ccitt(&bytes);
+                    (
+                        format!("0x{crc:04X}"),
+                        format!("{:02X} {:02X}", crc >> 8, crc & 0xFF),
+                    )
+                }
+                "crc32" => {
+                    let crc = crc32(&bytes);
+                    (format!("0x{crc:08X}"), format!("{crc:08X}"))
+                }
+                "sum8" => {
+                    let sum = bytes.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+                    (format!("0x{sum:02X}"), format!("{sum:02X}"))
+                }
+                _ => {
+                    let xor = bytes.iter().fold(0u8, |acc, &b| acc ^ b);
+                    (format!("0x{xor:02X}"), format!("{xor:02X}"))
+                }
+            };
+            Ok((format!("{text} over {count} byte(s)"), value))
+        }
+    }
+}
+
+/// `1234`, `0x4D2`, `0b10011010010`, `0o2322`; `_` may separate digits
+fn parse_number(text: &str) -> Result<u64, String> {
+    if text.is_empty() {
+        return Err("expected a number".to_string());
+    }
+    let digits = text.replace('_', "");
+    let lower = digits.to_ascii_lowercase();
+    let (radix, digits) = if let Some(d) = lower.strip_prefix("0x") {
+        (16, d)
+    } else if let Some(d) = lower.strip_prefix("0b") {
+        (2, d)
+    } else if let Some(d) = lower.strip_prefix("0o") {
+        (8, d)
+    } else {
+        (10, lower.as_str())
+    };
+    u64::from_str_radix(digits, radix).map_err(|e| format!("'{text}': {e}"))
+}
+
+/// `DE AD BE EF`, `DEADBEEF`, `0xDE,0xAD` or a quoted string (`"AT\r\n"`)
+fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    if let Some(quoted) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        return Ok(quoted
+            .replace("\\r", "\r")
+            .replace("\\n", "\n")
+            .replace("\\t", "\t")
+            .into_bytes());
+    }
+    let mut bytes = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if token.is_empty() {
+            continue;
+        }
+        if token.len() % 2 != 0 {
+            return Err(format!("'{token}' is not whole hex bytes"));
+        }
+        for i in (0..token.len()).step_by(2) {
+            let byte = token
+                .get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("'{token}' is not hex"))?;
+            bytes.push(byte);
+        }
+    }
+    if bytes.is_empty() {
+        return Err("expected hex bytes or a \"quoted string\"".to_string());
+    }
+    Ok(bytes)
+}
+
+/// CRC-16/MODBUS (poly 0xA001 reflected, init 0xFFFF)
+pub fn crc16_modbus(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |mut crc, &b| {
+        crc ^= u16::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF)
+fn crc16_ccitt(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |mut crc, &b| {
+        crc ^= u16::from(b) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-32 as zlib and Ethernet compute it
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |mut crc, &b| {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
@@ -0,0 +1,73 @@
+/// Defaults that changed between releases. `--compat <version>` brings back
+/// the behaviour of that release so scripts and muscle memory keep working:
+/// each default is on from the release it arrived in.
+#[derive(Copy, Clone, Debug)]
+pub struct Compat {
+    /// Run the setup wizard when started with no flags and no config
+    pub first_run_wizard: bool,
+    /// A trailing `\` on the input line suppresses the line ending; before
+    /// that it was sent like any other character
+    pub trailing_backslash_override: bool,
+}
+
+impl Default for Compat {
+    fn default() -> Self {
+        Self {
+            first_run_wizard: true,
+            trailing_backslash_override: true,
+        }
+    }
+}
+
+/// Release (MAJOR.MINOR) the setup wizard came with
+const FIRST_RUN_WIZARD_SINCE: (u32, u32) = (0, 2);
+
+/// Release (MAJOR.MINOR) the trailing `\` override came with
+const TRAILING_BACKSLASH_SINCE: (u32, u32) = (0, 2);
+
+/// Parse `MAJOR.MINOR[.PATCH]` (clap value parser)
+pub fn parse_compat(s: &str) -> Result<Compat, String> {
+    let mut parts = s.trim_start_matches('v').split('.');
+    let mut number = || -> Result<u32, String> {
+        parts
+            .next()
+            .unwrap_or("0")
+            .parse()
+            .map_err(|_| format!("expected a version like 1.2, got '{s}'"))
+    };
+    let release = (number()?, number()?);
+    Ok(Compat {
+        first_run_wizard: release >= FIRST_RUN_WIZARD_SINCE,
+        trailing_backslash_override: release >= TRAILING_BACKSLASH_SINCE,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compat_0_1_turns_off_the_new_defaults() {
+        let compat = parse_compat("0.1").unwrap();
+        assert!(!compat.first_run_wizard);
+        assert!(!compat.trailing_backslash_override);
+        let compat = parse_compat("v0.1.4").unwrap();
+        assert!(!compat.first_run_wizard);
+        assert!(!compat.trailing_backslash_override);
+    }
+
+    #[test]
+    fn releases_since_0_2_keep_the_new_defaults() {
+        for version in ["0.2", "0.2.0", "0.3", "1.0"] {
+            let compat = parse_compat(version).unwrap();
+            assert!(compat.first_run_wizard, "{version}");
+            assert!(compat.trailing_backslash_override, "{version}");
+        }
+    }
+
+    #[test]
+    fn bad_versions_are_errors() {
+        assert!(parse_compat("latest").is_err());
+        assert!(parse_compat("1.x").is_err());
+    }
+}
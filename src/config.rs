@@ -1,9 +1,43 @@
+use crate::alerts::Alerts;
+use crate::auto_login::AutoLogin;
+use crate::blocks::BlockRule;
+use crate::boot_capture::BootCapture;
+use crate::boot_profiler::Milestone;
+use crate::compat::Compat;
+#[cfg(feature = "decoders")]
+use crate::decoders::{DecoderRegistry, SharedPipeline};
+use crate::keymap::KeyMap;
+use crate::levels::{Level, LevelParser};
+use crate::origins::Origins;
+#[cfg(feature = "wasm")]
+use crate::plugins::PluginInstance;
+use crate::power::PowerCommands;
+use crate::quirks::QuirkDb;
+use crate::reconnect::{OpenOptions, PortGeneration, Reconnect};
+use crate::reset::{ResetInvert, ResetKind};
+use crate::stats_log::StatsLog;
+use crate::tagging::TagRule;
+use crate::template::Step;
+use crate::transcript::ExpectLine;
+use crate::tx_queue::TxQueue;
+use crate::ui::UiMessage;
+use crate::ui::exit_view::ExitView;
+#[cfg(feature = "web")]
+use crate::web::WebFeed;
 use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serialport::{DataBits, FlowControl, Parity, StopBits};
 use std::io::BufWriter;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex as StdMutex, atomic::AtomicBool};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Which line ending to send when you press Enter
-#[derive(Copy, Clone, Debug, ValueEnum)]
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LineEnding {
     /// Send nothing extra (no line ending)
     None,
@@ -33,11 +67,596 @@ impl LineEnding {
             LineEnding::Crlf => b"\r\n",
         }
     }
+
+    /// Cycle through the variants (used by the settings panel)
+    pub fn next(self) -> Self {
+        match self {
+            LineEnding::None => LineEnding::Nl,
+            LineEnding::Nl => LineEnding::Cr,
+            LineEnding::Cr => LineEnding::Crlf,
+            LineEnding::Crlf => LineEnding::None,
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        match self {
+            LineEnding::None => LineEnding::Crlf,
+            LineEnding::Nl => LineEnding::None,
+            LineEnding::Cr => LineEnding::Nl,
+            LineEnding::Crlf => LineEnding::Cr,
+        }
+    }
+}
+
+/// How received data is split into display lines
+#[derive(Copy, Clone, Debug, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RxLineEnding {
+    /// Split on '\n', dropping a preceding '\r'
+    #[default]
+    Lf,
+    /// Split on '\r', ignoring '\n'
+    Cr,
+    /// Split only on "\r\n"
+    Crlf,
+    /// Split on any of '\n', '\r' or "\r\n"
+    Any,
+}
+
+impl RxLineEnding {
+    pub fn describe(self) -> &'static str {
+        match self {
+            RxLineEnding::Lf => "LF (\\n)",
+            RxLineEnding::Cr => "CR (\\r)",
+            RxLineEnding::Crlf => "CRLF (\\r\\n)",
+            RxLineEnding::Any => "any",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            RxLineEnding::Lf => RxLineEnding::Cr,
+            RxLineEnding::Cr => RxLineEnding::Crlf,
+            RxLineEnding::Crlf => RxLineEnding::Any,
+            RxLineEnding::Any => RxLineEnding::Lf,
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        match self {
+            RxLineEnding::Lf => RxLineEnding::Any,
+            RxLineEnding::Cr => RxLineEnding::Lf,
+            RxLineEnding::Crlf => RxLineEnding::Cr,
+            RxLineEnding::Any => RxLineEnding::Crlf,
+        }
+    }
+}
+
+/// What to do with RX lines that merely echo the line just sent
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EchoMode {
+    /// Show echoes like any other line
+    #[default]
+    Show,
+    /// Show echoes dimmed with a `»` marker
+    Tag,
+    /// Hide echoes
+    Fold,
+}
+
+/// What Esc does on the main screen (prompts and popups always close on Esc)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EscAction {
+    /// Quit sermonizer
+    #[default]
+    Quit,
+    /// Clear the input line, or the display filter when the line is empty
+    Clear,
+    /// Transmit an ESC byte (0x1B)
+    Send,
+}
+
+/// Whether keystrokes go to a device-side line editor one by one
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PassthroughMode {
+    /// Edit lines locally, send on Enter
+    #[default]
+    Off,
+    /// Switch to passthrough once the device shows a line editor
+    Auto,
+    /// Always send every key right away
+    On,
+}
+
+/// What the output pane starts with (`--view`, `view = "..."` in
+/// profiles and quirks)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum View {
+    /// Lines of text
+    Text,
+    /// Hexdump with session offsets (`--hex`), for binary protocols
+    Hexdump,
+    /// VT100/ANSI screen (`--terminal`)
+    Terminal,
+}
+
+/// How a session is presented
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputMode {
+    /// Interactive terminal UI
+    #[default]
+    Tui,
+    /// No UI: one JSON object per event on stdout, stdin lines are sent
+    Json,
+}
+
+/// How often the session wakes up when nothing is happening
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Polling {
+    /// Keyboard checks (`--input-poll`)
+    pub input: Duration,
+    /// Shortest time between two redraws (`--render-interval`)
+    pub render: Duration,
+    /// Line error counters and port hotplug (`--status-poll`); `None` keeps
+    /// each watcher's own interval
+    pub status: Option<Duration>,
+}
+
+impl Polling {
+    /// `--power-save`: fewer wakeups for a monitor left running on battery
+    pub const POWER_SAVE: Polling = Polling {
+        input: Duration::from_millis(50),
+        render: Duration::from_millis(100),
+        status: Some(Duration::from_secs(5)),
+    };
+}
+
+impl Default for Polling {
+    fn default() -> Self {
+        Polling {
+            input: Duration::from_millis(1),
+            render: Duration::ZERO,
+            status: None,
+        }
+    }
+}
+
+/// Parity as it appears in profiles and on the command line
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParityMode {
+    None,
+    Odd,
+    Even,
+    /// Parity bit always 1 (9-bit multidrop address bytes; Linux only)
+    Mark,
+    /// Parity bit always 0 (Linux only)
+    Space,
+}
+
+impl ParityMode {
+    /// Mark/space: odd/even parity with the bit stuck (CMSPAR)
+    pub fn is_stick(self) -> bool {
+        matches!(self, ParityMode::Mark | ParityMode::Space)
+    }
+}
+
+impl From<ParityMode> for Parity {
+    fn from(mode: ParityMode) -> Self {
+        match mode {
+            ParityMode::None => Parity::None,
+            ParityMode::Odd | ParityMode::Mark => Parity::Odd,
+            ParityMode::Even | ParityMode::Space => Parity::Even,
+        }
+    }
+}
+
+/// `on`/`off` (also `1`/`0`, `high`/`low`) for control lines (clap value
+/// parser)
+pub fn parse_on_off(s: &str) -> Result<bool, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "on" | "1" | "high" | "true" => Ok(true),
+        "off" | "0" | "low" | "false" => Ok(false),
+        _ => Err(format!("expected on or off, got '{s}'")),
+    }
+}
+
+/// Data bits as they appear in profiles (`data_bits = 7`) and on the
+/// command line
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum DataBitsMode {
+    #[value(name = "5")]
+    Five,
+    #[value(name = "6")]
+    Six,
+    #[value(name = "7")]
+    Seven,
+    #[value(name = "8")]
+    Eight,
+}
+
+impl TryFrom<u8> for DataBitsMode {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, String> {
+        match bits {
+            5 => Ok(DataBitsMode::Five),
+            6 => Ok(DataBitsMode::Six),
+            7 => Ok(DataBitsMode::Seven),
+            8 => Ok(DataBitsMode::Eight),
+            _ => Err(format!("data bits must be 5-8, got {bits}")),
+        }
+    }
+}
+
+impl From<DataBitsMode> for u8 {
+    fn from(mode: DataBitsMode) -> Self {
+        u8::from(DataBits::from(mode))
+    }
+}
+
+impl From<DataBitsMode> for DataBits {
+    fn from(mode: DataBitsMode) -> Self {
+        match mode {
+            DataBitsMode::Five => DataBits::Five,
+            DataBitsMode::Six => DataBits::Six,
+            DataBitsMode::Seven => DataBits::Seven,
+            DataBitsMode::Eight => DataBits::Eight,
+        }
+    }
+}
+
+/// Stop bits as they appear in profiles (`stop_bits = 2`) and on the
+/// command line
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum StopBitsMode {
+    #[value(name = "1")]
+    One,
+    #[value(name = "2")]
+    Two,
+}
+
+impl TryFrom<u8> for StopBitsMode {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, String> {
+        match bits {
+            1 => Ok(StopBitsMode::One),
+            2 => Ok(StopBitsMode::Two),
+            _ => Err(format!("stop bits must be 1 or 2, got {bits}")),
+        }
+    }
+}
+
+impl From<StopBitsMode> for u8 {
+    fn from(mode: StopBitsMode) -> Self {
+        match mode {
+            StopBitsMode::One => 1,
+            StopBitsMode::Two => 2,
+        }
+    }
+}
+
+impl From<StopBitsMode> for StopBits {
+    fn from(mode: StopBitsMode) -> Self {
+        match mode {
+            StopBitsMode::One => StopBits::One,
+            StopBitsMode::Two => StopBits::Two,
+        }
+    }
+}
+
+/// Flow control as it appears in profiles and on the command line
+#[derive(Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowControlMode {
+    None,
+    /// XON/XOFF
+    Software,
+    /// RTS/CTS
+    Hardware,
+}
+
+impl From<FlowControlMode> for FlowControl {
+    fn from(mode: FlowControlMode) -> Self {
+        match mode {
+            FlowControlMode::None => FlowControl::None,
+            FlowControlMode::Software => FlowControl::Software,
+            FlowControlMode::Hardware => FlowControl::Hardware,
+        }
+    }
+}
+
+/// Current serial line settings, kept in sync with the open port
+#[derive(Copy, Clone, Debug)]
+pub struct PortSettings {
+    pub baud: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+    /// Last level written to DTR (most OSes assert it on open)
+    pub dtr: bool,
+    /// Last level written to RTS (most OSes assert it on open)
+    pub rts: bool,
+    /// Mark/space parity: `parity` Odd is mark, Even is space
+    pub stick_parity: bool,
+}
+
+impl PortSettings {
+    pub fn new(baud: u32) -> Self {
+        Self {
+            baud,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            dtr: true,
+            rts: true,
+            stick_parity: false,
+        }
+    }
+
+    /// Parity as shown in the settings panel
+    pub fn parity_label(&self) -> String {
+        match (self.parity, self.stick_parity) {
+            (Parity::Odd, true) => "Mark".to_string(),
+            (Parity::Even, true) => "Space".to_string(),
+            (parity, _) => parity.to_string(),
+        }
+    }
+
+    /// Flow control as shown in the title bar and startup banner
+    pub fn flow_label(&self) -> &'static str {
+        match self.flow_control {
+            FlowControl::None => "no flow control",
+            FlowControl::Software => "XON/XOFF",
+            FlowControl::Hardware => "RTS/CTS",
+        }
+    }
+
+    /// Character framing in the usual short form, e.g. `8N1`, `7E1` or
+    /// `8M1`
+    pub fn framing(&self) -> String {
+        let framing = framing(self.data_bits, self.parity, self.stop_bits);
+        match (self.parity, self.stick_parity) {
+            (Parity::Odd, true) => framing.replacen('O', "M", 1),
+            (Parity::Even, true) => framing.replacen('E', "S", 1),
+            _ => framing,
+        }
+    }
+}
+
+/// `8N1`-style notation for data bits, parity and stop bits
+pub fn framing(data_bits: DataBits, parity: Parity, stop_bits: StopBits) -> String {
+    let parity = match parity {
+        Parity::None => 'N',
+        Parity::Odd => 'O',
+        Parity::Even => 'E',
+    };
+    let stop_bits = match stop_bits {
+        StopBits::One => 1,
+        StopBits::Two => 2,
+    };
+    format!("{}{parity}{stop_bits}", u8::from(data_bits))
 }
 
 pub struct UiConfig {
     pub running: Arc<AtomicBool>,
+    pub port_name: String,
+    /// What the port is to a `--bench` rig ("dut"), for its tab
+    pub role: Option<String>,
+    /// USB details and driver of the open port (bug reports)
+    pub adapter_info: Vec<String>,
+    /// Known adapters, for the port switcher and adapter info after a switch
+    pub quirks: QuirkDb,
+    /// `--session` name, used for bundle directories
+    pub session_name: Option<String>,
     pub line_ending: LineEnding,
+    /// Ending sent with Shift+Enter
+    pub alt_line_ending: LineEnding,
+    pub rx_line_ending: RxLineEnding,
+    /// RX is shown as a hexdump, so binary data is expected
+    pub hex: bool,
+    /// Active decoder pipeline (shared with the reader)
+    #[cfg(feature = "decoders")]
+    pub decoder: SharedPipeline,
+    #[cfg(feature = "decoders")]
+    pub decoders: Arc<DecoderRegistry>,
+    /// `--decoder` chain, first in the F11 cycle
+    #[cfg(feature = "decoders")]
+    pub decoder_spec: Option<String>,
+    /// `on_line` plugins (taken by the UI)
+    #[cfg(feature = "wasm")]
+    pub plugin_actions: Vec<PluginInstance>,
+    /// `--web` live feed (taken by the UI)
+    #[cfg(feature = "web")]
+    pub web: Option<WebFeed>,
+    pub echo_mode: EchoMode,
+    pub esc_action: EscAction,
+    pub passthrough: PassthroughMode,
+    /// Start with the terminal emulation view (`--terminal`)
+    pub terminal_view: bool,
+    /// Borders, arrows and markers in plain ASCII (`--ascii-ui`)
+    pub ascii_ui: bool,
+    /// Nothing is sent; the input pane is off (`--read-only`)
+    pub read_only: bool,
+    /// Keyboard and redraw intervals (`--power-save`, `--input-poll`...)
+    pub polling: Polling,
+    /// The `--rx-pipe` command, when its output gets a pane
+    /// (`--rx-pipe-pane`)
+    pub rx_pipe_pane: Option<String>,
+    /// Output kept for the normal terminal after quitting (`--on-exit`)
+    pub on_exit: Option<ExitView>,
+    /// `--key` escape sequences for passthrough mode
+    pub keymap: KeyMap,
+    /// How long Alt+B holds the line in BREAK
+    pub break_duration: Duration,
+    /// `--reset` target behind Alt+T / Alt+U
+    pub reset: Option<ResetKind>,
+    pub reset_invert: Option<ResetInvert>,
+    /// `--capture-boot` file still being written (taken by the UI)
+    pub boot_capture: Option<BootCapture>,
+    /// `--stats-log` records (taken by the UI)
+    pub stats_log: Option<StatsLog>,
+    /// Characters sent immediately (without Enter) when typed into an
+    /// empty input line; toggled with F3
+    pub instant_keys: String,
+    pub instant_keys_enabled: bool,
+    pub tag_rules: Vec<TagRule>,
+    /// RX lines matching one of these fold into the line before them
+    pub fold_rules: Vec<Regex>,
+    /// `--block` rules (taken by the UI)
+    pub block_rules: Vec<BlockRule>,
+    pub level_parser: LevelParser,
+    /// Initial minimum display level (F5 changes it at runtime)
+    pub min_level: Option<Level>,
+    /// Expected transcript to check RX lines against (taken by the UI)
+    pub transcript: Option<Vec<ExpectLine>>,
+    /// Measure request/response round trips
+    pub latency: bool,
+    /// Only RX lines matching this count as a response
+    pub latency_response: Option<Regex>,
+    /// Commands sent right after connecting (taken by the UI)
+    pub startup: Vec<Step>,
+    /// Desktop notifications and alert patterns (taken by the UI)
+    pub alerts: Alerts,
+    /// `--pin` patterns (taken by the UI)
+    pub pins: Vec<Regex>,
+    /// Login prompt responder from the profile (taken by the UI)
+    pub auto_login: Option<AutoLogin>,
+    /// Boot profiler milestones (taken by the UI)
+    pub milestones: Vec<Milestone>,
+    pub port_settings: PortSettings,
+    /// The open port, for termios settings serialport doesn't cover
+    /// (mark/space parity)
+    #[cfg(target_os = "linux")]
+    pub port_fd: std::os::fd::RawFd,
+    /// Defaults pinned with `--compat`
+    pub compat: Compat,
+    /// Relay/PDU commands behind F7/F8/F9
+    pub power: PowerCommands,
+    /// Every complete RX line, for background sequences waiting on output
+    pub rx_lines: broadcast::Sender<String>,
+    /// For background work (power commands) reporting back to the UI
+    pub ui_tx: UnboundedSender<UiMessage>,
+    /// Wait for the port to come back after it went away (`--reconnect`)
+    pub reconnect: Option<Reconnect>,
+    /// Bumped on a port switch, which cancels a pending reconnect
+    pub port_generation: PortGeneration,
+    /// How the port was opened, for reopening it the same way. The reader
+    /// unescapes PARMRK marks when `mark_errors` is set.
+    pub open_options: OpenOptions,
+    /// Background sends in progress (widget, Ctrl+X)
+    pub tx_queue: TxQueue,
+    /// RX log writer, for stopwatch markers
+    pub rx_log: Option<Arc<StdMutex<BufWriter<std::fs::File>>>>,
     pub tx_log: Option<Arc<StdMutex<BufWriter<std::fs::File>>>>,
+    /// Log files being written (quitting asks for confirmation)
+    pub rx_log_path: Option<PathBuf>,
+    pub tx_log_path: Option<PathBuf>,
+    /// Where each setting came from (config view)
+    pub origins: Origins,
     pub log_ts: bool,
 }
+
+impl UiConfig {
+    /// The port (with its role, if any) in the tab bar
+    pub fn tab_label(&self) -> String {
+        match &self.role {
+            Some(role) => format!("{role} ({})", self.port_name),
+            None => self.port_name.clone(),
+        }
+    }
+
+    /// Settings for another port's tab: everything that describes how to
+    /// show and talk to a device is copied, while what belongs to the port
+    /// (its name, channels, logs, reconnect target) starts out empty for
+    /// the caller to fill in. Startup commands, transcripts, auto-login,
+    /// plugins and the web view stay with the first port.
+    pub fn for_tab(&self, port_name: &str, ui_tx: UnboundedSender<UiMessage>) -> Self {
+        UiConfig {
+            running: self.running.clone(),
+            port_name: port_name.to_string(),
+            role: None,
+            adapter_info: Vec::new(),
+            quirks: self.quirks.clone(),
+            session_name: self.session_name.clone(),
+            line_ending: self.line_ending,
+            alt_line_ending: self.alt_line_ending,
+            rx_line_ending: self.rx_line_ending,
+            hex: self.hex,
+            #[cfg(feature = "decoders")]
+            decoder: Arc::new(StdMutex::new(
+                self.decoder_spec
+                    .as_deref()
+                    .and_then(|spec| self.decoders.pipeline(spec).ok()),
+            )),
+            #[cfg(feature = "decoders")]
+            decoders: self.decoders.clone(),
+            #[cfg(feature = "decoders")]
+            decoder_spec: self.decoder_spec.clone(),
+            #[cfg(feature = "wasm")]
+            plugin_actions: Vec::new(),
+            #[cfg(feature = "web")]
+            web: None,
+            echo_mode: self.echo_mode,
+            esc_action: self.esc_action,
+            passthrough: self.passthrough,
+            terminal_view: self.terminal_view,
+            ascii_ui: self.ascii_ui,
+            read_only: self.read_only,
+            polling: self.polling,
+            rx_pipe_pane: None,
+            on_exit: None,
+            keymap: self.keymap.clone(),
+            break_duration: self.break_duration,
+            reset: self.reset,
+            reset_invert: self.reset_invert,
+            boot_capture: None,
+            stats_log: self.stats_log.as_ref().map(StatsLog::for_tab),
+            instant_keys: self.instant_keys.clone(),
+            instant_keys_enabled: self.instant_keys_enabled,
+            tag_rules: self.tag_rules.clone(),
+            fold_rules: self.fold_rules.clone(),
+            block_rules: self.block_rules.clone(),
+            level_parser: self.level_parser.clone(),
+            min_level: self.min_level,
+            transcript: None,
+            latency: self.latency,
+            latency_response: self.latency_response.clone(),
+            startup: Vec::new(),
+            alerts: Alerts::new(
+                self.alerts.desktop,
+                self.alerts.patterns.clone(),
+                self.alerts.sounds.clone(),
+            ),
+            pins: self.pins.clone(),
+            auto_login: None,
+            milestones: self.milestones.clone(),
+            port_settings: self.port_settings,
+            #[cfg(target_os = "linux")]
+            port_fd: -1,
+            compat: self.compat,
+            power: self.power.clone(),
+            rx_lines: broadcast::channel(256).0,
+            ui_tx,
+            reconnect: None,
+            port_generation: PortGeneration::default(),
+            open_options: self.open_options,
+            tx_queue: TxQueue::default(),
+            rx_log: None,
+            tx_log: None,
+            rx_log_path: None,
+            tx_log_path: None,
+            origins: self.origins.clone(),
+            log_ts: self.log_ts,
+        }
+    }
+}
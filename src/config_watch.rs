@@ -0,0 +1,179 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+use crate::keymap::KeyMap;
+use crate::profile::{Profile, config_path, load_config};
+use crate::tagging::TagRule;
+use crate::ui::UiMessage;
+
+/// How often the config file's modification time is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Profile settings a reload applies; editing anything else needs a restart
+const LIVE_SECTIONS: &[&str] = &["tags", "level_patterns", "alerts", "folds", "keys"];
+
+/// Profile rules that can be swapped while running. `None` leaves the
+/// current rules alone (they were given as flags, which always win).
+#[derive(Debug)]
+pub struct LiveRules {
+    pub tags: Option<Vec<TagRule>>,
+    pub level_patterns: Option<Vec<Regex>>,
+    pub alerts: Option<Vec<Regex>>,
+    pub folds: Option<Vec<Regex>>,
+    pub keys: Option<KeyMap>,
+    /// Other profile settings that were edited, which only a restart
+    /// picks up
+    pub restart_needed: Vec<String>,
+}
+
+/// Which rules came from flags and must not be replaced by a reload
+#[derive(Copy, Clone, Debug)]
+pub struct FixedRules {
+    pub tags: bool,
+    pub level_patterns: bool,
+    pub alerts: bool,
+    pub folds: bool,
+    pub keys: bool,
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The profile as the config file has it now
+fn load_profile(profile_name: Option<&str>) -> Result<Profile> {
+    let mut config = load_config()?.context("config file is gone")?;
+    let name = profile_name
+        .map(str::to_string)
+        .or(config.default_profile.take())
+        .context("no profile selected")?;
+    config
+        .profiles
+        .remove(&name)
+        .with_context(|| format!("profile '{name}' no longer exists"))
+}
+
+/// Settings outside `LIVE_SECTIONS` that differ between two versions of
+/// the profile
+fn restart_needed(old: &Profile, new: &Profile) -> Vec<String> {
+    let table = |profile: &Profile| match toml::Value::try_from(profile) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::Table::new(),
+    };
+    let (old, new) = (table(old), table(new));
+    let mut changed: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|key| !LIVE_SECTIONS.contains(&key.as_str()))
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+/// The rules of a reloaded profile, leaving out those set by flags
+fn live_rules(
+    profile: &Profile,
+    fixed: FixedRules,
+    restart_needed: Vec<String>,
+) -> Result<LiveRules> {
+    Ok(LiveRules {
+        tags: (!fixed.tags).then(|| profile.tag_rules()).transpose()?,
+        level_patterns: (!fixed.level_patterns)
+            .then(|| profile.level_regexes())
+            .transpose()?,
+        alerts: (!fixed.alerts)
+            .then(|| profile.alert_regexes())
+            .transpose()?,
+        folds: (!fixed.folds).then(|| profile.fold_regexes()).transpose()?,
+        keys: (!fixed.keys)
+            .then(|| profile.key_bindings().map(KeyMap::new))
+            .transpose()?,
+        restart_needed,
+    })
+}
+
+/// Watch the config file and send the profile's tag, level, alert, fold
+/// and key rules to the UI whenever it is saved, with the other settings
+/// that changed and need a restart
+pub async fn watch_config(
+    profile_name: Option<String>,
+    fixed: FixedRules,
+    running: Arc<AtomicBool>,
+    ui_tx: mpsc::UnboundedSender<UiMessage>,
+) {
+    let Some(path) = config_path() else {
+        return;
+    };
+    let mut last = modified(&path);
+    // What the session started with: edits a reload cannot apply stay
+    // listed until a restart
+    let started = load_profile(profile_name.as_deref()).unwrap_or_default();
+    while running.load(Ordering::SeqCst) {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let now = modified(&path);
+        if now == last {
+            continue;
+        }
+        last = now;
+        let rules = load_profile(profile_name.as_deref())
+            .and_then(|profile| live_rules(&profile, fixed, restart_needed(&started, &profile)));
+        let message = match rules {
+            Ok(rules) => UiMessage::RulesReloaded(rules),
+            Err(e) => UiMessage::Status(format!("Config not reloaded: {e:#}")),
+        };
+        if ui_tx.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_edits_outside_the_live_rules_need_a_restart() {
+        let old = Profile {
+            baud: Some(115200),
+            tags: [("net".to_string(), "eth0".to_string())].into(),
+            ..Default::default()
+        };
+        let new = Profile {
+            baud: Some(9600),
+            tags: [("net".to_string(), "wlan0".to_string())].into(),
+            folds: vec!["^\\s+at ".to_string()],
+            startup_commands: vec!["AT".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(restart_needed(&old, &new), ["baud", "startup_commands"]);
+        assert!(restart_needed(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn flags_keep_their_rules() {
+        let profile = Profile {
+            keys: vec!["f1".to_string()],
+            folds: vec!["^\\s+at ".to_string()],
+            ..Default::default()
+        };
+        let fixed = FixedRules {
+            tags: false,
+            level_patterns: false,
+            alerts: false,
+            folds: true,
+            keys: false,
+        };
+        let rules = live_rules(&profile, fixed, Vec::new()).unwrap();
+        assert!(rules.folds.is_none());
+        assert!(rules.keys.is_some());
+        assert!(rules.tags.is_some_and(|tags| tags.is_empty()));
+    }
+}
@@ -0,0 +1,140 @@
+use serialport::SerialPort;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::config::{PortSettings, framing};
+use crate::ui::UiMessage;
+
+/// How long after opening a silent port gets a note about it
+const SILENCE: Duration = Duration::from_secs(5);
+
+/// Bytes of the first read shown in hex
+const FIRST_BYTES_SHOWN: usize = 16;
+
+/// `--verbose-connect`: when tracing started, the port traced and the
+/// time of the previous stage
+static START: OnceLock<Instant> = OnceLock::new();
+static PORT: OnceLock<String> = OnceLock::new();
+static LAST: Mutex<Option<Instant>> = Mutex::new(None);
+static GOT_DATA: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    let _ = START.set(Instant::now());
+}
+
+pub fn enabled() -> bool {
+    START.get().is_some()
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
+
+/// Print one stage of connecting with the time since the start and since
+/// the stage before (stderr in JSON mode, and also when `--quiet`)
+pub fn stage(name: &str, detail: impl Display) {
+    let Some(start) = START.get() else {
+        return;
+    };
+    let now = Instant::now();
+    let since_last = LAST
+        .lock()
+        .ok()
+        .and_then(|mut last| last.replace(now))
+        .map_or(now - *start, |last| now - last);
+    let line = format!(
+        "[connect {:>10} +{:>9}] {name}: {detail}",
+        millis(now - *start),
+        millis(since_last)
+    );
+    if crate::JSON_OUTPUT.load(Ordering::Relaxed) {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+/// What the driver says the port is set to now, which may differ from
+/// what was asked for
+pub fn driver_settings(port: &dyn SerialPort) -> String {
+    let baud = port
+        .baud_rate()
+        .map_or_else(|e| format!("baud unknown ({e})"), |b| format!("{b} baud"));
+    let framing = match (port.data_bits(), port.parity(), port.stop_bits()) {
+        (Ok(data_bits), Ok(parity), Ok(stop_bits)) => framing(data_bits, parity, stop_bits),
+        _ => "framing unknown".to_string(),
+    };
+    let flow = port
+        .flow_control()
+        .map_or_else(|_| "unknown".to_string(), |f| f.to_string());
+    format!("driver reports {baud} {framing}, flow control {flow}")
+}
+
+/// The levels DTR/RTS were left at and the ones read on the inputs (`?`
+/// where the driver can't tell)
+pub fn control_lines(port: &mut dyn SerialPort, settings: &PortSettings) -> String {
+    let level = |on: bool| if on { "on" } else { "off" };
+    let input = |name: &str, read: serialport::Result<bool>| match read {
+        Ok(on) => format!("{name} {}", level(on)),
+        Err(_) => format!("{name} ?"),
+    };
+    let inputs = [
+        input("CTS", port.read_clear_to_send()),
+        input("DSR", port.read_data_set_ready()),
+        input("CD", port.read_carrier_detect()),
+        input("RI", port.read_ring_indicator()),
+    ];
+    format!(
+        "DTR {}, RTS {}; inputs {}",
+        level(settings.dtr),
+        level(settings.rts),
+        inputs.join(", ")
+    )
+}
+
+/// The port the session opened, whose first bytes get a note
+pub fn set_port(port_name: &str) {
+    if enabled() {
+        let _ = PORT.set(port_name.to_string());
+    }
+}
+
+/// The note for the first bytes read from `port_name`, once
+pub fn first_bytes(port_name: &str, bytes: &[u8]) -> Option<String> {
+    let start = START.get()?;
+    if bytes.is_empty() || PORT.get()? != port_name || GOT_DATA.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    let shown: Vec<String> = bytes
+        .iter()
+        .take(FIRST_BYTES_SHOWN)
+        .map(|b| format!("{b:02X}"))
+        .collect();
+    let more = if bytes.len() > FIRST_BYTES_SHOWN {
+        " ..."
+    } else {
+        ""
+    };
+    Some(format!(
+        "-- connect: first bytes {} after start ({} in the first read: {}{more}) --",
+        millis(start.elapsed()),
+        bytes.len(),
+        shown.join(" ")
+    ))
+}
+
+/// Note in the output when nothing has arrived a while after opening: the
+/// adapter enumerates and opens, but no data gets through
+pub async fn watch_silence(ui_tx: UnboundedSender<UiMessage>) {
+    tokio::time::sleep(SILENCE).await;
+    if !GOT_DATA.load(Ordering::SeqCst) {
+        let _ = ui_tx.send(UiMessage::Output(format!(
+            "-- connect: no data {} s after opening. Check the baud rate, TX/RX wiring \
+             (crossed?), that the device is powered and not held in reset by DTR/RTS --",
+            SILENCE.as_secs()
+        )));
+    }
+}
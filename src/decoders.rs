@@ -0,0 +1,609 @@
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use crate::calc::crc16_modbus;
+use crate::serial_io::hex_bytes;
+
+/// One stage of the RX decoding pipeline. It gets received bytes (or one
+/// frame of the previous stage at a time) and returns frames for the next
+/// stage; the frames of the last stage are shown as output lines.
+pub trait Decoder: Send {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>>;
+
+    /// Frame layout for the F1 reference, one line each
+    fn layout(&self) -> &'static [&'static str] {
+        &[]
+    }
+}
+
+type Factory = Box<dyn Fn() -> Result<Box<dyn Decoder>, String> + Send + Sync>;
+
+struct Registration {
+    name: String,
+    description: String,
+    factory: Factory,
+}
+
+/// Decoders selectable by name with `--decoder` and F11
+pub struct DecoderRegistry {
+    entries: Vec<Registration>,
+}
+
+impl DecoderRegistry {
+    pub fn with_builtins() -> Self {
+        let mut registry = Self {
+            entries: Vec::new(),
+        };
+        registry.register("hex", "each frame as hex bytes", || Ok(Box::new(Hex)));
+        registry.register("cobs", "split on 0x00 and undo COBS stuffing", || {
+            Ok(Box::<Cobs>::default())
+        });
+        registry.register("nmea", "check NMEA 0183 sentence checksums", || {
+            Ok(Box::<Nmea>::default())
+        });
+        registry.register("modbus", "Modbus RTU frame: unit, function, CRC", || {
+            Ok(Box::new(ModbusRtu))
+        });
+        registry.register(
+            "cmux",
+            "3GPP 27.010 basic mode: one [chN] line stream per DLCI",
+            || Ok(Box::<Cmux>::default()),
+        );
+        registry
+    }
+
+    /// Add a decoder; a later registration under the same name wins
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        factory: impl Fn() -> Result<Box<dyn Decoder>, String> + Send + Sync + 'static,
+    ) {
+        let name = name.into();
+        self.entries.retain(|r| r.name != name);
+        self.entries.push(Registration {
+            name,
+            description: description.into(),
+            factory: Box::new(factory),
+        });
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    /// `name: description` per decoder
+    pub fn describe(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|r| format!("{}: {}", r.name, r.description))
+            .collect()
+    }
+
+    /// Build a chain from a comma-separated spec such as `cobs,hex`
+    pub fn pipeline(&self, spec: &str) -> Result<Pipeline, String> {
+        let stages = spec
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                self.entries
+                    .iter()
+                    .find(|r| r.name == name)
+                    .ok_or_else(|| {
+                        format!(
+                            "unknown decoder '{name}' (available: {})",
+                            self.describe().join("; ")
+                        )
+                    })
+                    .and_then(|r| (r.factory)())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Pipeline {
+            spec: spec.to_string(),
+            stages,
+        })
+    }
+}
+
+/// The active pipeline, shared by the reader and the UI (F11 swaps it)
+pub type SharedPipeline = Arc<Mutex<Option<Pipeline>>>;
+
+/// Decoders chained in order
+pub struct Pipeline {
+    pub spec: String,
+    stages: Vec<Box<dyn Decoder>>,
+}
+
+impl Pipeline {
+    pub fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = vec![input.to_vec()];
+        for stage in &mut self.stages {
+            frames = frames
+                .iter()
+                .flat_map(|frame| stage.decode(frame))
+                .collect();
+        }
+        frames
+    }
+
+    /// Each stage's name and frame layout, in order
+    pub fn layout(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (name, stage) in self.spec.split(',').zip(&self.stages) {
+            lines.push(format!("{}:", name.trim()));
+            match stage.layout() {
+                [] => lines.push("  (no layout known)".to_string()),
+                layout => lines.extend(layout.iter().map(|l| format!("  {l}"))),
+            }
+        }
+        lines
+    }
+}
+
+struct Hex;
+
+impl Decoder for Hex {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        vec![hex_bytes(input).into_bytes()]
+    }
+
+    fn layout(&self) -> &'static [&'static str] {
+        &["each frame as space-separated hex bytes"]
+    }
+}
+
+/// Bytes a decoder holds without seeing the end of a frame or line (a
+/// wrong baud rate, a stream in another format, PPP on a CMUX channel)
+/// before they are shown as hex anyway
+const MAX_PENDING: usize = 1024;
+
+/// Consistent Overhead Byte Stuffing, frames terminated by 0x00
+#[derive(Default)]
+struct Cobs {
+    buf: Vec<u8>,
+}
+
+fn cobs_decode(frame: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(frame.len());
+    let mut i = 0;
+    while i < frame.len() {
+        let code = frame[i] as usize;
+        let end = i + code;
+        if code == 0 || end > frame.len() {
+            return Err(format!("bad code byte 0x{code:02X} at {i}"));
+        }
+        out.extend_from_slice(&frame[i + 1..end]);
+        i = end;
+        if code < 0xFF && i < frame.len() {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+impl Decoder for Cobs {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for &b in input {
+            if b != 0 {
+                self.buf.push(b);
+                if self.buf.len() >= MAX_PENDING {
+                    let text = format!("[COBS error: no frame delimiter] {}", hex_bytes(&self.buf));
+                    frames.push(text.into_bytes());
+                    self.buf.clear();
+                }
+                continue;
+            }
+            let frame = std::mem::take(&mut self.buf);
+            if frame.is_empty() {
+                continue;
+            }
+            frames.push(match cobs_decode(&frame) {
+                Ok(decoded) => decoded,
+                Err(e) => format!("[COBS error: {e}] {}", hex_bytes(&frame)).into_bytes(),
+            });
+        }
+        frames
+    }
+
+    fn layout(&self) -> &'static [&'static str] {
+        &[
+            "| code | code-1 data bytes | code | ... | 00 |",
+            "code: offset to the next zero of the data (or the end)",
+            "code FF: 254 data bytes, no zero follows",
+        ]
+    }
+}
+
+/// Splits into lines and annotates `$...*HH` sentences with their type and
+/// whether the checksum matches
+#[derive(Default)]
+struct Nmea {
+    buf: Vec<u8>,
+}
+
+fn annotate_nmea(line: &str) -> String {
+    let Some(body) = line.strip_prefix(['$', '!']) else {
+        return line.to_string();
+    };
+    let kind = body
+        .split(',')
+        .next()
+        .map(|t| t.get(2..).unwrap_or(t))
+        .unwrap_or("");
+    let Some((data, checksum)) = body.rsplit_once('*') else {
+        return format!("{line}  [{kind}, no checksum]");
+    };
+    let computed = data.bytes().fold(0u8, |acc, b| acc ^ b);
+    match u8::from_str_radix(checksum.trim(), 16) {
+        Ok(given) if given == computed => format!("{line}  [{kind}, checksum ok]"),
+        _ => format!("{line}  [{kind}, checksum BAD, expected {computed:02X}]"),
+    }
+}
+
+impl Decoder for Nmea {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        self.buf.extend_from_slice(input);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            lines.push(annotate_nmea(line.trim_end()).into_bytes());
+        }
+        if self.buf.len() >= MAX_PENDING {
+            let text = format!("[NMEA error: no line end] {}", hex_bytes(&self.buf));
+            lines.push(text.into_bytes());
+            self.buf.clear();
+        }
+        lines
+    }
+
+    fn layout(&self) -> &'static [&'static str] {
+        &[
+            "$ talker type , field , field ... * CS CR LF",
+            "talker: 2 letters (GP, GN...), type: 3 (GGA, RMC...)",
+            "CS: XOR of the bytes between $ and *, 2 hex digits (:xor8)",
+        ]
+    }
+}
+
+/// Treats each input frame as one Modbus RTU ADU (put `cobs` or similar
+/// in front when frames arrive split)
+struct ModbusRtu;
+
+fn modbus_function(code: u8) -> &'static str {
+    match code & 0x7F {
+        1 => "read coils",
+        2 => "read discrete inputs",
+        3 => "read holding registers",
+        4 => "read input registers",
+        5 => "write single coil",
+        6 => "write single register",
+        15 => "write multiple coils",
+        16 => "write multiple registers",
+        23 => "read/write multiple registers",
+        _ => "function",
+    }
+}
+
+impl Decoder for ModbusRtu {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        let mut text = hex_bytes(input);
+        if input.len() < 4 {
+            text.push_str("  [too short for Modbus RTU]");
+            return vec![text.into_bytes()];
+        }
+        let (body, crc) = input.split_at(input.len() - 2);
+        let crc_ok = crc16_modbus(body) == u16::from_le_bytes([crc[0], crc[1]]);
+        let (unit, code) = (body[0], body[1]);
+        let _ = write!(
+            text,
+            "  [unit {unit}, fc {} {}",
+            code & 0x7F,
+            modbus_function(code)
+        );
+        if code & 0x80 != 0 {
+            let _ = write!(text, ", EXCEPTION {}", body.get(2).copied().unwrap_or(0));
+        }
+        text.push_str(if crc_ok { ", CRC ok]" } else { ", CRC BAD]" });
+        vec![text.into_bytes()]
+    }
+
+    fn layout(&self) -> &'static [&'static str] {
+        &[
+            "| unit | function | data (0-252) | CRC lo | CRC hi |",
+            "function | 0x80: exception reply, data is the exception code",
+            "CRC: CRC-16/MODBUS of unit..data, low byte first (:crc16)",
+            "frames end after 3.5 character times of silence",
+        ]
+    }
+}
+
+/// Flag byte around 27.010 basic mode frames
+const CMUX_FLAG: u8 = 0xF9;
+
+/// 27.010 FCS: reflected CRC-8 (x^8 + x^2 + x + 1), sent inverted
+fn cmux_fcs(bytes: &[u8]) -> u8 {
+    let crc = bytes.iter().fold(0xFFu8, |mut crc, &b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xE0
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    });
+    0xFF - crc
+}
+
+/// Frame type from the control field, P/F bit masked
+fn cmux_frame_type(control: u8) -> &'static str {
+    match control & !0x10 {
+        0x2F => "SABM",
+        0x63 => "UA",
+        0x0F => "DM",
+        0x43 => "DISC",
+        0xEF => "UIH",
+        0x03 => "UI",
+        _ => "unknown frame",
+    }
+}
+
+/// Control channel (DLCI 0) message type
+fn cmux_control_message(kind: u8) -> &'static str {
+    match kind & !0x03 {
+        0x10 => "NSC",
+        0x20 => "TEST",
+        0x40 => "PSC",
+        0x50 => "RLS",
+        0x60 => "FCoff",
+        0x80 => "PN",
+        0x90 => "RPN",
+        0xA0 => "FCon",
+        0xC0 => "CLD",
+        0xD0 => "SNC",
+        0xE0 => "MSC",
+        _ => "message",
+    }
+}
+
+struct CmuxFrame {
+    dlci: u8,
+    control: u8,
+    info: Vec<u8>,
+    fcs_ok: bool,
+}
+
+/// 3GPP 27.010 (GSM 07.10) basic option demultiplexer, as used by cellular
+/// modems after AT+CMUX. Data channels come out line by line as `[chN] ...`
+/// (F4 shows one channel at a time), channels that don't send lines as hex
+/// every `MAX_PENDING` bytes; control frames and DLCI 0 are described.
+#[derive(Default)]
+struct Cmux {
+    buf: Vec<u8>,
+    /// Incomplete line per DLCI
+    lines: std::collections::BTreeMap<u8, Vec<u8>>,
+}
+
+impl Cmux {
+    /// Take one frame off the front of the buffer: `None` when more bytes
+    /// are needed, `Some(None)` when bytes were skipped resyncing
+    fn next_frame(&mut self) -> Option<Option<CmuxFrame>> {
+        let start = self.buf.iter().position(|&b| b == CMUX_FLAG)?;
+        // Back-to-back flags: the last one opens the frame
+        let run = self.buf[start..]
+            .iter()
+            .take_while(|&&b| b == CMUX_FLAG)
+            .count();
+        self.buf.drain(..start + run - 1);
+        let header = self.buf.get(1..4)?;
+        let (address, control) = (header[0], header[1]);
+        let (len, header_len) = if header[2] & 1 != 0 {
+            (usize::from(header[2] >> 1), 3)
+        } else {
+            let second = *self.buf.get(4)?;
+            (usize::from(header[2] >> 1) | usize::from(second) << 7, 4)
+        };
+        let end = 1 + header_len + len + 1;
+        let closing = *self.buf.get(end)?;
+        if closing != CMUX_FLAG {
+            self.buf.drain(..1);
+            return Some(None);
+        }
+        let info = self.buf[1 + header_len..1 + header_len + len].to_vec();
+        // UIH checks the header only, the other frames header and info
+        let checked = if control & !0x10 == 0xEF {
+            &self.buf[1..1 + header_len]
+        } else {
+            &self.buf[1..1 + header_len + len]
+        };
+        let fcs_ok = cmux_fcs(checked) == self.buf[end - 1];
+        // The closing flag may open the next frame
+        self.buf.drain(..end);
+        Some(Some(CmuxFrame {
+            dlci: address >> 2,
+            control,
+            info,
+            fcs_ok,
+        }))
+    }
+}
+
+impl Decoder for Cmux {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        self.buf.extend_from_slice(input);
+        let mut out = Vec::new();
+        while let Some(frame) = self.next_frame() {
+            let Some(CmuxFrame {
+                dlci,
+                control,
+                info,
+                fcs_ok,
+            }) = frame
+            else {
+                continue;
+            };
+            let kind = cmux_frame_type(control);
+            if !fcs_ok {
+                out.push(format!("[ch{dlci}] {kind} FCS BAD {}", hex_bytes(&info)).into_bytes());
+            } else if kind != "UIH" && kind != "UI" {
+                out.push(format!("[ch{dlci}] {kind}").into_bytes());
+            } else if dlci == 0 {
+                let message = info.first().map_or("empty", |&k| cmux_control_message(k));
+                out.push(format!("[ch0] {message} {}", hex_bytes(&info)).into_bytes());
+            } else {
+                let pending = self.lines.entry(dlci).or_default();
+                pending.extend_from_slice(&info);
+                while let Some(pos) = pending.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=pos).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    let line = line.trim_end();
+                    if !line.is_empty() {
+                        out.push(format!("[ch{dlci}] {line}").into_bytes());
+                    }
+                }
+                if pending.len() >= MAX_PENDING {
+                    out.push(format!("[ch{dlci}] {}", hex_bytes(pending)).into_bytes());
+                    pending.clear();
+                }
+            }
+        }
+        // Nothing like a frame in sight: don't hold on to garbage
+        if !self.buf.contains(&CMUX_FLAG) {
+            self.buf.clear();
+        }
+        out
+    }
+
+    fn layout(&self) -> &'static [&'static str] {
+        &[
+            "| F9 | address | control | length | info | FCS | F9 |",
+            "address: DLCI << 2 | C/R << 1 | EA (1); DLCI 0 is control",
+            "control: SABM 2F, UA 63, DM 0F, DISC 43, UIH EF, UI 03 (| 10 P/F)",
+            "length: len << 1 | EA, a second byte follows when EA is 0",
+            "FCS: CRC-8 (x^8+x^2+x+1, reflected, inverted) of address..length",
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A basic mode frame with a one-byte length
+    fn cmux_frame(dlci: u8, control: u8, info: &[u8]) -> Vec<u8> {
+        let header = [dlci << 2 | 0b11, control, (info.len() as u8) << 1 | 1];
+        let fcs = if control & !0x10 == 0xEF {
+            cmux_fcs(&header)
+        } else {
+            cmux_fcs(&[&header[..], info].concat())
+        };
+        [&[CMUX_FLAG][..], &header, info, &[fcs, CMUX_FLAG]].concat()
+    }
+
+    fn lines(out: Vec<Vec<u8>>) -> Vec<String> {
+        out.into_iter()
+            .map(|l| String::from_utf8(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn cmux_fcs_matches_the_spec_example() {
+        // SABM on DLCI 0 as sent after AT+CMUX: F9 03 3F 01 1C F9
+        assert_eq!(cmux_fcs(&[0x03, 0x3F, 0x01]), 0x1C);
+    }
+
+    #[test]
+    fn cmux_lines_per_channel() {
+        let mut cmux = Cmux::default();
+        let mut input = cmux_frame(1, 0xEF, b"OK\r\nRI");
+        input.extend(cmux_frame(2, 0xEF, b"+CREG: 1\r\n"));
+        input.extend(cmux_frame(1, 0xEF, b"NG\r\n"));
+        assert_eq!(
+            lines(cmux.decode(&input)),
+            ["[ch1] OK", "[ch2] +CREG: 1", "[ch1] RING"]
+        );
+    }
+
+    #[test]
+    fn cmux_frames_split_across_reads() {
+        let mut cmux = Cmux::default();
+        let frame = cmux_frame(3, 0xEF, b"hello\n");
+        let (a, b) = frame.split_at(4);
+        assert!(cmux.decode(a).is_empty());
+        assert_eq!(lines(cmux.decode(b)), ["[ch3] hello"]);
+    }
+
+    #[test]
+    fn cmux_control_frames_and_bad_fcs() {
+        let mut cmux = Cmux::default();
+        let mut input = cmux_frame(0, 0x3F, b"");
+        let mut bad = cmux_frame(1, 0xEF, b"x\n");
+        let fcs = bad.len() - 2;
+        bad[fcs] ^= 0xFF;
+        input.extend(bad);
+        assert_eq!(
+            lines(cmux.decode(&input)),
+            ["[ch0] SABM", "[ch1] UIH FCS BAD 78 0A"]
+        );
+    }
+
+    #[test]
+    fn cmux_garbage_before_a_frame_is_skipped() {
+        let mut cmux = Cmux::default();
+        let mut input = b"AT+CMUX=0\r\r\nOK\r\n".to_vec();
+        input.extend(cmux_frame(1, 0xEF, b"hi\n"));
+        assert_eq!(lines(cmux.decode(&input)), ["[ch1] hi"]);
+        let mut cmux = Cmux::default();
+        assert!(cmux.decode(b"noise without flags").is_empty());
+        assert!(cmux.buf.is_empty());
+    }
+
+    #[test]
+    fn cmux_channels_without_newlines_are_bounded() {
+        let mut cmux = Cmux::default();
+        let chunk = [0x7Eu8; 100];
+        let mut out = Vec::new();
+        for _ in 0..MAX_PENDING / chunk.len() + 1 {
+            out.extend(lines(cmux.decode(&cmux_frame(2, 0xEF, &chunk))));
+        }
+        assert_eq!(out.len(), 1);
+        assert!(out[0].starts_with("[ch2] 7E 7E"));
+        assert!(cmux.lines[&2].len() < MAX_PENDING);
+    }
+
+    #[test]
+    fn cobs_without_delimiters_is_bounded() {
+        let mut cobs = Cobs::default();
+        let chunk = [0x55u8; 100];
+        let mut out = Vec::new();
+        for _ in 0..MAX_PENDING / chunk.len() + 1 {
+            out.extend(lines(cobs.decode(&chunk)));
+        }
+        assert_eq!(out.len(), 1);
+        assert!(out[0].starts_with("[COBS error: no frame delimiter] 55 55"));
+        assert!(cobs.buf.len() < MAX_PENDING);
+        // The rest of the garbage ends at the next delimiter, then frames
+        // decode again
+        let out = lines(cobs.decode(&[0x00, 0x02, 0x41, 0x00]));
+        assert!(out[0].starts_with("[COBS error: bad code byte"));
+        assert_eq!(out[1], "A");
+    }
+
+    #[test]
+    fn nmea_without_line_ends_is_bounded() {
+        let mut nmea = Nmea::default();
+        let chunk = [0xA5u8; 100];
+        let mut out = Vec::new();
+        for _ in 0..MAX_PENDING / chunk.len() + 1 {
+            out.extend(lines(nmea.decode(&chunk)));
+        }
+        assert_eq!(out.len(), 1);
+        assert!(out[0].starts_with("[NMEA error: no line end] A5 A5"));
+        assert!(nmea.buf.len() < MAX_PENDING);
+        let out = lines(nmea.decode(b"$GPGGA,1*4B\r\n"));
+        assert_eq!(out, ["$GPGGA,1*4B  [GGA, checksum ok]"]);
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::template::parse_duration;
+
+/// Line noise for one direction: `flip=1e-4,drop=0.001,dup=0.01,delay=20ms`
+#[derive(Debug, Clone, Default)]
+pub struct FaultSpec {
+    /// Chance of each bit being inverted (bit error rate)
+    pub flip: f64,
+    /// Chance of each byte being lost
+    pub drop: f64,
+    /// Chance of a whole chunk arriving twice
+    pub dup: f64,
+    /// Latency added to every chunk
+    pub delay: Duration,
+    /// Random extra latency, up to this much
+    pub jitter: Duration,
+    /// Fixed seed for reproducible runs
+    pub seed: Option<u64>,
+}
+
+fn parse_rate(key: &str, value: &str) -> Result<f64, String> {
+    let rate: f64 = value
+        .parse()
+        .map_err(|_| format!("bad {key} rate '{value}'"))?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(format!("{key} rate must be between 0 and 1, got {value}"));
+    }
+    Ok(rate)
+}
+
+/// Parse a comma-separated fault spec (clap value parser)
+pub fn parse_fault_spec(s: &str) -> Result<FaultSpec, String> {
+    let mut spec = FaultSpec::default();
+    for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got '{part}'"))?;
+        match key {
+            "flip" => spec.flip = parse_rate(key, value)?,
+            "drop" => spec.drop = parse_rate(key, value)?,
+            "dup" => spec.dup = parse_rate(key, value)?,
+            "delay" => spec.delay = parse_duration(value)?,
+            "jitter" => spec.jitter = parse_duration(value)?,
+            "seed" => spec.seed = Some(value.parse().map_err(|_| format!("bad seed '{value}'"))?),
+            _ => {
+                return Err(format!(
+                    "unknown fault '{key}' (flip, drop, dup, delay, jitter, seed)"
+                ));
+            }
+        }
+    }
+    Ok(spec)
+}
+
+impl FaultSpec {
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.flip > 0.0 {
+            parts.push(format!("flip {}", self.flip));
+        }
+        if self.drop > 0.0 {
+            parts.push(format!("drop {}", self.drop));
+        }
+        if self.dup > 0.0 {
+            parts.push(format!("dup {}", self.dup));
+        }
+        if !self.delay.is_zero() || !self.jitter.is_zero() {
+            let mut delay = format!("delay {}ms", self.delay.as_millis());
+            if !self.jitter.is_zero() {
+                delay.push_str(&format!(" +0..{}ms", self.jitter.as_millis()));
+            }
+            parts.push(delay);
+        }
+        if parts.is_empty() {
+            "none".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// xorshift64*: plenty for noise, no extra dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        // Zero would stay zero forever; any other seed is its own run
+        Rng(if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        })
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.unit() < rate
+    }
+}
+
+/// One direction's noise source
+struct Injector {
+    spec: FaultSpec,
+    rng: Rng,
+}
+
+impl Injector {
+    fn new(spec: FaultSpec) -> Self {
+        let rng = Rng::new(spec.seed);
+        Injector { spec, rng }
+    }
+
+    /// The chunk as it comes out the other end of a bad line
+    fn corrupt(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len());
+        for &byte in data {
+            if self.rng.chance(self.spec.drop) {
+                continue;
+            }
+            let mut byte = byte;
+            for bit in 0..8 {
+                if self.rng.chance(self.spec.flip) {
+                    byte ^= 1 << bit;
+                }
+            }
+            out.push(byte);
+        }
+        if self.rng.chance(self.spec.dup) {
+            out.extend_from_within(..);
+        }
+        out
+    }
+
+    fn latency(&mut self) -> Duration {
+        let jitter = self.spec.jitter.mul_f64(self.rng.unit());
+        self.spec.delay + jitter
+    }
+}
+
+/// `--fault-tx`/`--fault-rx` for every port the session opens, reconnects
+/// and switches included
+static FAULTS: OnceLock<(Option<FaultSpec>, Option<FaultSpec>)> = OnceLock::new();
+
+/// Draws the TX latency, which is waited out before the port is locked
+static TX_DELAY: Mutex<Option<Injector>> = Mutex::new(None);
+
+/// From now on every opened port is wrapped in [`FaultyPort`]
+pub fn enable(tx: Option<FaultSpec>, rx: Option<FaultSpec>) {
+    if tx.is_none() && rx.is_none() {
+        return;
+    }
+    if let Ok(mut delay) = TX_DELAY.lock() {
+        *delay = tx.clone().map(Injector::new);
+    }
+    let _ = FAULTS.set((tx, rx));
+}
+
+/// `port` with the session's faults, if any
+pub fn wrap(port: Box<dyn SerialPort + Send>) -> Box<dyn SerialPort + Send> {
+    match FAULTS.get() {
+        Some((tx, rx)) => Box::new(FaultyPort::new(port, tx.clone(), rx.clone())),
+        None => port,
+    }
+}
+
+/// Latency to add before the next send. The caller sleeps it off without
+/// holding the port, so the reader keeps going meanwhile.
+pub fn tx_delay() -> Duration {
+    TX_DELAY
+        .lock()
+        .ok()
+        .and_then(|mut delay| delay.as_mut().map(Injector::latency))
+        .unwrap_or_default()
+}
+
+/// Wraps the opened port and damages traffic on its way through, for
+/// testing how firmware and host protocols cope with a bad link. Settings
+/// and control lines pass straight to the real port.
+pub struct FaultyPort {
+    inner: Box<dyn SerialPort + Send>,
+    tx: Option<Injector>,
+    rx: Option<Injector>,
+    /// Received chunks held back until their added latency has passed
+    rx_pending: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl FaultyPort {
+    pub fn new(
+        inner: Box<dyn SerialPort + Send>,
+        tx: Option<FaultSpec>,
+        rx: Option<FaultSpec>,
+    ) -> Self {
+        FaultyPort {
+            inner,
+            tx: tx.map(Injector::new),
+            rx: rx.map(Injector::new),
+            rx_pending: VecDeque::new(),
+        }
+    }
+
+    fn rx_due(&self) -> bool {
+        self.rx_pending
+            .front()
+            .is_some_and(|(due, _)| *due <= Instant::now())
+    }
+}
+
+impl io::Read for FaultyPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let waiting = !self.rx_pending.is_empty();
+        let due = self.rx_due();
+        let Some(rx) = self.rx.as_mut() else {
+            return self.inner.read(buf);
+        };
+        if !due {
+            // Nothing due yet: keep the device drained while we wait
+            let mut chunk = vec![0u8; buf.len().max(1)];
+            match self.inner.read(&mut chunk) {
+                Ok(0) => return Ok(0),
+                Ok(n) => {
+                    let data = rx.corrupt(&chunk[..n]);
+                    if !data.is_empty() {
+                        let due = Instant::now() + rx.latency();
+                        self.rx_pending.push_back((due, data));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::TimedOut && waiting => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.rx_due() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "no data due"));
+        }
+        let Some((_, data)) = self.rx_pending.front_mut() else {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "no data due"));
+        };
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        data.drain(..n);
+        if data.is_empty() {
+            self.rx_pending.pop_front();
+        }
+        Ok(n)
+    }
+}
+
+impl io::Write for FaultyPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Latency was added by the sender, see `tx_delay`
+        let Some(tx) = self.tx.as_mut() else {
+            return self.inner.write(buf);
+        };
+        let data = tx.corrupt(buf);
+        self.inner.write_all(&data)?;
+        // Report the caller's bytes as written; the losses are the point
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SerialPort for FaultyPort {
+    fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.inner.baud_rate()
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.inner.data_bits()
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.inner.flow_control()
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.inner.parity()
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.inner.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.inner.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.inner.set_flow_control(flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.inner.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.inner.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.inner.set_timeout(timeout)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.inner.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.inner.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.inner.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.inner.read_carrier_detect()
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_write()
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        self.inner.clear(buffer_to_clear)
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        // A clone would bypass the injector; hand out the real port
+        self.inner.try_clone()
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        self.inner.set_break()
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.inner.clear_break()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn injector(spec: &str) -> Injector {
+        Injector::new(parse_fault_spec(spec).unwrap())
+    }
+
+    #[test]
+    fn seeded_noise_repeats() {
+        let data: Vec<u8> = (0..=255).collect();
+        let first = injector("flip=0.05,drop=0.05,seed=42").corrupt(&data);
+        let second = injector("flip=0.05,drop=0.05,seed=42").corrupt(&data);
+        assert_eq!(first, second);
+        assert_ne!(first, data);
+        let other = injector("flip=0.05,drop=0.05,seed=43").corrupt(&data);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn certain_faults_always_happen() {
+        assert!(injector("drop=1").corrupt(b"hello").is_empty());
+        assert_eq!(injector("dup=1").corrupt(b"hello"), b"hellohello");
+        assert_eq!(injector("flip=1").corrupt(&[0x0F]), [0xF0]);
+        assert_eq!(injector("").corrupt(b"hello"), b"hello");
+    }
+
+    #[test]
+    fn specs_are_checked() {
+        let spec = parse_fault_spec("flip=1e-4, drop=0.5,delay=20ms,seed=7").unwrap();
+        assert_eq!(spec.flip, 1e-4);
+        assert_eq!(spec.drop, 0.5);
+        assert_eq!(spec.delay, Duration::from_millis(20));
+        assert_eq!(spec.seed, Some(7));
+        for bad in [
+            "flip=1.5",
+            "drop=-0.1",
+            "dup=NaN",
+            "drop=lots",
+            "loss=0.1",
+            "flip",
+        ] {
+            assert!(parse_fault_spec(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn delayed_chunks_are_held_until_due() {
+        use std::io::{Read, Write};
+
+        let (mut device, mut port) = serialport::TTYPort::pair().unwrap();
+        port.set_timeout(Duration::from_millis(10)).unwrap();
+        let rx = parse_fault_spec("delay=200ms").unwrap();
+        let mut faulty = FaultyPort::new(Box::new(port), None, Some(rx));
+        let sent = Instant::now();
+        device.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 16];
+        let n = loop {
+            match faulty.read(&mut buf) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    assert!(sent.elapsed() < Duration::from_secs(5), "never delivered");
+                }
+                Err(e) => panic!("{e}"),
+            }
+        };
+        assert!(sent.elapsed() >= Duration::from_millis(200));
+        assert_eq!(&buf[..n], b"hi");
+    }
+}
@@ -0,0 +1,201 @@
+use serialport::{DataBits, FlowControl, Parity, SerialPort, SerialPortInfo, StopBits};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+use crate::permissions;
+use crate::quirks::QuirkDb;
+
+/// Pattern sent for the loopback test: every byte value once
+fn loopback_pattern() -> Vec<u8> {
+    (0..=255u8).collect()
+}
+
+struct Report {
+    failed: bool,
+}
+
+impl Report {
+    fn pass(&self, what: &str, detail: impl AsRef<str>) {
+        println!("  [ OK ] {what}: {}", detail.as_ref());
+    }
+
+    fn fail(&mut self, what: &str, detail: impl AsRef<str>) {
+        self.failed = true;
+        println!("  [FAIL] {what}: {}", detail.as_ref());
+    }
+
+    fn info(&self, what: &str, detail: impl AsRef<str>) {
+        println!("  [INFO] {what}: {}", detail.as_ref());
+    }
+}
+
+fn open_hint(e: &serialport::Error) -> &'static str {
+    match e.kind() {
+        serialport::ErrorKind::NoDevice => " (device gone or in use by another program?)",
+        // Linux gets the details from `permissions::diagnose`
+        serialport::ErrorKind::Io(ErrorKind::PermissionDenied) if !cfg!(target_os = "linux") => {
+            " (permission denied)"
+        }
+        _ => "",
+    }
+}
+
+fn apply_settings(port: &mut dyn SerialPort, report: &mut Report, baud: u32) {
+    let steps: [(&str, serialport::Result<()>); 5] = [
+        ("baud rate", port.set_baud_rate(baud)),
+        ("data bits", port.set_data_bits(DataBits::Eight)),
+        ("parity", port.set_parity(Parity::None)),
+        ("stop bits", port.set_stop_bits(StopBits::One)),
+        ("flow control", port.set_flow_control(FlowControl::None)),
+    ];
+    let mut ok = true;
+    for (what, result) in steps {
+        if let Err(e) = result {
+            report.fail(&format!("set {what}"), e.to_string());
+            ok = false;
+        }
+    }
+    match port.baud_rate() {
+        Ok(actual) if actual == baud => {
+            if ok {
+                report.pass("settings", format!("{baud} 8N1, no flow control"));
+            }
+        }
+        Ok(actual) => report.fail("baud read-back", format!("asked {baud}, got {actual}")),
+        Err(e) => report.info("baud read-back", format!("not supported ({e})")),
+    }
+}
+
+fn read_modem_lines(port: &mut dyn SerialPort, report: &Report) {
+    let mut lines = Vec::new();
+    for (name, level) in [
+        ("CTS", port.read_clear_to_send()),
+        ("DSR", port.read_data_set_ready()),
+        ("CD", port.read_carrier_detect()),
+        ("RI", port.read_ring_indicator()),
+    ] {
+        match level {
+            Ok(level) => lines.push(format!("{name}={}", u8::from(level))),
+            Err(_) => lines.push(format!("{name}=?")),
+        }
+    }
+    report.info("modem inputs", lines.join(" "));
+}
+
+fn loopback_test(port: &mut dyn SerialPort, report: &mut Report) {
+    let pattern = loopback_pattern();
+    let _ = port.clear(serialport::ClearBuffer::All);
+    let started = Instant::now();
+    if let Err(e) = port.write_all(&pattern).and_then(|_| port.flush()) {
+        report.fail("loopback write", e.to_string());
+        return;
+    }
+
+    let mut received = Vec::with_capacity(pattern.len());
+    let mut buf = [0u8; 256];
+    while received.len() < pattern.len() && started.elapsed() < Duration::from_secs(2) {
+        match port.read(&mut buf) {
+            Ok(n) => received.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => {
+                report.fail("loopback read", e.to_string());
+                return;
+            }
+        }
+    }
+    let elapsed = started.elapsed();
+
+    if received.is_empty() {
+        report.fail("loopback", "nothing came back (is TX jumpered to RX?)");
+    } else if received == pattern {
+        report.pass(
+            "loopback",
+            format!(
+                "{} bytes echoed intact in {:.1} ms",
+                pattern.len(),
+                elapsed.as_secs_f64() * 1000.0
+            ),
+        );
+    } else {
+        let first_bad = received
+            .iter()
+            .zip(&pattern)
+            .position(|(a, b)| a != b)
+            .unwrap_or(received.len().min(pattern.len()));
+        report.fail(
+            "loopback",
+            format!(
+                "got {}/{} bytes, first difference at byte {first_bad}",
+                received.len(),
+                pattern.len()
+            ),
+        );
+    }
+}
+
+/// `sermonizer check`: open the port, apply settings, optionally run a
+/// loopback test, and print a diagnostic report. Returns whether every
+/// check passed.
+pub fn run_check(
+    port_name: &str,
+    baud: u32,
+    loopback: bool,
+    ports: &[SerialPortInfo],
+    quirks: &QuirkDb,
+) -> bool {
+    let mut report = Report { failed: false };
+    println!("Checking {port_name} at {baud} baud");
+
+    match ports.iter().find(|p| p.port_name == port_name) {
+        Some(info) => {
+            report.pass("enumerated", "port is listed by the OS");
+            if let Some(quirk) = quirks.for_port(info) {
+                report.info("adapter", &quirk.name);
+                if let Some(max) = quirk.max_baud
+                    && baud > max
+                {
+                    report.fail("baud", format!("above the reliable maximum of {max}"));
+                }
+            }
+        }
+        None => report.info("enumerated", "not among detected USB ports"),
+    }
+
+    let mut port = match serialport::new(port_name, baud)
+        .timeout(Duration::from_millis(100))
+        .open()
+    {
+        Ok(port) => {
+            report.pass("open", "ok");
+            port
+        }
+        Err(e) => {
+            let hint = open_hint(&e);
+            report.fail("open", format!("{e}{hint}"));
+            if e.kind() == serialport::ErrorKind::Io(ErrorKind::PermissionDenied) {
+                for line in permissions::diagnose(port_name) {
+                    report.info("permissions", line);
+                }
+            }
+            return false;
+        }
+    };
+
+    apply_settings(port.as_mut(), &mut report, baud);
+    read_modem_lines(port.as_mut(), &report);
+    if loopback {
+        loopback_test(port.as_mut(), &mut report);
+    } else {
+        report.info("loopback", "skipped (jumper TX to RX and pass --loopback)");
+    }
+
+    println!(
+        "{}",
+        if report.failed {
+            "Result: problems found"
+        } else {
+            "Result: host side looks healthy"
+        }
+    );
+    !report.failed
+}
@@ -0,0 +1,53 @@
+use anyhow::{Context, Result, bail};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// For connecting and for each read and write, so a dead host can't hang
+/// the caller
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Minimal HTTP/1.0 request, enough for relay boards, PDUs and metric
+/// endpoints; fails unless the reply is 2xx
+pub fn request(method: &str, url: &str, headers: &[(&str, String)], body: &[u8]) -> Result<()> {
+    let rest = url.trim_start_matches("http://");
+    let (host_port, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let addr = if host_port.contains(':') {
+        host_port.to_string()
+    } else {
+        format!("{host_port}:80")
+    };
+    let host = host_port.split(':').next().unwrap_or(host_port);
+
+    let socket_addr = addr
+        .to_socket_addrs()
+        .with_context(|| format!("Failed to resolve {addr}"))?
+        .next()
+        .with_context(|| format!("No address for {addr}"))?;
+    let mut stream = TcpStream::connect_timeout(&socket_addr, TIMEOUT)
+        .with_context(|| format!("Failed to connect to {addr}"))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut head = format!("{method} {path} HTTP/1.0\r\nHost: {host}\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    let status_line = response.lines().next().unwrap_or("");
+    let code = status_line.split_whitespace().nth(1).unwrap_or("");
+    if !code.starts_with('2') {
+        bail!("{method} {url} failed: {status_line}");
+    }
+    Ok(())
+}
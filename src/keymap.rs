@@ -0,0 +1,114 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+
+/// Key names accepted by `--key`, with the sequence a VT100/xterm sends
+const KEYS: [(&str, KeyCode, &str); 24] = [
+    ("up", KeyCode::Up, "\x1b[A"),
+    ("down", KeyCode::Down, "\x1b[B"),
+    ("right", KeyCode::Right, "\x1b[C"),
+    ("left", KeyCode::Left, "\x1b[D"),
+    ("home", KeyCode::Home, "\x1b[H"),
+    ("end", KeyCode::End, "\x1b[F"),
+    ("insert", KeyCode::Insert, "\x1b[2~"),
+    ("delete", KeyCode::Delete, "\x1b[3~"),
+    ("pageup", KeyCode::PageUp, "\x1b[5~"),
+    ("pagedown", KeyCode::PageDown, "\x1b[6~"),
+    ("tab", KeyCode::Tab, "\t"),
+    ("backspace", KeyCode::Backspace, "\x7f"),
+    ("f1", KeyCode::F(1), "\x1bOP"),
+    ("f2", KeyCode::F(2), "\x1bOQ"),
+    ("f3", KeyCode::F(3), "\x1bOR"),
+    ("f4", KeyCode::F(4), "\x1bOS"),
+    ("f5", KeyCode::F(5), "\x1b[15~"),
+    ("f6", KeyCode::F(6), "\x1b[17~"),
+    ("f7", KeyCode::F(7), "\x1b[18~"),
+    ("f8", KeyCode::F(8), "\x1b[19~"),
+    ("f9", KeyCode::F(9), "\x1b[20~"),
+    ("f10", KeyCode::F(10), "\x1b[21~"),
+    ("f11", KeyCode::F(11), "\x1b[23~"),
+    ("esc", KeyCode::Esc, "\x1b"),
+];
+
+/// Decode `\e`, `\xNN`, `\r`, `\n`, `\t`, `\0` and `\\` in a sequence
+pub fn parse_sequence(s: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('e') => bytes.push(0x1b),
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| format!("bad \\x escape '\\x{hex}' in '{s}'"))?;
+                bytes.push(byte);
+            }
+            Some(other) => return Err(format!("unknown escape '\\{other}' in '{s}'")),
+            None => return Err(format!("trailing backslash in '{s}'")),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Parse `KEY` or `KEY=SEQUENCE` for `--key`; a bare key sends its
+/// standard VT100/xterm sequence
+pub fn parse_key_binding(s: &str) -> Result<(KeyCode, Vec<u8>), String> {
+    let (name, sequence) = match s.split_once('=') {
+        Some((name, sequence)) => (name, Some(sequence)),
+        None => (s, None),
+    };
+    let name = name.trim().to_ascii_lowercase();
+    if name == "f12" {
+        return Err("F12 switches passthrough off and can't be mapped".to_string());
+    }
+    let Some((_, key, standard)) = KEYS.iter().find(|(n, _, _)| *n == name) else {
+        let names: Vec<&str> = KEYS.iter().map(|(n, _, _)| *n).collect();
+        return Err(format!(
+            "unknown key '{name}' (expected one of {})",
+            names.join(", ")
+        ));
+    };
+    let bytes = match sequence {
+        Some(sequence) => parse_sequence(sequence)?,
+        None => standard.as_bytes().to_vec(),
+    };
+    if bytes.is_empty() {
+        return Err(format!("empty sequence for '{name}'"));
+    }
+    Ok((*key, bytes))
+}
+
+/// Keys forwarded to the device as escape sequences in passthrough mode,
+/// ahead of their local meaning (F-keys, scrolling)
+#[derive(Debug, Clone, Default)]
+pub struct KeyMap {
+    keys: HashMap<KeyCode, Vec<u8>>,
+}
+
+impl KeyMap {
+    pub fn new(bindings: impl IntoIterator<Item = (KeyCode, Vec<u8>)>) -> Self {
+        KeyMap {
+            keys: bindings.into_iter().collect(),
+        }
+    }
+
+    /// The sequence for `key`, if it is mapped and pressed without Ctrl/Alt
+    pub fn get(&self, key: &KeyEvent) -> Option<&[u8]> {
+        if key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return None;
+        }
+        self.keys.get(&key.code).map(Vec::as_slice)
+    }
+}
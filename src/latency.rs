@@ -0,0 +1,143 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+fn format_ms(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
+
+/// Min/avg/max over a set of samples
+fn stats(samples: &[Duration]) -> String {
+    let min = samples.iter().min().copied().unwrap_or_default();
+    let max = samples.iter().max().copied().unwrap_or_default();
+    let avg = samples.iter().sum::<Duration>() / samples.len().max(1) as u32;
+    format!(
+        "n={} min {} avg {} max {}",
+        samples.len(),
+        format_ms(min),
+        format_ms(avg),
+        format_ms(max)
+    )
+}
+
+/// A request waiting for its response
+struct Pending {
+    command: String,
+    sent_at: Instant,
+    /// The device's echo of the command went by already
+    echoed: bool,
+}
+
+/// Times each sent line until the first RX line that answers it (any line,
+/// or only lines matching `response`). The device echoing the command back
+/// is not an answer.
+pub struct LatencyTracker {
+    response: Option<Regex>,
+    pending: Option<Pending>,
+    samples: BTreeMap<String, Vec<Duration>>,
+    unanswered: usize,
+}
+
+impl LatencyTracker {
+    pub fn new(response: Option<Regex>) -> Self {
+        Self {
+            response,
+            pending: None,
+            samples: BTreeMap::new(),
+            unanswered: 0,
+        }
+    }
+
+    /// A line was sent; a previous request still waiting counts as unanswered
+    pub fn on_send(&mut self, command: &str) {
+        if self.pending.is_some() {
+            self.unanswered += 1;
+        }
+        self.pending = Some(Pending {
+            command: command.trim().to_string(),
+            sent_at: Instant::now(),
+            echoed: false,
+        });
+    }
+
+    /// Feed a received line; returns an annotation when it answers the
+    /// pending request
+    pub fn on_line(&mut self, line: &str) -> Option<String> {
+        let pending = self.pending.as_mut()?;
+        if !pending.echoed && line.trim() == pending.command {
+            pending.echoed = true;
+            return None;
+        }
+        if self.response.as_ref().is_some_and(|re| !re.is_match(line)) {
+            return None;
+        }
+        let Pending {
+            command, sent_at, ..
+        } = self.pending.take()?;
+        let elapsed = sent_at.elapsed();
+        let note = format!("-- [latency] {command}: {} --", format_ms(elapsed));
+        self.samples.entry(command).or_default().push(elapsed);
+        Some(note)
+    }
+
+    /// Aggregate line for the status bar
+    pub fn summary(&self) -> String {
+        let all: Vec<Duration> = self.samples.values().flatten().copied().collect();
+        format!("Latency {}", stats(&all))
+    }
+
+    /// Per-command statistics, printed on exit
+    pub fn report(&self) -> Vec<String> {
+        if self.samples.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = vec!["Latency report:".to_string()];
+        for (command, samples) in &self.samples {
+            lines.push(format!("  {:<24} {}", command, stats(samples)));
+        }
+        let all: Vec<Duration> = self.samples.values().flatten().copied().collect();
+        lines.push(format!("  {:<24} {}", "(all)", stats(&all)));
+        if self.unanswered > 0 {
+            lines.push(format!(
+                "  {} request(s) without a response",
+                self.unanswered
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn echo_is_not_the_response() {
+        let mut latency = LatencyTracker::new(None);
+        latency.on_send("AT+CSQ");
+        assert_eq!(latency.on_line("AT+CSQ"), None);
+        let note = latency.on_line("+CSQ: 20,99").unwrap();
+        assert!(note.starts_with("-- [latency] AT+CSQ: "), "{note}");
+        assert_eq!(latency.on_line("OK"), None);
+    }
+
+    #[test]
+    fn without_echo_the_first_line_answers() {
+        let mut latency = LatencyTracker::new(None);
+        latency.on_send("version");
+        assert!(latency.on_line("v1.2.3").is_some());
+    }
+
+    #[test]
+    fn response_pattern_and_unanswered_requests() {
+        let mut latency = LatencyTracker::new(Some(Regex::new("^(OK|ERROR)$").unwrap()));
+        latency.on_send("AT");
+        latency.on_send("ATI");
+        assert_eq!(latency.on_line("ATI"), None);
+        assert_eq!(latency.on_line("Quectel"), None);
+        assert!(latency.on_line("OK").is_some());
+        let report = latency.report();
+        assert!(report.iter().any(|l| l.trim_start().starts_with("ATI ")));
+        assert!(report.iter().any(|l| l.contains("1 request(s) without")));
+    }
+}
@@ -0,0 +1,102 @@
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Severity of a received log line, lowest first
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Map the text captured by a `level` group onto a severity
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token.to_ascii_uppercase().as_str() {
+            "T" | "V" | "TRACE" | "VERBOSE" => Some(Level::Trace),
+            "D" | "DBG" | "DEBUG" => Some(Level::Debug),
+            "I" | "INF" | "INFO" | "NOTICE" => Some(Level::Info),
+            "W" | "WRN" | "WARN" | "WARNING" => Some(Level::Warn),
+            "E" | "ERR" | "ERROR" | "CRIT" | "CRITICAL" | "FATAL" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+
+    /// Next minimum-level setting: off -> DEBUG -> INFO -> WARN -> ERROR -> off
+    /// (TRACE as a minimum hides nothing, so it is skipped)
+    pub fn cycle_min(current: Option<Level>) -> Option<Level> {
+        match current {
+            None | Some(Level::Trace) => Some(Level::Debug),
+            Some(Level::Debug) => Some(Level::Info),
+            Some(Level::Info) => Some(Level::Warn),
+            Some(Level::Warn) => Some(Level::Error),
+            Some(Level::Error) => None,
+        }
+    }
+}
+
+/// Patterns used when no --level-pattern is given: plain `INFO`/`[WARN]`
+/// style words and ESP-IDF / Zephyr single-letter prefixes (`E (123) tag:`)
+const DEFAULT_LEVEL_PATTERNS: &[&str] = &[
+    r"\b(?P<level>TRACE|DEBUG|INFO|WARN(?:ING)?|ERROR|FATAL)\b",
+    r"^(?P<level>[EWIDV]) \(\d+\)",
+    r"<(?P<level>dbg|inf|wrn|err)>",
+];
+
+/// Extracts a [`Level`] from a line using the first matching pattern
+/// with a `level` named group
+#[derive(Debug, Clone)]
+pub struct LevelParser {
+    patterns: Vec<Regex>,
+}
+
+impl LevelParser {
+    pub fn new(patterns: Vec<Regex>) -> Self {
+        if patterns.is_empty() {
+            return Self::default();
+        }
+        Self { patterns }
+    }
+
+    pub fn parse(&self, line: &str) -> Option<Level> {
+        self.patterns.iter().find_map(|re| {
+            re.captures(line)
+                .and_then(|c| c.name("level"))
+                .and_then(|m| Level::from_token(m.as_str()))
+        })
+    }
+}
+
+impl Default for LevelParser {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_LEVEL_PATTERNS
+                .iter()
+                .map(|p| Regex::new(p).expect("built-in level pattern is valid"))
+                .collect(),
+        }
+    }
+}
+
+/// Parse a level regex (clap value parser); it must have a `level` group
+pub fn parse_level_pattern(s: &str) -> Result<Regex, String> {
+    let re = Regex::new(s).map_err(|e| format!("invalid regex: {e}"))?;
+    if !re.capture_names().any(|n| n == Some("level")) {
+        return Err("pattern needs a named group (?P<level>...)".to_string());
+    }
+    Ok(re)
+}
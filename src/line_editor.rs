@@ -0,0 +1,151 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::LineEnding;
+
+/// RX lines that must look like `<prompt><sent line>` before the device is
+/// taken to run its own line editor
+const ECHOES_NEEDED: u32 = 2;
+
+/// Cursor movement and erase sequences line editors use to redraw the
+/// input line: backspace-space-backspace, erase to end of line, cursor left
+const EDITOR_SEQUENCES: [&str; 3] = ["\x08 \x08", "\x1b[K", "\x1b[D"];
+
+/// Watches RX for signs of a device-side line editor (readline, linenoise,
+/// microrl, Zephyr shell, ...): redraw sequences, or sent lines coming back
+/// behind a prompt.
+#[derive(Default)]
+pub struct LineEditorDetector {
+    last_sent: Option<String>,
+    echoes: u32,
+    detected: bool,
+}
+
+impl LineEditorDetector {
+    pub fn on_send(&mut self, line: &str) {
+        let line = line.trim();
+        if !line.is_empty() {
+            self.last_sent = Some(line.to_string());
+        }
+    }
+
+    /// Raw received text, before it is split into lines
+    pub fn on_text(&mut self, text: &str) {
+        if EDITOR_SEQUENCES.iter().any(|seq| text.contains(seq)) {
+            self.detected = true;
+        }
+    }
+
+    /// A complete received line
+    pub fn on_line(&mut self, line: &str) {
+        let line = line.trim_end();
+        if let Some(sent) = &self.last_sent
+            && line.len() > sent.len()
+            && line.ends_with(sent.as_str())
+        {
+            self.last_sent = None;
+            self.echoes += 1;
+            if self.echoes >= ECHOES_NEEDED {
+                self.detected = true;
+            }
+        }
+    }
+
+    pub fn detected(&self) -> bool {
+        self.detected
+    }
+}
+
+/// What the device's line editor shows, from the raw text it sent since the
+/// last line break: carriage returns, backspaces, cursor left/right and
+/// erase-to-end are applied, other escape sequences dropped. Returns the
+/// text and the cursor column.
+pub fn render_edit_line(raw: &str) -> (String, usize) {
+    let mut line: Vec<char> = Vec::new();
+    let mut col: usize = 0;
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => col = 0,
+            '\x08' => col = col.saturating_sub(1),
+            '\x1b' => {
+                if chars.peek() != Some(&'[') {
+                    chars.next();
+                    continue;
+                }
+                chars.next();
+                let mut param = String::new();
+                let mut command = None;
+                for c in chars.by_ref() {
+                    if c.is_ascii_digit() || c == ';' || c == '?' {
+                        param.push(c);
+                    } else {
+                        command = Some(c);
+                        break;
+                    }
+                }
+                let n = param.parse::<usize>().unwrap_or(1).max(1);
+                match command {
+                    Some('D') => col = col.saturating_sub(n),
+                    Some('C') => col = (col + n).min(line.len()),
+                    Some('K') => line.truncate(col),
+                    _ => {}
+                }
+            }
+            c if c.is_control() => {}
+            c => {
+                if col < line.len() {
+                    line[col] = c;
+                } else {
+                    line.push(c);
+                }
+                col += 1;
+            }
+        }
+    }
+    (line.into_iter().collect(), col)
+}
+
+/// Ctrl+], which in passthrough hands the next key to sermonizer instead
+/// of the device (twice sends it)
+pub fn is_passthrough_escape(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && matches!(key.code, KeyCode::Char(']' | '5'))
+}
+
+/// Bytes a key sends in passthrough mode, as a terminal would send them;
+/// `None` for keys sermonizer keeps for itself
+pub fn key_bytes(key: &KeyEvent, line_ending: LineEnding) -> Option<Vec<u8>> {
+    let bytes: &[u8] = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            let byte = match c.to_ascii_lowercase() {
+                c @ 'a'..='z' => c as u8 - b'a' + 1,
+                // Without keyboard enhancement crossterm reports these as
+                // Ctrl+4..7
+                '\\' | '4' => 0x1C,
+                ']' | '5' => 0x1D,
+                '^' | '6' => 0x1E,
+                '_' | '7' => 0x1F,
+                _ => return None,
+            };
+            return Some(vec![byte]);
+        }
+        KeyCode::Char(c) => {
+            let mut buf = [0u8; 4];
+            return Some(c.encode_utf8(&mut buf).as_bytes().to_vec());
+        }
+        KeyCode::Enter => match line_ending {
+            LineEnding::None => b"\r",
+            end => end.bytes(),
+        },
+        KeyCode::Backspace => b"\x7f",
+        KeyCode::Tab => b"\t",
+        KeyCode::Up => b"\x1b[A",
+        KeyCode::Down => b"\x1b[B",
+        KeyCode::Right => b"\x1b[C",
+        KeyCode::Left => b"\x1b[D",
+        KeyCode::Home => b"\x1b[H",
+        KeyCode::End => b"\x1b[F",
+        KeyCode::Delete => b"\x1b[3~",
+        _ => return None,
+    };
+    Some(bytes.to_vec())
+}
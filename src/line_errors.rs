@@ -0,0 +1,135 @@
+/// Line error counters reported by the serial driver, relative to when
+/// sermonizer opened the port
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineErrorCounts {
+    pub framing: u32,
+    pub parity: u32,
+    pub overrun: u32,
+    /// Driver receive buffer overruns
+    pub buffer_overrun: u32,
+    pub breaks: u32,
+}
+
+impl LineErrorCounts {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Nonzero counters, e.g. "framing 3, overrun 1"
+    pub fn describe(&self) -> String {
+        [
+            ("framing", self.framing),
+            ("parity", self.parity),
+            ("overrun", self.overrun),
+            ("buffer overrun", self.buffer_overrun),
+            ("break", self.breaks),
+        ]
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(name, n)| format!("{name} {n}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::LineErrorCounts;
+    use crate::config::UiConfig;
+    use crate::reconnect::PortGeneration;
+    use crate::ui::UiMessage;
+    use std::os::fd::RawFd;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// How often the counters are read unless `--status-poll` says otherwise
+    const POLL: Duration = Duration::from_millis(500);
+
+    /// `struct serial_icounter_struct` from <linux/serial.h>
+    #[repr(C)]
+    #[derive(Default)]
+    struct SerialIcounter {
+        cts: libc::c_int,
+        dsr: libc::c_int,
+        rng: libc::c_int,
+        dcd: libc::c_int,
+        rx: libc::c_int,
+        tx: libc::c_int,
+        frame: libc::c_int,
+        overrun: libc::c_int,
+        parity: libc::c_int,
+        brk: libc::c_int,
+        buf_overrun: libc::c_int,
+        reserved: [libc::c_int; 9],
+    }
+
+    fn read_counters(fd: RawFd) -> std::io::Result<SerialIcounter> {
+        let mut counters = SerialIcounter::default();
+        // SAFETY: TIOCGICOUNT fills a serial_icounter_struct, which
+        // `SerialIcounter` mirrors; `fd` is the open port
+        let rc = unsafe { libc::ioctl(fd, libc::TIOCGICOUNT, &mut counters) };
+        if rc < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(counters)
+    }
+
+    /// Watch the port `ui_config` has open now. A reconnect or port switch
+    /// ends the watch; start another one for the new port then.
+    pub fn watch_line_errors(ui_config: &UiConfig) {
+        let generation = ui_config.port_generation.clone();
+        let spawned_at = generation.current();
+        tokio::spawn(watch(
+            ui_config.port_fd,
+            ui_config.polling.status,
+            generation,
+            spawned_at,
+            ui_config.running.clone(),
+            ui_config.ui_tx.clone(),
+        ));
+    }
+
+    /// Poll the driver's error counters (every `interval`, 500ms by default)
+    /// and report changes to the UI, as long as `fd` is the session's port.
+    /// Gives up quietly if the driver doesn't support TIOCGICOUNT.
+    async fn watch(
+        fd: RawFd,
+        interval: Option<Duration>,
+        generation: PortGeneration,
+        spawned_at: u64,
+        running: Arc<AtomicBool>,
+        ui_tx: mpsc::UnboundedSender<UiMessage>,
+    ) {
+        let Ok(base) = read_counters(fd) else {
+            return;
+        };
+        let mut last = LineErrorCounts::default();
+        while running.load(Ordering::SeqCst) {
+            tokio::time::sleep(interval.unwrap_or(POLL)).await;
+            // The port was replaced; `fd` may be closed or reused by now
+            if generation.current() != spawned_at {
+                return;
+            }
+            let Ok(now) = read_counters(fd) else {
+                return;
+            };
+            let delta = |now: libc::c_int, base: libc::c_int| now.wrapping_sub(base) as u32;
+            let counts = LineErrorCounts {
+                framing: delta(now.frame, base.frame),
+                parity: delta(now.parity, base.parity),
+                overrun: delta(now.overrun, base.overrun),
+                buffer_overrun: delta(now.buf_overrun, base.buf_overrun),
+                breaks: delta(now.brk, base.brk),
+            };
+            if counts != last {
+                last = counts;
+                let _ = ui_tx.send(UiMessage::LineErrors(counts));
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::watch_line_errors;
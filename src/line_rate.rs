@@ -0,0 +1,54 @@
+/// Bytes judged per verdict
+const WINDOW: usize = 512;
+
+/// Share of implausible bytes above which the window looks like a baud
+/// mismatch rather than text
+const GARBAGE_RATIO: f64 = 0.25;
+
+/// Spots the classic wrong-baud symptom: text that decodes to invalid
+/// UTF-8 and stray control characters
+#[derive(Default)]
+pub struct LineRateCheck {
+    seen: usize,
+    suspicious: usize,
+    warned: bool,
+}
+
+impl LineRateCheck {
+    /// Feed raw RX; returns a hint the first time a window looks garbled
+    pub fn feed(&mut self, bytes: &[u8]) -> Option<String> {
+        if self.warned {
+            return None;
+        }
+        for chunk in bytes.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                self.seen += c.len_utf8();
+                if c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x1b' | '\x08') {
+                    self.suspicious += c.len_utf8();
+                }
+            }
+            self.seen += chunk.invalid().len();
+            self.suspicious += chunk.invalid().len();
+        }
+        if self.seen < WINDOW {
+            return None;
+        }
+
+        let ratio = self.suspicious as f64 / self.seen as f64;
+        self.seen = 0;
+        self.suspicious = 0;
+        if ratio < GARBAGE_RATIO {
+            return None;
+        }
+        self.warned = true;
+        Some(format!(
+            "RX looks garbled ({:.0}% non-text bytes) - wrong baud rate? F2 to change it",
+            ratio * 100.0
+        ))
+    }
+
+    /// Start over, e.g. after the baud rate changed
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
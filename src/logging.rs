@@ -1,9 +1,12 @@
 use anyhow::{Context, Result};
 use std::fs::OpenOptions;
 use std::io::BufWriter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+use crate::config::RxLineEnding;
+use crate::port_discovery::short_name;
+
 pub type LogWriter = Arc<Mutex<BufWriter<std::fs::File>>>;
 
 pub fn create_log_writer(path: &PathBuf, log_type: &str) -> Result<LogWriter> {
@@ -13,7 +16,7 @@ pub fn create_log_writer(path: &PathBuf, log_type: &str) -> Result<LogWriter> {
         .open(path)
         .with_context(|| format!("Failed to open {} log file: {}", log_type, path.display()))?;
 
-    println!("Logging {} to: {}", log_type, path.display());
+    say!("Logging {} to: {}", log_type, path.display());
     Ok(Arc::new(Mutex::new(BufWriter::new(file))))
 }
 
@@ -30,3 +33,199 @@ pub fn create_tx_log_writer(path: Option<&PathBuf>) -> Result<Option<LogWriter>>
         None => Ok(None),
     }
 }
+
+/// Log file for another port's tab: the port's name goes before the
+/// extension, `rx.log` -> `rx-ttyUSB1.log`
+pub fn tab_log_path(path: &Path, port_name: &str) -> PathBuf {
+    let port = short_name(port_name);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name = format!("{stem}-{port}");
+    if let Some(ext) = path.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Timestamp format used for `--log-ts` prefixes
+pub const LOG_TS_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// Append a chunk to a log file, optionally prefixed with a timestamp
+pub fn write_log_chunk(writer: &LogWriter, bytes: &[u8], log_ts: bool) {
+    if let Ok(mut lw) = writer.lock() {
+        use std::io::Write;
+        if log_ts {
+            let _ = write!(lw, "[{}] ", chrono::Utc::now().format(LOG_TS_FORMAT));
+        }
+        let _ = lw.write_all(bytes);
+        let _ = lw.flush();
+    }
+}
+
+/// Bytes without a line ending held back before they are written anyway
+const MAX_PARTIAL: usize = 64 * 1024;
+
+/// Collapses runs of identical lines written to a log into a single
+/// "last message repeated N times" entry
+#[derive(Default)]
+pub struct LineDedup {
+    /// Where lines end, as in the output view
+    ending: RxLineEnding,
+    partial: Vec<u8>,
+    last: Option<Vec<u8>>,
+    repeats: usize,
+}
+
+/// A line without its line ending, for comparing lines split on CR that
+/// start with the LF of a CRLF
+fn line_text(line: &[u8]) -> &[u8] {
+    let is_text = |b: &u8| !matches!(b, b'\r' | b'\n');
+    let start = line.iter().position(is_text).unwrap_or(line.len());
+    let end = line.iter().rposition(is_text).map_or(start, |pos| pos + 1);
+    &line[start..end]
+}
+
+impl LineDedup {
+    /// Split lines on `ending` (`--rx-line-ending`)
+    pub fn new(ending: RxLineEnding) -> Self {
+        LineDedup {
+            ending,
+            ..Default::default()
+        }
+    }
+
+    /// Length of the first complete line in `partial`, line ending included
+    fn line_len(&self) -> Option<usize> {
+        let bytes = &self.partial;
+        match self.ending {
+            RxLineEnding::Lf => bytes.iter().position(|&b| b == b'\n').map(|pos| pos + 1),
+            RxLineEnding::Cr => bytes.iter().position(|&b| b == b'\r').map(|pos| pos + 1),
+            RxLineEnding::Crlf => bytes
+                .windows(2)
+                .position(|w| w == b"\r\n")
+                .map(|pos| pos + 2),
+            RxLineEnding::Any => {
+                let pos = bytes.iter().position(|&b| b == b'\r' || b == b'\n')?;
+                match &bytes[pos..] {
+                    [b'\r', b'\n', ..] => Some(pos + 2),
+                    // Could be the first half of CRLF
+                    [b'\r'] => None,
+                    _ => Some(pos + 1),
+                }
+            }
+        }
+    }
+
+    /// Feed raw bytes and get back the complete lines (with their line
+    /// endings) that should actually be written
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.partial.extend_from_slice(bytes);
+        let mut out = Vec::new();
+        while let Some(len) = self.line_len() {
+            let line: Vec<u8> = self.partial.drain(..len).collect();
+            if self
+                .last
+                .as_ref()
+                .is_some_and(|last| line_text(last) == line_text(&line))
+            {
+                self.repeats += 1;
+                continue;
+            }
+            out.extend(self.take_summary());
+            self.last = Some(line.clone());
+            out.push(line);
+        }
+        // A device that never ends its lines must not grow this forever
+        if self.partial.len() >= MAX_PARTIAL {
+            out.extend(self.take_summary());
+            self.last = None;
+            out.push(std::mem::take(&mut self.partial));
+        }
+        out
+    }
+
+    /// Flush the pending repeat summary and any unterminated line
+    pub fn finish(&mut self) -> Vec<Vec<u8>> {
+        let mut out: Vec<Vec<u8>> = self.take_summary().into_iter().collect();
+        if !self.partial.is_empty() {
+            out.push(std::mem::take(&mut self.partial));
+        }
+        out
+    }
+
+    fn take_summary(&mut self) -> Option<Vec<u8>> {
+        if self.repeats == 0 {
+            return None;
+        }
+        let summary = format!("last message repeated {} times\n", self.repeats);
+        self.repeats = 0;
+        Some(summary.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(dedup: &mut LineDedup, chunks: &[&[u8]]) -> Vec<u8> {
+        let mut out: Vec<u8> = chunks.iter().flat_map(|c| dedup.feed(c).concat()).collect();
+        out.extend(dedup.finish().concat());
+        out
+    }
+
+    #[test]
+    fn repeats_are_collapsed() {
+        let mut dedup = LineDedup::default();
+        let out = feed_all(&mut dedup, &[b"a\na\na\nb\n", b"b\nc"]);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a\nlast message repeated 2 times\nb\nlast message repeated 1 times\nc"
+        );
+    }
+
+    #[test]
+    fn cr_lines_are_split_on_cr() {
+        let mut dedup = LineDedup::new(RxLineEnding::Cr);
+        let out = feed_all(&mut dedup, &[b"ping\rping\rpong\r"]);
+        assert_eq!(out, b"ping\rlast message repeated 1 times\npong\r".to_vec());
+        // Without the setting nothing ends a line
+        let mut dedup = LineDedup::default();
+        assert!(dedup.feed(b"ping\rping\rping\r").is_empty());
+    }
+
+    #[test]
+    fn crlf_split_across_chunks() {
+        let mut dedup = LineDedup::new(RxLineEnding::Crlf);
+        let out = feed_all(&mut dedup, &[b"OK\r", b"\nOK\r\n", b"A\rB\r\n"]);
+        assert_eq!(
+            out,
+            b"OK\r\nlast message repeated 1 times\nA\rB\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn any_ending_waits_for_a_possible_lf() {
+        let mut dedup = LineDedup::new(RxLineEnding::Any);
+        assert!(dedup.feed(b"x\r").is_empty());
+        assert_eq!(dedup.feed(b"\nx\n"), [b"x\r\n".to_vec()]);
+        assert_eq!(
+            dedup.finish(),
+            [b"last message repeated 1 times\n".to_vec()]
+        );
+    }
+
+    #[test]
+    fn partial_line_is_bounded() {
+        let mut dedup = LineDedup::default();
+        let chunk = [b'x'; 4096];
+        let mut written = 0;
+        for _ in 0..MAX_PARTIAL / chunk.len() + 1 {
+            written += dedup.feed(&chunk).concat().len();
+            assert!(dedup.partial.len() < MAX_PARTIAL);
+        }
+        assert_eq!(written, MAX_PARTIAL);
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::os::fd::{AsRawFd, RawFd};
+    use std::path::{Path, PathBuf};
+
+    /// ASYNC_LOW_LATENCY from <linux/tty_flags.h>
+    const ASYNC_LOW_LATENCY: libc::c_int = 1 << 13;
+
+    /// `struct serial_struct` from <linux/serial.h>
+    #[repr(C)]
+    struct SerialStruct {
+        kind: libc::c_int,
+        line: libc::c_int,
+        port: libc::c_uint,
+        irq: libc::c_int,
+        flags: libc::c_int,
+        xmit_fifo_size: libc::c_int,
+        custom_divisor: libc::c_int,
+        baud_base: libc::c_int,
+        close_delay: libc::c_ushort,
+        io_type: libc::c_char,
+        reserved_char: [libc::c_char; 1],
+        hub6: libc::c_int,
+        closing_wait: libc::c_ushort,
+        closing_wait2: libc::c_ushort,
+        iomem_base: *mut libc::c_uchar,
+        iomem_reg_shift: libc::c_ushort,
+        port_high: libc::c_uint,
+        iomap_base: libc::c_ulong,
+    }
+
+    fn set_async_low_latency(fd: RawFd) -> std::io::Result<()> {
+        // SAFETY: all-zero is a valid serial_struct; TIOCGSERIAL fills it
+        // and TIOCSSERIAL reads it back, `fd` being the open port
+        unsafe {
+            let mut serial: SerialStruct = std::mem::zeroed();
+            if libc::ioctl(fd, libc::TIOCGSERIAL, &mut serial) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            serial.flags |= ASYNC_LOW_LATENCY;
+            if libc::ioctl(fd, libc::TIOCSSERIAL, &serial) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// sysfs knob of FTDI adapters (default 16 ms)
+    fn ftdi_latency_timer(port_name: &str) -> Option<PathBuf> {
+        let name = Path::new(port_name).file_name()?;
+        let path = Path::new("/sys/bus/usb-serial/devices")
+            .join(name)
+            .join("latency_timer");
+        path.exists().then_some(path)
+    }
+
+    /// `--low-latency`: undo the ~16 ms batching of USB-serial adapters.
+    /// Returns notes for the startup banner.
+    pub fn apply(port: &impl AsRawFd, port_name: &str) -> Vec<String> {
+        let mut notes = Vec::new();
+        match set_async_low_latency(port.as_raw_fd()) {
+            Ok(()) => notes.push("Low latency: ASYNC_LOW_LATENCY set".to_string()),
+            Err(e) => notes.push(format!(
+                "Low latency: driver refused ASYNC_LOW_LATENCY ({e})"
+            )),
+        }
+
+        let Some(timer) = ftdi_latency_timer(port_name) else {
+            return notes;
+        };
+        let current = std::fs::read_to_string(&timer).unwrap_or_default();
+        if current.trim() == "1" {
+            notes.push("Low latency: FTDI latency timer already 1 ms".to_string());
+            return notes;
+        }
+        match std::fs::write(&timer, "1") {
+            Ok(()) => notes.push(format!(
+                "Low latency: FTDI latency timer {} ms -> 1 ms",
+                current.trim()
+            )),
+            Err(e) => notes.push(format!(
+                "Low latency: can't set FTDI latency timer ({e}); run \
+                 `echo 1 | sudo tee {}` or add a udev rule",
+                timer.display()
+            )),
+        }
+        notes
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::apply;
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply<P>(_port: &P, _port_name: &str) -> Vec<String> {
+    vec![
+        "Low latency: no driver tweaks on this platform; for FTDI adapters set \
+         the latency timer to 1 ms in the driver's advanced port settings"
+            .to_string(),
+    ]
+}
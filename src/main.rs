@@ -1,95 +1,1578 @@
+/// Set by `--output json`: stdout then only carries JSON events
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--quiet`: no startup banner
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for startup messages; they go to stderr with `--output json`
+/// and nowhere with `--quiet`
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            // --quiet
+        } else if $crate::JSON_OUTPUT.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+mod alerts;
+mod auto_login;
+mod bench;
+mod blocks;
+mod boot_capture;
+mod boot_profiler;
+mod bridge;
+mod bug_report;
+mod calc;
+mod compat;
 mod config;
+mod config_watch;
+mod connect_trace;
+#[cfg(feature = "decoders")]
+mod decoders;
+mod faults;
+mod health;
+mod http;
+mod keymap;
+mod latency;
+mod levels;
+mod line_editor;
+mod line_errors;
+mod line_rate;
 mod logging;
+mod low_latency;
+mod metrics;
+mod origins;
+mod parity;
+mod permissions;
+#[cfg(feature = "wasm")]
+mod plugins;
 mod port_discovery;
+mod port_lock;
+mod port_picker;
+mod power;
+mod profile;
+mod quirks;
+mod read_only;
+mod reconnect;
+mod replay;
+mod reset;
+mod rx_pipe;
 mod serial_io;
+mod stats_log;
+mod sweep;
+mod tagging;
+mod tail;
+mod template;
+mod transcript;
+mod tx_fifo;
+mod tx_pipe;
+mod tx_queue;
 mod ui;
+mod update;
+#[cfg(feature = "web")]
+mod web;
+mod wizard;
 
-use anyhow::{Context, Result};
-use clap::Parser;
-use config::{LineEnding, UiConfig};
+use alerts::{AlertEvent, Alerts, Sound, parse_sound_rule};
+use anyhow::{Context, Result, bail};
+use auto_login::AutoLogin;
+use bench::BenchPort;
+use boot_capture::BootCapture;
+use boot_profiler::{Milestone, parse_milestone};
+use bridge::Bridge;
+use clap::builder::BoolishValueParser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use compat::{Compat, parse_compat};
+use config::{
+    DataBitsMode, EchoMode, EscAction, FlowControlMode, LineEnding, OutputMode, ParityMode,
+    PassthroughMode, Polling, PortSettings, RxLineEnding, StopBitsMode, UiConfig, View,
+    parse_on_off,
+};
+use config_watch::FixedRules;
+use crossterm::event::{
+    DisableBracketedPaste, EnableBracketedPaste, KeyCode, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
 use crossterm::terminal;
-use logging::{create_rx_log_writer, create_tx_log_writer};
-use port_discovery::{choose_port_interactive, get_available_ports, print_ports};
+#[cfg(feature = "decoders")]
+use decoders::{DecoderRegistry, SharedPipeline};
+use faults::{FaultSpec, parse_fault_spec};
+use keymap::{KeyMap, parse_key_binding};
+use levels::{Level, LevelParser, parse_level_pattern};
+use logging::{create_log_writer, create_rx_log_writer, create_tx_log_writer};
+use metrics::{InfluxExport, InfluxTarget, MetricRule, parse_metric_rule};
+use origins::{Origin, Origins};
+use port_discovery::{
+    PortFilter, by_id_path, choose_port_interactive, describe_port, get_available_ports,
+    parse_usb_id, port_present, print_ports, print_ports_json, resolve_port, wait_for_port,
+    watch_ports,
+};
+use port_lock::OnBusy;
+use power::{PowerAction, PowerCommands};
+use profile::{Profile, load_config};
+use quirks::{Quirk, QuirkDb};
 use ratatui::{Terminal, backend::CrosstermBackend};
-use serial_io::{SerialData, SerialReader};
+use read_only::ReadOnlyPort;
+use reconnect::{OpenOptions, PortGeneration, Reconnect};
+use regex::Regex;
+use replay::{ReplayCompare, load_expected, load_replay, run_replay};
+use reset::{ResetInvert, ResetKind};
+use rx_pipe::RxPipe;
+use serial_io::{DEFAULT_BREAK, Rs485, SerialData, SerialReader};
 use serialport::SerialPort;
-use std::io::Read;
+use stats_log::StatsLog;
+use std::collections::HashSet;
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::sync::{
     Arc, Mutex as StdMutex,
     atomic::{AtomicBool, Ordering},
 };
 use std::time::Duration;
-use tokio::sync::{Mutex, mpsc};
-use ui::{UiMessage, run_ui};
+use sweep::{COMMON_BAUDS, Sweep};
+use tagging::{TagRule, parse_tag_rule};
+use tokio::sync::{Mutex, broadcast, mpsc};
+use transcript::load_transcript;
+use tx_queue::TxQueue;
+use ui::config_view;
+use ui::exit_view::{ExitView, parse_exit_view};
+use ui::settings::SettingsField;
+use ui::{SessionEnd, Tab, UiMessage, run_ui};
+use wizard::run_wizard;
 
 /// sermonizer — a tiny, friendly serial monitor
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about)]
 struct Args {
-    /// Serial port path/name (auto-detect if omitted)
-    #[arg(short, long)]
-    port: Option<String>,
+    /// Serial port path/name (auto-detect if omitted), or a pattern such as
+    /// `ttyACM*` or `CP2102` matched against port names and USB product
+    /// strings. Give it more than once to monitor several ports, one tab
+    /// each
+    #[arg(short, long, env = "SERMONIZER_PORT")]
+    port: Vec<String>,
 
     /// Baud rate (default 115200)
-    #[arg(short = 'b', long)]
+    #[arg(short = 'b', long, env = "SERMONIZER_BAUD")]
     baud: Option<u32>,
 
+    /// Data bits (5-8). Default: 8
+    #[arg(long, env = "SERMONIZER_DATA_BITS", value_enum, value_name = "N")]
+    data_bits: Option<DataBitsMode>,
+
+    /// Parity (none|odd|even|mark|space; mark/space on Linux only).
+    /// Default: none
+    #[arg(long, env = "SERMONIZER_PARITY", value_enum)]
+    parity: Option<ParityMode>,
+
+    /// Stop bits (1|2). Default: 1
+    #[arg(long, env = "SERMONIZER_STOP_BITS", value_enum, value_name = "N")]
+    stop_bits: Option<StopBitsMode>,
+
+    /// Set DTR right after opening the port (on|off); off keeps boards that
+    /// reset on DTR from rebooting
+    #[arg(long, env = "SERMONIZER_DTR", value_name = "on|off", value_parser = parse_on_off)]
+    dtr: Option<bool>,
+
+    /// Set RTS right after opening the port (on|off)
+    #[arg(long, env = "SERMONIZER_RTS", value_name = "on|off", value_parser = parse_on_off)]
+    rts: Option<bool>,
+
+    /// Write the RX lines from connecting (and `--reset`) up to the
+    /// `--boot-done` match into FILE, timed from opening the port
+    #[arg(long, env = "SERMONIZER_CAPTURE_BOOT", value_name = "FILE")]
+    capture_boot: Option<PathBuf>,
+
+    /// Line that ends the boot capture, e.g. "login:" or "Ready"
+    #[arg(long, env = "SERMONIZER_BOOT_DONE", value_name = "REGEX")]
+    boot_done: Option<Regex>,
+
+    /// Reset the board through DTR/RTS after connecting: esp32 (reboot),
+    /// esp32-boot (ROM bootloader), stm32 (UART bootloader), stm32-run or
+    /// dtr-pulse. Alt+T repeats it, Alt+U enters the bootloader
+    #[arg(long, env = "SERMONIZER_RESET", value_enum, value_name = "TARGET")]
+    reset: Option<ResetKind>,
+
+    /// Control lines the reset sequences drive inverted (dtr|rts|both), for
+    /// boards wired through an inverting transistor
+    #[arg(
+        long,
+        env = "SERMONIZER_RESET_INVERT",
+        value_enum,
+        value_name = "LINES"
+    )]
+    reset_invert: Option<ResetInvert>,
+
+    /// Flow control (none|software|hardware): XON/XOFF or RTS/CTS.
+    /// Default: none, or what a known adapter needs
+    #[arg(long, env = "SERMONIZER_FLOW", value_enum, value_name = "MODE")]
+    flow: Option<FlowControlMode>,
+
     /// Line ending when you press Enter (none|nl|cr|crlf). Default: nl
-    #[arg(long, value_enum)]
+    #[arg(long, env = "SERMONIZER_LINE_ENDING", value_enum)]
     line_ending: Option<LineEnding>,
 
+    /// Line ending sent with Shift+Enter (none|nl|cr|crlf). Default: none
+    #[arg(long, env = "SERMONIZER_ALT_LINE_ENDING", value_enum)]
+    alt_line_ending: Option<LineEnding>,
+
+    /// How received data is split into lines (lf|cr|crlf|any). Default: lf
+    #[arg(long, env = "SERMONIZER_RX_LINE_ENDING", value_enum)]
+    rx_line_ending: Option<RxLineEnding>,
+
+    /// Device echoes of the line just sent: show, tag (dimmed) or fold
+    /// (hidden). Default: show
+    #[arg(long, env = "SERMONIZER_ECHO_MODE", value_enum)]
+    echo_mode: Option<EchoMode>,
+
+    /// What Esc does: quit, clear (input line, then display filter) or
+    /// send (an ESC byte to the device). Default: quit
+    #[arg(long, env = "SERMONIZER_ESC", value_enum, value_name = "ACTION")]
+    esc: Option<EscAction>,
+
+    /// Send each key straight to the device for CLIs with their own line
+    /// editor (history, completion) and show the device's echo as the
+    /// input line: off, auto (switch once an editor is detected) or on.
+    /// F12 toggles
+    #[arg(long, env = "SERMONIZER_PASSTHROUGH", value_enum, value_name = "MODE")]
+    passthrough: Option<PassthroughMode>,
+
+    /// Show the output as a VT100/ANSI terminal (cursor positioning,
+    /// clearing, colors) for full-screen device menus; Alt+V toggles
+    #[arg(long, env = "SERMONIZER_TERMINAL", value_parser = BoolishValueParser::new())]
+    terminal: bool,
+
+    /// View to start in: text, hexdump (as --hex) or terminal (as
+    /// --terminal). Profiles and adapter quirks can set it per device
+    #[arg(long, env = "SERMONIZER_VIEW", value_enum, conflicts_with_all = ["hex", "terminal"])]
+    view: Option<View>,
+
+    /// Draw the UI with ASCII only (borders, arrows, markers), for consoles
+    /// without Unicode. On by default when the locale isn't UTF-8
+    #[arg(long, env = "SERMONIZER_ASCII_UI", value_parser = BoolishValueParser::new())]
+    ascii_ui: bool,
+
+    /// Wake up less often for a monitor left running on battery: keyboard
+    /// checks every 50ms, at most 10 redraws a second, port status every 5s
+    #[arg(long, env = "SERMONIZER_POWER_SAVE", value_parser = BoolishValueParser::new())]
+    power_save: bool,
+
+    /// How often to check for key presses [default: 1ms, 50ms with
+    /// --power-save]
+    #[arg(long, env = "SERMONIZER_INPUT_POLL", value_name = "DURATION", value_parser = template::parse_duration)]
+    input_poll: Option<Duration>,
+
+    /// Shortest time between two redraws, so a busy port doesn't redraw on
+    /// every byte [default: 0, 100ms with --power-save]
+    #[arg(long, env = "SERMONIZER_RENDER_INTERVAL", value_name = "DURATION", value_parser = template::parse_duration)]
+    render_interval: Option<Duration>,
+
+    /// How often to check line error counters and plugged ports [default:
+    /// 500ms and 1s, 5s with --power-save]
+    #[arg(long, env = "SERMONIZER_STATUS_POLL", value_name = "DURATION", value_parser = template::parse_duration)]
+    status_poll: Option<Duration>,
+
+    /// With several ports: start in the merged view, every port's RX lines
+    /// in one scrollback with time and port; Alt+M toggles
+    #[arg(long, env = "SERMONIZER_MERGED", value_parser = BoolishValueParser::new())]
+    merged: bool,
+
+    /// In passthrough mode, send KEY to the device as SEQUENCE instead of
+    /// using it locally, e.g. `f1`, `pageup` or `up=\eOA` (repeatable). A
+    /// bare KEY sends its VT100/xterm sequence; `\e`, `\xNN`, `\r`, `\n`
+    /// and `\t` escapes work in SEQUENCE
+    #[arg(long = "key", env = "SERMONIZER_KEY", value_name = "KEY[=SEQUENCE]", value_parser = parse_key_binding)]
+    keys: Vec<(KeyCode, Vec<u8>)>,
+
+    /// Send these characters immediately (no Enter) when typed into an
+    /// empty input line, e.g. "yn0123456789". Toggle at runtime with F3
+    #[arg(long, env = "SERMONIZER_INSTANT_KEYS", value_name = "CHARS")]
+    instant_keys: Option<String>,
+
+    /// Tag received lines matching REGEX with NAME (repeatable); F4 cycles
+    /// the display filter through the tags, Ctrl+S exports what is shown
+    #[arg(long = "tag", env = "SERMONIZER_TAG", value_name = "NAME=REGEX", value_parser = parse_tag_rule)]
+    tags: Vec<TagRule>,
+
+    /// Fold RX lines matching REGEX into the line before them, e.g. stack
+    /// trace lines (repeatable); Ctrl+O expands and collapses them
+    #[arg(long = "fold", env = "SERMONIZER_FOLD", value_name = "REGEX")]
+    folds: Vec<Regex>,
+
+    /// Group the RX lines from one matching START through one matching END
+    /// into a block called NAME, e.g. a stack trace or register dump
+    /// (repeatable). Blocks are tagged, raise the `block` alert and go
+    /// into the session bundle
+    #[arg(long = "block", num_args = 3, value_names = ["NAME", "START", "END"])]
+    blocks: Vec<String>,
+
+    /// Regex with a (?P<level>...) group to extract a line's log level
+    /// (repeatable, first match wins). Defaults cover common formats
+    #[arg(long, env = "SERMONIZER_LEVEL_PATTERN", value_name = "REGEX", value_parser = parse_level_pattern)]
+    level_pattern: Vec<Regex>,
+
+    /// Hide lines below this level (trace|debug|info|warn|error); F5 cycles
+    #[arg(long, env = "SERMONIZER_MIN_LEVEL", value_enum)]
+    min_level: Option<Level>,
+
     /// Log received bytes to this file (appends)
-    #[arg(long)]
+    #[arg(long, env = "SERMONIZER_LOG")]
     log: Option<PathBuf>,
 
     /// Log transmitted bytes to this file (appends)
-    #[arg(long)]
+    #[arg(long, env = "SERMONIZER_TX_LOG")]
     tx_log: Option<PathBuf>,
 
     /// Prepend timestamps to logged chunks (and hex output)
-    #[arg(long = "log-ts")]
+    #[arg(long = "log-ts", env = "SERMONIZER_LOG_TS", value_parser = BoolishValueParser::new())]
     log_ts: bool,
 
-    /// Show RX as hex (space-separated bytes)
-    #[arg(long)]
+    /// Collapse runs of identical lines in the RX log
+    /// ("last message repeated N times"); lines end as --rx-line-ending says
+    #[arg(long, env = "SERMONIZER_LOG_DEDUP", value_parser = BoolishValueParser::new())]
+    log_dedup: bool,
+
+    /// Append a CSV summary (bytes, lines, errors, alerts...) per interval
+    /// to FILE, for long soak tests
+    #[arg(long, env = "SERMONIZER_STATS_LOG", value_name = "FILE")]
+    stats_log: Option<PathBuf>,
+
+    /// Seconds per --stats-log record (default 3600)
+    #[arg(long, env = "SERMONIZER_STATS_INTERVAL", value_name = "SECS")]
+    stats_interval: Option<u64>,
+
+    /// Reduce USB-serial batching delays (Linux: ASYNC_LOW_LATENCY and the
+    /// FTDI latency timer)
+    #[arg(long, env = "SERMONIZER_LOW_LATENCY", value_parser = BoolishValueParser::new())]
+    low_latency: bool,
+
+    /// Show RX as a hexdump (offsets count from the start of the session)
+    #[arg(long, env = "SERMONIZER_HEX", value_parser = BoolishValueParser::new())]
     hex: bool,
 
+    /// Load a sandboxed WebAssembly plugin (repeatable). Plugins exporting
+    /// `decode` become decoders named after the file, `on_line` ones see
+    /// every RX line and may answer
+    #[arg(long = "plugin", env = "SERMONIZER_PLUGIN", value_name = "FILE.wasm")]
+    plugins: Vec<PathBuf>,
+
+    /// Decode RX through a chain of decoders, e.g. `nmea` or `cobs,hex`
+    /// (hex, cobs, nmea, modbus). F11 switches decoders at runtime
+    #[arg(long, env = "SERMONIZER_DECODER", value_name = "SPEC")]
+    decoder: Option<String>,
+
+    /// Open the port at 1200 baud and close it first (Arduino SAMD/Leonardo
+    /// bootloader entry), then connect once the port is back
+    #[arg(long = "touch-1200", env = "SERMONIZER_TOUCH_1200", value_parser = BoolishValueParser::new())]
+    touch_1200: bool,
+
+    /// Send a serial BREAK right after opening the port, before anything
+    /// else (bootloaders that wait for one)
+    #[arg(long, env = "SERMONIZER_BREAK_ON_START", value_parser = BoolishValueParser::new())]
+    break_on_start: bool,
+
+    /// How long a BREAK holds the line, for `--break-on-start` and Alt+B
+    /// [default: 250ms]
+    #[arg(long, env = "SERMONIZER_BREAK_DURATION", value_name = "DURATION", value_parser = template::parse_duration)]
+    break_duration: Option<Duration>,
+
+    /// If the port isn't there yet, wait for it to appear instead of
+    /// failing (flash-then-monitor scripts, devices still enumerating);
+    /// likewise wait for a port another program has open to be free
+    #[arg(long, env = "SERMONIZER_WAIT", value_parser = BoolishValueParser::new())]
+    wait: bool,
+
+    /// Give up waiting for the port after this long (`--wait`)
+    #[arg(long, env = "SERMONIZER_WAIT_TIMEOUT", value_name = "DURATION", value_parser = template::parse_duration, requires = "wait")]
+    wait_timeout: Option<Duration>,
+
+    /// If the port is busy or access is denied when opening it, keep
+    /// trying with backoff for up to this many seconds (flashing tools
+    /// often hold it for a moment)
+    #[arg(
+        long,
+        env = "SERMONIZER_RETRY_OPEN",
+        value_name = "SECS",
+        conflicts_with = "wait"
+    )]
+    retry_open: Option<u64>,
+
+    /// When the port goes away (device reboots, cable pulled), wait for it
+    /// to come back, also under another name with the same USB VID/PID and
+    /// serial number, and reopen it with the same settings
+    #[arg(long, env = "SERMONIZER_RECONNECT", value_parser = BoolishValueParser::new())]
+    reconnect: bool,
+
+    /// Only listen: nothing is ever written to the port (no typed lines,
+    /// line endings, BREAK or DTR/RTS changes) and bytes already waiting
+    /// are kept, to watch a port another program transmits on
+    #[arg(long, env = "SERMONIZER_READ_ONLY", value_parser = BoolishValueParser::new(), conflicts_with_all = [
+        "reset",
+        "dtr",
+        "rts",
+        "touch_1200",
+        "break_on_start",
+        "rs485",
+        "startup_commands",
+        "tx_fifo",
+        "replay_tx",
+        "expect_transcript",
+    ])]
+    read_only: bool,
+
+    /// RS-485 half duplex: assert RTS to transmit and drop it once the
+    /// bytes are flushed (adapters without automatic direction control)
+    #[arg(long, env = "SERMONIZER_RS485", value_parser = BoolishValueParser::new())]
+    rs485: bool,
+
+    /// Wait this long after asserting RTS before transmitting (`--rs485`)
+    /// [default: 0]
+    #[arg(long, env = "SERMONIZER_RS485_BEFORE", value_name = "DURATION", value_parser = template::parse_duration)]
+    rs485_before: Option<Duration>,
+
+    /// Wait this long after the flush before dropping RTS (`--rs485`)
+    /// [default: 0]
+    #[arg(long, env = "SERMONIZER_RS485_AFTER", value_name = "DURATION", value_parser = template::parse_duration)]
+    rs485_after: Option<Duration>,
+
+    /// After quitting, print the last output lines (tail, or tail:N for
+    /// N lines; 40 by default) or page the whole scrollback with $PAGER
+    /// (pager), so the context survives the TUI closing
+    #[arg(long, env = "SERMONIZER_ON_EXIT", value_name = "VIEW", value_parser = parse_exit_view)]
+    on_exit: Option<ExitView>,
+
+    /// Send this command right after connecting (repeatable, in order;
+    /// `{sleep 200ms}` / `{wait "TEXT" 2s}` directives allowed)
+    #[arg(
+        long = "startup-command",
+        env = "SERMONIZER_STARTUP_COMMAND",
+        value_name = "CMD"
+    )]
+    startup_commands: Vec<String>,
+
+    /// Create a named pipe at PATH; lines other tools write to it are sent
+    /// with the normal line ending and shown in the output (Unix only)
+    #[arg(long, env = "SERMONIZER_TX_FIFO", value_name = "PATH")]
+    tx_fifo: Option<PathBuf>,
+
+    /// Stream raw RX into this shell command's stdin (e.g. `defmt-print -e
+    /// fw.elf`), starting it again whenever it exits
+    #[arg(long, env = "SERMONIZER_RX_PIPE", value_name = "CMD")]
+    rx_pipe: Option<String>,
+
+    /// Show what the --rx-pipe command prints in a pane below the output
+    #[arg(long, env = "SERMONIZER_RX_PIPE_PANE", value_parser = BoolishValueParser::new(), requires = "rx_pipe")]
+    rx_pipe_pane: bool,
+
+    /// Pass everything sent through this shell command first, kept running
+    /// for the session (what is sent goes to its stdin, what it prints goes
+    /// out), e.g. a script adding framing and a CRC
+    #[arg(long, env = "SERMONIZER_TX_PIPE", value_name = "CMD")]
+    tx_pipe: Option<String>,
+
+    /// Retransmit a captured TX log (or any file) to the device, keeping
+    /// the original timing when the file has --log-ts timestamps
+    #[arg(long, env = "SERMONIZER_REPLAY_TX", value_name = "FILE")]
+    replay_tx: Option<PathBuf>,
+
+    /// Diff the RX captured during --replay-tx against this recorded RX log
+    #[arg(
+        long,
+        env = "SERMONIZER_REPLAY_EXPECT",
+        value_name = "FILE",
+        requires = "replay_tx"
+    )]
+    replay_expect: Option<PathBuf>,
+
+    /// Skip lines containing this text when diffing replay RX (repeatable)
+    #[arg(
+        long,
+        env = "SERMONIZER_REPLAY_IGNORE",
+        value_name = "TEXT",
+        requires = "replay_expect"
+    )]
+    replay_ignore: Vec<String>,
+
+    /// Milliseconds to keep capturing RX after the last replayed chunk
+    #[arg(
+        long,
+        env = "SERMONIZER_REPLAY_SETTLE",
+        value_name = "MS",
+        default_value_t = 1000
+    )]
+    replay_settle: u64,
+
+    /// Damage transmitted bytes to simulate a noisy link, e.g.
+    /// `flip=1e-4,drop=0.001,dup=0.01,delay=20ms,jitter=10ms,seed=1`
+    #[arg(long, env = "SERMONIZER_FAULT_TX", value_name = "SPEC", value_parser = parse_fault_spec)]
+    fault_tx: Option<FaultSpec>,
+
+    /// Damage received bytes the same way (see --fault-tx)
+    #[arg(long, env = "SERMONIZER_FAULT_RX", value_name = "SPEC", value_parser = parse_fault_spec)]
+    fault_rx: Option<FaultSpec>,
+
+    /// tui (default) or json: no UI, every event (rx-line, tx, note,
+    /// status, error) as one JSON object per line on stdout; lines read
+    /// from stdin are sent to the device
+    #[arg(long, env = "SERMONIZER_OUTPUT", value_enum, value_name = "MODE")]
+    output: Option<OutputMode>,
+
+    /// Skip the startup banner (port, settings, ...); errors still show
+    #[arg(short, long, env = "SERMONIZER_QUIET", value_parser = BoolishValueParser::new())]
+    quiet: bool,
+
+    /// Print each stage of connecting (enumeration, open, settings, control
+    /// lines, first bytes) with timings, for adapters that show up but
+    /// never deliver data
+    #[arg(long, env = "SERMONIZER_VERBOSE_CONNECT", value_parser = BoolishValueParser::new())]
+    verbose_connect: bool,
+
+    /// Serve a read-only live view of the console at http://ADDR (PORT
+    /// alone listens on localhost; needs the `web` feature)
+    #[arg(long, env = "SERMONIZER_WEB", value_name = "ADDR")]
+    web: Option<String>,
+
+    /// Name this capture session; Ctrl+E exports a bundle directory named
+    /// after it (raw RX, text, CSV, metadata)
+    #[arg(long, env = "SERMONIZER_SESSION", value_name = "NAME")]
+    session: Option<String>,
+
+    /// Load options from this profile in the config file (flags still win)
+    #[arg(long, env = "SERMONIZER_PROFILE", value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Bring up a test rig from a bench file: its ports (one tab each) with
+    /// their roles, profiles, logs and startup commands
+    #[arg(
+        long,
+        env = "SERMONIZER_BENCH",
+        value_name = "FILE",
+        conflicts_with = "port"
+    )]
+    bench: Option<PathBuf>,
+
+    /// Role of the port in a `--bench` rig, for its tab
+    #[arg(skip)]
+    role: Option<String>,
+
+    /// Check RX lines against this expected transcript as they arrive
+    /// (`*` matches any text, a line of `...` skips any number of lines)
+    #[arg(long, env = "SERMONIZER_EXPECT_TRANSCRIPT", value_name = "FILE")]
+    expect_transcript: Option<PathBuf>,
+
+    /// Time each sent line to the first RX line after it and report
+    /// round-trip latency per command
+    #[arg(long, env = "SERMONIZER_LATENCY", value_parser = BoolishValueParser::new())]
+    latency: bool,
+
+    /// With --latency, only RX lines matching REGEX count as the response
+    /// (e.g. "^(OK|ERROR)")
+    #[arg(long, env = "SERMONIZER_LATENCY_RESPONSE", value_name = "REGEX")]
+    latency_response: Option<Regex>,
+
+    /// Extract a number from RX lines matching REGEX as metric NAME: the
+    /// first capture group, or the whole match (repeatable)
+    #[arg(long = "metric", env = "SERMONIZER_METRIC", value_name = "NAME=REGEX", value_parser = parse_metric_rule)]
+    metrics: Vec<MetricRule>,
+
+    /// Write metric snapshots in InfluxDB line protocol to this file or
+    /// `http://` write endpoint (token from $INFLUX_TOKEN)
+    #[arg(long, env = "SERMONIZER_INFLUX", value_name = "FILE|URL")]
+    influx: Option<String>,
+
+    /// Seconds between metric snapshots (default 10)
+    #[arg(long, env = "SERMONIZER_INFLUX_INTERVAL", value_name = "SECS")]
+    influx_interval: Option<u64>,
+
+    /// Show a desktop notification on disconnect and reconnect, alert
+    /// matches and when a sequence finishes
+    #[arg(long, env = "SERMONIZER_NOTIFY", value_parser = BoolishValueParser::new())]
+    notify: bool,
+
+    /// Raise an alert when an RX line matches REGEX (repeatable)
+    #[arg(long = "alert", env = "SERMONIZER_ALERT", value_name = "REGEX")]
+    alerts: Vec<Regex>,
+
+    /// Keep the latest RX line matching REGEX in a pinned region above the
+    /// output, e.g. a version banner or the current test (repeatable)
+    #[arg(long = "pin", env = "SERMONIZER_PIN", value_name = "REGEX")]
+    pins: Vec<Regex>,
+
+    /// Sound for an event: disconnect, reconnect, pattern, block or sequence-done, mapped to
+    /// `bell`, `bell:N` (N bells) or a sound file (repeatable)
+    #[arg(long = "sound", env = "SERMONIZER_SOUND", value_name = "EVENT=SOUND", value_parser = parse_sound_rule)]
+    sounds: Vec<(AlertEvent, Sound)>,
+
+    /// Boot profiler milestone (repeatable). F6 arms the profiler, as does
+    /// toggling DTR/RTS; time to each match is reported
+    #[arg(long = "milestone", env = "SERMONIZER_MILESTONE", value_name = "NAME=REGEX", value_parser = parse_milestone)]
+    milestones: Vec<Milestone>,
+
+    /// Command to switch the target on: a shell command or an `http://` URL
+    /// (optionally prefixed by GET/POST/PUT). F9 runs it
+    #[arg(long, env = "SERMONIZER_POWER_ON", value_name = "CMD")]
+    power_on: Option<String>,
+
+    /// Command to switch the target off (shell or URL). F8 runs it
+    #[arg(long, env = "SERMONIZER_POWER_OFF", value_name = "CMD")]
+    power_off: Option<String>,
+
+    /// Command to power-cycle the target (shell or URL). F7 runs it; without
+    /// it F7 runs --power-off, waits --power-cycle-delay, then --power-on
+    #[arg(long, env = "SERMONIZER_POWER_CYCLE", value_name = "CMD")]
+    power_cycle: Option<String>,
+
+    /// Milliseconds between off and on when cycling via the two commands
+    #[arg(
+        long,
+        env = "SERMONIZER_POWER_CYCLE_DELAY",
+        value_name = "MS",
+        default_value_t = 1000
+    )]
+    power_cycle_delay: u64,
+
+    /// Run a power action (on|off|cycle) and exit, e.g. from a script
+    #[arg(long, value_enum, value_name = "ACTION")]
+    power: Option<PowerAction>,
+
+    /// Keep the default behaviour of a release before this one (MAJOR.MINOR:
+    /// no setup wizard, trailing `\` sent literally)
+    #[arg(long, env = "SERMONIZER_COMPAT", value_name = "VERSION", value_parser = parse_compat)]
+    compat: Option<Compat>,
+
+    /// Check crates.io for a newer release and exit (set
+    /// `check_updates = true` in the config file to check on every start)
+    #[arg(long)]
+    check_update: bool,
+
+    /// Print environment, adapter and profile details as markdown for a
+    /// bug report and exit (Ctrl+R in a session adds settings and output)
+    #[arg(long)]
+    bug_report: bool,
+
+    /// Explain why the port can't be opened (group, mode, membership) and
+    /// print a udev rule that grants access, then exit (Linux)
+    #[arg(long)]
+    fix_perms: bool,
+
     /// Just list ports and exit
     #[arg(long)]
     list: bool,
+
+    /// Only consider USB ports with this vendor ID (hex, e.g. 0x0483) for
+    /// --list and picking a port
+    #[arg(long, env = "SERMONIZER_VID", value_name = "ID", value_parser = parse_usb_id)]
+    vid: Option<u16>,
+
+    /// Only consider USB ports with this product ID (hex)
+    #[arg(long, env = "SERMONIZER_PID", value_name = "ID", value_parser = parse_usb_id)]
+    pid: Option<u16>,
+
+    /// Only consider ports whose name, manufacturer, product or serial
+    /// number contains TEXT (any case)
+    #[arg(long = "match", env = "SERMONIZER_MATCH", value_name = "TEXT")]
+    match_text: Option<String>,
+
+    /// With --list, keep running and print ports as they appear/disappear
+    #[arg(long, requires = "list")]
+    watch: bool,
+
+    /// With --list, print machine-readable JSON
+    #[arg(long, requires = "list")]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Open a port, apply settings and print a diagnostic report
+    Check {
+        /// Port to check (defaults to --port / the profile / auto-detect)
+        port: Option<String>,
+
+        /// Baud rate to apply (defaults to --baud or 115200)
+        #[arg(short = 'b', long)]
+        baud: Option<u32>,
+
+        /// Send a test pattern and expect it back (jumper TX to RX first)
+        #[arg(long)]
+        loopback: bool,
+    },
+    /// Find the settings an unknown device talks at: send a probe at each
+    /// combination of baud rate, framing and parity and report which got a
+    /// valid reply
+    Sweep {
+        /// Port to probe (defaults to --port / the profile / auto-detect)
+        port: Option<String>,
+
+        /// Text to send at each setting, followed by --line-ending
+        #[arg(long, value_name = "TEXT", default_value = "")]
+        probe: String,
+
+        /// The reply must match REGEX (default: any mostly printable reply)
+        #[arg(long, value_name = "REGEX")]
+        expect: Option<Regex>,
+
+        /// Baud rates to try (default: common rates from 1200 to 921600)
+        #[arg(long, value_name = "BAUD,...", value_delimiter = ',')]
+        bauds: Vec<u32>,
+
+        /// Data bits to try
+        #[arg(long, value_name = "N,...", value_delimiter = ',', default_value = "8")]
+        data_bits: Vec<DataBitsMode>,
+
+        /// Parities to try
+        #[arg(
+            long,
+            value_name = "PARITY,...",
+            value_delimiter = ',',
+            default_value = "none,even,odd"
+        )]
+        parities: Vec<ParityMode>,
+
+        /// Stop bits to try
+        #[arg(
+            long,
+            value_name = "N,...",
+            value_delimiter = ',',
+            default_value = "1,2"
+        )]
+        stop_bits: Vec<StopBitsMode>,
+
+        /// Milliseconds to wait for a reply at each setting
+        #[arg(long, value_name = "MS", default_value_t = 300)]
+        wait: u64,
+
+        /// Stop at the first setting that answers
+        #[arg(long)]
+        first: bool,
+    },
+    /// Show a sermonizer log (RX, TX or bridge, with or without --log-ts)
+    /// in the usual view instead of a port, following it as it grows; runs
+    /// of identical lines show once. View flags such as --hex, --tag or
+    /// --min-level go before `tail`
+    Tail {
+        /// Log file to show
+        file: PathBuf,
+    },
+    /// Forward bytes between two ports and show (with --log, also log)
+    /// what each side sends: a man in the middle between two devices.
+    /// Both ports use --baud and the framing flags; --hex shows hex
+    Bridge {
+        /// Port of device A
+        a: String,
+
+        /// Port of device B
+        b: String,
+
+        /// Baud rate of B when it differs from A's
+        #[arg(long, value_name = "BAUD")]
+        baud_b: Option<u32>,
+    },
+}
+
+impl Args {
+    /// Fill in everything not given on the command line from `profile`
+    fn apply_profile(&mut self, profile: &Profile) -> Result<()> {
+        if self.port.is_empty() {
+            self.port.extend(profile.port.clone());
+        }
+        self.baud = self.baud.or(profile.baud);
+        self.data_bits = self.data_bits.or(profile.data_bits);
+        self.parity = self.parity.or(profile.parity);
+        self.stop_bits = self.stop_bits.or(profile.stop_bits);
+        self.flow = self.flow.or(profile.flow);
+        self.dtr = self.dtr.or(profile.dtr);
+        self.reset = self.reset.or(profile.reset);
+        self.reset_invert = self.reset_invert.or(profile.reset_invert);
+        self.rts = self.rts.or(profile.rts);
+        self.line_ending = self.line_ending.or(profile.line_ending);
+        self.alt_line_ending = self.alt_line_ending.or(profile.alt_line_ending);
+        self.rx_line_ending = self.rx_line_ending.or(profile.rx_line_ending);
+        self.echo_mode = self.echo_mode.or(profile.echo_mode);
+        self.esc = self.esc.or(profile.esc);
+        self.passthrough = self.passthrough.or(profile.passthrough);
+        if !self.hex && !self.terminal {
+            self.view = self.view.or(profile.view);
+        }
+        self.terminal |= profile.terminal;
+        self.rx_pipe = self.rx_pipe.take().or_else(|| profile.rx_pipe.clone());
+        self.rx_pipe_pane |= profile.rx_pipe_pane;
+        self.tx_pipe = self.tx_pipe.take().or_else(|| profile.tx_pipe.clone());
+        self.ascii_ui |= profile.ascii_ui;
+        self.power_save |= profile.power_save;
+        if self.keys.is_empty() {
+            self.keys = profile.key_bindings()?;
+        }
+        self.instant_keys = self
+            .instant_keys
+            .take()
+            .or_else(|| profile.instant_keys.clone());
+        self.log = self.log.take().or_else(|| profile.log.clone());
+        self.tx_log = self.tx_log.take().or_else(|| profile.tx_log.clone());
+        self.log_ts |= profile.log_ts;
+        self.log_dedup |= profile.log_dedup;
+        self.stats_log = self.stats_log.take().or_else(|| profile.stats_log.clone());
+        self.stats_interval = self.stats_interval.or(profile.stats_interval);
+        self.hex |= profile.hex;
+        self.decoder = self.decoder.take().or_else(|| profile.decoder.clone());
+        if self.plugins.is_empty() {
+            self.plugins = profile.plugins.clone();
+        }
+        self.low_latency |= profile.low_latency;
+        if self.tags.is_empty() {
+            self.tags = profile.tag_rules()?;
+        }
+        if self.folds.is_empty() {
+            self.folds = profile.fold_regexes()?;
+        }
+        if self.blocks.is_empty() {
+            for (name, block) in &profile.blocks {
+                self.blocks
+                    .extend([name.clone(), block.start.clone(), block.end.clone()]);
+            }
+        }
+        if self.level_pattern.is_empty() {
+            self.level_pattern = profile.level_regexes()?;
+        }
+        self.min_level = self.min_level.or(profile.min_level);
+        if self.metrics.is_empty() {
+            self.metrics = profile.metric_rules()?;
+        }
+        self.influx = self.influx.take().or_else(|| profile.influx.clone());
+        self.influx_interval = self.influx_interval.or(profile.influx_interval);
+        self.notify |= profile.notify;
+        if self.alerts.is_empty() {
+            self.alerts = profile.alert_regexes()?;
+        }
+        if self.pins.is_empty() {
+            self.pins = profile.pin_regexes()?;
+        }
+        if self.sounds.is_empty() {
+            for (event, sound) in &profile.sounds {
+                self.sounds.push(
+                    parse_sound_rule(&format!("{event}={sound}")).map_err(anyhow::Error::msg)?,
+                );
+            }
+        }
+        self.touch_1200 |= profile.touch_1200;
+        self.capture_boot = self
+            .capture_boot
+            .take()
+            .or_else(|| profile.capture_boot.clone());
+        if self.boot_done.is_none()
+            && let Some(pattern) = &profile.boot_done
+        {
+            self.boot_done = Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid boot_done pattern '{pattern}'"))?,
+            );
+        }
+        self.quiet |= profile.quiet;
+        self.break_on_start |= profile.break_on_start;
+        if self.break_duration.is_none()
+            && let Some(duration) = &profile.break_duration
+        {
+            self.break_duration =
+                Some(template::parse_duration(duration).map_err(anyhow::Error::msg)?);
+        }
+        self.wait |= profile.wait;
+        if self.wait_timeout.is_none()
+            && let Some(timeout) = &profile.wait_timeout
+        {
+            self.wait_timeout =
+                Some(template::parse_duration(timeout).map_err(anyhow::Error::msg)?);
+        }
+        self.reconnect |= profile.reconnect;
+        self.rs485 |= profile.rs485;
+        if self.rs485_before.is_none()
+            && let Some(delay) = &profile.rs485_before
+        {
+            self.rs485_before = Some(template::parse_duration(delay).map_err(anyhow::Error::msg)?);
+        }
+        if self.rs485_after.is_none()
+            && let Some(delay) = &profile.rs485_after
+        {
+            self.rs485_after = Some(template::parse_duration(delay).map_err(anyhow::Error::msg)?);
+        }
+        if self.on_exit.is_none()
+            && let Some(view) = &profile.on_exit
+        {
+            self.on_exit = Some(parse_exit_view(view).map_err(anyhow::Error::msg)?);
+        }
+        if self.startup_commands.is_empty() {
+            self.startup_commands = profile.startup_commands.clone();
+        }
+        if self.compat.is_none()
+            && let Some(version) = &profile.compat
+        {
+            self.compat = Some(parse_compat(version).map_err(anyhow::Error::msg)?);
+        }
+        self.power_on = self.power_on.take().or_else(|| profile.power_on.clone());
+        self.power_off = self.power_off.take().or_else(|| profile.power_off.clone());
+        self.power_cycle = self
+            .power_cycle
+            .take()
+            .or_else(|| profile.power_cycle.clone());
+        Ok(())
+    }
+
+    /// Where each setting in the config view (F10) comes from; call before
+    /// `apply_profile` so flags can be told apart from profile values.
+    /// `from_env` holds the ids of options set through their `SERMONIZER_*`
+    /// variable.
+    fn origins(
+        &self,
+        profile: Option<&Profile>,
+        profile_name: Option<String>,
+        from_env: &HashSet<String>,
+    ) -> Origins {
+        let p = profile.cloned().unwrap_or_default();
+        let mut o = Origins::new(profile_name);
+        let flag = |id: &str, given: bool| {
+            given.then(|| {
+                if from_env.contains(id) {
+                    Origin::Env
+                } else {
+                    Origin::Flag
+                }
+            })
+        };
+        o.resolve(
+            config_view::PORT,
+            flag("port", !self.port.is_empty()),
+            p.port.is_some(),
+        );
+        o.resolve(
+            SettingsField::Baud.label(),
+            flag("baud", self.baud.is_some()),
+            p.baud.is_some(),
+        );
+        o.resolve(
+            SettingsField::DataBits.label(),
+            flag("data_bits", self.data_bits.is_some()),
+            p.data_bits.is_some(),
+        );
+        o.resolve(
+            SettingsField::Parity.label(),
+            flag("parity", self.parity.is_some()),
+            p.parity.is_some(),
+        );
+        o.resolve(
+            SettingsField::StopBits.label(),
+            flag("stop_bits", self.stop_bits.is_some()),
+            p.stop_bits.is_some(),
+        );
+        o.resolve(
+            SettingsField::FlowControl.label(),
+            flag("flow", self.flow.is_some()),
+            p.flow.is_some(),
+        );
+        o.resolve(
+            SettingsField::Dtr.label(),
+            flag("dtr", self.dtr.is_some()),
+            p.dtr.is_some(),
+        );
+        o.resolve(
+            SettingsField::Rts.label(),
+            flag("rts", self.rts.is_some()),
+            p.rts.is_some(),
+        );
+        o.resolve(
+            SettingsField::LineEnding.label(),
+            flag("line_ending", self.line_ending.is_some()),
+            p.line_ending.is_some(),
+        );
+        o.resolve(
+            SettingsField::RxLineEnding.label(),
+            flag("rx_line_ending", self.rx_line_ending.is_some()),
+            p.rx_line_ending.is_some(),
+        );
+        o.resolve(
+            config_view::ALT_LINE_ENDING,
+            flag("alt_line_ending", self.alt_line_ending.is_some()),
+            p.alt_line_ending.is_some(),
+        );
+        o.resolve(
+            config_view::ECHO_MODE,
+            flag("echo_mode", self.echo_mode.is_some()),
+            p.echo_mode.is_some(),
+        );
+        o.resolve(
+            config_view::ESC,
+            flag("esc", self.esc.is_some()),
+            p.esc.is_some(),
+        );
+        o.resolve(
+            config_view::PASSTHROUGH,
+            flag("passthrough", self.passthrough.is_some()),
+            p.passthrough.is_some(),
+        );
+        let hexdump = Some(View::Hexdump);
+        o.resolve(
+            config_view::HEX,
+            flag("hex", self.hex).or(flag("view", self.view == hexdump)),
+            p.hex || p.view == hexdump,
+        );
+        o.resolve(
+            config_view::INSTANT_KEYS,
+            flag("instant_keys", self.instant_keys.is_some()),
+            p.instant_keys.is_some(),
+        );
+        o.resolve(
+            config_view::MIN_LEVEL,
+            flag("min_level", self.min_level.is_some()),
+            p.min_level.is_some(),
+        );
+        o.resolve(
+            config_view::RX_LOG,
+            flag("log", self.log.is_some()),
+            p.log.is_some(),
+        );
+        o.resolve(
+            config_view::TX_LOG,
+            flag("tx_log", self.tx_log.is_some()),
+            p.tx_log.is_some(),
+        );
+        o
+    }
+
+    /// Options for one port of a `--bench`: the bench's fields first, then
+    /// the command line, then the port's profile
+    fn for_bench_port(&self, entry: &BenchPort, profile: Option<&Profile>) -> Result<Args> {
+        let mut args = Args {
+            port: vec![entry.port.clone()],
+            role: entry.role.clone(),
+            baud: entry.baud.or(self.baud),
+            log: entry.log.clone().or_else(|| self.log.clone()),
+            tx_log: entry.tx_log.clone().or_else(|| self.tx_log.clone()),
+            ..self.clone()
+        };
+        if !entry.startup.is_empty() {
+            args.startup_commands = entry.startup.clone();
+        }
+        if let Some(profile) = profile {
+            args.apply_profile(profile)?;
+        }
+        args.apply_view();
+        Ok(args)
+    }
+
+    /// `--view` (or the profile's `view`) as `--hex`/`--terminal`
+    fn apply_view(&mut self) {
+        match self.view {
+            Some(View::Text) => (self.hex, self.terminal) = (false, false),
+            Some(View::Hexdump) => (self.hex, self.terminal) = (true, false),
+            Some(View::Terminal) => (self.hex, self.terminal) = (false, true),
+            None => {}
+        }
+    }
+
+    /// A known device's framing and view, where neither the command line
+    /// nor the profile says otherwise; returns the settings it filled in
+    fn apply_quirk(&mut self, quirk: &Quirk) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if self.data_bits.is_none() && quirk.data_bits.is_some() {
+            self.data_bits = quirk.data_bits;
+            applied.push(SettingsField::DataBits.label());
+        }
+        if self.parity.is_none() && quirk.parity.is_some() {
+            self.parity = quirk.parity;
+            applied.push(SettingsField::Parity.label());
+        }
+        if self.stop_bits.is_none() && quirk.stop_bits.is_some() {
+            self.stop_bits = quirk.stop_bits;
+            applied.push(SettingsField::StopBits.label());
+        }
+        if self.view.is_none() && !self.hex && !self.terminal && quirk.view.is_some() {
+            self.view = quirk.view;
+            self.apply_view();
+            if self.hex {
+                applied.push(config_view::HEX);
+            }
+        }
+        applied
+    }
+
+    /// `--read-only`: drop what the profile (or bench file) would send or
+    /// change on the line; returns what was dropped
+    fn drop_writes(&mut self) -> Vec<&'static str> {
+        let mut dropped = Vec::new();
+        if self.reset.take().is_some() {
+            dropped.push("reset");
+        }
+        if self.dtr.take().is_some() {
+            dropped.push("dtr");
+        }
+        if self.rts.take().is_some() {
+            dropped.push("rts");
+        }
+        if !self.startup_commands.is_empty() {
+            self.startup_commands.clear();
+            dropped.push("startup commands");
+        }
+        if self
+            .passthrough
+            .take()
+            .is_some_and(|mode| mode != PassthroughMode::Off)
+        {
+            dropped.push("passthrough");
+        }
+        dropped
+    }
+
+    /// `--power-save` preset with `--input-poll`/`--render-interval`/
+    /// `--status-poll` on top
+    fn polling(&self) -> Polling {
+        let preset = if self.power_save {
+            Polling::POWER_SAVE
+        } else {
+            Polling::default()
+        };
+        Polling {
+            input: self.input_poll.unwrap_or(preset.input),
+            render: self.render_interval.unwrap_or(preset.render),
+            status: self.status_poll.or(preset.status),
+        }
+    }
+
+    /// Baud rate and framing as given (defaults otherwise)
+    fn framing(&self, baud: u32) -> PortSettings {
+        let mut settings = PortSettings::new(baud);
+        if let Some(data_bits) = self.data_bits {
+            settings.data_bits = data_bits.into();
+        }
+        if let Some(parity) = self.parity {
+            settings.parity = parity.into();
+            settings.stick_parity = parity.is_stick();
+        }
+        if let Some(stop_bits) = self.stop_bits {
+            settings.stop_bits = stop_bits.into();
+        }
+        settings
+    }
+
+    /// How ports opened with `settings` are set up, for every reopen too.
+    /// Parity errors are tagged where the driver can say which bytes they
+    /// are.
+    fn open_options(&self, settings: &PortSettings) -> OpenOptions {
+        OpenOptions {
+            mark_errors: cfg!(target_os = "linux") && settings.parity != serialport::Parity::None,
+            low_latency: self.low_latency,
+        }
+    }
+
+    fn power_commands(&self) -> PowerCommands {
+        PowerCommands {
+            on: self.power_on.clone(),
+            off: self.power_off.clone(),
+            cycle: self.power_cycle.clone(),
+            cycle_delay: Duration::from_millis(self.power_cycle_delay),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    // Options set through SERMONIZER_* variables count as flags too
+    let no_flags = std::env::args_os().len() == 1
+        && !std::env::vars_os().any(|(name, _)| name.to_string_lossy().starts_with("SERMONIZER_"));
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let from_env: HashSet<String> = matches
+        .ids()
+        .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::EnvVariable))
+        .map(|id| id.to_string())
+        .collect();
+    let output = args.output.unwrap_or_default();
+    JSON_OUTPUT.store(output == OutputMode::Json, Ordering::Relaxed);
+    QUIET.store(args.quiet, Ordering::Relaxed);
+    if args.verbose_connect {
+        connect_trace::enable();
+    }
 
-    // Enumerate ports up front
+    // Enumerate ports up front, keeping those that match the filter
+    let filter = PortFilter {
+        vid: args.vid,
+        pid: args.pid,
+        text: args.match_text.clone(),
+    };
     let ports = get_available_ports()?;
+    connect_trace::stage("enumerate", format!("{} serial port(s)", ports.len()));
+    let ports = filter.apply(ports);
+
+    let config = load_config()?;
+    let quirks = QuirkDb::new(
+        config
+            .as_ref()
+            .map(|c| c.quirks.clone())
+            .unwrap_or_default(),
+    );
+
+    if args.check_update {
+        println!("{}", update::check_for_update());
+        return Ok(());
+    }
+    let startup_update_check = config.as_ref().is_some_and(|c| c.check_updates);
 
     if args.list {
-        print_ports(&ports);
+        if args.watch {
+            return watch_ports(&quirks, args.json, &filter);
+        }
+        if args.json {
+            return print_ports_json(&ports, &quirks);
+        }
+        print_ports(&ports, &quirks);
+        return Ok(());
+    }
+
+    // Without --profile, a profile naming the device (VID/PID, serial
+    // number) beats the default one, so a session picks its port first
+    let mut early_port = None;
+    let mut port_chosen = false;
+    let device_profile = match &config {
+        Some(config)
+            if args.profile.is_none()
+                && config.has_device_profiles()
+                && args.command.is_none()
+                && args.power.is_none()
+                && args.bench.is_none()
+                && !args.bug_report =>
+        {
+            let default_port = config
+                .default_profile
+                .as_ref()
+                .and_then(|name| config.profiles.get(name))
+                .and_then(|p| p.port.clone());
+            let port = match args.port.first().cloned().or(default_port) {
+                Some(p) => Some(resolve_port(
+                    &p,
+                    &ports,
+                    &quirks,
+                    output != OutputMode::Json,
+                )?),
+                // Left for the check below to refuse
+                None if output == OutputMode::Json && ports.len() > 1 => None,
+                None => {
+                    port_chosen = true;
+                    Some(choose_port_interactive(&ports, &quirks, &filter)?)
+                }
+            };
+            let found = port
+                .as_ref()
+                .and_then(|port| config.device_profile(port, &ports))
+                .map(|(name, profile)| (name.clone(), profile.clone()));
+            early_port = port;
+            found
+        }
+        _ => None,
+    };
+
+    // Pick a profile: the device's, --profile, else the config's default.
+    // On first run (no config, no flags) walk the user through a setup
+    // wizard instead.
+    let mut profile_name = None;
+    let profile = match (&config, device_profile) {
+        (_, Some((name, profile))) => {
+            say!("Profile: {name} (matches the device)");
+            profile_name = Some(name);
+            Some(profile)
+        }
+        (Some(config), None) => match args.profile.clone().or(config.default_profile.clone()) {
+            Some(name) => {
+                let profile = config
+                    .profiles
+                    .get(&name)
+                    .cloned()
+                    .with_context(|| format!("Profile '{name}' not found in config file"))?;
+                say!("Profile: {name}");
+                profile_name = Some(name);
+                Some(profile)
+            }
+            None => None,
+        },
+        (None, _) if args.profile.is_some() => bail!("--profile given but no config file exists"),
+        (None, _)
+            if no_flags
+                && args.compat.unwrap_or_default().first_run_wizard
+                && std::io::stdin().is_terminal() =>
+        {
+            Some(run_wizard(&ports)?)
+        }
+        (None, _) => None,
+    };
+    if args.bug_report {
+        let mut sections = Vec::new();
+        let mut port_lines: Vec<String> = ports
+            .iter()
+            .flat_map(|p| bug_report::adapter_info(&p.port_name, &ports, &quirks))
+            .collect();
+        if port_lines.is_empty() {
+            port_lines.push("- none found".to_string());
+        }
+        sections.push(("Serial ports", port_lines));
+        if let Some(profile) = &profile {
+            let toml = toml::to_string_pretty(&bug_report::redact_profile(profile))
+                .context("Failed to serialize profile")?;
+            let title = format!("Profile `{}`", profile_name.as_deref().unwrap_or("?"));
+            sections.push(("Profile", vec![title, format!("\n```toml\n{}```", toml)]));
+        }
+        println!(
+            "{}",
+            bug_report::redact(&bug_report::render(&sections, &[]), &[])
+        );
+        return Ok(());
+    }
+
+    let mut origins = args.origins(profile.as_ref(), profile_name.clone(), &from_env);
+    let fixed_rules = FixedRules {
+        tags: !args.tags.is_empty(),
+        level_patterns: !args.level_pattern.is_empty(),
+        alerts: !args.alerts.is_empty(),
+        folds: !args.folds.is_empty(),
+        keys: !args.keys.is_empty(),
+    };
+    let cli_args = args.clone();
+    if let Some(profile) = &profile {
+        args.apply_profile(profile)?;
+    }
+    args.apply_view();
+    if let Some(port) = early_port {
+        match args.port.first_mut() {
+            Some(first) => *first = port,
+            None => args.port.push(port),
+        }
+    }
+    // `tail FILE`: the log stands in for the one port, and is only read
+    let tail = match &args.command {
+        Some(Command::Tail { file }) => Some(file.clone()),
+        _ => None,
+    };
+    if let Some(file) = &tail {
+        args.port = vec![file.display().to_string()];
+        args.read_only = true;
+    }
+    // `-p ttyACM*`, `-p CP2102`: patterns become the port they pick
+    for port in &mut args.port {
+        *port = resolve_port(port, &ports, &quirks, output != OutputMode::Json)?;
+    }
+
+    // Ports for tabs besides the first: further `-p`, or the rest of a
+    // `--bench`, whose first port then stands in for the command line
+    let mut tab_args: Vec<Args> = args
+        .port
+        .iter()
+        .skip(1)
+        .map(|name| Args {
+            port: vec![name.clone()],
+            ..args.clone()
+        })
+        .collect();
+    if let Some(path) = &args.bench {
+        let bench = bench::load_bench(path)?;
+        say!("Bench: {} ({} port(s))", path.display(), bench.ports.len());
+        let profiles = config.as_ref().map(|c| &c.profiles);
+        tab_args = bench
+            .ports
+            .iter()
+            .map(|entry| {
+                let port_profile = match &entry.profile {
+                    Some(name) => {
+                        Some(profiles.and_then(|p| p.get(name)).with_context(|| {
+                            format!("Profile '{name}' not found in config file")
+                        })?)
+                    }
+                    None => profile.as_ref(),
+                };
+                cli_args.for_bench_port(entry, port_profile)
+            })
+            .collect::<Result<_>>()?;
+        args = tab_args.remove(0);
+    }
+    // Every port opened from here on gets the faults
+    faults::enable(args.fault_tx.clone(), args.fault_rx.clone());
+    if args.read_only {
+        read_only::enable();
+        let mut dropped = args.drop_writes();
+        for tab in &mut tab_args {
+            dropped.extend(tab.drop_writes());
+        }
+        dropped.sort_unstable();
+        dropped.dedup();
+        if !dropped.is_empty() {
+            say!("Read-only: ignoring the profile's {}", dropped.join(", "));
+        }
+    }
+
+    if let Some(Command::Check {
+        port,
+        baud,
+        loopback,
+    }) = &args.command
+    {
+        let port_name = match port.clone().or_else(|| args.port.first().cloned()) {
+            Some(p) => resolve_port(&p, &ports, &quirks, true)?,
+            None => choose_port_interactive(&ports, &quirks, &filter)?,
+        };
+        let baud = baud.or(args.baud).unwrap_or(115_200);
+        if !health::run_check(&port_name, baud, *loopback, &ports, &quirks) {
+            std::process::exit(1);
+        }
         return Ok(());
     }
 
+    if let Some(Command::Sweep {
+        port,
+        probe,
+        expect,
+        bauds,
+        data_bits,
+        parities,
+        stop_bits,
+        wait,
+        first,
+    }) = &args.command
+    {
+        let port_name = match port.clone().or_else(|| args.port.first().cloned()) {
+            Some(p) => resolve_port(&p, &ports, &quirks, true)?,
+            None => choose_port_interactive(&ports, &quirks, &filter)?,
+        };
+        let mut probe = probe.clone().into_bytes();
+        probe.extend_from_slice(args.line_ending.unwrap_or(LineEnding::Nl).bytes());
+        let sweep = Sweep {
+            bauds: if bauds.is_empty() {
+                COMMON_BAUDS.to_vec()
+            } else {
+                bauds.clone()
+            },
+            data_bits: data_bits.clone(),
+            parities: parities.clone(),
+            stop_bits: stop_bits.clone(),
+            probe,
+            expect: expect.clone(),
+            wait: Duration::from_millis(*wait),
+            first: *first,
+        };
+        if !sweep::run_sweep(&port_name, &sweep)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Bridge { a, b, baud_b }) = &args.command {
+        let mut settings_a = args.framing(args.baud.unwrap_or(115_200));
+        if let Some(flow) = args.flow {
+            settings_a.flow_control = flow.into();
+        }
+        let mut settings_b = settings_a;
+        settings_b.baud = baud_b.unwrap_or(settings_a.baud);
+        let bridge = Bridge {
+            a: a.clone(),
+            b: b.clone(),
+            settings_a,
+            settings_b,
+            hex: args.hex,
+            log: args
+                .log
+                .as_ref()
+                .map(|path| create_log_writer(path, "bridge"))
+                .transpose()?,
+        };
+        return bridge::run_bridge(&bridge);
+    }
+
+    // Scripted power action: no port needed
+    if let Some(action) = args.power {
+        let power = args.power_commands();
+        if !power.supports(action) {
+            bail!("No command configured for {}", action.name());
+        }
+        print!("{}", power.run(action)?);
+        println!("{} done", action.name());
+        return Ok(());
+    }
+    let compat = args.compat.unwrap_or_default();
+    let auto_login = profile
+        .as_ref()
+        .filter(|_| !args.read_only)
+        .and_then(|p| p.auto_login.as_ref())
+        .map(AutoLogin::new)
+        .transpose()?;
+    if let Some(auto_login) = &auto_login {
+        say!("Auto-login: as {}", auto_login.user());
+    }
+
     // Decide on port
-    let port_name = match &args.port {
+    if output == OutputMode::Json && !tab_args.is_empty() {
+        bail!("--output json follows one port; give --port once");
+    }
+    let port_name = match args.port.first() {
+        Some(p) if port_chosen => p.clone(),
+        Some(p) if tail.is_some() => {
+            say!("Following log: {p}");
+            p.clone()
+        }
         Some(p) => {
-            println!("Using port: {p}");
+            say!("Using port: {p}");
             p.clone()
         }
-        None => choose_port_interactive(&ports)?,
+        // stdin carries data in headless mode, so no prompt
+        None if output == OutputMode::Json && ports.len() > 1 => {
+            bail!("Several ports found; pick one with --port for --output json")
+        }
+        None => choose_port_interactive(&ports, &quirks, &filter)?,
+    };
+    if let Some(by_id) = by_id_path(&port_name)
+        && by_id != port_name
+    {
+        say!("  Stable path: {by_id}");
+    }
+    if args.fix_perms {
+        return permissions::fix_perms(&port_name, &ports);
+    }
+    let ports = if args.wait && !port_present(&port_name) {
+        say!("Waiting for {port_name} to appear...");
+        if !wait_for_port(&port_name, args.wait_timeout) {
+            bail!("{port_name} did not appear in time");
+        }
+        // Now with the device in the list, for quirks and USB details
+        get_available_ports()?
+    } else {
+        ports
+    };
+    let quirk = ports
+        .iter()
+        .find(|p| p.port_name == port_name)
+        .and_then(|p| quirks.for_port(p))
+        .cloned();
+    if connect_trace::enabled() {
+        let detail = match ports.iter().find(|p| p.port_name == port_name) {
+            Some(p) => format!("{port_name}{}", describe_port(p)),
+            None => format!("{port_name} (not among the enumerated ports)"),
+        };
+        let adapter = quirk.as_ref().map(|q| format!(", {}", q.name));
+        connect_trace::stage("port", format!("{detail}{}", adapter.unwrap_or_default()));
+    }
+    let quirk_fields = match &quirk {
+        Some(quirk) => args.apply_quirk(quirk),
+        None => Vec::new(),
     };
 
     // Decide on baud
     let baud = match args.baud {
         Some(b) => {
-            println!("Baud: {b}");
+            say!("Baud: {b}");
             b
         }
         None => {
             let b = 115_200u32;
-            println!("Baud: {b} (default)");
+            say!("Baud: {b} (default)");
             b
         }
     };
@@ -97,35 +1580,349 @@ async fn main() -> Result<()> {
     // Line ending
     let line_ending = args.line_ending.unwrap_or(LineEnding::Nl);
     if args.line_ending.is_none() {
-        println!("Line ending: {} (default)", line_ending.describe());
+        say!("Line ending: {} (default)", line_ending.describe());
     } else {
-        println!("Line ending: {}", line_ending.describe());
+        say!("Line ending: {}", line_ending.describe());
+    }
+    let alt_line_ending = args.alt_line_ending.unwrap_or(LineEnding::None);
+    if args.alt_line_ending.is_some() {
+        say!("Shift+Enter line ending: {}", alt_line_ending.describe());
+    }
+    let rx_line_ending = args.rx_line_ending.unwrap_or_default();
+    if args.rx_line_ending.is_some() {
+        say!("RX line ending: {}", rx_line_ending.describe());
     }
 
+    let echo_mode = args.echo_mode.unwrap_or_default();
+    if echo_mode != EchoMode::Show {
+        say!("Device echo: {echo_mode:?}");
+    }
+
+    if let Some(keys) = &args.instant_keys {
+        say!("Instant keys: {keys} (F3 to toggle)");
+    }
     if args.hex {
-        println!("RX view: HEX");
+        say!("RX view: HEX");
+    }
+    #[cfg(feature = "decoders")]
+    #[cfg_attr(not(feature = "wasm"), allow(unused_mut))]
+    let mut decoders = DecoderRegistry::with_builtins();
+    #[cfg(feature = "wasm")]
+    let plugin_actions = plugins::load_plugins(&args.plugins, &mut decoders)?;
+    #[cfg(not(feature = "wasm"))]
+    if !args.plugins.is_empty() {
+        bail!("--plugin needs sermonizer built with the `wasm` feature");
+    }
+    #[cfg(feature = "decoders")]
+    let pipeline: SharedPipeline = {
+        let pipeline = args
+            .decoder
+            .as_deref()
+            .map(|spec| decoders.pipeline(spec))
+            .transpose()
+            .map_err(anyhow::Error::msg)?;
+        if let Some(pipeline) = &pipeline {
+            say!("Decoder: {} (F11 to switch)", pipeline.spec);
+        }
+        Arc::new(StdMutex::new(pipeline))
+    };
+    #[cfg(not(feature = "decoders"))]
+    if args.decoder.is_some() {
+        bail!("--decoder needs sermonizer built with the `decoders` feature");
     }
     if args.log_ts {
-        println!("Timestamps in logs: ON");
+        say!("Timestamps in logs: ON");
+    }
+    if args.log_dedup {
+        say!("Repeated RX log lines: collapsed");
+    }
+    let stats_log = args.stats_log.as_ref().map(|path| {
+        let interval = args.stats_interval.unwrap_or(3600).max(1);
+        say!("Stats: {} every {interval}s", path.display());
+        StatsLog::new(path, Duration::from_secs(interval))
+    });
+    let influx = match (&args.influx, args.metrics.is_empty()) {
+        (Some(target), false) => {
+            let target = InfluxTarget::parse(target);
+            let interval = args.influx_interval.unwrap_or(10).max(1);
+            say!(
+                "Metrics: {} to {} every {interval}s",
+                args.metrics.len(),
+                target.describe()
+            );
+            Some((target, Duration::from_secs(interval)))
+        }
+        (Some(_), true) => bail!("--influx needs at least one --metric"),
+        (None, false) => bail!("--metric needs --influx to write the values somewhere"),
+        (None, true) => None,
+    };
+    #[cfg(feature = "web")]
+    let web = match &args.web {
+        Some(addr) => {
+            let addr = web::parse_web_addr(addr).map_err(anyhow::Error::msg)?;
+            let feed = web::WebFeed::new();
+            let local = web::spawn_web_view(addr, feed.clone())?;
+            say!("Web view: http://{local}/");
+            Some(feed)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "web"))]
+    if args.web.is_some() {
+        bail!("--web needs sermonizer built with the `web` feature");
+    }
+    let latency = args.latency || args.latency_response.is_some();
+    if latency {
+        say!("Latency measurement: ON");
     }
 
-    // Open port
-    let mut port = serialport::new(&port_name, baud)
-        .timeout(Duration::from_millis(100))
-        .open()
-        .with_context(|| format!("Failed to open serial port '{port_name}'"))?;
+    if !args.keys.is_empty() {
+        say!("Passthrough keys: {} mapped", args.keys.len());
+    }
 
-    // Clear any stale data from the serial buffer
-    let mut discard_buf = [0u8; 1024];
-    while port.read(&mut discard_buf).is_ok() {
-        // Keep reading until timeout to flush buffer
+    let block_rules = blocks::block_rules(&args.blocks)?;
+    if !block_rules.is_empty() {
+        say!("Block rules: {}", block_rules.len());
+    }
+
+    let startup = template::command_sequence(&args.startup_commands, line_ending)
+        .map_err(|e| anyhow::anyhow!("Bad startup command {e}"))?;
+    if !args.startup_commands.is_empty() {
+        say!("Startup commands: {}", args.startup_commands.len());
     }
 
-    println!("Connected. Type to send; press Ctrl-C to exit.\n");
+    // Load the replay file before touching the port so errors surface early
+    let replay_chunks = match &args.replay_tx {
+        Some(path) => {
+            let chunks = load_replay(path)?;
+            say!("Replay: {} ({} chunk(s))", path.display(), chunks.len());
+            Some(chunks)
+        }
+        None => None,
+    };
+    let transcript = match &args.expect_transcript {
+        Some(path) => {
+            let lines = load_transcript(path)?;
+            say!(
+                "Expecting transcript: {} ({} line(s))",
+                path.display(),
+                lines.len()
+            );
+            Some(lines)
+        }
+        None => None,
+    };
+    let replay_capture: replay::Capture = Arc::default();
+    let replay_compare = match &args.replay_expect {
+        Some(path) => {
+            let expected = load_expected(path)?;
+            say!(
+                "Replay diff against: {} ({} line(s))",
+                path.display(),
+                expected.len()
+            );
+            Some(ReplayCompare {
+                expected,
+                ignore: args.replay_ignore.clone(),
+                settle: Duration::from_millis(args.replay_settle),
+                capture: replay_capture.clone(),
+            })
+        }
+        None => None,
+    };
 
+    let mut port_settings = args.framing(baud);
+    #[cfg(not(target_os = "linux"))]
+    if port_settings.stick_parity {
+        anyhow::bail!("Mark/space parity is only supported on Linux");
+    }
+    if args.data_bits.is_none() && args.parity.is_none() && args.stop_bits.is_none() {
+        say!("Framing: {} (default)", port_settings.framing());
+    } else {
+        say!("Framing: {}", port_settings.framing());
+    }
+
+    // Known adapter: warn about limits and apply its defaults
+    if let Some(quirk) = &quirk {
+        say!("Adapter: {}", quirk.name);
+        if let Some(note) = &quirk.note {
+            say!("  Note: {note}");
+        }
+        for field in &quirk_fields {
+            origins.set(field, Origin::Quirk);
+        }
+        if let Some(view) = quirk.view.filter(|_| args.view == quirk.view) {
+            say!("  View: {}", format!("{view:?}").to_lowercase());
+        }
+        if let Some(max) = quirk.max_baud
+            && baud > max
+        {
+            say!("  Warning: {baud} baud is above the reliable maximum of {max}");
+        }
+        if let Some(flow) = quirk.flow_control
+            && args.flow.is_none()
+        {
+            port_settings.flow_control = flow.into();
+            origins.set(SettingsField::FlowControl.label(), Origin::Quirk);
+            say!("  Flow control: {}", port_settings.flow_label());
+        }
+    }
+    if let Some(flow) = args.flow {
+        port_settings.flow_control = flow.into();
+        say!("Flow control: {}", port_settings.flow_label());
+    }
+
+    if args.touch_1200 {
+        say!("1200-baud touch: rebooting the board into its bootloader...");
+        if !reset::touch_1200(&port_name)? {
+            say!("Warning: {port_name} did not come back; the bootloader may use another port");
+        }
+    }
+
+    // Open port, unless another program has it (lock file or exclusive
+    // open). Headless sessions read stdin as data, so they don't ask
+    let retry_open = args.retry_open.map(Duration::from_secs);
+    let on_busy = match output {
+        OutputMode::Json if !args.wait && retry_open.is_none() => OnBusy::Fail,
+        _ => OnBusy::at_start(args.wait, retry_open),
+    };
+    connect_trace::stage(
+        "open",
+        format!("{port_name} at {baud} {}", port_settings.framing()),
+    );
+    // Have bytes with parity errors (or, with mark/space, the other 9th bit)
+    // tagged in the output, where the driver can say which they are
+    let mut open_options = args.open_options(&port_settings);
+    #[cfg(target_os = "linux")]
+    let mut port_fd = -1;
+    let mut port: Box<dyn SerialPort + Send> = match &tail {
+        Some(file) => {
+            open_options.mark_errors = false;
+            Box::new(ReadOnlyPort::new(faults::wrap(Box::new(
+                tail::LogPort::open(file)?,
+            ))))
+        }
+        None => {
+            let (port, opened) =
+                reconnect::open_port(&port_name, &port_settings, open_options, on_busy)?;
+            for note in &opened.notes {
+                say!("{note}");
+            }
+            #[cfg(target_os = "linux")]
+            {
+                port_fd = opened.port_fd;
+            }
+            open_options.mark_errors = opened.error_marks;
+            connect_trace::stage(
+                "opened",
+                if opened.lock_file {
+                    "exclusive, lock file taken"
+                } else {
+                    "exclusive"
+                },
+            );
+            port
+        }
+    };
+    let error_marks = open_options.mark_errors;
+    if connect_trace::enabled() {
+        connect_trace::stage("settings", connect_trace::driver_settings(port.as_ref()));
+    }
+
+    // Before control line changes and resets make the device say something
+    // worth keeping
+    let discarded = discard_stale(port.as_mut(), args.read_only);
+    connect_trace::stage("flush", format!("{discarded} stale byte(s) discarded"));
+    let boot_capture = create_boot_capture(&args, args.capture_boot.clone(), &port_name)?;
+    // Control lines: --dtr/--rts (or the profile) win over adapter defaults
+    let quirk_dtr = quirk
+        .as_ref()
+        .and_then(|q| q.dtr)
+        .filter(|_| !args.read_only);
+    if let Some(dtr) = args.dtr.or(quirk_dtr) {
+        port.write_data_terminal_ready(dtr)
+            .context("Failed to set DTR")?;
+        port_settings.dtr = dtr;
+        if args.dtr.is_none() {
+            origins.set(SettingsField::Dtr.label(), Origin::Quirk);
+        }
+    }
+    let quirk_rts = quirk
+        .as_ref()
+        .and_then(|q| q.rts)
+        .filter(|_| !args.read_only);
+    if let Some(rts) = args.rts.or(quirk_rts) {
+        port.write_request_to_send(rts)
+            .context("Failed to set RTS")?;
+        port_settings.rts = rts;
+        if args.rts.is_none() {
+            origins.set(SettingsField::Rts.label(), Origin::Quirk);
+        }
+    }
+    if args.dtr.is_some() || args.rts.is_some() {
+        let level = |on: bool| if on { "on" } else { "off" };
+        say!(
+            "DTR: {}, RTS: {}",
+            level(port_settings.dtr),
+            level(port_settings.rts)
+        );
+    }
+    if connect_trace::enabled() {
+        connect_trace::stage(
+            "control lines",
+            connect_trace::control_lines(port.as_mut(), &port_settings),
+        );
+    }
+    let break_duration = args.break_duration.unwrap_or(DEFAULT_BREAK);
+    let polling = args.polling();
+    start_port(port.as_mut(), &args, &mut port_settings)?;
+    if args.rs485 {
+        if port_settings.flow_control == serialport::FlowControl::Hardware {
+            anyhow::bail!("--rs485 needs RTS, which RTS/CTS flow control uses");
+        }
+        // Receive until there is something to send
+        port.write_request_to_send(false)
+            .context("Failed to set RTS")?;
+        port_settings.rts = false;
+        let rs485 = Rs485 {
+            before: args.rs485_before.unwrap_or_default(),
+            after: args.rs485_after.unwrap_or_default(),
+        };
+        serial_io::enable_rs485(rs485);
+        say!(
+            "RS-485: RTS high while transmitting ({} ms before, {} ms after)",
+            rs485.before.as_millis(),
+            rs485.after.as_millis()
+        );
+    }
+
+    if let Some(cmd) = args.tx_pipe.clone() {
+        say!("TX pipe: everything sent goes through '{cmd}'");
+        tx_pipe::enable(cmd);
+    }
+
+    if let Some(spec) = &args.fault_tx {
+        say!("Fault injection on TX: {}", spec.describe());
+    }
+    if let Some(spec) = &args.fault_rx {
+        say!("Fault injection on RX: {}", spec.describe());
+    }
+    if args.read_only && tail.is_none() {
+        say!("Read-only: nothing will be written to {port_name}");
+    }
     // Shared port between reader/writer
     let port: Arc<Mutex<Box<dyn SerialPort + Send>>> = Arc::new(Mutex::new(port));
 
+    if tail.is_some() {
+        say!("Following the log; press Ctrl-C to exit.\n");
+    } else if args.read_only {
+        say!("Connected, listening only; press Ctrl-C to exit.\n");
+    } else {
+        say!("Connected. Type to send; press Ctrl-C to exit.\n");
+    }
+    connect_trace::set_port(&port_name);
+    connect_trace::stage("ready", "waiting for data");
+
     // Optional log files
     let rx_log_writer = create_rx_log_writer(args.log.as_ref())?;
     let tx_log_writer = create_tx_log_writer(args.tx_log.as_ref())?;
@@ -156,7 +1953,7 @@ async fn main() -> Result<()> {
     *shutdown_tx.lock().unwrap() = Some(ui_tx.clone());
 
     // Spawn reader thread (RX) - now using the optimized SerialReader
-    let serial_reader = SerialReader::new(
+    let mut serial_reader = SerialReader::new(
         port.clone(),
         running.clone(),
         serial_tx.clone(),
@@ -164,39 +1961,431 @@ async fn main() -> Result<()> {
         args.log_ts,
         rx_log_writer.clone(),
     );
+    if replay_compare.is_some() {
+        serial_reader = serial_reader.with_capture(replay_capture);
+    }
+    if args.log_dedup {
+        serial_reader = serial_reader.with_log_dedup(args.rx_line_ending.unwrap_or_default());
+    }
+    if error_marks {
+        serial_reader = serial_reader.with_error_marks();
+    }
+    if args.reconnect {
+        serial_reader = serial_reader.with_reconnect();
+    }
+    if let Some(cmd) = &args.rx_pipe {
+        say!("RX pipe: {cmd}");
+        let pipe = RxPipe::spawn(cmd.clone(), args.rx_pipe_pane, ui_tx.clone());
+        serial_reader = serial_reader.with_rx_pipe(pipe);
+    }
+    #[cfg(feature = "decoders")]
+    {
+        serial_reader = serial_reader.with_decoder(pipeline.clone());
+    }
     let reader_handle = tokio::spawn(async move {
         serial_reader.run().await;
     });
 
-    // Setup terminal for ratatui
-    terminal::enable_raw_mode().context("Failed to enable raw mode")?;
-    let mut stdout = std::io::stdout();
-    crossterm::execute!(stdout, terminal::EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    if connect_trace::enabled() {
+        tokio::spawn(connect_trace::watch_silence(ui_tx.clone()));
+    }
+
+    // Notes for ports plugged in or pulled out meanwhile
+    tokio::spawn(port_discovery::watch_hotplug(
+        polling.status,
+        running.clone(),
+        ui_tx.clone(),
+    ));
+
+    if startup_update_check {
+        tokio::spawn(update::check_in_background(ui_tx.clone()));
+    }
+
+    // Pick up rule edits in the config file without a restart
+    if profile_name.is_some() {
+        tokio::spawn(config_watch::watch_config(
+            profile_name,
+            fixed_rules,
+            running.clone(),
+            ui_tx.clone(),
+        ));
+    }
+
+    if let Some(path) = &args.tx_fifo {
+        tx_fifo::spawn_tx_fifo(path.clone(), ui_tx.clone())?;
+        say!("TX FIFO: {}", path.display());
+    }
+
+    // Spawn TX replay alongside the UI so responses show up live
+    let tx_queue = TxQueue::default();
+    if let Some(chunks) = replay_chunks {
+        let job = tx_queue.start("replay", chunks.len());
+        tokio::spawn(run_replay(
+            port.clone(),
+            chunks,
+            job,
+            tx_log_writer.clone(),
+            args.log_ts,
+            ui_tx.clone(),
+            replay_compare,
+        ));
+    }
 
     let ui_config = UiConfig {
         running: running.clone(),
+        port_name: port_name.clone(),
+        role: args.role.clone(),
+        adapter_info: bug_report::adapter_info(&port_name, &ports, &quirks),
+        quirks,
+        session_name: args.session.clone(),
         line_ending,
+        alt_line_ending,
+        rx_line_ending,
+        hex: args.hex,
+        #[cfg(feature = "decoders")]
+        decoder: pipeline,
+        #[cfg(feature = "decoders")]
+        decoders: Arc::new(decoders),
+        #[cfg(feature = "decoders")]
+        decoder_spec: args.decoder.clone(),
+        #[cfg(feature = "wasm")]
+        plugin_actions,
+        #[cfg(feature = "web")]
+        web,
+        echo_mode,
+        esc_action: args.esc.unwrap_or_default(),
+        passthrough: args.passthrough.unwrap_or_default(),
+        terminal_view: args.terminal,
+        read_only: args.read_only,
+        ascii_ui: args.ascii_ui || !ui::rendering::unicode_terminal(),
+        polling,
+        rx_pipe_pane: args.rx_pipe.clone().filter(|_| args.rx_pipe_pane),
+        keymap: KeyMap::new(args.keys.clone()),
+        on_exit: args.on_exit,
+        break_duration,
+        reset: args.reset,
+        boot_capture,
+        stats_log,
+        reset_invert: args.reset_invert,
+        instant_keys_enabled: args.instant_keys.is_some(),
+        instant_keys: args.instant_keys.clone().unwrap_or_default(),
+        tag_rules: args.tags.clone(),
+        fold_rules: args.folds.clone(),
+        block_rules,
+        level_parser: LevelParser::new(args.level_pattern.clone()),
+        min_level: args.min_level,
+        transcript,
+        latency,
+        startup,
+        alerts: Alerts::new(args.notify, args.alerts.clone(), args.sounds.clone()),
+        pins: args.pins.clone(),
+        auto_login,
+        latency_response: args.latency_response.clone(),
+        milestones: args.milestones.clone(),
+        port_settings,
+        #[cfg(target_os = "linux")]
+        port_fd,
+        reconnect: args
+            .reconnect
+            .then(|| Reconnect::new(&port_name, &ports, open_options)),
+        port_generation: PortGeneration::default(),
+        open_options,
+        compat,
+        power: args.power_commands(),
+        rx_lines: broadcast::channel(256).0,
+        ui_tx: ui_tx.clone(),
+        tx_queue: tx_queue.clone(),
+        rx_log: rx_log_writer.clone(),
         tx_log: tx_log_writer.clone(),
+        rx_log_path: args.log.clone(),
+        tx_log_path: args.tx_log.clone(),
+        origins,
         log_ts: args.log_ts,
     };
 
-    let ui_res = run_ui(&mut terminal, ui_rx, serial_rx, port.clone(), ui_config).await;
+    // Framing/parity/overrun counters, where the driver reports them
+    #[cfg(target_os = "linux")]
+    line_errors::watch_line_errors(&ui_config);
 
-    // Cleanup terminal
-    terminal::disable_raw_mode()?;
-    crossterm::execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    if let Some((target, interval)) = influx {
+        let export = InfluxExport {
+            rules: args.metrics.clone(),
+            target,
+            interval,
+            port_name: port_name.clone(),
+            session: args.session.clone(),
+        };
+        tokio::spawn(metrics::run_influx_export(
+            export,
+            ui_config.rx_lines.subscribe(),
+            running.clone(),
+            ui_tx.clone(),
+        ));
+    }
+
+    // Further ports get a tab each, set up like the first
+    let mut reader_handles = vec![reader_handle];
+    let mut tabs = Vec::new();
+    for (i, tab_args) in tab_args.iter().enumerate() {
+        let (tab, handle) = open_tab(tab_args, &ui_config, &ports)?;
+        say!("Tab {}: {}", i + 2, tab.ui_config.tab_label());
+        tabs.push(tab);
+        reader_handles.push(handle);
+    }
 
-    // Ensure we stop and join reader
+    let ui_res = match output {
+        OutputMode::Tui => {
+            let first = Tab {
+                ui_rx,
+                serial_rx,
+                port: port.clone(),
+                ui_config,
+            };
+            tabs.insert(0, first);
+            run_tui(tabs, args.merged).await?
+        }
+        OutputMode::Json => ui::headless::run_headless(ui_rx, serial_rx, port.clone(), ui_config)
+            .await
+            .map(|()| SessionEnd::default()),
+    };
+
+    // Ensure we stop and join reader; pending sends end with the session
     running.store(false, Ordering::SeqCst);
-    let _ = reader_handle.await;
+    tx_queue.cancel_all();
+    for handle in reader_handles {
+        let _ = handle.await;
+    }
 
-    if let Err(e) = ui_res {
-        eprintln!("\nError: {e:?}");
+    match ui_res {
+        Ok(end) => {
+            if let Some(view) = args.on_exit
+                && let Err(e) = ui::exit_view::show(view, &end.scrollback)
+            {
+                eprintln!("{e:#}");
+            }
+            for line in end.report {
+                println!("{line}");
+            }
+        }
+        Err(e) => eprintln!("\nError: {e:?}"),
     }
 
-    println!("\nDisconnected. Bye!");
+    say!("\nDisconnected. Bye!");
     Ok(())
 }
+
+/// Read and drop what the port buffered before the session. A read-only
+/// session keeps it: it may be another program's reply. Returns the number
+/// of bytes dropped.
+fn discard_stale(port: &mut dyn SerialPort, read_only: bool) -> usize {
+    let mut discard_buf = [0u8; 1024];
+    let mut discarded = 0;
+    while !read_only && let Ok(n) = port.read(&mut discard_buf) {
+        // Keep reading until timeout to flush buffer
+        discarded += n;
+    }
+    discarded
+}
+
+/// `--capture-boot` for `port_name`, written to `path`
+fn create_boot_capture(
+    args: &Args,
+    path: Option<PathBuf>,
+    port_name: &str,
+) -> Result<Option<BootCapture>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let done = args
+        .boot_done
+        .clone()
+        .context("--capture-boot needs --boot-done")?;
+    let capture = BootCapture::create(&path, done, port_name)?;
+    say!("Boot capture: {}", path.display());
+    Ok(Some(capture))
+}
+
+/// `--break-on-start` and `--reset`, once the port is open and flushed
+fn start_port(port: &mut dyn SerialPort, args: &Args, settings: &mut PortSettings) -> Result<()> {
+    if args.break_on_start {
+        let break_duration = args.break_duration.unwrap_or(DEFAULT_BREAK);
+        port.set_break().context("Failed to send BREAK")?;
+        std::thread::sleep(break_duration);
+        port.clear_break().context("Failed to send BREAK")?;
+        say!("Sent BREAK ({} ms)", break_duration.as_millis());
+        connect_trace::stage("break", format!("{} ms", break_duration.as_millis()));
+    }
+    if let Some(kind) = args.reset {
+        reset::run_blocking(port, kind, args.reset_invert, settings)?;
+        say!("Reset: {}", kind.name());
+        connect_trace::stage("reset", kind.name());
+    }
+    Ok(())
+}
+
+/// Open another port for a tab, with its own reader and logs. `args` are
+/// the port's options (the first port's for further `-p`, its own for a
+/// `--bench`); the rest of the session settings follow the first port.
+fn open_tab(
+    args: &Args,
+    first: &UiConfig,
+    ports: &[serialport::SerialPortInfo],
+) -> Result<(Tab, tokio::task::JoinHandle<()>)> {
+    let port_name = args.port[0].as_str();
+    if args.wait && !port_present(port_name) {
+        say!("Waiting for {port_name} to appear...");
+        if !wait_for_port(port_name, args.wait_timeout) {
+            bail!("{port_name} did not appear in time");
+        }
+    }
+    let mut settings = args.framing(args.baud.unwrap_or(115_200));
+    if let Some(flow) = args.flow {
+        settings.flow_control = flow.into();
+    }
+    settings.dtr = args.dtr.unwrap_or(settings.dtr);
+    settings.rts = args.rts.unwrap_or(settings.rts);
+    #[cfg(not(target_os = "linux"))]
+    if settings.stick_parity {
+        anyhow::bail!("Mark/space parity is only supported on Linux");
+    }
+    // Opened and started like the first port
+    let mut open_options = args.open_options(&settings);
+    let on_busy = OnBusy::at_start(args.wait, args.retry_open.map(Duration::from_secs));
+    let (mut port, opened) = reconnect::open_port(port_name, &settings, open_options, on_busy)?;
+    for note in &opened.notes {
+        say!("{port_name}: {note}");
+    }
+    open_options.mark_errors = opened.error_marks;
+    let error_marks = opened.error_marks;
+    discard_stale(port.as_mut(), args.read_only);
+    // A boot capture shared with the first port gets the port's name added
+    let capture_path = args.capture_boot.as_ref().map(|path| {
+        match first.boot_capture.as_ref().map(BootCapture::path) {
+            Some(first_path) if first_path == path => logging::tab_log_path(path, port_name),
+            _ => path.clone(),
+        }
+    });
+    let boot_capture = create_boot_capture(args, capture_path, port_name)?;
+    start_port(port.as_mut(), args, &mut settings)?;
+    let port: Arc<Mutex<Box<dyn SerialPort + Send>>> = Arc::new(Mutex::new(port));
+
+    // A log file shared with the first port gets the port's name added
+    let log_path = |path: &Option<PathBuf>, first_path: &Option<PathBuf>| {
+        path.as_ref().map(|path| match first_path {
+            Some(first_path) if first_path == path => logging::tab_log_path(path, port_name),
+            _ => path.clone(),
+        })
+    };
+    let rx_log_path = log_path(&args.log, &first.rx_log_path);
+    let tx_log_path = log_path(&args.tx_log, &first.tx_log_path);
+    let rx_log_writer = create_rx_log_writer(rx_log_path.as_ref())?;
+    let tx_log_writer = create_tx_log_writer(tx_log_path.as_ref())?;
+
+    let (ui_tx, ui_rx) = mpsc::unbounded_channel::<UiMessage>();
+    let (serial_tx, serial_rx) = mpsc::unbounded_channel::<SerialData>();
+    let mut ui_config = first.for_tab(port_name, ui_tx.clone());
+    ui_config.role = args.role.clone();
+    ui_config.port_settings = settings;
+    ui_config.line_ending = args.line_ending.unwrap_or(LineEnding::Nl);
+    ui_config.alt_line_ending = args.alt_line_ending.unwrap_or(LineEnding::None);
+    ui_config.rx_line_ending = args.rx_line_ending.unwrap_or_default();
+    ui_config.hex = args.hex;
+    ui_config.reset = args.reset;
+    ui_config.reset_invert = args.reset_invert;
+    ui_config.tag_rules = args.tags.clone();
+    ui_config.fold_rules = args.folds.clone();
+    ui_config.pins = args.pins.clone();
+    ui_config.startup = template::command_sequence(&args.startup_commands, ui_config.line_ending)
+        .map_err(|e| anyhow::anyhow!("Bad startup command for {port_name} {e}"))?;
+    ui_config.adapter_info = bug_report::adapter_info(port_name, ports, &first.quirks);
+    #[cfg(target_os = "linux")]
+    {
+        ui_config.port_fd = opened.port_fd;
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = opened;
+    ui_config.open_options = open_options;
+    ui_config.boot_capture = boot_capture;
+    if first.reconnect.is_some() {
+        ui_config.reconnect = Some(Reconnect::new(port_name, ports, open_options));
+    }
+    ui_config.rx_log = rx_log_writer.clone();
+    ui_config.tx_log = tx_log_writer;
+    ui_config.rx_log_path = rx_log_path;
+    ui_config.tx_log_path = tx_log_path;
+
+    let mut serial_reader = SerialReader::new(
+        port.clone(),
+        first.running.clone(),
+        serial_tx,
+        args.hex,
+        args.log_ts,
+        rx_log_writer,
+    );
+    if args.log_dedup {
+        serial_reader = serial_reader.with_log_dedup(args.rx_line_ending.unwrap_or_default());
+    }
+    if error_marks {
+        serial_reader = serial_reader.with_error_marks();
+    }
+    if args.reconnect {
+        serial_reader = serial_reader.with_reconnect();
+    }
+    #[cfg(feature = "decoders")]
+    {
+        serial_reader = serial_reader.with_decoder(ui_config.decoder.clone());
+    }
+    let handle = tokio::spawn(async move {
+        serial_reader.run().await;
+    });
+    #[cfg(target_os = "linux")]
+    line_errors::watch_line_errors(&ui_config);
+
+    let tab = Tab {
+        ui_rx,
+        serial_rx,
+        port,
+        ui_config,
+    };
+    Ok((tab, handle))
+}
+
+/// Run the interactive UI in the alternate screen; the outer result is
+/// terminal setup/teardown, the inner one the session
+async fn run_tui(tabs: Vec<Tab>, merged_view: bool) -> Result<Result<SessionEnd>> {
+    // Setup terminal for ratatui
+    terminal::enable_raw_mode().context("Failed to enable raw mode")?;
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, terminal::EnterAlternateScreen)?;
+    // Save the window title so it can be restored on exit (xterm, tmux)
+    let _ = crossterm::execute!(stdout, crossterm::style::Print("\x1b[22;0t"));
+    // Pasted text (and IME commits delivered as pastes) arrives as one
+    // event; not available on legacy Windows consoles
+    let _ = crossterm::execute!(stdout, EnableBracketedPaste);
+    // Ask for disambiguated key codes so Shift+Enter can be told apart
+    let keyboard_enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhanced {
+        crossterm::execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let ui_res = run_ui(&mut terminal, tabs, merged_view).await;
+
+    // Cleanup terminal
+    terminal::disable_raw_mode()?;
+    if keyboard_enhanced {
+        crossterm::execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    let _ = crossterm::execute!(terminal.backend_mut(), DisableBracketedPaste);
+    crossterm::execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)?;
+    let _ = crossterm::execute!(
+        terminal.backend_mut(),
+        crossterm::style::Print("\x1b[23;0t")
+    );
+    terminal.show_cursor()?;
+    Ok(ui_res)
+}
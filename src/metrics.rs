@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::http;
+use crate::ui::UiMessage;
+
+/// Extracts the number named `name` from matching RX lines: the first
+/// capture group, or the whole match when there is none
+#[derive(Debug, Clone)]
+pub struct MetricRule {
+    pub name: String,
+    pub pattern: Regex,
+}
+
+/// Parse a `NAME=REGEX` metric (clap value parser)
+pub fn parse_metric_rule(s: &str) -> Result<MetricRule, String> {
+    let (name, pattern) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=REGEX, got '{s}'"))?;
+    if name.is_empty() {
+        return Err("metric name must not be empty".to_string());
+    }
+    let pattern =
+        Regex::new(pattern).map_err(|e| format!("invalid regex for metric '{name}': {e}"))?;
+    Ok(MetricRule {
+        name: name.to_string(),
+        pattern,
+    })
+}
+
+impl MetricRule {
+    /// The value in `line`; `nan` and `inf` parse as numbers but line
+    /// protocol has no way to write them
+    fn extract(&self, line: &str) -> Option<f64> {
+        let caps = self.pattern.captures(line)?;
+        let text = caps.get(1).or_else(|| caps.get(0))?.as_str();
+        text.trim()
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite())
+    }
+}
+
+/// Where `--influx` snapshots go
+#[derive(Debug, Clone)]
+pub enum InfluxTarget {
+    /// Appended to a file
+    File(PathBuf),
+    /// POSTed to a write endpoint, e.g. `http://host:8086/api/v2/write?org=o&bucket=b`
+    Http(String),
+}
+
+impl InfluxTarget {
+    pub fn parse(s: &str) -> Self {
+        if s.starts_with("http://") {
+            InfluxTarget::Http(s.to_string())
+        } else {
+            InfluxTarget::File(PathBuf::from(s))
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            InfluxTarget::File(path) => path.display().to_string(),
+            InfluxTarget::Http(url) => url.clone(),
+        }
+    }
+
+    fn write(&self, lines: &str) -> Result<()> {
+        match self {
+            InfluxTarget::File(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                file.write_all(lines.as_bytes())?;
+                Ok(())
+            }
+            InfluxTarget::Http(url) => {
+                // InfluxDB 2.x wants a token; 1.x endpoints work without
+                let mut headers = vec![("Content-Type", "text/plain; charset=utf-8".to_string())];
+                if let Ok(token) = std::env::var("INFLUX_TOKEN") {
+                    headers.push(("Authorization", format!("Token {token}")));
+                }
+                http::request("POST", url, &headers, lines.as_bytes())
+            }
+        }
+    }
+}
+
+/// Escape a measurement, tag key/value or field key for line protocol
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// One line-protocol point: `sermonizer,port=..,session=.. a=1,b=2 <ns>`
+fn snapshot_line(tags: &[(&str, String)], values: &BTreeMap<String, f64>) -> String {
+    let mut line = String::from("sermonizer");
+    for (key, value) in tags {
+        line.push_str(&format!(",{}={}", escape(key), escape(value)));
+    }
+    let fields: Vec<String> = values
+        .iter()
+        .map(|(name, value)| format!("{}={value}", escape(name)))
+        .collect();
+    line.push(' ');
+    line.push_str(&fields.join(","));
+    let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    line.push_str(&format!(" {nanos}\n"));
+    line
+}
+
+/// Settings for the background exporter
+pub struct InfluxExport {
+    pub rules: Vec<MetricRule>,
+    pub target: InfluxTarget,
+    pub interval: Duration,
+    pub port_name: String,
+    pub session: Option<String>,
+}
+
+/// Pick metrics out of RX lines and write the values that changed since the
+/// previous snapshot every `interval`. Export errors are reported once per
+/// outage on the status line, and the snapshot that failed is dropped.
+pub async fn run_influx_export(
+    export: InfluxExport,
+    mut rx_lines: broadcast::Receiver<String>,
+    running: Arc<AtomicBool>,
+    ui_tx: mpsc::UnboundedSender<UiMessage>,
+) {
+    let mut tags = vec![("port", export.port_name.clone())];
+    if let Some(session) = &export.session {
+        tags.push(("session", session.clone()));
+    }
+    let mut pending: BTreeMap<String, f64> = BTreeMap::new();
+    let mut ticker = tokio::time::interval(export.interval);
+    ticker.tick().await;
+    let mut failing = false;
+
+    while running.load(Ordering::SeqCst) {
+        tokio::select! {
+            line = rx_lines.recv() => match line {
+                Ok(line) => {
+                    for rule in &export.rules {
+                        if let Some(value) = rule.extract(&line) {
+                            pending.insert(rule.name.clone(), value);
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => {
+                if pending.is_empty() {
+                    continue;
+                }
+                let line = snapshot_line(&tags, &pending);
+                let target = export.target.clone();
+                let result = tokio::task::spawn_blocking(move || target.write(&line))
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()));
+                // Sent or not, the next snapshot starts over: a long outage
+                // must not pile up values to replay
+                pending.clear();
+                match result {
+                    Ok(()) => {
+                        if failing {
+                            failing = false;
+                            let _ = ui_tx.send(UiMessage::Status("InfluxDB export recovered".to_string()));
+                        }
+                    }
+                    Err(e) if !failing => {
+                        failing = true;
+                        let _ = ui_tx.send(UiMessage::Status(format!("InfluxDB export failed: {e:#}")));
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_values_are_not_exported() {
+        let rule = parse_metric_rule(r"temp=temp: (\S+)").unwrap();
+        assert_eq!(rule.extract("temp: 21.5"), Some(21.5));
+        assert_eq!(rule.extract("temp: NaN"), None);
+        assert_eq!(rule.extract("temp: inf"), None);
+        assert_eq!(rule.extract("temp: -infinity"), None);
+        assert_eq!(rule.extract("temp: n/a"), None);
+    }
+
+    #[test]
+    fn snapshot_escapes_names() {
+        let values = BTreeMap::from([("rx rate".to_string(), 2.5)]);
+        let line = snapshot_line(&[("port", "COM 3".to_string())], &values);
+        assert!(line.starts_with("sermonizer,port=COM\\ 3 rx\\ rate=2.5 "));
+    }
+}
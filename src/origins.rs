@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+/// Where the value of an effective setting came from
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// Built-in default
+    #[default]
+    Default,
+    /// Command-line flag
+    Flag,
+    /// A `SERMONIZER_*` environment variable
+    Env,
+    /// The selected profile in the config file
+    Profile,
+    /// Adapter quirk for the connected USB device
+    Quirk,
+    /// Changed while running (F2 settings, F3, F5, ...)
+    Runtime,
+}
+
+/// Origin per setting, keyed by the label shown in the config view
+#[derive(Clone, Debug, Default)]
+pub struct Origins {
+    /// Name of the profile [`Origin::Profile`] refers to
+    pub profile: Option<String>,
+    map: BTreeMap<&'static str, Origin>,
+}
+
+impl Origins {
+    pub fn new(profile: Option<String>) -> Self {
+        Self {
+            profile,
+            map: BTreeMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, key: &'static str, origin: Origin) {
+        self.map.insert(key, origin);
+    }
+
+    /// `given` (flag or environment) if the option was set, else profile
+    /// if it set the value, else default
+    pub fn resolve(&mut self, key: &'static str, given: Option<Origin>, profile: bool) {
+        let origin = given.unwrap_or(if profile {
+            Origin::Profile
+        } else {
+            Origin::Default
+        });
+        self.set(key, origin);
+    }
+
+    pub fn describe(&self, key: &str) -> String {
+        match self.map.get(key).copied().unwrap_or_default() {
+            Origin::Default => "default".to_string(),
+            Origin::Flag => "flag".to_string(),
+            Origin::Env => "environment".to_string(),
+            Origin::Profile => match &self.profile {
+                Some(name) => format!("profile '{name}'"),
+                None => "profile".to_string(),
+            },
+            Origin::Quirk => "adapter quirk".to_string(),
+            Origin::Runtime => "changed at runtime".to_string(),
+        }
+    }
+}
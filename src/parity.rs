@@ -0,0 +1,145 @@
+/// Around a byte received with a parity (or framing) error in the text view,
+/// e.g. `⟦41⟧`; the output pane highlights these
+pub const MARK_OPEN: char = '⟦';
+pub const MARK_CLOSE: char = '⟧';
+
+/// How a byte received with an error shows up in the text view
+pub fn marked_byte(byte: u8) -> String {
+    format!("{MARK_OPEN}{byte:02X}{MARK_CLOSE}")
+}
+
+/// Undoes the driver's PARMRK escaping: `FF FF` is a literal 0xFF and
+/// `FF 00 X` is byte X received with a parity or framing error (`FF 00 00`
+/// being a BREAK). An escape split across reads is held back until the next.
+#[derive(Debug, Default)]
+pub struct ErrorMarks {
+    pending: Vec<u8>,
+}
+
+impl ErrorMarks {
+    /// The clean bytes and the indices (into them) of the ones received with
+    /// an error
+    pub fn unescape(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<usize>) {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+        let mut clean = Vec::with_capacity(input.len());
+        let mut errors = Vec::new();
+        let mut i = 0;
+        while i < input.len() {
+            if input[i] != 0xFF {
+                clean.push(input[i]);
+                i += 1;
+                continue;
+            }
+            match input.get(i + 1) {
+                Some(0xFF) => {
+                    clean.push(0xFF);
+                    i += 2;
+                }
+                Some(0x00) => {
+                    let Some(&byte) = input.get(i + 2) else {
+                        break;
+                    };
+                    errors.push(clean.len());
+                    clean.push(byte);
+                    i += 3;
+                }
+                // Not an escape the driver produces; keep it as it came
+                Some(_) => {
+                    clean.push(0xFF);
+                    i += 1;
+                }
+                None => break,
+            }
+        }
+        self.pending = input[i..].to_vec();
+        (clean, errors)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::os::fd::RawFd;
+
+    fn update_termios(fd: RawFd, change: impl FnOnce(&mut libc::termios2)) -> std::io::Result<()> {
+        // SAFETY: TCGETS2 fills a termios2 and TCSETS2 reads it back, `fd`
+        // being the open port
+        unsafe {
+            let mut termios: libc::termios2 = std::mem::zeroed();
+            if libc::ioctl(fd, libc::TCGETS2, &mut termios) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            change(&mut termios);
+            if libc::ioctl(fd, libc::TCSETS2, &termios) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Switch mark/space ("stick") parity on or off: with it the parity bit
+    /// is always 1 (odd parity set) or always 0 (even parity set)
+    pub fn set_stick_parity(fd: RawFd, on: bool) -> std::io::Result<()> {
+        update_termios(fd, |termios| {
+            if on {
+                termios.c_cflag |= libc::CMSPAR;
+            } else {
+                termios.c_cflag &= !libc::CMSPAR;
+            }
+        })
+    }
+
+    /// Have the driver escape bytes received with a parity or framing error
+    /// (PARMRK) instead of passing them on as if they were fine; see
+    /// [`super::ErrorMarks`]
+    pub fn mark_errors(fd: RawFd) -> std::io::Result<()> {
+        update_termios(fd, |termios| {
+            termios.c_iflag |= libc::PARMRK;
+            termios.c_iflag &= !(libc::IGNPAR | libc::ISTRIP);
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::{mark_errors, set_stick_parity};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_bytes_pass_through() {
+        let mut marks = ErrorMarks::default();
+        assert_eq!(marks.unescape(b"abc"), (b"abc".to_vec(), vec![]));
+    }
+
+    #[test]
+    fn escapes_become_bytes_and_error_indices() {
+        let mut marks = ErrorMarks::default();
+        let (clean, errors) =
+            marks.unescape(&[b'a', 0xFF, 0xFF, 0xFF, 0x00, 0x41, 0xFF, 0x00, 0x00]);
+        assert_eq!(clean, [b'a', 0xFF, 0x41, 0x00]);
+        assert_eq!(errors, [2, 3]);
+    }
+
+    #[test]
+    fn escapes_split_across_reads_are_held_back() {
+        let mut marks = ErrorMarks::default();
+        assert_eq!(marks.unescape(&[b'a', 0xFF]), (vec![b'a'], vec![]));
+        assert_eq!(marks.unescape(&[0x00]), (vec![], vec![]));
+        assert_eq!(marks.unescape(&[0x41, b'b']), (vec![0x41, b'b'], vec![0]));
+        assert_eq!(marks.unescape(&[0xFF]), (vec![], vec![]));
+        assert_eq!(marks.unescape(&[0xFF]), (vec![0xFF], vec![]));
+    }
+
+    #[test]
+    fn unknown_escapes_are_kept() {
+        let mut marks = ErrorMarks::default();
+        assert_eq!(marks.unescape(&[0xFF, b'x']), (vec![0xFF, b'x'], vec![]));
+    }
+
+    #[test]
+    fn marks_show_the_byte_in_hex() {
+        assert_eq!(marked_byte(0x0A), "⟦0A⟧");
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{Result, bail};
+use serialport::{SerialPortInfo, SerialPortType};
+
+/// Where `--fix-perms` suggests putting its udev rule
+const RULES_FILE: &str = "/etc/udev/rules.d/99-sermonizer.rules";
+
+/// Why opening `port_name` was refused and what to do about it, one line
+/// each: the device's group and mode, and whether you are in that group
+/// (Linux; elsewhere nothing to add)
+pub fn diagnose(port_name: &str) -> Vec<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut lines = linux::diagnose(port_name);
+        if !lines.is_empty() {
+            lines.push(format!(
+                "`sermonizer --fix-perms -p {port_name}` prints a udev rule that grants access"
+            ));
+        }
+        lines
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = port_name;
+        Vec::new()
+    }
+}
+
+/// `--fix-perms`: the diagnosis, then a udev rule giving the port's group
+/// (and the user at the seat) access, keyed on its USB VID/PID when it
+/// has them
+pub fn fix_perms(port_name: &str, ports: &[SerialPortInfo]) -> Result<()> {
+    if !cfg!(target_os = "linux") {
+        bail!("--fix-perms writes a udev rule, which only exists on Linux");
+    }
+    #[cfg(target_os = "linux")]
+    for line in linux::diagnose(port_name) {
+        println!("{line}");
+    }
+    let usb = ports.iter().find_map(|p| match &p.port_type {
+        SerialPortType::UsbPort(info) if p.port_name == port_name => Some(info),
+        _ => None,
+    });
+    let device = match usb {
+        Some(info) => format!(
+            "ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\"",
+            info.vid, info.pid
+        ),
+        None => {
+            let device = std::fs::canonicalize(port_name).unwrap_or_else(|_| port_name.into());
+            let kernel = device.file_name().map_or_else(
+                || port_name.to_string(),
+                |n| n.to_string_lossy().into_owned(),
+            );
+            format!("KERNEL==\"{kernel}\"")
+        }
+    };
+    #[cfg(target_os = "linux")]
+    let group = linux::serial_group();
+    #[cfg(not(target_os = "linux"))]
+    let group = "dialout";
+    println!();
+    println!("udev rule for {port_name} (save as {RULES_FILE}):");
+    println!();
+    println!("SUBSYSTEM==\"tty\", {device}, MODE=\"0660\", GROUP=\"{group}\", TAG+=\"uaccess\"");
+    println!();
+    println!("then load it and replug the device:");
+    println!("  sudo udevadm control --reload-rules && sudo udevadm trigger");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::os::unix::fs::MetadataExt;
+
+    /// The groups distributions give serial ports, in order of preference
+    const SERIAL_GROUPS: &[&str] = &["dialout", "uucp", "tty"];
+
+    /// A line of /etc/group: name, gid and listed members
+    struct Group {
+        name: String,
+        gid: u32,
+        members: Vec<String>,
+    }
+
+    fn groups() -> Vec<Group> {
+        let text = std::fs::read_to_string("/etc/group").unwrap_or_default();
+        text.lines()
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?.to_string();
+                let gid = fields.nth(1)?.parse().ok()?;
+                let members = fields
+                    .next()
+                    .unwrap_or("")
+                    .split(',')
+                    .filter(|m| !m.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                Some(Group { name, gid, members })
+            })
+            .collect()
+    }
+
+    /// Our user name and primary group from /etc/passwd, falling back to
+    /// `$USER` for directory users
+    fn user() -> (String, Option<u32>) {
+        let uid = unsafe { libc::getuid() };
+        let text = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+        let entry = text.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.get(2)?.parse::<u32>().ok()? != uid {
+                return None;
+            }
+            Some((fields[0].to_string(), fields.get(3)?.parse().ok()))
+        });
+        entry.unwrap_or_else(|| {
+            (
+                std::env::var("USER").unwrap_or_else(|_| uid.to_string()),
+                None,
+            )
+        })
+    }
+
+    /// The groups this process actually has: joining a group only takes
+    /// effect at the next login
+    fn session_groups() -> Vec<u32> {
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        let mut gids = vec![0; count.max(0) as usize];
+        let count = unsafe { libc::getgroups(count, gids.as_mut_ptr()) };
+        gids.truncate(count.max(0) as usize);
+        gids.push(unsafe { libc::getegid() });
+        gids
+    }
+
+    /// The serial group this system has, for a new udev rule
+    pub fn serial_group() -> &'static str {
+        let groups = groups();
+        SERIAL_GROUPS
+            .iter()
+            .find(|name| groups.iter().any(|g| g.name == **name))
+            .unwrap_or(&SERIAL_GROUPS[0])
+    }
+
+    fn mode_string(mode: u32) -> String {
+        (0..9)
+            .map(|i| {
+                if mode & (0o400 >> i) == 0 {
+                    '-'
+                } else {
+                    ['r', 'w', 'x'][i % 3]
+                }
+            })
+            .collect()
+    }
+
+    pub fn diagnose(port_name: &str) -> Vec<String> {
+        let Ok(meta) = std::fs::metadata(port_name) else {
+            return Vec::new();
+        };
+        let groups = groups();
+        let group = groups.iter().find(|g| g.gid == meta.gid());
+        let group_name = group.map_or_else(|| meta.gid().to_string(), |g| g.name.clone());
+        let (user, primary) = user();
+        let mut lines = vec![format!(
+            "{port_name}: group {group_name}, mode {}",
+            mode_string(meta.mode())
+        )];
+        if meta.mode() & 0o060 != 0o060 {
+            lines.push(format!(
+                "The {group_name} group can't read and write it either: give the device a serial group with a udev rule"
+            ));
+        } else if session_groups().contains(&meta.gid()) {
+            lines.push(format!(
+                "You are in {group_name}, yet access was denied: an ACL or security module may be in the way"
+            ));
+        } else if primary == Some(meta.gid()) || group.is_some_and(|g| g.members.contains(&user)) {
+            lines.push(format!(
+                "You ({user}) are in {group_name}, but not in this login session yet: log out and back in (or run `newgrp {group_name}`)"
+            ));
+        } else {
+            lines.push(format!(
+                "You ({user}) are not in {group_name}: run `sudo usermod -aG {group_name} {user}`, then log out and back in"
+            ));
+        }
+        lines
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::decoders::{Decoder, DecoderRegistry};
+
+/// Instructions a plugin may execute per call before it is stopped
+const FUEL_PER_CALL: u64 = 50_000_000;
+
+/// Linear memory a plugin instance may grow to
+const MAX_MEMORY: usize = 64 << 20;
+
+/// Largest output a plugin may hand back from one call
+const MAX_OUTPUT: usize = 1 << 20;
+
+/// A sandboxed WebAssembly plugin loaded with `--plugin`. The module gets no
+/// imports at all (no files, network or clock), a capped memory and a fuel
+/// budget per call; it exports `memory`,
+/// `alloc(len) -> ptr` and at least one of
+///
+/// - `decode(ptr, len) -> i64`: a decoder stage; the result packs
+///   `(out_ptr << 32) | out_len`, and each `\n`-separated line of the output
+///   becomes one frame
+/// - `on_line(ptr, len) -> i64`: an action called with every RX line; the
+///   output (same packing) is sent to the device unless it is empty
+#[derive(Clone)]
+pub struct WasmPlugin {
+    pub name: String,
+    engine: Engine,
+    module: Arc<Module>,
+    has_decode: bool,
+    has_on_line: bool,
+}
+
+impl WasmPlugin {
+    pub fn load(path: &Path) -> Result<Self> {
+        let wasm = std::fs::read(path)
+            .with_context(|| format!("Failed to read plugin {}", path.display()))?;
+        Self::from_wasm(path, &wasm)
+    }
+
+    /// The plugin in `wasm`, named after `path`
+    fn from_wasm(path: &Path, wasm: &[u8]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)
+            .map_err(|e| anyhow!("Invalid plugin {}: {e}", path.display()))?;
+        if module.imports().len() > 0 {
+            bail!(
+                "Plugin {} imports host functions; plugins must be self-contained",
+                path.display()
+            );
+        }
+        let exports: Vec<String> = module.exports().map(|e| e.name().to_string()).collect();
+        let has = |name: &str| exports.iter().any(|e| e == name);
+        let plugin = Self {
+            name: path
+                .file_stem()
+                .map_or("plugin".into(), |s| s.to_string_lossy().into_owned()),
+            engine,
+            module: Arc::new(module),
+            has_decode: has("decode"),
+            has_on_line: has("on_line"),
+        };
+        if !plugin.has_decode && !plugin.has_on_line {
+            bail!(
+                "Plugin {} exports neither `decode` nor `on_line`",
+                path.display()
+            );
+        }
+        // Catch missing `memory`/`alloc` now rather than on the first line
+        plugin.instantiate()?;
+        Ok(plugin)
+    }
+
+    pub fn is_decoder(&self) -> bool {
+        self.has_decode
+    }
+
+    pub fn is_action(&self) -> bool {
+        self.has_on_line
+    }
+
+    /// Fresh instance with its own memory
+    pub fn instantiate(&self) -> Result<PluginInstance> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .memories(1)
+            .tables(1)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| anyhow!("Plugin {}: {e}", self.name))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .with_context(|| format!("Plugin {} does not export `memory`", self.name))?;
+        let func = |name: &str| -> Result<TypedFunc<(i32, i32), i64>> {
+            instance
+                .get_typed_func(&store, name)
+                .map_err(|e| anyhow!("Plugin {}: `{name}`: {e}", self.name))
+        };
+        Ok(PluginInstance {
+            name: self.name.clone(),
+            decode: if self.has_decode {
+                Some(func("decode")?)
+            } else {
+                None
+            },
+            on_line: if self.has_on_line {
+                Some(func("on_line")?)
+            } else {
+                None
+            },
+            alloc: instance
+                .get_typed_func(&store, "alloc")
+                .map_err(|e| anyhow!("Plugin {}: `alloc`: {e}", self.name))?,
+            memory,
+            store,
+        })
+    }
+}
+
+pub struct PluginInstance {
+    pub name: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    decode: Option<TypedFunc<(i32, i32), i64>>,
+    on_line: Option<TypedFunc<(i32, i32), i64>>,
+}
+
+impl PluginInstance {
+    fn call(&mut self, func: TypedFunc<(i32, i32), i64>, input: &[u8]) -> Result<Vec<u8>> {
+        self.store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| anyhow!("{e}"))?;
+        let len = i32::try_from(input.len())?;
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, input)
+            .map_err(|e| anyhow!("{e}"))?;
+        let packed = func.call(&mut self.store, (ptr, len))? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize);
+        if out_len > MAX_OUTPUT {
+            bail!("output of {out_len} bytes is over the {MAX_OUTPUT} byte limit");
+        }
+        // Checked before allocating: the plugin picks `out_len`
+        let data = self.memory.data(&self.store);
+        let out = out_ptr
+            .checked_add(out_len)
+            .and_then(|end| data.get(out_ptr..end))
+            .ok_or_else(|| anyhow!("output {out_ptr:#x}+{out_len} is outside its memory"))?;
+        Ok(out.to_vec())
+    }
+
+    /// Run `on_line`; `None` when there is nothing to send
+    pub fn on_line(&mut self, line: &str) -> Result<Option<String>> {
+        let Some(func) = self.on_line else {
+            return Ok(None);
+        };
+        let out = self.call(func, line.as_bytes())?;
+        Ok((!out.is_empty()).then(|| String::from_utf8_lossy(&out).into_owned()))
+    }
+}
+
+impl Decoder for PluginInstance {
+    fn decode(&mut self, input: &[u8]) -> Vec<Vec<u8>> {
+        let Some(func) = self.decode else {
+            return vec![input.to_vec()];
+        };
+        match self.call(func, input) {
+            Ok(out) if out.is_empty() => Vec::new(),
+            Ok(out) => out
+                .strip_suffix(b"\n")
+                .unwrap_or(&out)
+                .split(|&b| b == b'\n')
+                .map(<[u8]>::to_vec)
+                .collect(),
+            Err(e) => vec![format!("[plugin {} error: {e}]", self.name).into_bytes()],
+        }
+    }
+}
+
+/// Load `--plugin` files: decoders are added to `registry`, instances of
+/// the `on_line` plugins are returned
+pub fn load_plugins(
+    paths: &[PathBuf],
+    registry: &mut DecoderRegistry,
+) -> Result<Vec<PluginInstance>> {
+    let mut actions = Vec::new();
+    for path in paths {
+        let plugin = WasmPlugin::load(path)?;
+        say!("Plugin: {}", plugin.name);
+        if plugin.is_action() {
+            actions.push(plugin.instantiate()?);
+        }
+        if plugin.is_decoder() {
+            let name = plugin.name.clone();
+            registry.register(name, "WebAssembly plugin", move || {
+                plugin
+                    .instantiate()
+                    .map(|i| Box::new(i) as Box<dyn Decoder>)
+                    .map_err(|e| format!("{e:#}"))
+            });
+        }
+    }
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plugin from WebAssembly text
+    fn plugin(wat: &str) -> Result<WasmPlugin> {
+        WasmPlugin::from_wasm(Path::new("test.wasm"), &wat::parse_str(wat).unwrap())
+    }
+
+    /// A decoder plugin whose `decode` body leaves the packed result
+    fn decoder(body: &str) -> PluginInstance {
+        plugin(&format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "decode") (param $ptr i32) (param $len i32) (result i64)
+                    {body}))"#
+        ))
+        .unwrap()
+        .instantiate()
+        .unwrap()
+    }
+
+    fn packed(ptr: &str, len: &str) -> String {
+        format!(
+            "(i64.or (i64.shl (i64.extend_i32_u {ptr}) (i64.const 32)) (i64.extend_i32_u {len}))"
+        )
+    }
+
+    fn error_of(frames: Vec<Vec<u8>>) -> String {
+        assert_eq!(frames.len(), 1);
+        let text = String::from_utf8(frames.into_iter().next().unwrap()).unwrap();
+        assert!(text.starts_with("[plugin test error: "), "{text}");
+        text
+    }
+
+    #[test]
+    fn output_lines_become_frames() {
+        let mut echo = decoder(&packed("(local.get $ptr)", "(local.get $len)"));
+        assert_eq!(echo.decode(b"a\nb\n"), [b"a".to_vec(), b"b".to_vec()]);
+        assert!(echo.decode(b"").is_empty());
+    }
+
+    #[test]
+    fn output_over_the_limit_is_refused() {
+        let len = format!("(i32.const {})", MAX_OUTPUT + 1);
+        let mut big = decoder(&packed("(i32.const 0)", &len));
+        assert!(error_of(big.decode(b"x")).contains("byte limit"));
+    }
+
+    #[test]
+    fn output_outside_memory_is_refused() {
+        let mut past_end = decoder(&packed("(i32.const 65000)", "(i32.const 1000)"));
+        assert!(error_of(past_end.decode(b"x")).contains("outside its memory"));
+        let mut wrapping = decoder(&packed("(i32.const -1)", "(i32.const 16)"));
+        assert!(error_of(wrapping.decode(b"x")).contains("outside its memory"));
+    }
+
+    #[test]
+    fn endless_loops_run_out_of_fuel() {
+        let mut spin = decoder("(loop $l (br $l)) (i64.const 0)");
+        error_of(spin.decode(b"x"));
+        // The next call gets a fresh budget
+        error_of(spin.decode(b"x"));
+    }
+
+    #[test]
+    fn memory_cannot_grow_past_the_cap() {
+        // One byte of output when the grow is refused, none if it worked
+        let pages = MAX_MEMORY / 65536 + 1;
+        let refused = format!("(i32.eq (memory.grow (i32.const {pages})) (i32.const -1))");
+        let mut grow = decoder(&packed("(i32.const 0)", &refused));
+        assert_eq!(grow.decode(b"x"), [vec![0]]);
+    }
+
+    #[test]
+    fn host_imports_are_refused() {
+        let err = plugin(
+            r#"(module
+                (import "env" "clock" (func $clock (result i64)))
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "decode") (param i32 i32) (result i64) (call $clock)))"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("imports host functions"), "{err}");
+    }
+
+    #[test]
+    fn missing_exports_are_reported_on_load() {
+        let no_memory = plugin(
+            r#"(module
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "decode") (param i32 i32) (result i64) (i64.const 0)))"#,
+        );
+        assert!(format!("{:#}", no_memory.err().unwrap()).contains("`memory`"));
+        let no_alloc = plugin(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "decode") (param i32 i32) (result i64) (i64.const 0)))"#,
+        );
+        assert!(format!("{:#}", no_alloc.err().unwrap()).contains("`alloc`"));
+        let neither = plugin(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0)))"#,
+        );
+        assert!(format!("{:#}", neither.err().unwrap()).contains("neither"));
+    }
+}
@@ -1,6 +1,17 @@
 use anyhow::{Context, Result, bail};
+use chrono::Local;
+use serde::Serialize;
 use serialport::{SerialPortInfo, SerialPortType};
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::port_picker::pick_port;
+use crate::quirks::QuirkDb;
+use crate::transcript::glob_match;
+use crate::ui::UiMessage;
 
 pub fn get_available_ports() -> Result<Vec<SerialPortInfo>> {
     let all_ports = serialport::available_ports().context("Failed to list serial ports")?;
@@ -14,45 +25,496 @@ pub fn get_available_ports() -> Result<Vec<SerialPortInfo>> {
     Ok(ports)
 }
 
-pub fn print_ports(ports: &[SerialPortInfo]) {
+/// `--vid`/`--pid`/`--match`: the ports `--list` and auto-selection
+/// consider
+#[derive(Clone, Debug, Default)]
+pub struct PortFilter {
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    /// Part of the port name, manufacturer, product or serial number
+    /// (any case)
+    pub text: Option<String>,
+}
+
+impl PortFilter {
+    pub fn is_empty(&self) -> bool {
+        self.vid.is_none() && self.pid.is_none() && self.text.is_none()
+    }
+
+    pub fn matches(&self, p: &SerialPortInfo) -> bool {
+        let usb = match &p.port_type {
+            SerialPortType::UsbPort(info) => Some(info),
+            _ => None,
+        };
+        if self.vid.is_some() && usb.map(|i| i.vid) != self.vid {
+            return false;
+        }
+        if self.pid.is_some() && usb.map(|i| i.pid) != self.pid {
+            return false;
+        }
+        let Some(text) = &self.text else {
+            return true;
+        };
+        let text = text.to_lowercase();
+        let mut fields = vec![Some(p.port_name.as_str())];
+        if let Some(info) = usb {
+            fields.extend([
+                info.manufacturer.as_deref(),
+                info.product.as_deref(),
+                info.serial_number.as_deref(),
+            ]);
+        }
+        fields
+            .into_iter()
+            .flatten()
+            .any(|f| f.to_lowercase().contains(&text))
+    }
+
+    pub fn apply(&self, ports: Vec<SerialPortInfo>) -> Vec<SerialPortInfo> {
+        ports.into_iter().filter(|p| self.matches(p)).collect()
+    }
+
+    /// The filter as given, e.g. `--vid 0x0483 --match stlink`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(vid) = self.vid {
+            parts.push(format!("--vid 0x{vid:04x}"));
+        }
+        if let Some(pid) = self.pid {
+            parts.push(format!("--pid 0x{pid:04x}"));
+        }
+        if let Some(text) = &self.text {
+            parts.push(format!("--match {text:?}"));
+        }
+        parts.join(" ")
+    }
+}
+
+/// A USB vendor or product ID in hex, as lsusb shows it: `0483` or
+/// `0x0483` (clap value parser)
+pub fn parse_usb_id(s: &str) -> Result<u16, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("'{s}' is not a hex USB ID like 0x0483"))
+}
+
+/// How often a session checks for ports coming and going
+const HOTPLUG_POLL: Duration = Duration::from_secs(1);
+
+/// How often `--wait` looks for the port
+const WAIT_POLL: Duration = Duration::from_millis(250);
+
+/// Whether `port_name` can be opened: enumerated, or at least there as a
+/// device node or symlink (`/dev/serial/by-id/...`, ptys)
+pub fn port_present(port_name: &str) -> bool {
+    serialport::available_ports()
+        .map(|ports| ports.iter().any(|p| p.port_name == port_name))
+        .unwrap_or(false)
+        || std::path::Path::new(port_name).exists()
+}
+
+/// Whether `-p PATTERN` picks `p`: a glob (`ttyACM*`, `*CP2102*`) against
+/// the port name, with or without its directory, and the USB strings, or
+/// else part of any of them, in any case
+fn pattern_matches(pattern: &str, p: &SerialPortInfo) -> bool {
+    if !pattern.contains('*') {
+        let filter = PortFilter {
+            text: Some(pattern.to_string()),
+            ..PortFilter::default()
+        };
+        return filter.matches(p);
+    }
+    let pattern = pattern.to_lowercase();
+    let short = short_name(&p.port_name);
+    let mut fields = vec![Some(p.port_name.as_str()), Some(short.as_str())];
+    if let SerialPortType::UsbPort(info) = &p.port_type {
+        fields.extend([
+            info.manufacturer.as_deref(),
+            info.product.as_deref(),
+            info.serial_number.as_deref(),
+        ]);
+    }
+    fields
+        .into_iter()
+        .flatten()
+        .any(|f| glob_match(&pattern, &f.to_lowercase()))
+}
+
+/// What `-p` names: a port that is there as given (a by-id link becomes
+/// the device it points to), else the one port the pattern matches.
+/// Several matches go to the chooser, restricted to them, or are an error
+/// without a `prompt`.
+pub fn resolve_port(
+    arg: &str,
+    ports: &[SerialPortInfo],
+    quirks: &QuirkDb,
+    prompt: bool,
+) -> Result<String> {
+    if port_present(arg) {
+        // A by-id (or other) link to a listed port: use the device itself,
+        // so its quirks and USB details apply
+        let device = std::fs::canonicalize(arg).map(|d| d.to_string_lossy().into_owned());
+        if let Ok(device) = device
+            && device != arg
+            && ports.iter().any(|p| p.port_name == device)
+        {
+            say!("{arg} is {device}");
+            return Ok(device);
+        }
+        return Ok(arg.to_string());
+    }
+    let matches: Vec<SerialPortInfo> = ports
+        .iter()
+        .filter(|p| pattern_matches(arg, p))
+        .cloned()
+        .collect();
+    match matches.len() {
+        // A name that isn't there yet (`--wait`) or that the open will
+        // explain; a glob can't be opened, though
+        0 if arg.contains('*') => bail!("No serial port matches '{arg}'"),
+        0 => Ok(arg.to_string()),
+        1 => {
+            let name = matches[0].port_name.clone();
+            say!("'{arg}' matches {name}");
+            Ok(name)
+        }
+        n if !prompt => {
+            let names: Vec<&str> = matches.iter().map(|p| p.port_name.as_str()).collect();
+            bail!(
+                "'{arg}' matches {n} ports ({}); be more specific",
+                names.join(", ")
+            )
+        }
+        n => {
+            say!("'{arg}' matches {n} ports");
+            choose_port(&matches, quirks, &PortFilter::default(), &|p| {
+                pattern_matches(arg, p)
+            })
+        }
+    }
+}
+
+/// Where udev keeps names for serial devices that survive reboots and
+/// replugging (Linux)
+const BY_ID_DIR: &str = "/dev/serial/by-id";
+
+/// The `/dev/serial/by-id/...` link to `port_name`: the same every time
+/// the adapter is plugged in, while ttyUSB numbers shuffle
+pub fn by_id_path(port_name: &str) -> Option<String> {
+    let device = std::fs::canonicalize(port_name).ok()?;
+    std::fs::read_dir(BY_ID_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|link| std::fs::canonicalize(link).is_ok_and(|target| target == device))
+        .map(|link| link.to_string_lossy().into_owned())
+}
+
+/// The port without its directory, `/dev/ttyUSB0` -> `ttyUSB0` (`COM3`
+/// stays as it is), for labels and file names
+pub fn short_name(port_name: &str) -> String {
+    std::path::Path::new(port_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| port_name.to_string())
+}
+
+/// `--wait`: block until `port_name` shows up, or `timeout` runs out
+/// (never without one). Returns whether it showed up.
+pub fn wait_for_port(port_name: &str, timeout: Option<Duration>) -> bool {
+    let started = std::time::Instant::now();
+    while !port_present(port_name) {
+        if timeout.is_some_and(|t| started.elapsed() >= t) {
+            return false;
+        }
+        std::thread::sleep(WAIT_POLL);
+    }
+    // A freshly enumerated device may not have its permissions yet
+    std::thread::sleep(Duration::from_millis(200));
+    true
+}
+
+/// Which interface of a composite USB device a port belongs to: several
+/// CDC ACM functions (console, data, debug probe UART) share VID, PID and
+/// serial number
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsbInterface {
+    /// bInterfaceNumber
+    pub number: Option<u8>,
+    /// The interface string descriptor, e.g. "CDC Console" (Linux)
+    pub name: Option<String>,
+}
+
+impl UsbInterface {
+    pub fn of(p: &SerialPortInfo) -> Self {
+        let number = match &p.port_type {
+            SerialPortType::UsbPort(info) => info.interface,
+            _ => None,
+        };
+        UsbInterface {
+            number,
+            name: interface_name(&p.port_name),
+        }
+    }
+
+    /// ` if#2 "CDC Console"`
+    fn describe(&self) -> String {
+        let mut s = String::new();
+        if let Some(number) = self.number {
+            s.push_str(&format!(" if#{number}"));
+        }
+        if let Some(name) = &self.name {
+            s.push_str(&format!(" \"{name}\""));
+        }
+        s
+    }
+}
+
+/// The USB interface string from sysfs: ACM ports hang off the interface
+/// directly, usb-serial ports (FTDI, CP210x) one level further down
+#[cfg(target_os = "linux")]
+fn interface_name(port_name: &str) -> Option<String> {
+    let tty = std::path::Path::new(port_name).file_name()?;
+    let device = std::path::Path::new("/sys/class/tty")
+        .join(tty)
+        .join("device");
+    [device.join("interface"), device.join("../interface")]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_name(_port_name: &str) -> Option<String> {
+    None
+}
+
+/// Type and USB details after the port name, e.g. `  (USB vid=0x0403 ...)`
+pub fn describe_port(p: &SerialPortInfo) -> String {
+    match &p.port_type {
+        SerialPortType::UsbPort(info) => {
+            let mut s = format!("  (USB vid=0x{:04x} pid=0x{:04x}", info.vid, info.pid);
+            if let Some(m) = &info.manufacturer {
+                s.push_str(&format!(" {m}"));
+            }
+            if let Some(pn) = &info.product {
+                s.push_str(&format!(" {pn}"));
+            }
+            s.push_str(&UsbInterface::of(p).describe());
+            s.push(')');
+            s
+        }
+        SerialPortType::BluetoothPort => "  (Bluetooth)".to_string(),
+        SerialPortType::PciPort => "  (PCI)".to_string(),
+        SerialPortType::Unknown => String::new(),
+    }
+}
+
+pub fn print_ports(ports: &[SerialPortInfo], quirks: &QuirkDb) {
     if ports.is_empty() {
         println!("No serial ports found.");
         return;
     }
     println!("Available serial ports:");
     for (i, p) in ports.iter().enumerate() {
-        print!("  [{}] {}", i + 1, p.port_name);
-        match &p.port_type {
-            SerialPortType::UsbPort(info) => {
-                print!("  (USB");
-                print!(" vid=0x{:04x}", info.vid);
-                print!(" pid=0x{:04x}", info.pid);
-                if let Some(m) = &info.manufacturer {
-                    print!(" {m}");
-                }
-                if let Some(pn) = &info.product {
-                    print!(" {pn}");
-                }
-                print!(")");
+        println!("  [{}] {}{}", i + 1, p.port_name, describe_port(p));
+        if let Some(by_id) = by_id_path(&p.port_name) {
+            println!("      {by_id}");
+        }
+        if let Some(quirk) = quirks.for_port(p) {
+            print!("      {}", quirk.name);
+            if let Some(max) = quirk.max_baud {
+                print!(", reliable up to {max} baud");
+            }
+            if let Some(note) = &quirk.note {
+                print!("; {note}");
             }
-            SerialPortType::BluetoothPort => print!("  (Bluetooth)"),
-            SerialPortType::PciPort => print!("  (PCI)"),
-            SerialPortType::Unknown => {}
+            println!();
         }
-        println!();
     }
 }
 
-pub fn choose_port_interactive(ports: &[SerialPortInfo]) -> Result<String> {
+/// A port as printed by `--list --json`
+#[derive(Serialize)]
+struct PortJson<'a> {
+    port: &'a str,
+    /// Stable `/dev/serial/by-id` link (Linux)
+    by_id: Option<String>,
+    kind: &'static str,
+    vid: Option<u16>,
+    pid: Option<u16>,
+    serial_number: Option<&'a str>,
+    manufacturer: Option<&'a str>,
+    product: Option<&'a str>,
+    interface: Option<u8>,
+    interface_name: Option<String>,
+    /// Name from the quirk database
+    adapter: Option<&'a str>,
+}
+
+impl<'a> PortJson<'a> {
+    fn new(p: &'a SerialPortInfo, quirks: &'a QuirkDb) -> Self {
+        let usb = match &p.port_type {
+            SerialPortType::UsbPort(info) => Some(info),
+            _ => None,
+        };
+        let interface = UsbInterface::of(p);
+        Self {
+            port: &p.port_name,
+            by_id: by_id_path(&p.port_name),
+            kind: match p.port_type {
+                SerialPortType::UsbPort(_) => "usb",
+                SerialPortType::BluetoothPort => "bluetooth",
+                SerialPortType::PciPort => "pci",
+                SerialPortType::Unknown => "unknown",
+            },
+            vid: usb.map(|i| i.vid),
+            pid: usb.map(|i| i.pid),
+            serial_number: usb.and_then(|i| i.serial_number.as_deref()),
+            manufacturer: usb.and_then(|i| i.manufacturer.as_deref()),
+            product: usb.and_then(|i| i.product.as_deref()),
+            interface: interface.number,
+            interface_name: interface.name,
+            adapter: quirks.for_port(p).map(|q| q.name.as_str()),
+        }
+    }
+}
+
+pub fn print_ports_json(ports: &[SerialPortInfo], quirks: &QuirkDb) -> Result<()> {
+    let list: Vec<PortJson> = ports.iter().map(|p| PortJson::new(p, quirks)).collect();
+    println!("{}", serde_json::to_string_pretty(&list)?);
+    Ok(())
+}
+
+/// One `--list --watch --json` line
+#[derive(Serialize)]
+struct PortEvent<'a> {
+    time: String,
+    event: &'static str,
+    #[serde(flatten)]
+    port: PortJson<'a>,
+}
+
+/// Print ports as they appear and disappear until interrupted
+/// Ports in `now` but not `known` ("added") and the other way round
+/// ("removed"), removals first
+fn port_changes<'a>(
+    known: &'a [SerialPortInfo],
+    now: &'a [SerialPortInfo],
+) -> Vec<(&'static str, &'a SerialPortInfo)> {
+    let added = now
+        .iter()
+        .filter(|p| !known.iter().any(|k| k.port_name == p.port_name))
+        .map(|p| ("added", p));
+    let removed = known
+        .iter()
+        .filter(|k| !now.iter().any(|p| p.port_name == k.port_name))
+        .map(|p| ("removed", p));
+    removed.chain(added).collect()
+}
+
+pub fn watch_ports(quirks: &QuirkDb, json: bool, filter: &PortFilter) -> Result<()> {
+    let mut known: Vec<SerialPortInfo> = Vec::new();
+    if !json {
+        println!("Watching for serial ports (Ctrl-C to stop)...");
+    }
+    loop {
+        let now = filter.apply(get_available_ports()?);
+        for (event, p) in port_changes(&known, &now) {
+            if json {
+                let line = PortEvent {
+                    time: Local::now().to_rfc3339(),
+                    event,
+                    port: PortJson::new(p, quirks),
+                };
+                println!("{}", serde_json::to_string(&line)?);
+            } else {
+                let sign = if event == "added" { '+' } else { '-' };
+                println!(
+                    "[{}] {sign} {}{}",
+                    Local::now().format("%H:%M:%S%.3f"),
+                    p.port_name,
+                    describe_port(p)
+                );
+            }
+        }
+        let _ = io::stdout().flush();
+
+        known = now;
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Post a note into the session whenever a serial port appears or goes
+/// away, so juggling several boards doesn't need a second terminal
+pub async fn watch_hotplug(
+    interval: Option<Duration>,
+    running: Arc<AtomicBool>,
+    ui_tx: mpsc::UnboundedSender<UiMessage>,
+) {
+    let Ok(mut known) = get_available_ports() else {
+        return;
+    };
+    while running.load(Ordering::SeqCst) {
+        tokio::time::sleep(interval.unwrap_or(HOTPLUG_POLL)).await;
+        let Ok(now) = get_available_ports() else {
+            continue;
+        };
+        for (event, p) in port_changes(&known, &now) {
+            let what = if event == "added" {
+                "appeared"
+            } else {
+                "disappeared"
+            };
+            let _ = ui_tx.send(UiMessage::PortsChanged(format!(
+                "[sermonizer] {} {what}{}",
+                p.port_name,
+                describe_port(p)
+            )));
+        }
+        known = now;
+    }
+}
+
+/// The port to use when `-p` doesn't say: the only one there is, else the
+/// user's pick (from those `filter` lets through)
+pub fn choose_port_interactive(
+    ports: &[SerialPortInfo],
+    quirks: &QuirkDb,
+    filter: &PortFilter,
+) -> Result<String> {
+    choose_port(ports, quirks, filter, &|p| filter.matches(p))
+}
+
+/// As [`choose_port_interactive`], with `keep` saying which ports coming
+/// and going belong in the picker's list
+fn choose_port(
+    ports: &[SerialPortInfo],
+    quirks: &QuirkDb,
+    filter: &PortFilter,
+    keep: &dyn Fn(&SerialPortInfo) -> bool,
+) -> Result<String> {
+    // A full-screen picker needs a terminal to draw on and read keys from,
+    // and stdout to itself
+    let picker = io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+        && !crate::JSON_OUTPUT.load(Ordering::Relaxed);
     match ports.len() {
-        0 => bail!("No serial ports detected. Plug your device in and try again."),
+        0 if !picker && !filter.is_empty() => {
+            bail!("No serial port matches {}.", filter.describe())
+        }
+        0 if !picker => bail!("No serial ports detected. Plug your device in and try again."),
         1 => {
             let name = ports[0].port_name.clone();
-            println!("Auto-selected sole port: {name}");
+            say!("Auto-selected sole port: {name}");
             Ok(name)
         }
+        _ if picker => pick_port(ports, quirks, keep),
         _ => {
-            print_ports(ports);
+            print_ports(ports, quirks);
             println!();
             // Prompt in cooked mode for a clean input experience
             print!("Select port [1-{}] (Enter for 1): ", ports.len());
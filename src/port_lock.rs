@@ -0,0 +1,671 @@
+use anyhow::{Result, bail};
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::permissions;
+use crate::read_only;
+
+/// How often a busy port is tried again while waiting for it
+const BUSY_POLL: Duration = Duration::from_millis(500);
+
+/// `--retry-open` backoff: the first wait, doubled after each try up to
+/// the last
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How often the retry countdown is redrawn
+const COUNTDOWN_TICK: Duration = Duration::from_millis(100);
+
+/// Attempts at creating the lock file when a stale one keeps being
+/// replaced under us
+#[cfg(unix)]
+const LOCK_TRIES: usize = 3;
+
+/// What opening a port that is in use does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnBusy {
+    /// Say who has it and give up
+    Fail,
+    /// Wait until it is free (`--wait`)
+    Wait,
+    /// Ask whether to wait, retry or give up
+    Ask,
+    /// Try again with backoff for this long, also when access is denied
+    /// (`--retry-open`): flashing tools and udev rules need a moment
+    Retry(Duration),
+}
+
+impl OnBusy {
+    /// Before the UI starts: retry for a while with `--retry-open`, wait
+    /// with `--wait`, otherwise ask when there is someone at the terminal
+    /// to answer
+    pub fn at_start(wait: bool, retry: Option<Duration>) -> Self {
+        if let Some(limit) = retry {
+            OnBusy::Retry(limit)
+        } else if wait {
+            OnBusy::Wait
+        } else if io::stdin().is_terminal() && io::stdout().is_terminal() {
+            OnBusy::Ask
+        } else {
+            OnBusy::Fail
+        }
+    }
+}
+
+/// A process that has the port open or locked
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Holder {
+    pub pid: u32,
+    pub name: Option<String>,
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} (pid {})", self.pid),
+            None => write!(f, "pid {}", self.pid),
+        }
+    }
+}
+
+/// A UUCP lock file (`/var/lock/LCK..ttyUSB0`) this process created,
+/// removed again when dropped
+pub struct LockFile {
+    path: PathBuf,
+    /// The file we wrote: a session that reopened the port has replaced it
+    /// with its own by the time the old port is dropped
+    #[cfg(unix)]
+    ino: u64,
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if std::fs::metadata(&self.path).is_ok_and(|m| m.ino() != self.ino) {
+                return;
+            }
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Who else has `port_name`: the owner of a live lock file, or (Linux) a
+/// process with the device open that we are allowed to see
+pub fn holder(port_name: &str) -> Option<Holder> {
+    #[cfg(unix)]
+    {
+        let locked = unix::lock_path(port_name)
+            .and_then(|path| unix::lock_owner(&path))
+            .filter(|&pid| pid != std::process::id() && unix::alive(pid));
+        if let Some(pid) = locked {
+            return Some(unix::holder(pid));
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(pid) = unix::open_by(port_name) {
+            return Some(unix::holder(pid));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = port_name;
+    None
+}
+
+/// Take the port's lock file, unless someone else holds it. `Ok(None)`
+/// when there is nothing to lock with (no writable lock directory, not a
+/// Unix system) or in `--read-only` mode, which listens alongside others.
+fn lock(port_name: &str) -> Result<Option<LockFile>, Option<Holder>> {
+    if read_only::enabled() {
+        return Ok(None);
+    }
+    if let Some(holder) = holder(port_name) {
+        return Err(Some(holder));
+    }
+    #[cfg(unix)]
+    {
+        let Some(path) = unix::lock_path(port_name) else {
+            return Ok(None);
+        };
+        take_lock(&path).map_err(|who| who.or_else(|| holder(port_name)))
+    }
+    #[cfg(not(unix))]
+    Ok(None)
+}
+
+/// Create the lock file at `path`, replacing a stale one. `Ok(None)` when
+/// the file cannot be created at all; `Err(None)` when someone else has it
+/// but it doesn't say who (yet).
+#[cfg(unix)]
+fn take_lock(path: &std::path::Path) -> Result<Option<LockFile>, Option<Holder>> {
+    for _ in 0..LOCK_TRIES {
+        match unix::create(path) {
+            Ok(lock) => return Ok(Some(lock)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => match unix::lock_owner(path) {
+                Some(pid) if pid != std::process::id() && unix::alive(pid) => {
+                    return Err(Some(unix::holder(pid)));
+                }
+                // No pid yet: its owner may still be writing it
+                None if unix::is_fresh(path) => return Err(None),
+                // Stale (its process is gone) or ours from before a
+                // reopen; whoever creates the next one wins
+                _ => {
+                    let _ = std::fs::remove_file(path);
+                }
+            },
+            Err(_) => return Ok(None),
+        }
+    }
+    Err(None)
+}
+
+/// The driver refused a second open (TIOCEXCL)
+fn is_busy(e: &serialport::Error) -> bool {
+    e.kind() == serialport::ErrorKind::Unknown && e.to_string().to_lowercase().contains("busy")
+}
+
+fn is_denied(e: &serialport::Error) -> bool {
+    e.kind() == serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied)
+}
+
+/// A failed open, with what to do about it when access was denied
+fn open_error(port_name: &str, e: serialport::Error) -> anyhow::Error {
+    let mut context = format!("Failed to open serial port '{port_name}'");
+    if is_denied(&e) {
+        for line in permissions::diagnose(port_name) {
+            context.push_str("\n  ");
+            context.push_str(&line);
+        }
+    }
+    anyhow::Error::new(e).context(context)
+}
+
+/// The `--retry-open` status line, redrawn in place on a terminal and
+/// printed once per try elsewhere
+struct Countdown {
+    live: bool,
+    shown: bool,
+}
+
+impl Countdown {
+    fn new() -> Self {
+        let quiet =
+            crate::QUIET.load(Ordering::Relaxed) || crate::JSON_OUTPUT.load(Ordering::Relaxed);
+        Countdown {
+            live: !quiet && io::stdout().is_terminal(),
+            shown: false,
+        }
+    }
+
+    /// Sleep until `next`, then say nothing more about this try
+    fn wait(&mut self, port_name: &str, reason: &str, next: Instant, deadline: Instant) {
+        if !self.live {
+            let retry_in = next.saturating_duration_since(Instant::now());
+            say!(
+                "{port_name} is {reason}; retrying in {:.1}s",
+                retry_in.as_secs_f32()
+            );
+        }
+        loop {
+            let now = Instant::now();
+            if now >= next {
+                return;
+            }
+            if self.live {
+                let retry_in = next - now;
+                let give_up = deadline.saturating_duration_since(now);
+                print!(
+                    "\r{port_name} is {reason}; retrying in {:.1}s (giving up in {}s)\x1b[K",
+                    retry_in.as_secs_f32(),
+                    give_up.as_secs_f32().ceil()
+                );
+                let _ = io::stdout().flush();
+                self.shown = true;
+            }
+            std::thread::sleep(COUNTDOWN_TICK.min(next - now));
+        }
+    }
+
+    /// Leave the line the countdown was drawn on
+    fn finish(&mut self) {
+        if self.shown {
+            println!();
+            self.shown = false;
+        }
+    }
+}
+
+/// Open `port_name` with `open`, holding its lock file. A port in use by
+/// another program is reported with who has it, then handled as
+/// `on_busy` says.
+pub fn open_locked<T>(
+    port_name: &str,
+    mut on_busy: OnBusy,
+    mut open: impl FnMut() -> serialport::Result<T>,
+) -> Result<(T, Option<LockFile>)> {
+    let mut waiting = false;
+    let mut countdown = Countdown::new();
+    let mut retry_delay = RETRY_DELAY;
+    let mut retry_until = None;
+    loop {
+        let holder = match lock(port_name) {
+            Ok(lock) => match open() {
+                Ok(port) => {
+                    countdown.finish();
+                    return Ok((port, lock));
+                }
+                Err(e) if is_busy(&e) => {
+                    drop(lock);
+                    holder(port_name)
+                }
+                Err(e) => {
+                    let OnBusy::Retry(limit) = on_busy else {
+                        return Err(open_error(port_name, e));
+                    };
+                    drop(lock);
+                    let deadline = *retry_until.get_or_insert_with(|| Instant::now() + limit);
+                    if !is_denied(&e) || Instant::now() >= deadline {
+                        countdown.finish();
+                        return Err(open_error(port_name, e));
+                    }
+                    let next = (Instant::now() + retry_delay).min(deadline);
+                    countdown.wait(
+                        port_name,
+                        "not accessible (permission denied)",
+                        next,
+                        deadline,
+                    );
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                    continue;
+                }
+            },
+            Err(holder) => holder,
+        };
+        let who = holder.map_or_else(|| "another program".to_string(), |h| h.to_string());
+        match on_busy {
+            OnBusy::Retry(limit) => {
+                let deadline = *retry_until.get_or_insert_with(|| Instant::now() + limit);
+                if Instant::now() >= deadline {
+                    countdown.finish();
+                    bail!(
+                        "Serial port '{port_name}' is still in use by {who} after {}s",
+                        limit.as_secs_f32()
+                    );
+                }
+                let next = (Instant::now() + retry_delay).min(deadline);
+                countdown.wait(port_name, &format!("in use by {who}"), next, deadline);
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+            }
+            OnBusy::Fail => bail!("Serial port '{port_name}' is in use by {who}"),
+            OnBusy::Wait => {
+                if !waiting {
+                    say!("{port_name} is in use by {who}; waiting for it to be free...");
+                    waiting = true;
+                }
+                std::thread::sleep(BUSY_POLL);
+            }
+            OnBusy::Ask => {
+                print!("{port_name} is in use by {who}. [w]ait for it, [r]etry, [q]uit? ");
+                let _ = io::stdout().flush();
+                let mut line = String::new();
+                io::stdin().read_line(&mut line)?;
+                match line.trim().to_ascii_lowercase().as_str() {
+                    "w" | "wait" => on_busy = OnBusy::Wait,
+                    "r" | "retry" | "" => {}
+                    _ => bail!("Serial port '{port_name}' is in use by {who}"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::{Holder, LockFile};
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    /// How long an empty lock file counts as being written
+    const FRESH: Duration = Duration::from_secs(2);
+
+    /// Where lock files go, the first that exists
+    const LOCK_DIRS: &[&str] = &["/var/lock", "/run/lock"];
+
+    /// `LCK..<device>` in the lock directory, as minicom, picocom and
+    /// the uucp tools name it (`/dev/pts/3` becomes `LCK..pts_3`)
+    pub fn lock_path(port_name: &str) -> Option<PathBuf> {
+        let dirs: Vec<&Path> = LOCK_DIRS.iter().map(Path::new).collect();
+        lock_path_in(&dirs, port_name)
+    }
+
+    /// `lock_path` with the lock directories to choose from
+    pub fn lock_path_in(dirs: &[&Path], port_name: &str) -> Option<PathBuf> {
+        let device = std::fs::canonicalize(port_name).unwrap_or_else(|_| port_name.into());
+        let device = device.to_str()?;
+        let name = device.strip_prefix("/dev/").unwrap_or(device);
+        let dir = dirs.iter().find(|d| d.is_dir())?;
+        Some(dir.join(format!("LCK..{}", name.replace('/', "_"))))
+    }
+
+    /// The pid in a lock file: ASCII (HDB style) or a binary int (old
+    /// UUCP)
+    pub fn lock_owner(path: &Path) -> Option<u32> {
+        let bytes = std::fs::read(path).ok()?;
+        match std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+        {
+            Some(pid) => Some(pid),
+            None => Some(u32::from_ne_bytes(bytes.get(..4)?.try_into().ok()?)),
+        }
+    }
+
+    /// Written so recently that the process creating it may not have put
+    /// its pid in yet
+    pub fn is_fresh(path: &Path) -> bool {
+        std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|t| t.elapsed().is_ok_and(|age| age < FRESH))
+    }
+
+    /// A running process has `pid`. 0 and anything past `pid_t` are never
+    /// a process: `kill` would take them as our process group.
+    pub fn alive(pid: u32) -> bool {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        if pid <= 0 {
+            return false;
+        }
+        // SAFETY: signal 0 only checks that the process exists
+        let rc = unsafe { libc::kill(pid, 0) };
+        rc == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    pub fn holder(pid: u32) -> Holder {
+        let name = std::fs::read_to_string(format!("/proc/{pid}/comm"))
+            .ok()
+            .map(|s| s.trim().to_string());
+        Holder { pid, name }
+    }
+
+    /// Another process with the device open, as far as /proc lets us see.
+    /// Not for ptys: whoever made one (socat, QEMU) keeps its other end open
+    #[cfg(target_os = "linux")]
+    pub fn open_by(port_name: &str) -> Option<u32> {
+        let device = std::fs::canonicalize(port_name).ok()?;
+        if device.starts_with("/dev/pts") {
+            return None;
+        }
+        let me = std::process::id();
+        std::fs::read_dir("/proc")
+            .ok()?
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|&pid| pid != me)
+            .find(|pid| {
+                std::fs::read_dir(format!("/proc/{pid}/fd")).is_ok_and(|fds| {
+                    fds.flatten()
+                        .any(|fd| std::fs::read_link(fd.path()).is_ok_and(|l| l == device))
+                })
+            })
+    }
+
+    pub fn create(path: &Path) -> io::Result<LockFile> {
+        use std::os::unix::fs::MetadataExt;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        writeln!(file, "{:>10}", std::process::id())?;
+        Ok(LockFile {
+            path: path.to_path_buf(),
+            ino: file.metadata()?.ino(),
+        })
+    }
+}
+
+/// A port together with its lock file, so the lock goes when the session
+/// drops the port (quit, reconnect, port switch)
+pub struct LockedPort {
+    inner: Box<dyn SerialPort + Send>,
+    _lock: LockFile,
+}
+
+impl LockedPort {
+    /// `port` as is when there is no lock to keep
+    pub fn wrap(
+        port: Box<dyn SerialPort + Send>,
+        lock: Option<LockFile>,
+    ) -> Box<dyn SerialPort + Send> {
+        match lock {
+            Some(lock) => Box::new(LockedPort {
+                inner: port,
+                _lock: lock,
+            }),
+            None => port,
+        }
+    }
+}
+
+impl io::Read for LockedPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl io::Write for LockedPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SerialPort for LockedPort {
+    fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.inner.baud_rate()
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.inner.data_bits()
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.inner.flow_control()
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.inner.parity()
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.inner.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.inner.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.inner.set_flow_control(flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.inner.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.inner.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.inner.set_timeout(timeout)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.inner.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.inner.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.inner.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.inner.read_carrier_detect()
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_write()
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        self.inner.clear(buffer_to_clear)
+    }
+
+    /// The clone shares the port but not the lock
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        self.inner.try_clone()
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        self.inner.set_break()
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.inner.clear_break()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    /// An empty directory of its own for each test
+    fn lock_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sermonizer-lock-{}-{test}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The pid of a process that has exited
+    fn dead_pid() -> u32 {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
+    #[test]
+    fn lock_paths_name_the_device() {
+        let dir = lock_dir("path");
+        let missing = Path::new("/nonexistent/lock/dir");
+        assert_eq!(
+            unix::lock_path_in(&[missing, &dir], "/dev/ttyUSB7"),
+            Some(dir.join("LCK..ttyUSB7"))
+        );
+        assert_eq!(unix::lock_path_in(&[missing], "/dev/ttyUSB7"), None);
+    }
+
+    #[test]
+    fn lock_owner_reads_ascii_and_binary_pids() {
+        let dir = lock_dir("owner");
+        let path = dir.join("LCK..ttyS0");
+        std::fs::write(&path, "      1234\n").unwrap();
+        assert_eq!(unix::lock_owner(&path), Some(1234));
+        std::fs::write(&path, 4321u32.to_ne_bytes()).unwrap();
+        assert_eq!(unix::lock_owner(&path), Some(4321));
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(unix::lock_owner(&path), None);
+    }
+
+    #[test]
+    fn pid_zero_and_dead_pids_are_not_alive() {
+        assert!(unix::alive(std::process::id()));
+        assert!(!unix::alive(0));
+        assert!(!unix::alive(u32::MAX));
+        assert!(!unix::alive(dead_pid()));
+    }
+
+    #[test]
+    fn stale_locks_are_taken_over() {
+        let dir = lock_dir("stale");
+        let path = dir.join("LCK..ttyS0");
+        for stale in [dead_pid(), 0, std::process::id()] {
+            std::fs::write(&path, format!("{stale:>10}\n")).unwrap();
+            let lock = take_lock(&path).unwrap().unwrap();
+            assert_eq!(unix::lock_owner(&path), Some(std::process::id()));
+            drop(lock);
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn live_and_fresh_locks_are_refused() {
+        let dir = lock_dir("live");
+        let path = dir.join("LCK..ttyS0");
+        let mut other = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        std::fs::write(&path, format!("{:>10}\n", other.id())).unwrap();
+        let holder = take_lock(&path).err().flatten().unwrap();
+        assert_eq!(holder.pid, other.id());
+        let _ = other.kill();
+        let _ = other.wait();
+        // Empty and just created: its owner is still writing the pid
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(take_lock(&path), Err(None)));
+    }
+
+    #[test]
+    fn drop_leaves_a_replaced_lock_alone() {
+        let dir = lock_dir("replaced");
+        let path = dir.join("LCK..ttyS0");
+        let lock = take_lock(&path).unwrap().unwrap();
+        // Another session's lock in its place; renaming keeps the old
+        // inode in use so the new file can't get the same number
+        std::fs::rename(&path, dir.join("old")).unwrap();
+        std::fs::write(&path, "     99999\n").unwrap();
+        drop(lock);
+        assert!(path.exists());
+    }
+}
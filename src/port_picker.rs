@@ -0,0 +1,249 @@
+use anyhow::{Context, Result, bail};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use serialport::SerialPortInfo;
+use std::time::{Duration, Instant};
+
+use crate::port_discovery::{by_id_path, describe_port, get_available_ports};
+use crate::quirks::QuirkDb;
+
+/// How often the list is enumerated again without pressing `r`
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// Whether `query` is found in `text` in order, gaps allowed, in any case
+/// (`acm0` finds `/dev/ttyACM0`, `stl` finds `ST-LINK`)
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| text.any(|t| t == q))
+}
+
+/// The full-screen port list shown before a session starts, when there
+/// is more than one port (or none yet) to choose from
+struct Picker<'a> {
+    quirks: &'a QuirkDb,
+    /// Which enumerated ports belong in the list
+    keep: &'a dyn Fn(&SerialPortInfo) -> bool,
+    ports: Vec<SerialPortInfo>,
+    /// One line per port, searched by the filter
+    lines: Vec<String>,
+    query: String,
+    /// Typing goes to the filter (after `/`)
+    filtering: bool,
+    /// Index into the filtered list
+    selected: usize,
+    refreshed: Instant,
+}
+
+impl<'a> Picker<'a> {
+    fn new(
+        ports: &[SerialPortInfo],
+        quirks: &'a QuirkDb,
+        keep: &'a dyn Fn(&SerialPortInfo) -> bool,
+    ) -> Self {
+        let mut picker = Picker {
+            quirks,
+            keep,
+            ports: Vec::new(),
+            lines: Vec::new(),
+            query: String::new(),
+            filtering: false,
+            selected: 0,
+            refreshed: Instant::now(),
+        };
+        picker.set_ports(ports.to_vec());
+        picker
+    }
+
+    fn set_ports(&mut self, ports: Vec<SerialPortInfo>) {
+        let selected = self.selected_port().map(str::to_string);
+        self.lines = ports
+            .iter()
+            .map(|p| {
+                let mut line = format!("{}{}", p.port_name, describe_port(p));
+                if let Some(quirk) = self.quirks.for_port(p) {
+                    line.push_str(&format!(" - {}", quirk.name));
+                }
+                if let Some(by_id) = by_id_path(&p.port_name) {
+                    line.push_str(&format!("  {by_id}"));
+                }
+                line
+            })
+            .collect();
+        self.ports = ports;
+        self.refreshed = Instant::now();
+        // Stay on the same port when it is still there
+        let visible = self.visible();
+        self.selected = selected
+            .and_then(|name| {
+                visible
+                    .iter()
+                    .position(|&i| self.ports[i].port_name == name)
+            })
+            .unwrap_or(self.selected)
+            .min(visible.len().saturating_sub(1));
+    }
+
+    /// Enumerate again: ports plugged in or pulled since
+    fn refresh(&mut self) {
+        let ports = get_available_ports()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| (self.keep)(p))
+            .collect();
+        self.set_ports(ports);
+    }
+
+    /// Indices of the ports the filter lets through
+    fn visible(&self) -> Vec<usize> {
+        (0..self.ports.len())
+            .filter(|&i| fuzzy_match(&self.query, &self.lines[i]))
+            .collect()
+    }
+
+    fn selected_port(&self) -> Option<&str> {
+        let i = *self.visible().get(self.selected)?;
+        Some(&self.ports[i].port_name)
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let last = self.visible().len().saturating_sub(1);
+        self.selected = if down {
+            (self.selected + 1).min(last)
+        } else {
+            self.selected.saturating_sub(1)
+        };
+    }
+
+    fn edit_query(&mut self, edit: impl FnOnce(&mut String)) {
+        edit(&mut self.query);
+        self.selected = 0;
+    }
+
+    /// `Some(Some(port))` picked, `Some(None)` cancelled, `None` keep going
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Option<String>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Some(None),
+            KeyCode::Enter => match self.selected_port() {
+                Some(name) => return Some(Some(name.to_string())),
+                None => self.filtering = false,
+            },
+            KeyCode::Up => self.move_selection(false),
+            KeyCode::Down => self.move_selection(true),
+            KeyCode::Esc if self.filtering => {
+                self.filtering = false;
+                self.edit_query(String::clear);
+            }
+            KeyCode::Esc => return Some(None),
+            KeyCode::Backspace if self.filtering => self.edit_query(|q| {
+                q.pop();
+            }),
+            KeyCode::Char(c) if self.filtering => self.edit_query(|q| q.push(c)),
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Char('q') => return Some(None),
+            _ => {}
+        }
+        None
+    }
+
+    fn draw(&self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(f.area());
+
+        let visible = self.visible();
+        let items: Vec<ListItem> = if visible.is_empty() {
+            let text = if self.ports.is_empty() {
+                "No serial ports yet: plug one in, the list refreshes on its own"
+            } else {
+                "No port matches the filter"
+            };
+            vec![ListItem::new(text)]
+        } else {
+            visible
+                .iter()
+                .map(|&i| ListItem::new(self.lines[i].as_str()))
+                .collect()
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(
+                "Select a serial port (↑↓ select, Enter open, / filter, r refresh, Esc quit)",
+            ))
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+        let mut state =
+            ListState::default().with_selected((!visible.is_empty()).then_some(self.selected));
+        f.render_stateful_widget(list, chunks[0], &mut state);
+
+        let status = if self.filtering || !self.query.is_empty() {
+            let cursor = if self.filtering { "_" } else { "" };
+            format!(" Filter: {}{cursor}", self.query)
+        } else {
+            format!(" {} port(s)", self.ports.len())
+        };
+        f.render_widget(
+            Paragraph::new(status).style(Style::default().fg(Color::Yellow)),
+            chunks[1],
+        );
+    }
+}
+
+fn run(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    picker: &mut Picker,
+) -> Result<Option<String>> {
+    loop {
+        terminal.draw(|f| picker.draw(f))?;
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && let Some(picked) = picker.handle_key(key)
+        {
+            return Ok(picked);
+        }
+        if picker.refreshed.elapsed() >= REFRESH {
+            picker.refresh();
+        }
+    }
+}
+
+/// Let the user pick one of `ports` on a full-screen list with arrow
+/// keys, a fuzzy filter and the list refreshed as ports come and go
+/// (those `keep` lets through)
+pub fn pick_port(
+    ports: &[SerialPortInfo],
+    quirks: &QuirkDb,
+    keep: &dyn Fn(&SerialPortInfo) -> bool,
+) -> Result<String> {
+    let mut picker = Picker::new(ports, quirks, keep);
+
+    terminal::enable_raw_mode().context("Failed to enable raw mode")?;
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, terminal::EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let picked = run(&mut terminal, &mut picker);
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    match picked? {
+        Some(name) => {
+            say!("Using port: {name}");
+            Ok(name)
+        }
+        None => bail!("No port selected"),
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::http;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PowerAction {
+    On,
+    Off,
+    Cycle,
+}
+
+impl PowerAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerAction::On => "power-on",
+            PowerAction::Off => "power-off",
+            PowerAction::Cycle => "power-cycle",
+        }
+    }
+}
+
+/// External commands driving a bench PDU or USB relay. Each is either a
+/// shell command or an `http://` URL (optionally prefixed with a method,
+/// e.g. `POST http://pdu/outlet/1/off`).
+#[derive(Debug, Clone, Default)]
+pub struct PowerCommands {
+    pub on: Option<String>,
+    pub off: Option<String>,
+    /// Falls back to off, wait `cycle_delay`, on
+    pub cycle: Option<String>,
+    pub cycle_delay: Duration,
+}
+
+impl PowerCommands {
+    pub fn supports(&self, action: PowerAction) -> bool {
+        match action {
+            PowerAction::On => self.on.is_some(),
+            PowerAction::Off => self.off.is_some(),
+            PowerAction::Cycle => self.cycle.is_some() || (self.on.is_some() && self.off.is_some()),
+        }
+    }
+
+    /// Run the action to completion (blocking). Returns what the commands
+    /// printed: they must not write to the terminal while the UI owns it.
+    pub fn run(&self, action: PowerAction) -> Result<String> {
+        match action {
+            PowerAction::On => run_command(self.on.as_deref().context("no power-on command")?),
+            PowerAction::Off => run_command(self.off.as_deref().context("no power-off command")?),
+            PowerAction::Cycle => {
+                if let Some(cmd) = &self.cycle {
+                    return run_command(cmd);
+                }
+                let mut output = self.run(PowerAction::Off)?;
+                std::thread::sleep(self.cycle_delay);
+                output.push_str(&self.run(PowerAction::On)?);
+                Ok(output)
+            }
+        }
+    }
+}
+
+fn run_command(cmd: &str) -> Result<String> {
+    let (method, target) = match cmd.split_once(' ') {
+        Some((m, rest))
+            if matches!(m, "GET" | "POST" | "PUT") && rest.trim_start().starts_with("http") =>
+        {
+            (m, rest.trim_start())
+        }
+        _ => ("GET", cmd),
+    };
+    if target.starts_with("http://") {
+        return http::request(method, target, &[], &[]).map(|()| String::new());
+    }
+    if target.starts_with("https://") {
+        bail!("https is not supported directly; use a shell command such as `curl -fsS {target}`");
+    }
+
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+
+    let output = Command::new(shell)
+        .args([flag, cmd])
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run '{cmd}'"))?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() {
+        match text.trim() {
+            "" => bail!("'{cmd}' exited with {}", output.status),
+            text => bail!("'{cmd}' exited with {}: {text}", output.status),
+        }
+    }
+    Ok(text)
+}
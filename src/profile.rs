@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use serialport::{SerialPortInfo, SerialPortType};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::auto_login::AutoLoginSettings;
+use crate::blocks::BlockPatterns;
+use crate::config::{
+    DataBitsMode, EchoMode, EscAction, FlowControlMode, LineEnding, ParityMode, PassthroughMode,
+    RxLineEnding, StopBitsMode, View,
+};
+use crate::keymap::parse_key_binding;
+use crate::levels::{Level, parse_level_pattern};
+use crate::metrics::{MetricRule, parse_metric_rule};
+use crate::quirks::Quirk;
+use crate::reset::{ResetInvert, ResetKind};
+use crate::tagging::{TagRule, parse_tag_rule};
+use regex::Regex;
+
+/// A named set of connection/logging options stored in the config file.
+/// Every field is optional; command-line flags always win.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// USB device the profile is for: used instead of the default profile
+    /// when the port is this device (all given fields must match)
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub port: Option<String>,
+    pub baud: Option<u32>,
+    pub data_bits: Option<DataBitsMode>,
+    pub parity: Option<ParityMode>,
+    pub stop_bits: Option<StopBitsMode>,
+    pub flow: Option<FlowControlMode>,
+    /// Control line levels right after opening (`--dtr`, `--rts`)
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+    /// Reset sequence run after connecting (`--reset`)
+    pub reset: Option<ResetKind>,
+    pub reset_invert: Option<ResetInvert>,
+    pub line_ending: Option<LineEnding>,
+    pub alt_line_ending: Option<LineEnding>,
+    pub rx_line_ending: Option<RxLineEnding>,
+    pub echo_mode: Option<EchoMode>,
+    pub esc: Option<EscAction>,
+    pub passthrough: Option<PassthroughMode>,
+    /// Start in the terminal emulation view (`--terminal`)
+    pub terminal: bool,
+    /// Starting view: `"text"`, `"hexdump"` or `"terminal"` (`--view`)
+    pub view: Option<View>,
+    /// ASCII-only UI (`--ascii-ui`)
+    pub ascii_ui: bool,
+    /// Fewer wakeups on battery (`--power-save`)
+    pub power_save: bool,
+    /// Keys sent as escape sequences in passthrough mode, as for `--key`
+    pub keys: Vec<String>,
+    pub instant_keys: Option<String>,
+    /// Tag rules as `name = "regex"`
+    pub tags: BTreeMap<String, String>,
+    /// Regexes for RX lines that fold into the line before them
+    pub folds: Vec<String>,
+    /// Multi-line blocks as `name = { start = "regex", end = "regex" }`
+    pub blocks: BTreeMap<String, BlockPatterns>,
+    /// Regexes with a `level` named group
+    pub level_patterns: Vec<String>,
+    pub min_level: Option<Level>,
+    pub log: Option<PathBuf>,
+    pub tx_log: Option<PathBuf>,
+    pub log_ts: bool,
+    pub log_dedup: bool,
+    /// CSV summary per interval (`--stats-log`, `--stats-interval`)
+    pub stats_log: Option<PathBuf>,
+    pub stats_interval: Option<u64>,
+    pub hex: bool,
+    /// Decoder chain, e.g. `"cobs,hex"`
+    pub decoder: Option<String>,
+    /// Command fed with raw RX (`--rx-pipe`), its output shown in a pane
+    /// (`--rx-pipe-pane`)
+    pub rx_pipe: Option<String>,
+    pub rx_pipe_pane: bool,
+    /// Filter command for everything sent (`--tx-pipe`)
+    pub tx_pipe: Option<String>,
+    /// WebAssembly plugins to load
+    pub plugins: Vec<PathBuf>,
+    pub low_latency: bool,
+    /// Relay/PDU commands (shell command or `http://` URL)
+    pub power_on: Option<String>,
+    pub power_off: Option<String>,
+    pub power_cycle: Option<String>,
+    /// Metrics as `name = "regex"` (`--metric`)
+    pub metrics: BTreeMap<String, String>,
+    /// InfluxDB line protocol file or `http://` endpoint (`--influx`)
+    pub influx: Option<String>,
+    pub influx_interval: Option<u64>,
+    /// Desktop notifications (`--notify`)
+    pub notify: bool,
+    /// Regexes for RX lines that raise an alert
+    pub alerts: Vec<String>,
+    /// Regexes for RX lines to keep in the pinned region
+    pub pins: Vec<String>,
+    /// Sound per event, e.g. `disconnect = "bell:3"`
+    pub sounds: BTreeMap<String, String>,
+    /// Boot log file and the line that ends it (`--capture-boot`)
+    pub capture_boot: Option<PathBuf>,
+    pub boot_done: Option<String>,
+    /// No startup banner (`--quiet`)
+    pub quiet: bool,
+    /// 1200-baud touch before connecting (`--touch-1200`)
+    pub touch_1200: bool,
+    /// Send a BREAK right after opening the port (`--break-on-start`)
+    pub break_on_start: bool,
+    /// BREAK length, e.g. `"500ms"` (`--break-duration`)
+    pub break_duration: Option<String>,
+    /// Wait for the port to appear (`--wait`), at most this long, e.g.
+    /// `"30s"` (`--wait-timeout`)
+    pub wait: bool,
+    pub wait_timeout: Option<String>,
+    /// Reopen the port when it comes back after going away (`--reconnect`)
+    pub reconnect: bool,
+    /// RS-485 direction control through RTS (`--rs485`), with the delays
+    /// around transmitting, e.g. `"1ms"`
+    pub rs485: bool,
+    pub rs485_before: Option<String>,
+    pub rs485_after: Option<String>,
+    /// Output left in the terminal after quitting (`--on-exit`)
+    pub on_exit: Option<String>,
+    /// Commands sent after connecting, in order; `{sleep ..}` and
+    /// `{wait ..}` directives work here too
+    pub startup_commands: Vec<String>,
+    /// Answer login/password prompts (`[profiles.<name>.auto_login]`)
+    pub auto_login: Option<AutoLoginSettings>,
+    /// Release whose default behaviour to keep (`--compat`)
+    pub compat: Option<String>,
+}
+
+impl Profile {
+    /// Whether the profile names a USB device and `port` is it
+    pub fn matches_device(&self, port: &SerialPortInfo) -> bool {
+        let SerialPortType::UsbPort(usb) = &port.port_type else {
+            return false;
+        };
+        (self.vid.is_some() || self.pid.is_some() || self.serial_number.is_some())
+            && self.vid.is_none_or(|vid| vid == usb.vid)
+            && self.pid.is_none_or(|pid| pid == usb.pid)
+            && self
+                .serial_number
+                .as_ref()
+                .is_none_or(|sn| usb.serial_number.as_ref() == Some(sn))
+    }
+
+    pub fn tag_rules(&self) -> Result<Vec<TagRule>> {
+        self.tags
+            .iter()
+            .map(|(name, pattern)| {
+                parse_tag_rule(&format!("{name}={pattern}")).map_err(anyhow::Error::msg)
+            })
+            .collect()
+    }
+
+    pub fn metric_rules(&self) -> Result<Vec<MetricRule>> {
+        self.metrics
+            .iter()
+            .map(|(name, pattern)| {
+                parse_metric_rule(&format!("{name}={pattern}")).map_err(anyhow::Error::msg)
+            })
+            .collect()
+    }
+
+    pub fn level_regexes(&self) -> Result<Vec<Regex>> {
+        self.level_patterns
+            .iter()
+            .map(|pattern| parse_level_pattern(pattern).map_err(anyhow::Error::msg))
+            .collect()
+    }
+
+    pub fn key_bindings(&self) -> Result<Vec<(KeyCode, Vec<u8>)>> {
+        self.keys
+            .iter()
+            .map(|binding| parse_key_binding(binding).map_err(anyhow::Error::msg))
+            .collect()
+    }
+
+    pub fn fold_regexes(&self) -> Result<Vec<Regex>> {
+        self.folds
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).with_context(|| format!("Invalid fold pattern '{pattern}'"))
+            })
+            .collect()
+    }
+
+    pub fn pin_regexes(&self) -> Result<Vec<Regex>> {
+        self.pins
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).with_context(|| format!("Invalid pin pattern '{pattern}'"))
+            })
+            .collect()
+    }
+
+    pub fn alert_regexes(&self) -> Result<Vec<Regex>> {
+        self.alerts
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).with_context(|| format!("Invalid alert pattern '{pattern}'"))
+            })
+            .collect()
+    }
+}
+
+/// Contents of `config.toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    /// Profile used when `--profile` is not given
+    pub default_profile: Option<String>,
+    /// Check crates.io for a newer release on every start
+    pub check_updates: bool,
+    pub profiles: BTreeMap<String, Profile>,
+    /// Adapter quirks, checked before the built-in ones
+    pub quirks: Vec<Quirk>,
+}
+
+impl ConfigFile {
+    pub fn has_device_profiles(&self) -> bool {
+        self.profiles
+            .values()
+            .any(|p| p.vid.is_some() || p.pid.is_some() || p.serial_number.is_some())
+    }
+
+    /// The profile naming the USB device at `port_name`, if any; one that
+    /// gives the serial number beats one that only gives the VID/PID
+    pub fn device_profile(
+        &self,
+        port_name: &str,
+        ports: &[SerialPortInfo],
+    ) -> Option<(&String, &Profile)> {
+        let port = ports.iter().find(|p| p.port_name == port_name)?;
+        self.profiles
+            .iter()
+            .filter(|(_, profile)| profile.matches_device(port))
+            .min_by_key(|(_, profile)| profile.serial_number.is_none())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/sermonizer/config.toml`, falling back to
+/// `~/.config` (or `%APPDATA%` on Windows)
+pub fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("sermonizer").join("config.toml"))
+}
+
+/// Load the config file, or `None` if there isn't one yet
+pub fn load_config() -> Result<Option<ConfigFile>> {
+    let Some(path) = config_path() else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let config = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    Ok(Some(config))
+}
+
+pub fn save_config(config: &ConfigFile) -> Result<PathBuf> {
+    let path = config_path().context("Could not determine config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create config directory: {}", dir.display()))?;
+    }
+    let text = toml::to_string_pretty(config).context("Failed to serialize config")?;
+    std::fs::write(&path, text)
+        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use serialport::{SerialPortInfo, SerialPortType};
+
+use crate::config::{DataBitsMode, FlowControlMode, ParityMode, StopBitsMode, View};
+
+/// Known behaviour of a USB-serial adapter, keyed by VID/PID. Entries in
+/// the config file (`[[quirks]]`) take precedence over the built-in ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirk {
+    pub vid: u16,
+    pub pid: u16,
+    pub name: String,
+    /// Highest baud rate known to work reliably
+    pub max_baud: Option<u32>,
+    /// Flow control forced at open
+    pub flow_control: Option<FlowControlMode>,
+    /// DTR/RTS levels set right after open (e.g. to keep a board out of reset)
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+    /// Framing the device talks, unless given on the command line or in
+    /// the profile
+    pub data_bits: Option<DataBitsMode>,
+    pub parity: Option<ParityMode>,
+    pub stop_bits: Option<StopBitsMode>,
+    /// View to start in, e.g. `"hexdump"` for a binary protocol device
+    pub view: Option<View>,
+    pub note: Option<String>,
+}
+
+fn builtin(vid: u16, pid: u16, name: &str, max_baud: u32, note: Option<&str>) -> Quirk {
+    Quirk {
+        vid,
+        pid,
+        name: name.to_string(),
+        max_baud: Some(max_baud),
+        note: note.map(str::to_string),
+        ..Quirk::default()
+    }
+}
+
+fn builtin_quirks() -> Vec<Quirk> {
+    vec![
+        builtin(
+            0x0403,
+            0x6001,
+            "FTDI FT232R",
+            3_000_000,
+            Some("16 ms latency timer by default, see --low-latency"),
+        ),
+        builtin(
+            0x0403,
+            0x6010,
+            "FTDI FT2232H",
+            12_000_000,
+            Some("16 ms latency timer by default, see --low-latency"),
+        ),
+        builtin(
+            0x0403,
+            0x6014,
+            "FTDI FT232H",
+            12_000_000,
+            Some("16 ms latency timer by default, see --low-latency"),
+        ),
+        builtin(0x10c4, 0xea60, "Silicon Labs CP210x", 921_600, None),
+        Quirk {
+            flow_control: Some(FlowControlMode::None),
+            ..builtin(
+                0x1a86,
+                0x7523,
+                "WCH CH340",
+                2_000_000,
+                Some("no RTS/CTS flow control"),
+            )
+        },
+        builtin(
+            0x067b,
+            0x2303,
+            "Prolific PL2303",
+            1_228_800,
+            Some("counterfeit chips are common and may be rejected by recent drivers"),
+        ),
+        builtin(
+            0x0483,
+            0x374b,
+            "ST-LINK/V2-1 VCP",
+            2_000_000,
+            Some("baud rates are approximated by the ST-LINK firmware"),
+        ),
+        Quirk {
+            vid: 0x303a,
+            pid: 0x1001,
+            name: "Espressif USB-Serial/JTAG".to_string(),
+            note: Some("native USB: baud rate is ignored, DTR/RTS drive reset/boot".to_string()),
+            ..Quirk::default()
+        },
+        Quirk {
+            vid: 0x2e8a,
+            pid: 0x000a,
+            name: "Raspberry Pi Pico (CDC)".to_string(),
+            note: Some("native USB: baud rate is ignored".to_string()),
+            ..Quirk::default()
+        },
+    ]
+}
+
+/// User quirks followed by the built-in ones; the first match wins
+#[derive(Clone)]
+pub struct QuirkDb {
+    entries: Vec<Quirk>,
+}
+
+impl QuirkDb {
+    pub fn new(user: Vec<Quirk>) -> Self {
+        let mut entries = user;
+        entries.extend(builtin_quirks());
+        Self { entries }
+    }
+
+    pub fn lookup(&self, vid: u16, pid: u16) -> Option<&Quirk> {
+        self.entries.iter().find(|q| q.vid == vid && q.pid == pid)
+    }
+
+    /// Quirk for an enumerated port, if it is a known USB adapter
+    pub fn for_port(&self, port: &SerialPortInfo) -> Option<&Quirk> {
+        match &port.port_type {
+            SerialPortType::UsbPort(info) => self.lookup(info.vid, info.pid),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// `--read-only`: every port opened from now on is wrapped in
+/// [`ReadOnlyPort`]
+pub fn enable() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+fn refused() -> serialport::Error {
+    serialport::Error::new(
+        serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied),
+        "port is read-only (--read-only)",
+    )
+}
+
+/// A port that only listens: data, BREAK and control line changes are
+/// refused, as is dropping buffered bytes another program may still want.
+/// Line settings (baud, framing) can still change.
+pub struct ReadOnlyPort {
+    inner: Box<dyn SerialPort>,
+}
+
+impl ReadOnlyPort {
+    pub fn new(inner: Box<dyn SerialPort>) -> Self {
+        ReadOnlyPort { inner }
+    }
+}
+
+impl io::Read for ReadOnlyPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl io::Write for ReadOnlyPort {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(refused().into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for ReadOnlyPort {
+    fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.inner.baud_rate()
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.inner.data_bits()
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.inner.flow_control()
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.inner.parity()
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.inner.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.inner.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.inner.set_flow_control(flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.inner.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.inner.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.inner.set_timeout(timeout)
+    }
+
+    fn write_request_to_send(&mut self, _level: bool) -> serialport::Result<()> {
+        Err(refused())
+    }
+
+    fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+        Err(refused())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.inner.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.inner.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.inner.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.inner.read_carrier_detect()
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_write()
+    }
+
+    fn clear(&self, _buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        Err(refused())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        let clone = self.inner.try_clone()?;
+        Ok(Box::new(ReadOnlyPort::new(clone)))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Err(refused())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Err(refused())
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::{Context, Result};
+use serialport::{SerialPort, SerialPortInfo, SerialPortType};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+
+use crate::config::PortSettings;
+use crate::faults;
+use crate::low_latency;
+use crate::port_discovery::{UsbInterface, port_present};
+use crate::port_lock::{self, LockedPort, OnBusy};
+use crate::read_only::{self, ReadOnlyPort};
+use crate::ui::UiMessage;
+
+/// How often the port list is checked while the device is away
+const POLL: Duration = Duration::from_millis(500);
+
+/// Equal, or unknown on one side
+fn agree<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    a.is_none() || b.is_none() || a == b
+}
+
+/// USB identity of the port, so a device that comes back under another
+/// name (ttyACM0 -> ttyACM1) is still found. The interface tells the
+/// functions of a composite device apart, so the session gets the console
+/// back rather than the data channel next to it.
+#[derive(Clone, Debug, PartialEq)]
+struct UsbIdentity {
+    vid: u16,
+    pid: u16,
+    serial_number: Option<String>,
+    interface: UsbInterface,
+}
+
+impl UsbIdentity {
+    fn of(info: &SerialPortInfo) -> Option<Self> {
+        match &info.port_type {
+            SerialPortType::UsbPort(usb) => Some(UsbIdentity {
+                vid: usb.vid,
+                pid: usb.pid,
+                serial_number: usb.serial_number.clone(),
+                interface: UsbInterface::of(info),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Same device and, as far as both sides know it, the same interface
+    fn matches(&self, other: &UsbIdentity) -> bool {
+        self.vid == other.vid
+            && self.pid == other.pid
+            && self.serial_number == other.serial_number
+            && agree(&self.interface.number, &other.interface.number)
+            && agree(&self.interface.name, &other.interface.name)
+    }
+}
+
+/// What opening one of the session's ports involves besides its settings.
+/// The first open, further tabs, reconnects and port switches all go
+/// through [`open_port`] with these.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpenOptions {
+    /// PARMRK error marks, for the reader to tag parity errors with
+    pub mark_errors: bool,
+    /// `--low-latency` driver tweaks
+    pub low_latency: bool,
+}
+
+/// Where the session continues after opening a port
+#[derive(Debug)]
+pub struct Reopened {
+    pub port_name: String,
+    #[cfg(target_os = "linux")]
+    pub port_fd: std::os::fd::RawFd,
+    /// The driver took the error marks asked for
+    pub error_marks: bool,
+    /// A lock file was taken for the port
+    pub lock_file: bool,
+    /// What `--low-latency` changed or couldn't
+    pub notes: Vec<String>,
+}
+
+/// Open `port_name` with the session's current `settings`, including what
+/// serialport doesn't cover (mark/space parity, PARMRK error marks when the
+/// reader expects them). The port keeps its lock file until dropped.
+pub fn open_port(
+    port_name: &str,
+    settings: &PortSettings,
+    options: OpenOptions,
+    on_busy: OnBusy,
+) -> Result<(Box<dyn SerialPort + Send>, Reopened)> {
+    let (mut port, lock) = port_lock::open_locked(port_name, on_busy, || {
+        serialport::new(port_name, settings.baud)
+            .data_bits(settings.data_bits)
+            .parity(settings.parity)
+            .stop_bits(settings.stop_bits)
+            .flow_control(settings.flow_control)
+            .timeout(Duration::from_millis(100))
+            .open_native()
+    })?;
+    // A listener leaves the port open to the program it listens alongside
+    #[cfg(unix)]
+    if read_only::enabled() {
+        let _ = port.set_exclusive(false);
+    }
+    let notes = if options.low_latency {
+        low_latency::apply(&port, port_name)
+    } else {
+        Vec::new()
+    };
+    #[cfg(target_os = "linux")]
+    let port_fd = std::os::fd::AsRawFd::as_raw_fd(&port);
+    #[cfg(target_os = "linux")]
+    if settings.stick_parity {
+        crate::parity::set_stick_parity(port_fd, true)
+            .context("Failed to set mark/space parity")?;
+    }
+    #[cfg(target_os = "linux")]
+    let error_marks = options.mark_errors && crate::parity::mark_errors(port_fd).is_ok();
+    #[cfg(not(target_os = "linux"))]
+    let error_marks = false;
+    let reopened = Reopened {
+        port_name: port_name.to_string(),
+        #[cfg(target_os = "linux")]
+        port_fd,
+        error_marks,
+        lock_file: lock.is_some(),
+        notes,
+    };
+    let port: Box<dyn SerialPort + Send> = if read_only::enabled() {
+        Box::new(ReadOnlyPort::new(faults::wrap(Box::new(port))))
+    } else {
+        // Not every port has control lines (USB CDC without them, ptys)
+        let _ = port.write_data_terminal_ready(settings.dtr);
+        let _ = port.write_request_to_send(settings.rts);
+        faults::wrap(Box::new(port))
+    };
+    Ok((LockedPort::wrap(port, lock), reopened))
+}
+
+/// Counts the times a session's port was replaced (reconnect or port
+/// switch), so work tied to the old port stops: a reconnect still waiting
+/// for it must not swap it back in, and its fd may be closed or reused
+#[derive(Clone, Debug, Default)]
+pub struct PortGeneration(Arc<AtomicU64>);
+
+impl PortGeneration {
+    pub fn current(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Call with the port mutex held, right as the port is swapped
+    pub fn bump(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// `--reconnect`: what to look for once the port is gone
+#[derive(Clone, Debug)]
+pub struct Reconnect {
+    port_name: String,
+    usb: Option<UsbIdentity>,
+    /// As the port was first opened: the reader unescapes PARMRK marks, so
+    /// the new port needs them too
+    options: OpenOptions,
+}
+
+impl Reconnect {
+    pub fn new(port_name: &str, ports: &[SerialPortInfo], options: OpenOptions) -> Self {
+        Reconnect {
+            port_name: port_name.to_string(),
+            usb: ports
+                .iter()
+                .find(|p| p.port_name == port_name)
+                .and_then(UsbIdentity::of),
+            options,
+        }
+    }
+
+    /// The port's name if it is back. A USB port is looked up by identity,
+    /// its old name preferred: after re-enumeration that name may belong to
+    /// another interface of the same device.
+    fn find(&self) -> Option<String> {
+        let Some(usb) = &self.usb else {
+            return port_present(&self.port_name).then(|| self.port_name.clone());
+        };
+        let ports = serialport::available_ports().unwrap_or_default();
+        let matching: Vec<&str> = ports
+            .iter()
+            .filter(|p| UsbIdentity::of(p).is_some_and(|id| id.matches(usb)))
+            .map(|p| p.port_name.as_str())
+            .collect();
+        matching
+            .iter()
+            .find(|&&name| name == self.port_name)
+            .or(matching.first())
+            .map(|name| name.to_string())
+    }
+}
+
+/// Wait for the port to come back, reopen it with `settings` and swap it in
+/// for the dead one; the reader picks it up on its next read. Gives up when
+/// the session ends or the port is switched away from `generation`.
+pub async fn reconnect(
+    reconnect: Reconnect,
+    settings: PortSettings,
+    port: Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    generation: PortGeneration,
+    spawned_at: u64,
+    running: Arc<AtomicBool>,
+    ui_tx: mpsc::UnboundedSender<UiMessage>,
+) {
+    while running.load(Ordering::SeqCst) && generation.current() == spawned_at {
+        tokio::time::sleep(POLL).await;
+        let Some(port_name) = reconnect.find() else {
+            continue;
+        };
+        // Freshly enumerated devices can refuse to open until udev is done
+        let Ok((new_port, reopened)) =
+            open_port(&port_name, &settings, reconnect.options, OnBusy::Fail)
+        else {
+            continue;
+        };
+        let mut current = port.lock().await;
+        // A switch while this was opening wins; the reopened port is dropped
+        if generation.current() != spawned_at {
+            return;
+        }
+        generation.bump();
+        *current = new_port;
+        drop(current);
+        let _ = ui_tx.send(UiMessage::Reconnected(reopened));
+        return;
+    }
+}
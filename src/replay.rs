@@ -0,0 +1,398 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use serialport::SerialPort;
+use std::path::Path;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+
+use crate::logging::{LOG_TS_FORMAT, LogWriter, write_log_chunk};
+use crate::serial_io::write_bytes_async;
+use crate::tx_queue::JobHandle;
+use crate::ui::UiMessage;
+
+/// Length of a `[YYYY-MM-DD HH:MM:SS.fff] ` log timestamp prefix
+pub const TS_PREFIX_LEN: usize = 26;
+
+/// A chunk of bytes to send after waiting `delay` since the previous chunk
+#[derive(Debug, Clone)]
+pub struct ReplayChunk {
+    pub delay: Duration,
+    pub bytes: Vec<u8>,
+}
+
+/// Load a replay file. Files written with `--tx-log --log-ts` are split at
+/// each timestamp so the original timing can be reproduced; anything else
+/// is sent as a single chunk.
+pub fn load_replay(path: &Path) -> Result<Vec<ReplayChunk>> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("Failed to read replay file: {}", path.display()))?;
+    Ok(parse_replay(&data))
+}
+
+pub fn parse_timestamp(bytes: &[u8]) -> Option<NaiveDateTime> {
+    if bytes.len() < TS_PREFIX_LEN
+        || bytes[0] != b'['
+        || bytes[TS_PREFIX_LEN - 2] != b']'
+        || bytes[TS_PREFIX_LEN - 1] != b' '
+    {
+        return None;
+    }
+    let s = std::str::from_utf8(&bytes[1..TS_PREFIX_LEN - 2]).ok()?;
+    NaiveDateTime::parse_from_str(s, LOG_TS_FORMAT).ok()
+}
+
+fn parse_replay(data: &[u8]) -> Vec<ReplayChunk> {
+    if parse_timestamp(data).is_none() {
+        // No timestamps: raw byte stream, send it all at once
+        return vec![ReplayChunk {
+            delay: Duration::ZERO,
+            bytes: data.to_vec(),
+        }];
+    }
+
+    let mut chunks = Vec::new();
+    let mut prev_ts: Option<NaiveDateTime> = None;
+    let mut pos = 0;
+    while pos < data.len() {
+        // Every chunk starts with a timestamp (guaranteed by the check above
+        // and by where we stop scanning below)
+        let ts = parse_timestamp(&data[pos..]).expect("chunk starts with timestamp");
+        let start = pos + TS_PREFIX_LEN;
+        let mut end = start;
+        while end < data.len() && parse_timestamp(&data[end..]).is_none() {
+            end += 1;
+        }
+
+        let delay = prev_ts
+            .and_then(|p| (ts - p).to_std().ok())
+            .unwrap_or(Duration::ZERO);
+        prev_ts = Some(ts);
+
+        chunks.push(ReplayChunk {
+            delay,
+            bytes: data[start..end].to_vec(),
+        });
+        pos = end;
+    }
+    chunks
+}
+
+/// Maximum number of divergences listed in the output pane
+const MAX_REPORTED_DIVERGENCES: usize = 20;
+
+/// RX kept for one replay diff; a device that keeps talking can't grow it
+/// past this
+const MAX_CAPTURE: usize = 16 << 20;
+
+/// How far ahead a diff looks on either side for the lines to agree again
+const RESYNC_WINDOW: usize = 50;
+
+/// RX received while a replay runs, for its diff; `None` outside of one
+pub type Capture = Arc<StdMutex<Option<Vec<u8>>>>;
+
+/// Keep `bytes` if a replay is capturing
+pub fn capture_bytes(capture: &Capture, bytes: &[u8]) {
+    if let Ok(mut capture) = capture.lock()
+        && let Some(buf) = capture.as_mut()
+    {
+        let room = MAX_CAPTURE.saturating_sub(buf.len());
+        buf.extend_from_slice(&bytes[..bytes.len().min(room)]);
+    }
+}
+
+/// Compare the RX captured during a replay against a previously recorded
+/// RX log (written in text mode, with or without --log-ts)
+pub struct ReplayCompare {
+    pub expected: Vec<String>,
+    /// Lines containing any of these substrings are skipped on both sides
+    pub ignore: Vec<String>,
+    /// How long to keep capturing after the last chunk was sent
+    pub settle: Duration,
+    pub capture: Capture,
+}
+
+/// Load a recorded RX log as normalized lines for comparison
+pub fn load_expected(path: &Path) -> Result<Vec<String>> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("Failed to read expected RX file: {}", path.display()))?;
+    Ok(normalize_lines(&strip_timestamps(&data)))
+}
+
+/// Remove every `[timestamp] ` prefix, wherever chunk boundaries put it
+fn strip_timestamps(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    while pos < data.len() {
+        if parse_timestamp(&data[pos..]).is_some() {
+            pos += TS_PREFIX_LEN;
+        } else {
+            out.push(data[pos]);
+            pos += 1;
+        }
+    }
+    out
+}
+
+/// Split into lines, trimming trailing whitespace and dropping blank lines
+fn normalize_lines(data: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .map(|l| l.trim_end().to_string())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// Offsets into `expected` and `actual` where the two agree again, the
+/// nearest first; `None` if they don't within the window
+fn resync(expected: &[&String], actual: &[&String]) -> Option<(usize, usize)> {
+    for distance in 1..=2 * RESYNC_WINDOW {
+        for skip_expected in distance.saturating_sub(RESYNC_WINDOW)..=distance.min(RESYNC_WINDOW) {
+            let skip_actual = distance - skip_expected;
+            if let (Some(e), Some(a)) = (expected.get(skip_expected), actual.get(skip_actual))
+                && e == a
+            {
+                return Some((skip_expected, skip_actual));
+            }
+        }
+    }
+    None
+}
+
+/// Line-by-line comparison that picks up again after inserted, dropped or
+/// changed lines; returns a human readable entry per divergence, numbered
+/// by the expected lines
+fn diff_lines(expected: &[String], actual: &[String], ignore: &[String]) -> Vec<String> {
+    let keep = |l: &&String| !ignore.iter().any(|pat| l.contains(pat.as_str()));
+    let expected: Vec<&String> = expected.iter().filter(keep).collect();
+    let actual: Vec<&String> = actual.iter().filter(keep).collect();
+
+    let mut divergences = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    loop {
+        match (expected.get(i), actual.get(j)) {
+            (Some(e), Some(a)) if e == a => {
+                i += 1;
+                j += 1;
+            }
+            (Some(_), Some(_)) => {
+                // Unknown how long the difference runs: treat the rest as
+                // changed line by line
+                let (skip_expected, skip_actual) =
+                    resync(&expected[i..], &actual[j..]).unwrap_or((1, 1));
+                let changed = skip_expected.min(skip_actual);
+                for k in 0..changed {
+                    divergences.push(format!(
+                        "line {}: expected {:?}, got {:?}",
+                        i + k + 1,
+                        expected[i + k],
+                        actual[j + k]
+                    ));
+                }
+                for k in changed..skip_expected {
+                    divergences.push(format!("line {}: missing {:?}", i + k + 1, expected[i + k]));
+                }
+                for k in changed..skip_actual {
+                    divergences.push(format!(
+                        "after line {}: unexpected {:?}",
+                        i + changed,
+                        actual[j + k]
+                    ));
+                }
+                i += skip_expected;
+                j += skip_actual;
+            }
+            (Some(e), None) => {
+                divergences.push(format!("line {}: missing {e:?}", i + 1));
+                i += 1;
+            }
+            (None, Some(a)) => {
+                divergences.push(format!("after line {i}: unexpected {a:?}"));
+                j += 1;
+            }
+            (None, None) => return divergences,
+        }
+    }
+}
+
+fn report_comparison(compare: &ReplayCompare, ui_tx: &mpsc::UnboundedSender<UiMessage>) {
+    // Capturing ends with the diff
+    let captured = compare
+        .capture
+        .lock()
+        .ok()
+        .and_then(|mut buf| buf.take())
+        .unwrap_or_default();
+    let actual = normalize_lines(&captured);
+    let divergences = diff_lines(&compare.expected, &actual, &compare.ignore);
+
+    if divergences.is_empty() {
+        let _ = ui_tx.send(UiMessage::Status("Replay diff: RX matches".to_string()));
+        return;
+    }
+
+    let _ = ui_tx.send(UiMessage::Output(format!(
+        "== Replay diff: {} divergence(s) ==",
+        divergences.len()
+    )));
+    for d in divergences.iter().take(MAX_REPORTED_DIVERGENCES) {
+        let _ = ui_tx.send(UiMessage::Output(format!("!! {d}")));
+    }
+    if divergences.len() > MAX_REPORTED_DIVERGENCES {
+        let _ = ui_tx.send(UiMessage::Output(format!(
+            "!! ... and {} more",
+            divergences.len() - MAX_REPORTED_DIVERGENCES
+        )));
+    }
+    let _ = ui_tx.send(UiMessage::Status(format!(
+        "Replay diff: {} divergence(s)",
+        divergences.len()
+    )));
+}
+
+/// A replay that ends without a diff drops what it captured
+fn stop_capture(compare: Option<&ReplayCompare>) {
+    if let Some(c) = compare
+        && let Ok(mut buf) = c.capture.lock()
+    {
+        *buf = None;
+    }
+}
+
+/// Send the chunks to the port, sleeping between them to honour the
+/// recorded timing. Stops early if the app is shutting down. With
+/// `compare`, the RX captured during the replay is diffed at the end.
+pub async fn run_replay(
+    port: Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    chunks: Vec<ReplayChunk>,
+    job: JobHandle,
+    tx_log: Option<LogWriter>,
+    log_ts: bool,
+    ui_tx: mpsc::UnboundedSender<UiMessage>,
+    compare: Option<ReplayCompare>,
+) {
+    let total = chunks.len();
+    if let Some(c) = &compare
+        && let Ok(mut buf) = c.capture.lock()
+    {
+        *buf = Some(Vec::new());
+    }
+    let _ = ui_tx.send(UiMessage::Status(format!("Replaying {total} chunk(s)...")));
+
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        job.resumed().await;
+        if !chunk.delay.is_zero() {
+            tokio::select! {
+                _ = tokio::time::sleep(chunk.delay) => {}
+                _ = job.cancelled() => {}
+            }
+        }
+        if job.is_cancelled() {
+            let _ = ui_tx.send(UiMessage::Status(format!(
+                "Replay cancelled at {i}/{total}"
+            )));
+            stop_capture(compare.as_ref());
+            return;
+        }
+        if let Err(e) = write_bytes_async(&port, &chunk.bytes).await {
+            let _ = ui_tx.send(UiMessage::Status(format!("Replay failed: {e}")));
+            stop_capture(compare.as_ref());
+            return;
+        }
+        job.sent(chunk.bytes.len());
+        if let Some(w) = &tx_log {
+            write_log_chunk(w, &chunk.bytes, log_ts);
+        }
+        let _ = ui_tx.send(UiMessage::Status(format!("Replaying {}/{total}", i + 1)));
+        job.advance();
+    }
+    drop(job);
+
+    let _ = ui_tx.send(UiMessage::Status(format!("Replay done ({total} chunk(s))")));
+
+    if let Some(compare) = compare {
+        tokio::time::sleep(compare.settle).await;
+        if ui_tx.is_closed() {
+            stop_capture(Some(&compare));
+        } else {
+            report_comparison(&compare, &ui_tx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn diff(expected: &str, actual: &str) -> Vec<String> {
+        diff_lines(&lines(expected), &lines(actual), &[])
+    }
+
+    #[test]
+    fn equal_lines_have_no_divergences() {
+        assert!(diff("a b c", "a b c").is_empty());
+        assert!(diff("", "").is_empty());
+    }
+
+    #[test]
+    fn changed_line() {
+        assert_eq!(diff("a b c", "a x c"), [r#"line 2: expected "b", got "x""#]);
+    }
+
+    #[test]
+    fn inserted_line_does_not_shift_the_rest() {
+        assert_eq!(
+            diff("a b c d", "a x b c d"),
+            [r#"after line 1: unexpected "x""#]
+        );
+    }
+
+    #[test]
+    fn dropped_line_does_not_shift_the_rest() {
+        assert_eq!(diff("a b c d", "a c d"), [r#"line 2: missing "b""#]);
+    }
+
+    #[test]
+    fn trailing_differences() {
+        assert_eq!(diff("a b", "a"), [r#"line 2: missing "b""#]);
+        assert_eq!(diff("a", "a b"), [r#"after line 1: unexpected "b""#]);
+    }
+
+    #[test]
+    fn no_common_lines() {
+        assert_eq!(
+            diff("a b", "x y z"),
+            [
+                r#"line 1: expected "a", got "x""#,
+                r#"line 2: expected "b", got "y""#,
+                r#"after line 2: unexpected "z""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn ignored_lines_are_skipped() {
+        let ignore = ["uptime".to_string()];
+        let expected = lines("a uptime=1 b");
+        let actual = lines("a b uptime=2");
+        assert!(diff_lines(&expected, &actual, &ignore).is_empty());
+    }
+
+    #[test]
+    fn capture_only_while_replaying_and_bounded() {
+        let capture: Capture = Arc::default();
+        capture_bytes(&capture, b"before");
+        assert!(capture.lock().unwrap().is_none());
+        *capture.lock().unwrap() = Some(Vec::new());
+        capture_bytes(&capture, &vec![0; MAX_CAPTURE + 10]);
+        capture_bytes(&capture, b"more");
+        assert_eq!(
+            capture.lock().unwrap().as_ref().map(Vec::len),
+            Some(MAX_CAPTURE)
+        );
+    }
+}
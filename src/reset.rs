@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::config::PortSettings;
+use crate::port_discovery::port_present;
+use ResetStep::{Dtr, Rts};
+
+/// One step of a reset sequence
+#[derive(Copy, Clone, Debug)]
+pub enum ResetStep {
+    Dtr(bool),
+    Rts(bool),
+    Sleep(Duration),
+}
+
+const fn ms(millis: u64) -> ResetStep {
+    ResetStep::Sleep(Duration::from_millis(millis))
+}
+
+/// EN low, IO0 high; then release EN
+const ESP32_RUN: &[ResetStep] = &[Dtr(false), Rts(true), ms(100), Rts(false)];
+
+/// esptool's classic reset: EN low, then IO0 low while EN rises, then
+/// release IO0 once the ROM has sampled it
+const ESP32_BOOT: &[ResetStep] = &[
+    Dtr(false),
+    Rts(true),
+    ms(100),
+    Dtr(true),
+    Rts(false),
+    ms(50),
+    Dtr(false),
+];
+
+const DTR_PULSE: &[ResetStep] = &[Dtr(false), ms(100), Dtr(true)];
+
+/// Assert reset (DTR) with BOOT0 high (RTS), release reset, then let BOOT0
+/// go: the system memory bootloader is running
+const STM32_BOOT: &[ResetStep] = &[
+    Rts(true),
+    Dtr(true),
+    ms(100),
+    Dtr(false),
+    ms(100),
+    Rts(false),
+];
+
+/// Reset with BOOT0 low: back to the application in flash
+const STM32_RUN: &[ResetStep] = &[Rts(false), Dtr(true), ms(100), Dtr(false)];
+
+/// Boards reset (or strapped into their bootloader) through DTR/RTS.
+/// Another target is another variant plus its steps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetKind {
+    /// ESP32/ESP8266 auto-reset circuit (RTS to EN, DTR to IO0): reboot
+    /// into the application
+    Esp32,
+    /// The same circuit, holding IO0 low through reset: ROM bootloader
+    Esp32Boot,
+    /// STM32 with DTR to NRST and RTS to BOOT0: enter the UART bootloader
+    /// (it wants 8E1: `--parity even`)
+    Stm32,
+    /// The same wiring, BOOT0 low: reboot into the application
+    Stm32Run,
+    /// Pulse DTR low, as the Arduino IDE does before uploading
+    DtrPulse,
+}
+
+/// Control lines wired through an inverter, for `--reset-invert`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetInvert {
+    Dtr,
+    Rts,
+    Both,
+}
+
+impl ResetInvert {
+    fn dtr(invert: Option<ResetInvert>) -> bool {
+        matches!(invert, Some(ResetInvert::Dtr | ResetInvert::Both))
+    }
+
+    fn rts(invert: Option<ResetInvert>) -> bool {
+        matches!(invert, Some(ResetInvert::Rts | ResetInvert::Both))
+    }
+}
+
+impl ResetKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ResetKind::Esp32 => "esp32",
+            ResetKind::Esp32Boot => "esp32-boot",
+            ResetKind::Stm32 => "stm32",
+            ResetKind::Stm32Run => "stm32-run",
+            ResetKind::DtrPulse => "dtr-pulse",
+        }
+    }
+
+    pub fn steps(self) -> &'static [ResetStep] {
+        match self {
+            ResetKind::Esp32 => ESP32_RUN,
+            ResetKind::Esp32Boot => ESP32_BOOT,
+            ResetKind::Stm32 => STM32_BOOT,
+            ResetKind::Stm32Run => STM32_RUN,
+            ResetKind::DtrPulse => DTR_PULSE,
+        }
+    }
+
+    /// The sequence that enters this target's bootloader, if it has one
+    pub fn bootloader(self) -> Option<ResetKind> {
+        match self {
+            ResetKind::Esp32 | ResetKind::Esp32Boot => Some(ResetKind::Esp32Boot),
+            ResetKind::Stm32 | ResetKind::Stm32Run => Some(ResetKind::Stm32),
+            ResetKind::DtrPulse => None,
+        }
+    }
+}
+
+fn apply(
+    port: &mut dyn SerialPort,
+    step: ResetStep,
+    invert: Option<ResetInvert>,
+    settings: &mut PortSettings,
+) -> Result<()> {
+    match step {
+        ResetStep::Dtr(level) => {
+            let level = level != ResetInvert::dtr(invert);
+            port.write_data_terminal_ready(level)
+                .context("Failed to set DTR")?;
+            settings.dtr = level;
+        }
+        ResetStep::Rts(level) => {
+            let level = level != ResetInvert::rts(invert);
+            port.write_request_to_send(level)
+                .context("Failed to set RTS")?;
+            settings.rts = level;
+        }
+        ResetStep::Sleep(_) => {}
+    }
+    Ok(())
+}
+
+/// Run a sequence on the port before it is shared (`--reset` on connect)
+pub fn run_blocking(
+    port: &mut dyn SerialPort,
+    kind: ResetKind,
+    invert: Option<ResetInvert>,
+    settings: &mut PortSettings,
+) -> Result<()> {
+    for &step in kind.steps() {
+        if let ResetStep::Sleep(duration) = step {
+            std::thread::sleep(duration);
+        }
+        apply(port, step, invert, settings)?;
+    }
+    Ok(())
+}
+
+/// Run a sequence on the shared port, letting the reader in between steps
+pub async fn run(
+    port: &Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    kind: ResetKind,
+    invert: Option<ResetInvert>,
+    settings: &mut PortSettings,
+) -> Result<()> {
+    for &step in kind.steps() {
+        if let ResetStep::Sleep(duration) = step {
+            tokio::time::sleep(duration).await;
+        }
+        apply(port.lock().await.as_mut(), step, invert, settings)?;
+    }
+    Ok(())
+}
+
+/// How long the bootloader's port gets to show up after a 1200-baud touch
+const TOUCH_REENUMERATE: Duration = Duration::from_secs(5);
+
+/// Arduino 1200-baud touch (SAMD, Leonardo/Micro, RP2040 with the Arduino
+/// core): opening the port at 1200 baud and dropping DTR makes the board
+/// reboot into its bootloader. Waits for the port to come back, as the USB
+/// device re-enumerates. Returns whether it did in time.
+pub fn touch_1200(port_name: &str) -> Result<bool> {
+    {
+        let mut port = serialport::new(port_name, 1200)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .with_context(|| format!("Failed to open '{port_name}' at 1200 baud"))?;
+        // Some drivers only report the change if DTR goes low explicitly
+        let _ = port.write_data_terminal_ready(false);
+    }
+    // Give the old device time to disappear before looking for the new one
+    std::thread::sleep(Duration::from_millis(500));
+    let deadline = std::time::Instant::now() + TOUCH_REENUMERATE;
+    while std::time::Instant::now() < deadline {
+        if port_present(port_name) {
+            // Let udev finish setting permissions
+            std::thread::sleep(Duration::from_millis(200));
+            return Ok(true);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(false)
+}
@@ -0,0 +1,142 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::ui::UiMessage;
+
+/// First wait before starting the command again; doubled while it keeps
+/// exiting right away
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// How often an idle pipe checks whether the command is still running
+const EXIT_CHECK: Duration = Duration::from_secs(1);
+
+/// `--rx-pipe`: raw RX bytes go to a command's stdin (`defmt-print`, a
+/// Python parser...), which is started again whenever it exits
+#[derive(Clone)]
+pub struct RxPipe {
+    tx: mpsc::Sender<Vec<u8>>,
+}
+
+impl RxPipe {
+    /// Run `cmd` through the shell. With `show_output` its stdout and
+    /// stderr lines come back as [`UiMessage::PipeOutput`]; otherwise
+    /// they are dropped so they can't scribble over the TUI.
+    pub fn spawn(cmd: String, show_output: bool, ui_tx: UnboundedSender<UiMessage>) -> Self {
+        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            let mut delay = RESTART_DELAY;
+            loop {
+                let started = Instant::now();
+                let mut child = match start(&cmd, show_output) {
+                    Ok(child) => child,
+                    Err(e) => {
+                        let _ = ui_tx.send(UiMessage::Status(format!(
+                            "RX pipe: failed to run '{cmd}': {e}"
+                        )));
+                        return;
+                    }
+                };
+                if show_output {
+                    if let Some(out) = child.stdout.take() {
+                        forward_lines(out, ui_tx.clone());
+                    }
+                    if let Some(err) = child.stderr.take() {
+                        forward_lines(err, ui_tx.clone());
+                    }
+                }
+                let Some(mut stdin) = child.stdin.take() else {
+                    return;
+                };
+                loop {
+                    match rx.recv_timeout(EXIT_CHECK) {
+                        Ok(bytes) => {
+                            if stdin
+                                .write_all(&bytes)
+                                .and_then(|()| stdin.flush())
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {
+                            if !matches!(child.try_wait(), Ok(None)) {
+                                break;
+                            }
+                        }
+                        // Session over: EOF tells the command to finish up
+                        Err(RecvTimeoutError::Disconnected) => {
+                            drop(stdin);
+                            let _ = child.wait();
+                            return;
+                        }
+                    }
+                }
+                drop(stdin);
+                let status = child
+                    .wait()
+                    .map_or_else(|e| e.to_string(), |s| s.to_string());
+                if started.elapsed() < delay {
+                    delay = (delay * 2).min(MAX_RESTART_DELAY);
+                } else {
+                    delay = RESTART_DELAY;
+                }
+                let _ = ui_tx.send(UiMessage::Status(format!(
+                    "RX pipe '{cmd}' exited ({status}); restarting in {}s",
+                    delay.as_secs()
+                )));
+                std::thread::sleep(delay);
+            }
+        });
+        RxPipe { tx }
+    }
+
+    pub fn send(&self, bytes: &[u8]) {
+        let _ = self.tx.send(bytes.to_vec());
+    }
+}
+
+/// `cmd` run by the platform shell
+pub fn shell(cmd: &str) -> Command {
+    #[cfg(windows)]
+    let mut command = Command::new("cmd");
+    #[cfg(windows)]
+    command.args(["/C", cmd]);
+    #[cfg(not(windows))]
+    let mut command = Command::new("sh");
+    #[cfg(not(windows))]
+    command.args(["-c", cmd]);
+    command
+}
+
+fn start(cmd: &str, show_output: bool) -> std::io::Result<Child> {
+    let output = || {
+        if show_output {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        }
+    };
+    shell(cmd)
+        .stdin(Stdio::piped())
+        .stdout(output())
+        .stderr(output())
+        .spawn()
+}
+
+fn forward_lines(stream: impl Read + Send + 'static, ui_tx: UnboundedSender<UiMessage>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).split(b'\n') {
+            let Ok(line) = line else {
+                return;
+            };
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            if ui_tx.send(UiMessage::PipeOutput(line)).is_err() {
+                return;
+            }
+        }
+    });
+}
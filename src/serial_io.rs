@@ -1,13 +1,54 @@
+use crate::config::RxLineEnding;
+#[cfg(feature = "decoders")]
+use crate::decoders::SharedPipeline;
+use crate::faults;
+use crate::logging::{LOG_TS_FORMAT, LineDedup};
+use crate::parity::{ErrorMarks, marked_byte};
+use crate::replay::{self, Capture};
+use crate::rx_pipe::RxPipe;
+use crate::tx_pipe;
 use anyhow::Result;
 use chrono::Utc;
 use serialport::SerialPort;
-use std::sync::Arc;
+use std::fmt::Write as _;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tokio::sync::{Mutex, mpsc};
 
+/// BREAK length unless `--break-duration` says otherwise
+pub const DEFAULT_BREAK: Duration = Duration::from_millis(250);
+
+/// RS-485 direction control through RTS (`--rs485`): RTS is asserted to
+/// transmit and dropped again once the bytes are out
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Rs485 {
+    /// Between asserting RTS and the first byte
+    pub before: Duration,
+    /// Between the flush and dropping RTS, for adapters whose flush returns
+    /// before the last byte left the wire
+    pub after: Duration,
+}
+
+static RS485: OnceLock<Rs485> = OnceLock::new();
+
+/// Switch every later `write_bytes_async` to RS-485 direction control
+pub fn enable_rs485(rs485: Rs485) {
+    let _ = RS485.set(rs485);
+}
+
+/// Bytes per row in the hexdump view
+const HEXDUMP_ROW_LEN: usize = 16;
+
 #[derive(Debug, Clone)]
 pub enum SerialData {
-    Received(String),
+    /// Display text and the raw bytes it was formatted from
+    Received { text: String, bytes: Vec<u8> },
+    /// Reading failed; the reader has stopped
+    Disconnected(String),
+    /// Reading failed; the reader keeps trying until the port is swapped
+    /// for a reopened one (`--reconnect`)
+    Lost(String),
 }
 
 pub struct SerialReader {
@@ -19,6 +60,18 @@ pub struct SerialReader {
     rx_log_writer: Option<Arc<std::sync::Mutex<std::io::BufWriter<std::fs::File>>>>,
     // No cached timestamp needed with chrono
     buffer: Vec<u8>, // Pre-allocated buffer
+    capture: Option<Capture>,
+    log_dedup: Option<LineDedup>,
+    /// Session-wide offset of the next received byte (hexdump view)
+    hex_offset: u64,
+    /// The driver escapes bytes received with parity errors (PARMRK)
+    error_marks: Option<ErrorMarks>,
+    /// Wait for the port to be reopened instead of stopping on errors
+    reconnect: bool,
+    /// `--rx-pipe` command fed with every received byte
+    rx_pipe: Option<RxPipe>,
+    #[cfg(feature = "decoders")]
+    decoder: Option<SharedPipeline>,
 }
 
 impl SerialReader {
@@ -39,68 +92,212 @@ impl SerialReader {
             rx_log_writer,
             // No cached timestamp initialization needed
             buffer: vec![0u8; 4096], // Pre-allocate buffer to avoid allocations
+            capture: None,
+            log_dedup: None,
+            hex_offset: 0,
+            error_marks: None,
+            reconnect: false,
+            rx_pipe: None,
+            #[cfg(feature = "decoders")]
+            decoder: None,
         }
     }
 
+    /// Collapse repeated identical lines in the RX log, lines ending as
+    /// `ending` says (hex rows always end in LF)
+    pub fn with_log_dedup(mut self, ending: RxLineEnding) -> Self {
+        let ending = if self.hex_mode {
+            RxLineEnding::Lf
+        } else {
+            ending
+        };
+        self.log_dedup = Some(LineDedup::new(ending));
+        self
+    }
+
+    /// Run received bytes through the selected decoder pipeline (if any)
+    #[cfg(feature = "decoders")]
+    pub fn with_decoder(mut self, decoder: SharedPipeline) -> Self {
+        self.decoder = Some(decoder);
+        self
+    }
+
+    /// Unescape the driver's parity error marks and tag those bytes in the
+    /// text view
+    pub fn with_error_marks(mut self) -> Self {
+        self.error_marks = Some(ErrorMarks::default());
+        self
+    }
+
+    /// Keep going after a read error; the port gets swapped for a reopened
+    /// one (`--reconnect`)
+    pub fn with_reconnect(mut self) -> Self {
+        self.reconnect = true;
+        self
+    }
+
+    /// Also stream every received byte into an external command
+    /// (`--rx-pipe`)
+    pub fn with_rx_pipe(mut self, pipe: RxPipe) -> Self {
+        self.rx_pipe = Some(pipe);
+        self
+    }
+
+    /// Also keep received bytes in `capture` while a replay diff wants them
+    pub fn with_capture(mut self, capture: Capture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
     pub async fn run(mut self) {
+        let mut lost = false;
         while self.running.load(Ordering::SeqCst) {
             let n = {
                 let mut guard = self.port.lock().await;
                 match guard.read(&mut self.buffer) {
-                    Ok(n) => n,
-                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => 0,
-                    Err(_) => break,
+                    Ok(n) => {
+                        lost = false;
+                        n
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                        lost = false;
+                        0
+                    }
+                    Err(e) if self.reconnect => {
+                        drop(guard);
+                        if !lost {
+                            lost = true;
+                            let _ = self.sender.send(SerialData::Lost(e.to_string()));
+                        }
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                    Err(e) => {
+                        let _ = self.sender.send(SerialData::Disconnected(e.to_string()));
+                        break;
+                    }
                 }
             };
 
             if n > 0 {
                 // Make a copy to avoid borrow checker issues
-                let bytes = self.buffer[..n].to_vec();
-                self.process_received_data(&bytes).await;
+                let (bytes, errors) = match &mut self.error_marks {
+                    Some(marks) => marks.unescape(&self.buffer[..n]),
+                    None => (self.buffer[..n].to_vec(), Vec::new()),
+                };
+                if !bytes.is_empty() {
+                    self.process_received_data(&bytes, &errors).await;
+                }
             } else {
                 // Small async yield to prevent busy waiting
                 tokio::task::yield_now().await;
             }
         }
+
+        if let Some(dedup) = &mut self.log_dedup {
+            let lines = dedup.finish();
+            self.write_log_lines(&lines);
+        }
     }
 
-    async fn process_received_data(&mut self, bytes: &[u8]) {
+    /// `errors`: indices of bytes received with a parity error
+    async fn process_received_data(&mut self, bytes: &[u8], errors: &[usize]) {
+        if let Some(capture) = &self.capture {
+            replay::capture_bytes(capture, bytes);
+        }
+        if let Some(pipe) = &self.rx_pipe {
+            pipe.send(bytes);
+        }
+
         // Format the data - optimized to avoid multiple allocations
         let display_text = if self.hex_mode {
             self.format_hex_data(bytes)
+        } else if let Some(text) = self.format_decoded(bytes) {
+            text
         } else {
-            self.format_text_data(bytes)
+            self.format_text_data(bytes, errors)
         };
 
         // Send to UI
-        let _ = self.sender.send(SerialData::Received(display_text));
+        let _ = self.sender.send(SerialData::Received {
+            text: display_text,
+            bytes: bytes.to_vec(),
+        });
 
         // Write to RX log file if configured
         self.write_to_log(bytes).await;
     }
 
+    /// Hexdump rows (`offset  hex  |ascii|`), with the offset counting
+    /// every byte received this session so rows line up across chunks
     fn format_hex_data(&mut self, bytes: &[u8]) -> String {
-        let capacity = if self.log_ts { 32 } else { 0 } + bytes.len() * 3; // Estimate capacity
+        let rows = bytes.len().div_ceil(HEXDUMP_ROW_LEN);
+        let capacity = (if self.log_ts { 32 } else { 0 } + 80) * rows; // Estimate capacity
         let mut hex_str = String::with_capacity(capacity);
+        let ts = self
+            .log_ts
+            .then(|| Utc::now().format(LOG_TS_FORMAT).to_string());
 
-        if self.log_ts {
-            hex_str.push('[');
-            hex_str.push_str(&Utc::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string());
-            hex_str.push_str("] ");
-        }
+        for row in bytes.chunks(HEXDUMP_ROW_LEN) {
+            if let Some(ts) = &ts {
+                hex_str.push('[');
+                hex_str.push_str(ts);
+                hex_str.push_str("] ");
+            }
+            let _ = write!(hex_str, "{:08X}  ", self.hex_offset);
 
-        // Optimize hex formatting with pre-allocated string
-        for (i, b) in bytes.iter().enumerate() {
-            if i > 0 {
-                hex_str.push(' ');
+            for i in 0..HEXDUMP_ROW_LEN {
+                match row.get(i) {
+                    Some(b) => {
+                        let _ = write!(hex_str, "{b:02X} ");
+                    }
+                    None => hex_str.push_str("   "),
+                }
+                if i == HEXDUMP_ROW_LEN / 2 - 1 {
+                    hex_str.push(' ');
+                }
             }
-            hex_str.push_str(&format!("{:02X}", b));
+
+            hex_str.push_str(" |");
+            hex_str.extend(row.iter().map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            }));
+            hex_str.push_str("|\n");
+
+            self.hex_offset += row.len() as u64;
         }
 
         hex_str
     }
 
-    fn format_text_data(&mut self, bytes: &[u8]) -> String {
+    /// One line per frame out of the decoder pipeline, or `None` when no
+    /// decoder is selected
+    #[cfg(feature = "decoders")]
+    fn format_decoded(&self, bytes: &[u8]) -> Option<String> {
+        let mut guard = self.decoder.as_ref()?.lock().ok()?;
+        let frames = guard.as_mut()?.decode(bytes);
+        let mut text = String::new();
+        for frame in frames {
+            if self.log_ts {
+                let _ = write!(text, "[{}] ", Utc::now().format(LOG_TS_FORMAT));
+            }
+            text.push_str(&String::from_utf8_lossy(&frame));
+            // CRLF ends the line whatever --rx-line-ending is
+            text.push_str("\r\n");
+        }
+        Some(text)
+    }
+
+    #[cfg(not(feature = "decoders"))]
+    fn format_decoded(&self, _bytes: &[u8]) -> Option<String> {
+        None
+    }
+
+    fn format_text_data(&mut self, bytes: &[u8], errors: &[usize]) -> String {
         let capacity = if self.log_ts { 32 } else { 0 } + bytes.len();
         let mut text = String::with_capacity(capacity);
 
@@ -111,11 +308,30 @@ impl SerialReader {
         }
 
         // Use from_utf8_lossy but avoid extra allocations where possible
-        text.push_str(&String::from_utf8_lossy(bytes));
+        let mut start = 0;
+        for &i in errors {
+            text.push_str(&String::from_utf8_lossy(&bytes[start..i]));
+            text.push_str(&marked_byte(bytes[i]));
+            start = i + 1;
+        }
+        text.push_str(&String::from_utf8_lossy(&bytes[start..]));
         text
     }
 
     async fn write_to_log(&mut self, bytes: &[u8]) {
+        if let Some(dedup) = &mut self.log_dedup {
+            // Dedup works on whole lines; in hex mode each chunk is a line
+            let lines = if self.hex_mode {
+                let mut line = hex_bytes(bytes).into_bytes();
+                line.push(b'\n');
+                dedup.feed(&line)
+            } else {
+                dedup.feed(bytes)
+            };
+            self.write_log_lines(&lines);
+            return;
+        }
+
         if let Some(w) = &self.rx_log_writer
             && let Ok(mut lw) = w.lock()
         {
@@ -138,14 +354,79 @@ impl SerialReader {
             let _ = lw.flush();
         }
     }
+
+    /// Write whole lines to the RX log, timestamping each one
+    fn write_log_lines(&self, lines: &[Vec<u8>]) {
+        if lines.is_empty() {
+            return;
+        }
+        if let Some(w) = &self.rx_log_writer
+            && let Ok(mut lw) = w.lock()
+        {
+            use std::io::Write;
+
+            for line in lines {
+                if self.log_ts {
+                    let _ = write!(lw, "[{}] ", Utc::now().format(LOG_TS_FORMAT));
+                }
+                let _ = lw.write_all(line);
+            }
+            let _ = lw.flush();
+        }
+    }
+}
+
+/// Space-separated uppercase hex, as written to hex-mode logs
+pub fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
+/// Send `bytes`, through the `--tx-pipe` filter when there is one (its
+/// output reaches the port once the command prints it)
 pub async fn write_bytes_async(
     port: &Arc<Mutex<Box<dyn SerialPort + Send>>>,
     bytes: &[u8],
 ) -> Result<()> {
+    match tx_pipe::command() {
+        Some(cmd) => Ok(tx_pipe::feed(cmd, port, bytes)?),
+        None => write_port(port, bytes).await,
+    }
+}
+
+/// Write `bytes` to the port as they are, with the `--fault` delay and the
+/// RS-485 direction switching
+pub async fn write_port(port: &Mutex<Box<dyn SerialPort + Send>>, bytes: &[u8]) -> Result<()> {
+    let delay = faults::tx_delay();
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
     let mut guard = port.lock().await;
-    guard.write_all(bytes)?;
-    guard.flush()?;
+    let Some(rs485) = RS485.get() else {
+        guard.write_all(bytes)?;
+        guard.flush()?;
+        return Ok(());
+    };
+    // The guard keeps the reader off the port while the driver is enabled
+    guard.write_request_to_send(true)?;
+    tokio::time::sleep(rs485.before).await;
+    let written = guard.write_all(bytes).and_then(|_| guard.flush());
+    tokio::time::sleep(rs485.after).await;
+    guard.write_request_to_send(false)?;
+    written?;
+    Ok(())
+}
+
+/// Hold the line in a break condition for `duration` (bootloaders, SysRq)
+pub async fn send_break(
+    port: &Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    duration: Duration,
+) -> Result<()> {
+    port.lock().await.set_break()?;
+    tokio::time::sleep(duration).await;
+    port.lock().await.clear_break()?;
     Ok(())
 }
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::levels::Level;
+use crate::line_errors::LineErrorCounts;
+use crate::ui::UiMessage;
+
+const HEADER: &str =
+    "start,end,port,rx_bytes,rx_lines,errors,warnings,alerts,line_errors,disconnects\n";
+
+/// What happened during one interval
+#[derive(Default)]
+struct Counts {
+    rx_bytes: u64,
+    rx_lines: u64,
+    /// RX lines at error level and at warning level
+    errors: u64,
+    warnings: u64,
+    /// RX lines matching an `--alert` pattern
+    alerts: u64,
+    /// Framing, parity, overrun and break counts the driver reported
+    line_errors: u64,
+    disconnects: u64,
+}
+
+/// `--stats-log`: one CSV record per interval with how the session is
+/// doing, so a soak test running for days leaves a short health overview
+/// next to its full log. Tabs sharing the file tell their rows apart by
+/// the port column.
+pub struct StatsLog {
+    path: PathBuf,
+    pub interval: Duration,
+    start: DateTime<Local>,
+    counts: Counts,
+    /// Driver counters at the last update, to count what is new
+    line_errors: LineErrorCounts,
+}
+
+impl StatsLog {
+    pub fn new(path: &Path, interval: Duration) -> Self {
+        StatsLog {
+            path: path.to_path_buf(),
+            interval,
+            start: Local::now(),
+            counts: Counts::default(),
+            line_errors: LineErrorCounts::default(),
+        }
+    }
+
+    /// The same file for another port's tab
+    pub fn for_tab(&self) -> Self {
+        StatsLog::new(&self.path, self.interval)
+    }
+
+    pub fn on_bytes(&mut self, n: usize) {
+        self.counts.rx_bytes += n as u64;
+    }
+
+    pub fn on_line(&mut self, level: Option<Level>, alert: bool) {
+        self.counts.rx_lines += 1;
+        match level {
+            Some(Level::Error) => self.counts.errors += 1,
+            Some(Level::Warn) => self.counts.warnings += 1,
+            _ => {}
+        }
+        if alert {
+            self.counts.alerts += 1;
+        }
+    }
+
+    /// The driver's running totals; a reopened port starts them over
+    pub fn on_line_errors(&mut self, counts: LineErrorCounts) {
+        let total = |c: &LineErrorCounts| {
+            [c.framing, c.parity, c.overrun, c.buffer_overrun, c.breaks]
+                .iter()
+                .map(|&n| u64::from(n))
+                .sum::<u64>()
+        };
+        let (now, before) = (total(&counts), total(&self.line_errors));
+        self.counts.line_errors += now.checked_sub(before).unwrap_or(now);
+        self.line_errors = counts;
+    }
+
+    pub fn on_disconnect(&mut self) {
+        self.counts.disconnects += 1;
+    }
+
+    /// Append the record for the interval that just ended and start the
+    /// next one
+    pub fn write(&mut self, port_name: &str) -> std::io::Result<()> {
+        let end = Local::now();
+        let counts = std::mem::take(&mut self.counts);
+        let start = std::mem::replace(&mut self.start, end);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut record = String::new();
+        if file.metadata()?.len() == 0 {
+            record.push_str(HEADER);
+        }
+        let format = "%Y-%m-%d %H:%M:%S";
+        record.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            start.format(format),
+            end.format(format),
+            csv_field(port_name),
+            counts.rx_bytes,
+            counts.rx_lines,
+            counts.errors,
+            counts.warnings,
+            counts.alerts,
+            counts.line_errors,
+            counts.disconnects
+        ));
+        file.write_all(record.as_bytes())
+    }
+}
+
+/// `s` quoted when it holds a comma or quote
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Ask the UI for a record every `interval`, quiet intervals included
+pub async fn tick(interval: Duration, running: Arc<AtomicBool>, ui_tx: UnboundedSender<UiMessage>) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    while running.load(Ordering::SeqCst) {
+        ticker.tick().await;
+        if ui_tx.send(UiMessage::StatsDue).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serialport::{ClearBuffer, DataBits, Parity, SerialPort, StopBits};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+use crate::config::{DataBitsMode, ParityMode, StopBitsMode, framing};
+
+/// Baud rates tried when none are given, most common first
+pub const COMMON_BAUDS: [u32; 11] = [
+    115_200, 9600, 57_600, 38_400, 19_200, 230_400, 460_800, 921_600, 4800, 2400, 1200,
+];
+
+/// Share of printable bytes a reply needs to count as readable when there is
+/// no `--expect` pattern
+const MIN_PRINTABLE: f64 = 0.9;
+
+/// Time for the line to settle after changing settings
+const SETTLE: Duration = Duration::from_millis(30);
+
+/// What `sermonizer sweep` tries and what counts as an answer
+pub struct Sweep {
+    pub bauds: Vec<u32>,
+    pub data_bits: Vec<DataBitsMode>,
+    pub parities: Vec<ParityMode>,
+    pub stop_bits: Vec<StopBitsMode>,
+    /// Sent at every setting, line ending included
+    pub probe: Vec<u8>,
+    /// A reply must match this; without it any mostly printable reply counts
+    pub expect: Option<Regex>,
+    /// How long to listen for a reply
+    pub wait: Duration,
+    /// Stop at the first configuration that answers
+    pub first: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Framing {
+    baud: u32,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+}
+
+impl Framing {
+    /// `115200 8N1`
+    fn describe(&self) -> String {
+        format!(
+            "{} {}",
+            self.baud,
+            framing(self.data_bits, self.parity, self.stop_bits)
+        )
+    }
+
+    fn apply(&self, port: &mut dyn SerialPort) -> serialport::Result<()> {
+        port.set_baud_rate(self.baud)?;
+        port.set_data_bits(self.data_bits)?;
+        port.set_parity(self.parity)?;
+        port.set_stop_bits(self.stop_bits)
+    }
+}
+
+fn printable_share(bytes: &[u8]) -> f64 {
+    let printable = bytes
+        .iter()
+        .filter(|b| b.is_ascii_graphic() || matches!(b, b' ' | b'\r' | b'\n' | b'\t'))
+        .count();
+    printable as f64 / bytes.len() as f64
+}
+
+/// Reply text for the report, shortened and with control characters escaped
+fn preview(bytes: &[u8]) -> String {
+    const MAX: usize = 40;
+    let text: String = String::from_utf8_lossy(bytes)
+        .trim()
+        .chars()
+        .flat_map(char::escape_default)
+        .collect();
+    if text.chars().count() > MAX {
+        format!("{}...", text.chars().take(MAX).collect::<String>())
+    } else {
+        text
+    }
+}
+
+impl Sweep {
+    fn combinations(&self) -> Vec<Framing> {
+        let mut all = Vec::new();
+        for &baud in &self.bauds {
+            for &data_bits in &self.data_bits {
+                for &parity in &self.parities {
+                    for &stop_bits in &self.stop_bits {
+                        all.push(Framing {
+                            baud,
+                            data_bits: data_bits.into(),
+                            parity: parity.into(),
+                            stop_bits: stop_bits.into(),
+                        });
+                    }
+                }
+            }
+        }
+        all
+    }
+
+    /// Send the probe and collect what comes back within `wait`; returns
+    /// early once the reply matches `expect`
+    fn probe(&self, port: &mut dyn SerialPort) -> std::io::Result<Vec<u8>> {
+        std::thread::sleep(SETTLE);
+        let _ = port.clear(ClearBuffer::All);
+        port.write_all(&self.probe)?;
+        port.flush()?;
+
+        let started = Instant::now();
+        let mut reply = Vec::new();
+        let mut buf = [0u8; 256];
+        while started.elapsed() < self.wait {
+            match port.read(&mut buf) {
+                Ok(n) => reply.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+            if let Some(expect) = &self.expect
+                && expect.is_match(&String::from_utf8_lossy(&reply))
+            {
+                break;
+            }
+        }
+        Ok(reply)
+    }
+
+    fn is_answer(&self, reply: &[u8]) -> bool {
+        match &self.expect {
+            Some(expect) => expect.is_match(&String::from_utf8_lossy(reply)),
+            None => !reply.trim_ascii().is_empty() && printable_share(reply) >= MIN_PRINTABLE,
+        }
+    }
+}
+
+/// `sermonizer sweep`: try every combination of settings against the probe
+/// and report which ones got a valid reply. Returns whether any did.
+pub fn run_sweep(port_name: &str, sweep: &Sweep) -> Result<bool> {
+    if sweep.parities.iter().any(|p| p.is_stick()) {
+        bail!("The sweep doesn't try mark/space parity");
+    }
+    let combinations = sweep.combinations();
+    println!(
+        "Sweeping {port_name}: {} configuration(s), {} ms each",
+        combinations.len(),
+        sweep.wait.as_millis()
+    );
+    let Some(first) = combinations.first() else {
+        bail!("Nothing to sweep");
+    };
+    let mut port = serialport::new(port_name, first.baud)
+        .timeout(Duration::from_millis(20))
+        .open()
+        .with_context(|| format!("Failed to open serial port '{port_name}'"))?;
+
+    let mut answered = Vec::new();
+    for framing in combinations {
+        let name = framing.describe();
+        if let Err(e) = framing.apply(port.as_mut()) {
+            println!("  [SKIP] {name}: {e}");
+            continue;
+        }
+        let reply = sweep
+            .probe(port.as_mut())
+            .with_context(|| format!("Probe failed at {name}"))?;
+        if sweep.is_answer(&reply) {
+            println!("  [ OK ] {name}: \"{}\"", preview(&reply));
+            answered.push((name, printable_share(&reply)));
+            if sweep.first {
+                break;
+            }
+        } else if reply.is_empty() {
+            println!("  [ -- ] {name}: no reply");
+        } else {
+            println!(
+                "  [ -- ] {name}: {} byte(s), {:.0}% printable",
+                reply.len(),
+                printable_share(&reply) * 100.0
+            );
+        }
+    }
+
+    // Earlier (more common) settings win ties
+    let best = answered.iter().reduce(|best, candidate| {
+        if candidate.1 > best.1 {
+            candidate
+        } else {
+            best
+        }
+    });
+    match best {
+        Some((name, _)) => {
+            println!(
+                "Result: {} configuration(s) answered; best: {name}",
+                answered.len()
+            );
+            Ok(true)
+        }
+        None => {
+            println!("Result: no configuration got a valid reply");
+            Ok(false)
+        }
+    }
+}
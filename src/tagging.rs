@@ -0,0 +1,55 @@
+use regex::Regex;
+
+/// Tags every received line matching `pattern` with `tag`
+#[derive(Debug, Clone)]
+pub struct TagRule {
+    pub tag: String,
+    pub pattern: Regex,
+}
+
+/// Parse a `NAME=REGEX` rule (clap value parser)
+pub fn parse_tag_rule(s: &str) -> Result<TagRule, String> {
+    let (tag, pattern) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=REGEX, got '{s}'"))?;
+    if tag.is_empty() {
+        return Err("tag name must not be empty".to_string());
+    }
+    let pattern = Regex::new(pattern).map_err(|e| format!("invalid regex for tag '{tag}': {e}"))?;
+    Ok(TagRule {
+        tag: tag.to_string(),
+        pattern,
+    })
+}
+
+/// All tags whose rule matches `line`, in rule order, without duplicates
+pub fn tags_for(line: &str, rules: &[TagRule]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for rule in rules {
+        if rule.pattern.is_match(line) && !tags.contains(&rule.tag) {
+            tags.push(rule.tag.clone());
+        }
+    }
+    tags
+}
+
+/// Distinct tag names in rule order (used to cycle the display filter)
+pub fn tag_names(rules: &[TagRule]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for rule in rules {
+        if !names.contains(&rule.tag) {
+            names.push(rule.tag.clone());
+        }
+    }
+    names
+}
+
+/// Channel of a line out of a demultiplexing decoder (`[ch2] OK` -> `ch2`),
+/// after the timestamp if there is one
+pub fn channel_tag(line: &str) -> Option<&str> {
+    line.match_indices("[ch").find_map(|(start, _)| {
+        let (tag, _) = line[start + 1..].split_once("] ")?;
+        let number = &tag[2..];
+        (!number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())).then_some(tag)
+    })
+}
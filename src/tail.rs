@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::logging::LineDedup;
+use crate::replay::{TS_PREFIX_LEN, parse_timestamp};
+
+/// How long the file has to stay quiet before a line without its newline
+/// yet, or a pending "repeated" count, is shown anyway
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// `sermonizer tail FILE`: a log file standing in for the port. Reads
+/// give what is in the file, then what gets appended to it (`tail -f`),
+/// with `--log-ts` timestamps taken out and runs of identical lines shown
+/// once. There is nothing to write to.
+pub struct LogPort {
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    dedup: LineDedup,
+    /// Lines ready to hand out, and how far reads have got into them
+    pending: Vec<u8>,
+    pending_pos: usize,
+    last_data: Instant,
+    settled: bool,
+    timeout: Duration,
+    baud_rate: u32,
+    data_bits: DataBits,
+    flow_control: FlowControl,
+    parity: Parity,
+    stop_bits: StopBits,
+}
+
+impl LogPort {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open log {}", path.display()))?;
+        Ok(LogPort {
+            path: path.to_path_buf(),
+            file,
+            pos: 0,
+            dedup: LineDedup::default(),
+            pending: Vec::new(),
+            pending_pos: 0,
+            last_data: Instant::now(),
+            settled: true,
+            timeout: Duration::from_millis(100),
+            baud_rate: 115_200,
+            data_bits: DataBits::Eight,
+            flow_control: FlowControl::None,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+        })
+    }
+
+    /// Read what was appended since last time. A file that shrank was
+    /// truncated or rotated: start over from its beginning.
+    fn read_more(&mut self) -> io::Result<Vec<u8>> {
+        let len = std::fs::metadata(&self.path)?.len();
+        if len < self.pos {
+            self.file = File::open(&self.path)?;
+            self.pos = 0;
+        }
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        let mut data = Vec::new();
+        (&mut self.file).take(1 << 16).read_to_end(&mut data)?;
+        self.pos += data.len() as u64;
+        Ok(data)
+    }
+}
+
+/// `data` without the `[YYYY-MM-DD HH:MM:SS.fff] ` prefixes `--log-ts`
+/// puts before every chunk, wherever they landed
+fn strip_timestamps(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'[' && parse_timestamp(&data[i..]).is_some() {
+            i += TS_PREFIX_LEN;
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+impl Read for LogPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let started = Instant::now();
+        while self.pending_pos >= self.pending.len() {
+            let data = self.read_more()?;
+            let lines = if !data.is_empty() {
+                self.last_data = Instant::now();
+                self.settled = false;
+                self.dedup.feed(&strip_timestamps(&data))
+            } else if !self.settled && self.last_data.elapsed() >= SETTLE {
+                self.settled = true;
+                self.dedup.finish()
+            } else {
+                Vec::new()
+            };
+            self.pending = lines.concat();
+            self.pending_pos = 0;
+            if self.pending.is_empty() {
+                if started.elapsed() >= self.timeout {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+        let n = buf.len().min(self.pending.len() - self.pending_pos);
+        buf[..n].copy_from_slice(&self.pending[self.pending_pos..self.pending_pos + n]);
+        self.pending_pos += n;
+        Ok(n)
+    }
+}
+
+fn no_port() -> serialport::Error {
+    serialport::Error::new(
+        serialport::ErrorKind::Io(io::ErrorKind::Unsupported),
+        "a log file has no port to write to (tail)",
+    )
+}
+
+impl io::Write for LogPort {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(no_port().into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for LogPort {
+    fn name(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(self.baud_rate)
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(self.data_bits)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(self.flow_control)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(self.parity)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(self.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.baud_rate = baud_rate;
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.data_bits = data_bits;
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.flow_control = flow_control;
+        Ok(())
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.parity = parity;
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.stop_bits = stop_bits;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, _level: bool) -> serialport::Result<()> {
+        Err(no_port())
+    }
+
+    fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+        Err(no_port())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, _buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Err(no_port())
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Err(no_port())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Err(no_port())
+    }
+}
@@ -0,0 +1,312 @@
+use serialport::SerialPort;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, broadcast, mpsc};
+
+use crate::config::LineEnding;
+use crate::logging::{LogWriter, write_log_chunk};
+use crate::serial_io::write_bytes_async;
+use crate::tx_queue::JobHandle;
+use crate::ui::UiMessage;
+
+/// One step of an outgoing line with inline directives
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Bytes to transmit as-is
+    Send(String),
+    /// `{sleep 200ms}`
+    Sleep(Duration),
+    /// `{wait "OK" 2s}`: continue once an RX line contains the text
+    Wait { text: String, timeout: Duration },
+}
+
+/// `200ms`, `2s`, `1.5s`; a bare number is milliseconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, scale) = if let Some(n) = s.strip_suffix("ms") {
+        (n, 0.001)
+    } else if let Some(n) = s.strip_suffix('s') {
+        (n, 1.0)
+    } else {
+        (s, 0.001)
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("bad duration '{s}'"))?;
+    if !value.is_finite() {
+        return Err(format!("bad duration '{s}'"));
+    }
+    if value < 0.0 {
+        return Err(format!("negative duration '{s}'"));
+    }
+    Duration::try_from_secs_f64(value * scale).map_err(|_| format!("duration '{s}' is too long"))
+}
+
+fn parse_directive(body: &str) -> Result<Step, String> {
+    if let Some(arg) = body.strip_prefix("sleep ") {
+        return Ok(Step::Sleep(parse_duration(arg)?));
+    }
+    let arg = body
+        .strip_prefix("wait ")
+        .ok_or_else(|| format!("unknown directive '{{{body}}}'"))?
+        .trim();
+    let rest = arg
+        .strip_prefix('"')
+        .ok_or_else(|| "wait needs a quoted text, e.g. {wait \"OK\" 2s}".to_string())?;
+    let (text, timeout) = rest
+        .split_once('"')
+        .ok_or_else(|| "unterminated quote in {wait ...}".to_string())?;
+    let timeout = match timeout.trim() {
+        "" => Duration::from_secs(5),
+        t => parse_duration(t)?,
+    };
+    Ok(Step::Wait {
+        text: text.to_string(),
+        timeout,
+    })
+}
+
+/// Split a line into steps. Only `{sleep ...}` and `{wait ...}` are
+/// directives; any other brace is sent literally. Returns `None` when the
+/// line has no directives.
+pub fn parse_template(input: &str) -> Result<Option<Vec<Step>>, String> {
+    let mut steps = Vec::new();
+    let mut literal = String::new();
+    let mut rest = input;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let is_directive = after.starts_with("sleep ") || after.starts_with("wait ");
+        let end = after.find('}').filter(|_| is_directive);
+        let Some(end) = end else {
+            literal.push_str(&rest[..=start]);
+            rest = after;
+            continue;
+        };
+        literal.push_str(&rest[..start]);
+        if !literal.is_empty() {
+            steps.push(Step::Send(std::mem::take(&mut literal)));
+        }
+        steps.push(parse_directive(&after[..end])?);
+        rest = &after[end + 1..];
+    }
+    literal.push_str(rest);
+
+    if steps.is_empty() {
+        return Ok(None);
+    }
+    if !literal.is_empty() {
+        steps.push(Step::Send(literal));
+    }
+    Ok(Some(steps))
+}
+
+/// End a line of steps with `line_ending`. It joins the text sent last, so
+/// both go out in one write and a `--tx-pipe` filter sees the line whole;
+/// after a directive it is a send of its own.
+pub fn push_line_ending(steps: &mut Vec<Step>, line_ending: LineEnding) {
+    let end = String::from_utf8_lossy(line_ending.bytes()).into_owned();
+    if end.is_empty() {
+        return;
+    }
+    match steps.last_mut() {
+        Some(Step::Send(text)) => text.push_str(&end),
+        _ => steps.push(Step::Send(end)),
+    }
+}
+
+/// Steps for a list of commands sent one after another, each followed by
+/// `line_ending` (commands may contain directives themselves)
+pub fn command_sequence(commands: &[String], line_ending: LineEnding) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for command in commands {
+        let mut command_steps = parse_template(command)
+            .map_err(|e| format!("'{command}': {e}"))?
+            .unwrap_or_else(|| vec![Step::Send(command.clone())]);
+        push_line_ending(&mut command_steps, line_ending);
+        steps.extend(command_steps);
+    }
+    Ok(steps)
+}
+
+/// Run the steps in the background. Waits watch `rx_lines`; a timeout or
+/// cancelling the job (Ctrl+X) aborts the rest, pausing it (Alt+X) holds
+/// the next step back.
+pub async fn run_template(
+    steps: Vec<Step>,
+    job: JobHandle,
+    port: Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    mut rx_lines: broadcast::Receiver<String>,
+    tx_log: Option<LogWriter>,
+    log_ts: bool,
+    ui_tx: mpsc::UnboundedSender<UiMessage>,
+) {
+    let send = async |bytes: &[u8]| -> bool {
+        if bytes.is_empty() {
+            return true;
+        }
+        if let Err(e) = write_bytes_async(&port, bytes).await {
+            let _ = ui_tx.send(UiMessage::SequenceDone(Err(e.to_string())));
+            return false;
+        }
+        job.sent(bytes.len());
+        if let Some(w) = &tx_log {
+            write_log_chunk(w, bytes, log_ts);
+        }
+        true
+    };
+
+    for step in steps {
+        job.resumed().await;
+        if job.is_cancelled() {
+            let _ = ui_tx.send(UiMessage::SequenceDone(Err("cancelled".to_string())));
+            return;
+        }
+        match step {
+            Step::Send(text) => {
+                // Only lines received after this send can satisfy a wait
+                while rx_lines.try_recv().is_ok() {}
+                if !send(text.as_bytes()).await {
+                    return;
+                }
+            }
+            Step::Sleep(d) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(d) => {}
+                    _ = job.cancelled() => continue,
+                }
+            }
+            Step::Wait { text, timeout } => {
+                let found = tokio::time::timeout(timeout, async {
+                    loop {
+                        tokio::select! {
+                            line = rx_lines.recv() => match line {
+                                Ok(line) if line.contains(&text) => return true,
+                                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                                Err(broadcast::error::RecvError::Closed) => return false,
+                            },
+                            _ = job.cancelled() => return false,
+                        }
+                    }
+                })
+                .await;
+                if job.is_cancelled() {
+                    continue;
+                }
+                if found != Ok(true) {
+                    let _ = ui_tx.send(UiMessage::SequenceDone(Err(format!(
+                        "'{text}' not received within {:.1} s",
+                        timeout.as_secs_f64()
+                    ))));
+                    return;
+                }
+            }
+        }
+        job.advance();
+    }
+    let result = if job.is_cancelled() {
+        Err("cancelled".to_string())
+    } else {
+        Ok(())
+    };
+    let _ = ui_tx.send(UiMessage::SequenceDone(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("200ms"), Ok(Duration::from_millis(200)));
+        assert_eq!(parse_duration(" 1.5s "), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn bad_durations_are_errors() {
+        for s in [
+            "", "s", "abc", "-1s", "-0.5ms", "inf", "infs", "-inf", "NaN", "nanms", "1e300s",
+            "1e30",
+        ] {
+            assert!(parse_duration(s).is_err(), "{s:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn plain_lines_have_no_steps() {
+        assert_eq!(parse_template("AT+CGMI"), Ok(None));
+        assert_eq!(parse_template("{\"json\": 1}"), Ok(None));
+        assert_eq!(parse_template("{sleep"), Ok(None));
+    }
+
+    #[test]
+    fn directives_split_the_line() {
+        assert_eq!(
+            parse_template("AT{sleep 200ms}ATI{wait \"OK\" 2s}done"),
+            Ok(Some(vec![
+                Step::Send("AT".into()),
+                Step::Sleep(Duration::from_millis(200)),
+                Step::Send("ATI".into()),
+                Step::Wait {
+                    text: "OK".into(),
+                    timeout: Duration::from_secs(2)
+                },
+                Step::Send("done".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn literal_braces_around_directives_are_kept() {
+        assert_eq!(
+            parse_template("{a}{sleep 1}{b"),
+            Ok(Some(vec![
+                Step::Send("{a}".into()),
+                Step::Sleep(Duration::from_millis(1)),
+                Step::Send("{b".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn wait_defaults_to_five_seconds() {
+        assert_eq!(
+            parse_template("{wait \"> \"}"),
+            Ok(Some(vec![Step::Wait {
+                text: "> ".into(),
+                timeout: Duration::from_secs(5)
+            }]))
+        );
+    }
+
+    #[test]
+    fn line_ending_joins_the_last_send() {
+        let commands = ["AT".to_string(), "ATI{wait \"OK\"}".to_string()];
+        assert_eq!(
+            command_sequence(&commands, LineEnding::Crlf),
+            Ok(vec![
+                Step::Send("AT\r\n".into()),
+                Step::Send("ATI".into()),
+                Step::Wait {
+                    text: "OK".into(),
+                    timeout: Duration::from_secs(5)
+                },
+                Step::Send("\r\n".into()),
+            ])
+        );
+        let mut steps = vec![Step::Send("x".into())];
+        push_line_ending(&mut steps, LineEnding::None);
+        assert_eq!(steps, [Step::Send("x".into())]);
+    }
+
+    #[test]
+    fn bad_directives_are_errors() {
+        assert!(parse_template("{sleep inf}").is_err());
+        assert!(parse_template("{sleep soon}").is_err());
+        assert!(parse_template("{wait OK}").is_err());
+        assert!(parse_template("{wait \"OK}").is_err());
+        assert!(parse_template("{wait \"OK\" nan}").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// One line of an expected transcript
+#[derive(Debug, Clone)]
+pub enum ExpectLine {
+    /// Must match exactly, except that `*` matches any run of characters
+    Pattern(String),
+    /// `...` on its own line: any number of lines until the next pattern
+    Skip,
+}
+
+/// Load an expected transcript; trailing whitespace is ignored and blank
+/// lines are dropped, like on the received side
+pub fn load_transcript(path: &Path) -> Result<Vec<ExpectLine>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read transcript: {}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            if l == "..." {
+                ExpectLine::Skip
+            } else {
+                ExpectLine::Pattern(l.to_string())
+            }
+        })
+        .collect())
+}
+
+/// Glob match where `*` is the only wildcard
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all: exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Outcome of checking one received line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Match,
+    /// Didn't match; carries the expected pattern
+    Mismatch(String),
+    /// Transcript already fully consumed
+    Beyond,
+}
+
+/// Walks the expected transcript as lines arrive
+pub struct TranscriptChecker {
+    expected: Vec<ExpectLine>,
+    next: usize,
+    pub matched: usize,
+    pub mismatched: usize,
+}
+
+impl TranscriptChecker {
+    pub fn new(expected: Vec<ExpectLine>) -> Self {
+        Self {
+            expected,
+            next: 0,
+            matched: 0,
+            mismatched: 0,
+        }
+    }
+
+    /// Whether every expected line has been consumed (a trailing `...`
+    /// counts as consumed)
+    pub fn is_complete(&self) -> bool {
+        self.expected[self.next..]
+            .iter()
+            .all(|l| matches!(l, ExpectLine::Skip))
+    }
+
+    pub fn check(&mut self, line: &str) -> Verdict {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Verdict::Match;
+        }
+        loop {
+            let Some(expected) = self.expected.get(self.next) else {
+                return Verdict::Beyond;
+            };
+            match expected {
+                ExpectLine::Skip => {
+                    // Leave the skip once the following pattern matches
+                    match self.expected.get(self.next + 1) {
+                        Some(ExpectLine::Pattern(p)) if glob_match(p, line) => {
+                            self.next += 2;
+                            self.matched += 1;
+                        }
+                        Some(ExpectLine::Skip) => {
+                            self.next += 1;
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    return Verdict::Match;
+                }
+                ExpectLine::Pattern(p) => {
+                    self.next += 1;
+                    if glob_match(p, line) {
+                        self.matched += 1;
+                        return Verdict::Match;
+                    }
+                    self.mismatched += 1;
+                    return Verdict::Mismatch(p.clone());
+                }
+            }
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let pattern_count = self
+            .expected
+            .iter()
+            .filter(|l| matches!(l, ExpectLine::Pattern(_)))
+            .count();
+        let verdict = if self.mismatched > 0 {
+            "FAIL"
+        } else if self.is_complete() {
+            "PASS"
+        } else {
+            "INCOMPLETE"
+        };
+        format!(
+            "Transcript {verdict}: {}/{pattern_count} matched, {} mismatch(es)",
+            self.matched, self.mismatched
+        )
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+use crate::ui::UiMessage;
+
+/// `--tx-fifo`: create (if needed) a named pipe at `path` and transmit every
+/// line other tools write to it. Reopens after each writer closes.
+#[cfg(unix)]
+pub fn spawn_tx_fifo(path: PathBuf, ui_tx: mpsc::UnboundedSender<UiMessage>) -> Result<()> {
+    use anyhow::{Context, bail};
+    use std::ffi::CString;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::metadata(&path) {
+        Ok(meta) if meta.file_type().is_fifo() => {}
+        Ok(_) => bail!("{} exists and is not a named pipe", path.display()),
+        Err(_) => {
+            let c_path = CString::new(path.as_os_str().as_bytes())
+                .context("FIFO path contains a NUL byte")?;
+            // SAFETY: `c_path` is a valid NUL-terminated string
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(std::io::Error::last_os_error())
+                    .with_context(|| format!("Failed to create FIFO {}", path.display()));
+            }
+        }
+    }
+
+    // Plain thread: opening a FIFO blocks until a writer shows up
+    std::thread::spawn(move || {
+        loop {
+            let file = match std::fs::File::open(&path) {
+                Ok(f) => f,
+                Err(e) => {
+                    let _ = ui_tx.send(UiMessage::Status(format!("TX FIFO closed: {e}")));
+                    return;
+                }
+            };
+            for line in BufReader::new(file).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let message = UiMessage::SendLine {
+                    source: "fifo",
+                    line: line.trim_end_matches('\r').to_string(),
+                };
+                if ui_tx.send(message).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn_tx_fifo(_path: PathBuf, _ui_tx: mpsc::UnboundedSender<UiMessage>) -> Result<()> {
+    anyhow::bail!("--tx-fifo needs a Unix named pipe")
+}
@@ -0,0 +1,170 @@
+use serialport::SerialPort;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use crate::rx_pipe::shell;
+use crate::serial_io::write_port;
+
+type Port = tokio::sync::Mutex<Box<dyn SerialPort + Send>>;
+
+static TX_PIPE: OnceLock<String> = OnceLock::new();
+
+/// The running filter of each port (tabs get their own)
+static FILTERS: Mutex<Vec<Filter>> = Mutex::new(Vec::new());
+
+/// The filter failed and nothing was sent. Unlike a port write error this
+/// is worth a status line, not the end of the session.
+#[derive(Debug)]
+pub struct Failed(String);
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Failed {}
+
+/// `--tx-pipe`: every later `write_bytes_async` payload goes through `cmd`
+/// first
+pub fn enable(cmd: String) {
+    let _ = TX_PIPE.set(cmd);
+}
+
+pub fn command() -> Option<&'static str> {
+    TX_PIPE.get().map(String::as_str)
+}
+
+/// One run of the command for one port: payloads go to its stdin from a
+/// thread, whatever it prints is written to the port as it comes
+struct Filter {
+    port: Weak<Port>,
+    child: Child,
+    stdin: mpsc::Sender<Vec<u8>>,
+    /// Last line the command printed on stderr
+    last_error: Arc<Mutex<String>>,
+}
+
+impl Filter {
+    fn start(cmd: &str, port: &Arc<Port>) -> Result<Self, Failed> {
+        let mut child = shell(cmd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Failed(format!("Failed to run TX pipe '{cmd}': {e}")))?;
+        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        if let Some(mut stdin) = child.stdin.take() {
+            std::thread::spawn(move || {
+                for bytes in rx {
+                    if stdin
+                        .write_all(&bytes)
+                        .and_then(|()| stdin.flush())
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            });
+        }
+        if let Some(stdout) = child.stdout.take() {
+            let port = Arc::downgrade(port);
+            let runtime = tokio::runtime::Handle::current();
+            std::thread::spawn(move || forward(stdout, port, runtime));
+        }
+        let last_error = Arc::new(Mutex::new(String::new()));
+        if let Some(stderr) = child.stderr.take() {
+            let last_error = last_error.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if let Ok(mut last) = last_error.lock() {
+                        *last = line;
+                    }
+                }
+            });
+        }
+        Ok(Filter {
+            port: Arc::downgrade(port),
+            child,
+            stdin: tx,
+            last_error,
+        })
+    }
+
+    /// Why the command is no longer running, `None` while it is
+    fn exited(&mut self, cmd: &str) -> Option<Failed> {
+        let status = match self.child.try_wait() {
+            Ok(None) => return None,
+            Ok(Some(status)) => status.to_string(),
+            Err(e) => e.to_string(),
+        };
+        let detail = self
+            .last_error
+            .lock()
+            .ok()
+            .filter(|last| !last.is_empty())
+            .map(|last| format!(": {last}"))
+            .unwrap_or_default();
+        Some(Failed(format!(
+            "TX pipe '{cmd}' exited ({status}), nothing sent{detail}; it starts again with the next send"
+        )))
+    }
+}
+
+/// Write what the command prints to the port until it closes its stdout
+/// or the port is gone
+fn forward(mut stdout: impl Read, port: Weak<Port>, runtime: tokio::runtime::Handle) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = match stdout.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        let Some(port) = port.upgrade() else {
+            return;
+        };
+        // A failed write means the port went away; the reader reports that
+        let _ = runtime.block_on(write_port(&port, &buf[..n]));
+    }
+}
+
+/// Hand `bytes` to the filter of `port`, starting it when needed. The
+/// command runs for the whole session, so it sees one continuous stream
+/// and may answer a payload whenever it is ready (a line-based framer, a
+/// CRC appender...). A command that has exited is reported once and
+/// started again on the following send.
+pub fn feed(cmd: &str, port: &Arc<Port>, bytes: &[u8]) -> Result<(), Failed> {
+    let mut filters = FILTERS
+        .lock()
+        .map_err(|_| Failed("TX pipe state poisoned".to_string()))?;
+    filters.retain(|filter| filter.port.strong_count() > 0);
+    let own = Arc::downgrade(port);
+    let index = match filters
+        .iter()
+        .position(|filter| Weak::ptr_eq(&filter.port, &own))
+    {
+        Some(index) => {
+            if let Some(failed) = filters[index].exited(cmd) {
+                filters.remove(index);
+                return Err(failed);
+            }
+            index
+        }
+        None => {
+            filters.push(Filter::start(cmd, port)?);
+            filters.len() - 1
+        }
+    };
+    if filters[index].stdin.send(bytes.to_vec()).is_err() {
+        let mut filter = filters.remove(index);
+        let _ = filter.child.kill();
+        let _ = filter.child.wait();
+        return Err(Failed(format!(
+            "TX pipe '{cmd}' stopped reading, nothing sent; it starts again with the next send"
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often a waiting job looks at its cancel and pause flags
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// Time spent paused, so rates and ETAs only count the time sending
+#[derive(Default)]
+struct PauseClock {
+    since: Option<Instant>,
+    total: Duration,
+}
+
+/// A background transmission (sequence, replay) as shown in the task
+/// widget. Senders call `sent` per write and `advance` per step, wait in
+/// `resumed` before each step and stop once cancelled.
+pub struct TxJob {
+    pub label: String,
+    total: usize,
+    done: AtomicUsize,
+    bytes: AtomicU64,
+    started: Instant,
+    paused: Mutex<PauseClock>,
+    cancelled: AtomicBool,
+}
+
+impl TxJob {
+    pub fn advance(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `n` more bytes written to the port
+    pub fn sent(&self, n: usize) {
+        self.bytes.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// (steps done, steps in total)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done.load(Ordering::Relaxed), self.total)
+    }
+
+    /// Time since the start, minus the time spent paused
+    fn active_time(&self) -> Duration {
+        let paused = self.paused.lock().map_or(Duration::ZERO, |p| {
+            p.total + p.since.map_or(Duration::ZERO, |t| t.elapsed())
+        });
+        self.started.elapsed().saturating_sub(paused)
+    }
+
+    /// Bytes per second written so far
+    pub fn throughput(&self) -> f64 {
+        let secs = self.active_time().as_secs_f64();
+        if secs < 0.1 {
+            return 0.0;
+        }
+        self.bytes.load(Ordering::Relaxed) as f64 / secs
+    }
+
+    /// Time left at the pace of the steps so far
+    pub fn eta(&self) -> Option<Duration> {
+        let (done, total) = self.progress();
+        if done == 0 {
+            return None;
+        }
+        Some(
+            self.active_time()
+                .mul_f64((total.saturating_sub(done)) as f64 / done as f64),
+        )
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.lock().is_ok_and(|p| p.since.is_some())
+    }
+
+    fn set_paused(&self, paused: bool) {
+        let Ok(mut clock) = self.paused.lock() else {
+            return;
+        };
+        match (clock.since, paused) {
+            (None, true) => clock.since = Some(Instant::now()),
+            (Some(since), false) => {
+                clock.total += since.elapsed();
+                clock.since = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Resolves once the job isn't paused (or is cancelled); senders wait
+    /// here before each step
+    pub async fn resumed(&self) {
+        while self.is_paused() && !self.is_cancelled() {
+            tokio::time::sleep(CANCEL_POLL).await;
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the job is cancelled (for `select!` around sleeps and
+    /// waits)
+    pub async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            tokio::time::sleep(CANCEL_POLL).await;
+        }
+    }
+}
+
+/// Pending background TX, shared by the senders and the UI (Ctrl+X)
+#[derive(Clone, Default)]
+pub struct TxQueue {
+    jobs: Arc<Mutex<Vec<Arc<TxJob>>>>,
+}
+
+impl TxQueue {
+    /// Register a job; it leaves the queue when the handle is dropped
+    pub fn start(&self, label: impl Into<String>, total: usize) -> JobHandle {
+        let job = Arc::new(TxJob {
+            label: label.into(),
+            total,
+            done: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            started: Instant::now(),
+            paused: Mutex::new(PauseClock::default()),
+            cancelled: AtomicBool::new(false),
+        });
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.push(job.clone());
+        }
+        JobHandle {
+            queue: self.clone(),
+            job,
+        }
+    }
+
+    pub fn jobs(&self) -> Vec<Arc<TxJob>> {
+        self.jobs.lock().map(|j| j.clone()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.lock().map_or(true, |j| j.is_empty())
+    }
+
+    /// Cancel every queued job; returns how many there were
+    pub fn cancel_all(&self) -> usize {
+        let jobs = self.jobs();
+        for job in &jobs {
+            job.cancelled.store(true, Ordering::SeqCst);
+        }
+        jobs.len()
+    }
+
+    /// Pause every job, or resume them all when all are paused already;
+    /// `None` when nothing is queued, else whether they are paused now
+    pub fn toggle_pause(&self) -> Option<bool> {
+        let jobs = self.jobs();
+        if jobs.is_empty() {
+            return None;
+        }
+        let pause = !jobs.iter().all(|job| job.is_paused());
+        for job in &jobs {
+            job.set_paused(pause);
+        }
+        Some(pause)
+    }
+}
+
+/// A running job; dropping it removes the job from the queue
+pub struct JobHandle {
+    queue: TxQueue,
+    job: Arc<TxJob>,
+}
+
+impl std::ops::Deref for JobHandle {
+    type Target = TxJob;
+
+    fn deref(&self) -> &TxJob {
+        &self.job
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = self.queue.jobs.lock() {
+            jobs.retain(|j| !Arc::ptr_eq(j, &self.job));
+        }
+    }
+}
@@ -1,14 +1,225 @@
+#[cfg(feature = "web")]
+use super::headless::event_json;
+use super::headless::{Event, EventLog};
+use super::macros::MacroRecorder;
+use super::pins::PinnedLines;
+use super::popup::Popup;
+use super::port_switcher::PortSwitcher;
+use super::search::LastSearch;
+use super::settings::SettingsPanel;
+use super::stopwatch::Stopwatch;
+use super::vt100::TerminalScreen;
+use crate::alerts::{AlertEvent, Alerts};
+use crate::auto_login::{AutoLogin, LoginAction};
+use crate::blocks::BlockTracker;
+use crate::boot_capture::BootCapture;
+use crate::boot_profiler::BootProfiler;
+use crate::config::{EchoMode, RxLineEnding, UiConfig};
+use crate::latency::LatencyTracker;
+use crate::levels::Level;
+use crate::line_editor::{LineEditorDetector, render_edit_line};
+use crate::line_errors::LineErrorCounts;
+use crate::line_rate::LineRateCheck;
+#[cfg(feature = "wasm")]
+use crate::plugins::PluginInstance;
+use crate::stats_log::StatsLog;
+use crate::tagging::{channel_tag, tags_for};
+use crate::transcript::{TranscriptChecker, Verdict};
+#[cfg(feature = "wasm")]
+use crate::ui::UiMessage;
+#[cfg(feature = "web")]
+use crate::web::WebFeed;
+use chrono::{DateTime, Local};
 use ratatui::widgets::ListState;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many raw RX bytes are kept for byte pattern search
+const RAW_RX_RETAIN: usize = 1024 * 1024;
+
+/// Lines folded into one entry at most; the next matching line starts a
+/// new entry, so the output cap (which counts entries) still bounds memory
+const MAX_FOLDED: usize = 100;
+
+/// How long after sending a line an identical RX line counts as its echo
+const ECHO_WINDOW: Duration = Duration::from_secs(2);
+
+/// One line of the output pane plus what we learned about it at ingest
+pub struct OutputEntry {
+    pub text: String,
+    pub tags: Vec<String>,
+    pub level: Option<Level>,
+    /// Expected transcript line this one failed to match
+    pub expected: Option<String>,
+    /// The device echoing a line we just sent
+    pub echo: bool,
+    /// Following lines folded into this one by `--fold` rules
+    pub folded: Vec<String>,
+    /// Folded lines are shown (Ctrl+O)
+    pub expanded: bool,
+    /// Tab and arrival time of a line in the merged view, shown in the
+    /// tab's color before the text
+    pub source: Option<(usize, String)>,
+}
+
+/// What the output pane currently hides
+#[derive(Default, Clone)]
+pub struct DisplayFilter {
+    /// Only show lines carrying this tag
+    pub tag: Option<String>,
+    /// Hide lines below this level (lines without a level always show)
+    pub min_level: Option<Level>,
+    /// Hide device echoes (`--echo-mode fold`)
+    pub hide_echo: bool,
+}
+
+impl DisplayFilter {
+    pub fn is_active(&self) -> bool {
+        self.tag.is_some() || self.min_level.is_some()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(tag) = &self.tag {
+            parts.push(format!("tag: {tag}"));
+        }
+        if let Some(level) = self.min_level {
+            parts.push(format!("level >= {}", level.name()));
+        }
+        parts.join(", ")
+    }
+}
+
+impl OutputEntry {
+    /// A line generated by sermonizer itself rather than received
+    pub fn local(text: String) -> Self {
+        Self {
+            text,
+            tags: Vec::new(),
+            level: None,
+            expected: None,
+            echo: false,
+            folded: Vec::new(),
+            expanded: false,
+            source: None,
+        }
+    }
+
+    /// An RX line from tab `tab` for the merged view
+    pub fn merged(tab: usize, port_label: &str, text: String, level: Option<Level>) -> Self {
+        let time = Local::now().format("%H:%M:%S%.3f");
+        Self {
+            level,
+            source: Some((tab, format!("{time} {port_label}"))),
+            ..Self::local(text)
+        }
+    }
+
+    /// The line itself followed by the lines folded into it
+    pub fn all_lines(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.text.as_str()).chain(self.folded.iter().map(String::as_str))
+    }
+
+    /// Whether the entry passes the display filter
+    pub fn is_shown(&self, filter: &DisplayFilter) -> bool {
+        if filter.hide_echo && self.echo {
+            return false;
+        }
+        if let Some(tag) = &filter.tag
+            && !self.tags.contains(tag)
+        {
+            return false;
+        }
+        match (filter.min_level, self.level) {
+            (Some(min), Some(level)) => level >= min,
+            _ => true,
+        }
+    }
+}
 
 pub struct AppState {
     pub input_line: String,
-    pub output_lines: Vec<String>,
+    pub output_lines: Vec<OutputEntry>,
     pub partial_line: String,
     pub list_state: ListState,
     pub auto_scroll_state: ListState,
     pub should_quit: bool,
+    pub started_at: DateTime<Local>,
+    /// Quit was requested while busy; the next quit key confirms it
+    pub quit_pending: bool,
+    /// Background `{sleep}`/`{wait}` sequences still running
+    pub sequences_running: usize,
     pub auto_scroll: bool,
     pub needs_render: bool, // Optimization: only render when needed
+    pub status: Option<String>,
+    pub settings_panel: Option<SettingsPanel>,
+    /// Alt+P port list
+    pub port_switcher: Option<PortSwitcher>,
+    /// F1 reference (ASCII table, decoder frame layout)
+    pub popup: Option<Popup>,
+    /// Value of the last `:hex`/`:crc16`... helper, for Ctrl+Y
+    pub calc_result: Option<String>,
+    /// Effective configuration popup (F10) is open
+    pub config_view: bool,
+    pub filter: DisplayFilter,
+    /// Most recent raw RX bytes and the stream offset of the first one
+    pub raw_rx: VecDeque<u8>,
+    pub raw_base_offset: u64,
+    /// Search prompt text while the prompt is open
+    pub search_input: Option<String>,
+    pub last_search: Option<LastSearch>,
+    pub transcript: Option<TranscriptChecker>,
+    transcript_reported: bool,
+    pub boot_profiler: BootProfiler,
+    /// `--capture-boot`, until the boot is done
+    pub boot_capture: Option<BootCapture>,
+    /// `--stats-log` counts for the current interval
+    pub stats_log: Option<StatsLog>,
+    pub latency: Option<LatencyTracker>,
+    pub line_rate: LineRateCheck,
+    pub auto_login: Option<AutoLogin>,
+    pub alerts: Alerts,
+    /// `on_line` WebAssembly plugins
+    #[cfg(feature = "wasm")]
+    pub plugin_actions: Vec<PluginInstance>,
+    /// Ctrl+K / Ctrl+P keyboard macro
+    pub macros: MacroRecorder,
+    /// Lines shown above the output regardless of scrolling
+    pub pinned: PinnedLines,
+    /// What the `--rx-pipe` command printed, newest last
+    pub pipe_lines: VecDeque<String>,
+    /// New entries start with their folded lines shown
+    pub expand_folds: bool,
+    /// `--block` start/end matching and the blocks captured so far
+    pub blocks: BlockTracker,
+    /// RX run through the VT100 emulator, for full-screen device UIs
+    pub screen: TerminalScreen,
+    /// The output pane shows `screen` instead of lines (Alt+V)
+    pub terminal_view: bool,
+    /// Alt+S / Alt+L / Alt+0 timing
+    pub stopwatch: Stopwatch,
+    /// Keys go straight to the device's own line editor (F12)
+    pub passthrough: bool,
+    /// Ctrl+] was pressed in passthrough: the next key is handled here
+    pub passthrough_escape: bool,
+    /// Looking for a device-side line editor (`--passthrough auto`)
+    pub line_editor: Option<LineEditorDetector>,
+    /// Set by `add_output` when a login prompt needs answering
+    pub login_action: Option<LoginAction>,
+    /// Framing/parity/overrun errors since the port was opened
+    pub line_errors: LineErrorCounts,
+    /// The port went away and `--reconnect` is waiting for it
+    pub disconnected: bool,
+    /// Multiplexer channels seen so far (`[ch2] ...` lines), for F4
+    pub channels: Vec<String>,
+    skip_leading_lf: bool,
+    /// Recently sent lines that may come back as an echo
+    pending_echo: VecDeque<(String, Instant)>,
+    /// Events not yet written out (`--output json` only)
+    pub events: Option<EventLog>,
+    /// Live feed for the `--web` view
+    #[cfg(feature = "web")]
+    pub web: Option<WebFeed>,
 }
 
 impl AppState {
@@ -20,57 +231,408 @@ impl AppState {
             list_state: ListState::default(),
             auto_scroll_state: ListState::default(),
             should_quit: false,
+            started_at: Local::now(),
+            quit_pending: false,
+            sequences_running: 0,
             auto_scroll: true,
             needs_render: true,
+            status: None,
+            settings_panel: None,
+            port_switcher: None,
+            popup: None,
+            calc_result: None,
+            config_view: false,
+            filter: DisplayFilter::default(),
+            raw_rx: VecDeque::new(),
+            raw_base_offset: 0,
+            search_input: None,
+            last_search: None,
+            transcript: None,
+            transcript_reported: false,
+            boot_profiler: BootProfiler::new(Vec::new()),
+            boot_capture: None,
+            stats_log: None,
+            latency: None,
+            line_rate: LineRateCheck::default(),
+            auto_login: None,
+            alerts: Alerts::default(),
+            #[cfg(feature = "wasm")]
+            plugin_actions: Vec::new(),
+            macros: MacroRecorder::default(),
+            pinned: PinnedLines::default(),
+            pipe_lines: VecDeque::new(),
+            expand_folds: false,
+            blocks: BlockTracker::default(),
+            screen: TerminalScreen::default(),
+            terminal_view: false,
+            stopwatch: Stopwatch::default(),
+            passthrough: false,
+            passthrough_escape: false,
+            line_editor: None,
+            login_action: None,
+            line_errors: LineErrorCounts::default(),
+            disconnected: false,
+            channels: Vec::new(),
+            skip_leading_lf: false,
+            pending_echo: VecDeque::new(),
+            events: None,
+            #[cfg(feature = "web")]
+            web: None,
         }
     }
 
-    pub fn add_output(&mut self, data: String) {
+    pub fn add_output(&mut self, data: String, ui_config: &UiConfig) {
+        // A CR that ended the previous chunk already closed its line in
+        // `Any` mode, so a LF right after it must not produce an empty line
+        let data = if self.skip_leading_lf {
+            self.skip_leading_lf = false;
+            data.strip_prefix('\n').unwrap_or(&data).to_string()
+        } else {
+            data
+        };
+
+        if let Some(detector) = self.line_editor.as_mut() {
+            detector.on_text(&data);
+        }
+
         // Append to partial line buffer
         self.partial_line.push_str(&data);
 
-        // Check if we have complete lines (ending with \n or \r\n)
+        // Check if we have complete lines
         let mut has_new_lines = false;
-        while let Some(newline_pos) = self.partial_line.find('\n') {
-            // Extract complete line (without the newline)
-            let complete_line = self.partial_line[..newline_pos]
-                .trim_end_matches('\r')
-                .to_string();
-            self.output_lines.push(complete_line);
+        while let Some(mut complete_line) = self.take_line(ui_config.rx_line_ending) {
+            if let Some(detector) = self.line_editor.as_mut() {
+                detector.on_line(&complete_line);
+            }
+            if self.passthrough {
+                // Drop the editor's redraw sequences, keep what it showed
+                complete_line = render_edit_line(&complete_line).0;
+            }
+            if ui_config.rx_lines.receiver_count() > 0 {
+                let _ = ui_config.rx_lines.send(complete_line.clone());
+            }
+            let mut tags = tags_for(&complete_line, &ui_config.tag_rules);
+            if let Some(channel) = channel_tag(&complete_line) {
+                let channel = channel.to_string();
+                if !self.channels.contains(&channel) {
+                    self.channels.push(channel.clone());
+                }
+                if !tags.contains(&channel) {
+                    tags.push(channel);
+                }
+            }
+            let (block, captured) = self.blocks.on_line(&complete_line);
+            let captured = captured.map(|b| (b.name.clone(), b.lines.len()));
+            if let Some(name) = block
+                && !tags.contains(&name)
+            {
+                tags.push(name);
+            }
+            let level = ui_config.level_parser.parse(&complete_line);
+            let expected = match self.transcript.as_mut().map(|t| t.check(&complete_line)) {
+                Some(Verdict::Mismatch(pattern)) => Some(pattern),
+                _ => None,
+            };
+            if let Some(auto_login) = self.auto_login.as_mut() {
+                if let Some(action) = auto_login.on_text(&complete_line) {
+                    self.login_action = Some(action);
+                }
+                auto_login.on_new_line();
+            }
+            #[cfg(feature = "wasm")]
+            self.run_plugin_actions(&complete_line, ui_config);
+            let alert = self.alerts.matches(&complete_line);
+            if alert {
+                self.alerts.fire(AlertEvent::Pattern, &complete_line);
+            }
+            if let Some(stats) = self.stats_log.as_mut() {
+                stats.on_line(level, alert);
+            }
+            self.pinned.on_line(&complete_line);
+            let mut notes = self.boot_profiler.on_line(&complete_line);
+            if let Some(note) = self
+                .boot_capture
+                .as_mut()
+                .and_then(|capture| capture.on_line(&complete_line))
+            {
+                self.boot_capture = None;
+                notes.push(note);
+            }
+            if let Some((name, lines)) = captured {
+                self.alerts.fire(AlertEvent::Block, &name);
+                notes.push(format!("[block] {name} captured: {lines} lines"));
+            }
+            let echo = ui_config.echo_mode != EchoMode::Show && self.take_echo(&complete_line);
+            // The tracker skips the echo itself, whatever --echo-mode says
+            if let Some(latency) = self.latency.as_mut()
+                && let Some(note) = latency.on_line(&complete_line)
+            {
+                notes.push(note);
+                let summary = latency.summary();
+                self.set_status(summary);
+            }
+            self.emit(|| Event::RxLine {
+                text: complete_line.clone(),
+                level: level.map(Level::name),
+                tags: tags.clone(),
+                echo,
+            });
+            let fold_into = self.output_lines.last_mut().filter(|last| {
+                !last.echo
+                    && last.folded.len() < MAX_FOLDED
+                    && ui_config
+                        .fold_rules
+                        .iter()
+                        .any(|r| r.is_match(&complete_line))
+            });
+            match fold_into {
+                Some(last) => last.folded.push(complete_line),
+                None => self.output_lines.push(OutputEntry {
+                    text: complete_line,
+                    tags,
+                    level,
+                    expected,
+                    echo,
+                    folded: Vec::new(),
+                    expanded: self.expand_folds,
+                    source: None,
+                }),
+            }
+            for note in notes {
+                self.emit(|| Event::Note { text: note.clone() });
+                self.output_lines.push(OutputEntry::local(note));
+            }
             has_new_lines = true;
+        }
+
+        if has_new_lines {
+            self.update_transcript_status();
+        }
+
+        if self.line_editor.as_ref().is_some_and(|d| d.detected()) {
+            self.line_editor = None;
+            self.passthrough = true;
+            self.set_status(
+                "Device line editor detected: keys now go to the device (F12 to edit locally)"
+                    .to_string(),
+            );
+        }
 
-            // Remove processed part from partial_line
-            self.partial_line.drain(..=newline_pos);
+        // Prompts usually arrive without a line ending
+        if let Some(auto_login) = self.auto_login.as_mut()
+            && let Some(action) = auto_login.on_text(&self.partial_line)
+        {
+            self.login_action = Some(action);
         }
 
         // Only trigger expensive operations if we have new complete lines
         if has_new_lines {
-            // Keep only the last 1000 lines to prevent memory issues
-            if self.output_lines.len() > 1000 {
-                self.output_lines.drain(..self.output_lines.len() - 1000);
+            self.on_lines_added();
+        }
+    }
+
+    /// Remove and return the next complete line from `partial_line`
+    fn take_line(&mut self, rx_line_ending: RxLineEnding) -> Option<String> {
+        let (line, consumed) = match rx_line_ending {
+            RxLineEnding::Lf => {
+                let pos = self.partial_line.find('\n')?;
+                (
+                    self.partial_line[..pos].trim_end_matches('\r').to_string(),
+                    pos + 1,
+                )
+            }
+            RxLineEnding::Cr => {
+                let pos = self.partial_line.find('\r')?;
+                (self.partial_line[..pos].replace('\n', ""), pos + 1)
+            }
+            RxLineEnding::Crlf => {
+                let pos = self.partial_line.find("\r\n")?;
+                (self.partial_line[..pos].to_string(), pos + 2)
+            }
+            RxLineEnding::Any => {
+                let pos = self.partial_line.find(['\r', '\n'])?;
+                let rest = &self.partial_line.as_bytes()[pos..];
+                let consumed = match rest {
+                    [b'\r', b'\n', ..] => 2,
+                    [b'\r'] => {
+                        self.skip_leading_lf = true;
+                        1
+                    }
+                    _ => 1,
+                };
+                (self.partial_line[..pos].to_string(), pos + consumed)
             }
+        };
 
-            // Update auto-scroll state to point to the new bottom
-            if !self.output_lines.is_empty() {
-                self.auto_scroll_state
-                    .select(Some(self.output_lines.len() - 1));
+        // Remove processed part from partial_line
+        self.partial_line.drain(..consumed);
+        Some(line)
+    }
+
+    /// Let `on_line` plugins see the line; their answers are sent like
+    /// FIFO lines
+    #[cfg(feature = "wasm")]
+    fn run_plugin_actions(&mut self, line: &str, ui_config: &UiConfig) {
+        let mut errors = Vec::new();
+        for plugin in &mut self.plugin_actions {
+            match plugin.on_line(line) {
+                Ok(Some(reply)) => {
+                    let _ = ui_config.ui_tx.send(UiMessage::SendLine {
+                        source: "plugin",
+                        line: reply,
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(format!("Plugin {}: {e}", plugin.name)),
             }
+        }
+        if let Some(error) = errors.pop() {
+            self.set_status(error);
+        }
+    }
+
+    /// Remember a sent line so its echo can be recognised
+    pub fn expect_echo(&mut self, sent: &str) {
+        let sent = sent.trim();
+        if !sent.is_empty() {
+            self.pending_echo
+                .push_back((sent.to_string(), Instant::now()));
+        }
+    }
 
+    /// Whether `line` echoes a recently sent line; consumes that entry and
+    /// any older ones
+    fn take_echo(&mut self, line: &str) -> bool {
+        self.pending_echo
+            .retain(|(_, sent_at)| sent_at.elapsed() < ECHO_WINDOW);
+        let line = line.trim();
+        match self.pending_echo.iter().position(|(sent, _)| sent == line) {
+            Some(idx) => {
+                self.pending_echo.drain(..=idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn update_transcript_status(&mut self) {
+        let Some(transcript) = &self.transcript else {
+            return;
+        };
+        let summary = transcript.summary();
+        let complete = transcript.is_complete();
+        if complete && !self.transcript_reported {
+            self.transcript_reported = true;
+            self.add_local_line(format!("== {summary} =="));
+        }
+        self.set_status(summary);
+    }
+
+    /// Retain raw RX bytes for byte pattern search
+    pub fn add_raw(&mut self, bytes: &[u8]) {
+        self.raw_rx.extend(bytes);
+        if self.raw_rx.len() > RAW_RX_RETAIN {
+            let excess = self.raw_rx.len() - RAW_RX_RETAIN;
+            self.raw_rx.drain(..excess);
+            self.raw_base_offset += excess as u64;
+        }
+    }
+
+    /// Append a locally generated line (not from the device) to the output
+    /// Append a ready-made entry (the merged view's lines)
+    pub fn add_entry(&mut self, entry: OutputEntry) {
+        self.output_lines.push(entry);
+        self.on_lines_added();
+    }
+
+    pub fn add_local_line(&mut self, line: String) {
+        self.emit(|| Event::Note { text: line.clone() });
+        self.output_lines.push(OutputEntry::local(line));
+        self.on_lines_added();
+    }
+
+    /// Lines that pass the current filter, in order
+    pub fn visible_lines(&self) -> impl Iterator<Item = &OutputEntry> {
+        self.output_lines
+            .iter()
+            .filter(|e| e.is_shown(&self.filter))
+    }
+
+    pub fn visible_len(&self) -> usize {
+        self.visible_lines().count()
+    }
+
+    /// Ctrl+O: show or hide the lines folded into the highlighted entry;
+    /// with auto-scroll on, into every entry. Returns a status line
+    pub fn toggle_fold(&mut self) -> String {
+        if self.auto_scroll {
+            self.expand_folds = !self.expand_folds;
+            for entry in &mut self.output_lines {
+                entry.expanded = self.expand_folds;
+            }
             self.needs_render = true;
+            return if self.expand_folds {
+                "Folded lines expanded (Ctrl+O to collapse)".to_string()
+            } else {
+                "Folded lines collapsed (Ctrl+O to expand)".to_string()
+            };
         }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let filter = self.filter.clone();
+        let Some(entry) = self
+            .output_lines
+            .iter_mut()
+            .filter(|e| e.is_shown(&filter))
+            .nth(selected)
+        else {
+            return "Nothing highlighted".to_string();
+        };
+        if entry.folded.is_empty() {
+            return "No folded lines here".to_string();
+        }
+        entry.expanded = !entry.expanded;
+        self.needs_render = true;
+        format!(
+            "{} {} folded line(s)",
+            if entry.expanded { "Showing" } else { "Hiding" },
+            entry.folded.len()
+        )
+    }
+
+    /// Switch the display filter, resetting the scroll position since
+    /// indices into the visible list change
+    pub fn set_filter(&mut self, filter: DisplayFilter) {
+        self.filter = filter;
+        self.list_state.select(None);
+        let len = self.visible_len();
+        self.auto_scroll_state.select(len.checked_sub(1));
+        self.needs_render = true;
+    }
+
+    fn on_lines_added(&mut self) {
+        // Keep only the last 1000 lines to prevent memory issues
+        if self.output_lines.len() > 1000 {
+            self.output_lines.drain(..self.output_lines.len() - 1000);
+        }
+
+        // Update auto-scroll state to point to the new bottom
+        let len = self.visible_len();
+        if len > 0 {
+            self.auto_scroll_state.select(Some(len - 1));
+        }
+
+        self.needs_render = true;
     }
 
     pub fn scroll_up(&mut self) {
-        if self.output_lines.is_empty() {
+        let len = self.visible_len();
+        if len == 0 {
             return;
         }
         // Disable auto-scroll when manually scrolling
         self.auto_scroll = false;
 
-        let selected = self
-            .list_state
-            .selected()
-            .unwrap_or(self.output_lines.len() - 1);
+        let selected = self.list_state.selected().unwrap_or(len - 1);
         if selected > 0 {
             self.list_state.select(Some(selected - 1));
             self.needs_render = true;
@@ -78,28 +640,37 @@ impl AppState {
     }
 
     pub fn scroll_down(&mut self) {
-        if self.output_lines.is_empty() {
+        let len = self.visible_len();
+        if len == 0 {
             return;
         }
         // Disable auto-scroll when manually scrolling
         self.auto_scroll = false;
 
         let selected = self.list_state.selected().unwrap_or(0);
-        if selected < self.output_lines.len() - 1 {
+        if selected < len - 1 {
             self.list_state.select(Some(selected + 1));
             self.needs_render = true;
         }
     }
 
     pub fn scroll_to_bottom(&mut self) {
-        if !self.output_lines.is_empty() {
+        let len = self.visible_len();
+        if len > 0 {
             // Disable auto-scroll when manually scrolling to bottom
             self.auto_scroll = false;
-            self.list_state.select(Some(self.output_lines.len() - 1));
+            self.list_state.select(Some(len - 1));
             self.needs_render = true;
         }
     }
 
+    /// Select a visible line in manual scroll mode
+    pub fn select_line(&mut self, idx: usize) {
+        self.auto_scroll = false;
+        self.list_state.select(Some(idx));
+        self.needs_render = true;
+    }
+
     pub fn enable_auto_scroll(&mut self) {
         self.auto_scroll = true;
         self.list_state.select(None); // Clear selection when re-enabling auto-scroll
@@ -107,7 +678,7 @@ impl AppState {
     }
 
     pub fn scroll_to_home(&mut self) {
-        if !self.output_lines.is_empty() {
+        if self.visible_len() > 0 {
             // Disable auto-scroll when manually scrolling to top
             self.auto_scroll = false;
             self.list_state.select(Some(0));
@@ -116,26 +687,25 @@ impl AppState {
     }
 
     pub fn scroll_page_up(&mut self, page_size: usize) {
-        if self.output_lines.is_empty() {
+        let len = self.visible_len();
+        if len == 0 {
             return;
         }
         self.auto_scroll = false;
-        let current = self
-            .list_state
-            .selected()
-            .unwrap_or(self.output_lines.len().saturating_sub(1));
+        let current = self.list_state.selected().unwrap_or(len.saturating_sub(1));
         let new_selected = current.saturating_sub(page_size);
         self.list_state.select(Some(new_selected));
         self.needs_render = true;
     }
 
     pub fn scroll_page_down(&mut self, page_size: usize) {
-        if self.output_lines.is_empty() {
+        let len = self.visible_len();
+        if len == 0 {
             return;
         }
         self.auto_scroll = false;
         let current = self.list_state.selected().unwrap_or(0);
-        let new_selected = (current + page_size).min(self.output_lines.len().saturating_sub(1));
+        let new_selected = (current + page_size).min(len.saturating_sub(1));
         self.list_state.select(Some(new_selected));
         self.needs_render = true;
     }
@@ -159,6 +729,22 @@ impl AppState {
         input
     }
 
+    pub fn set_status(&mut self, text: String) {
+        self.emit(|| Event::Status {
+            message: text.clone(),
+        });
+        self.status = Some(text);
+        self.needs_render = true;
+    }
+
+    pub fn toggle_settings_panel(&mut self) {
+        self.settings_panel = match self.settings_panel {
+            Some(_) => None,
+            None => Some(SettingsPanel::default()),
+        };
+        self.needs_render = true;
+    }
+
     pub fn quit(&mut self) {
         self.should_quit = true;
         self.needs_render = true;
@@ -167,4 +753,24 @@ impl AppState {
     pub fn mark_rendered(&mut self) {
         self.needs_render = false;
     }
+
+    /// Record an event for `--output json` and the `--web` view; `event`
+    /// is only built when one of them is on
+    pub fn emit(&mut self, event: impl FnOnce() -> Event) {
+        #[cfg(feature = "web")]
+        let wanted = self.events.is_some() || self.web.is_some();
+        #[cfg(not(feature = "web"))]
+        let wanted = self.events.is_some();
+        if !wanted {
+            return;
+        }
+        let (time, event) = (Local::now(), event());
+        #[cfg(feature = "web")]
+        if let Some(web) = &self.web {
+            web.publish(event_json(time, &event));
+        }
+        if let Some(events) = self.events.as_mut() {
+            events.push((time, event));
+        }
+    }
 }
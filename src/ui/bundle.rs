@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::path::PathBuf;
+
+use super::AppState;
+use super::config_view::effective_config;
+use crate::config::UiConfig;
+
+/// Quote a CSV field when it needs it
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Write the session so far into a new directory, ready to attach to a
+/// ticket:
+///
+/// - `rx.bin`: raw received bytes (the most recent 1 MiB)
+/// - `output.txt`: every output line, unfiltered
+/// - `lines.csv`: one row per line with its level, tags and echo flag
+/// - `screen.txt`: what the output pane currently shows
+/// - `blocks.txt`: the `--block` blocks captured, when there are any
+/// - `session.json`: port, settings and counters
+pub fn export_bundle(app_state: &AppState, ui_config: &UiConfig) -> Result<PathBuf> {
+    let now = Local::now();
+    let name = match &ui_config.session_name {
+        Some(session) => format!("sermonizer-{session}-{}", now.format("%Y%m%d-%H%M%S")),
+        None => format!("sermonizer-bundle-{}", now.format("%Y%m%d-%H%M%S")),
+    };
+    let dir = PathBuf::from(name);
+    std::fs::create_dir(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let write = |file: &str, contents: &[u8]| {
+        std::fs::write(dir.join(file), contents).with_context(|| format!("Failed to write {file}"))
+    };
+
+    let (front, back) = app_state.raw_rx.as_slices();
+    write("rx.bin", &[front, back].concat())?;
+
+    let mut text = String::new();
+    let mut csv = String::from("line,level,tags,echo,text\n");
+    for (i, entry) in app_state.output_lines.iter().enumerate() {
+        for line in entry.all_lines() {
+            text.push_str(line);
+            text.push('\n');
+        }
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            i + 1,
+            entry.level.map_or("", |l| l.name()),
+            csv_field(&entry.tags.join(" ")),
+            entry.echo,
+            csv_field(&entry.text)
+        ));
+    }
+    write("output.txt", text.as_bytes())?;
+    write("lines.csv", csv.as_bytes())?;
+
+    // Text "screenshot": the bottom of the output pane as shown
+    let rows = crossterm::terminal::size().map_or(24, |(_, h)| h as usize);
+    let shown: Vec<&str> = app_state
+        .visible_lines()
+        .map(|entry| entry.text.as_str())
+        .collect();
+    let screen = shown[shown.len().saturating_sub(rows.saturating_sub(5))..].join("\n");
+    write("screen.txt", screen.as_bytes())?;
+
+    let mut blocks = String::new();
+    for block in app_state.blocks.captured() {
+        blocks.push_str(&format!(
+            "=== {} {} ({} lines)\n",
+            block.name,
+            block.time.format("%H:%M:%S%.3f"),
+            block.lines.len()
+        ));
+        for line in &block.lines {
+            blocks.push_str(line);
+            blocks.push('\n');
+        }
+    }
+    if !blocks.is_empty() {
+        write("blocks.txt", blocks.as_bytes())?;
+    }
+
+    let settings: serde_json::Map<String, serde_json::Value> =
+        effective_config(app_state, ui_config)
+            .into_iter()
+            .map(|[label, value, _]| (label, value.into()))
+            .collect();
+    let metadata = serde_json::json!({
+        "session": ui_config.session_name,
+        "sermonizer_version": env!("CARGO_PKG_VERSION"),
+        "started_at": app_state.started_at.to_rfc3339(),
+        "exported_at": now.to_rfc3339(),
+        "port": ui_config.port_name,
+        "profile": ui_config.origins.profile,
+        "settings": settings,
+        "lines": app_state.output_lines.len(),
+        "rx_bytes_total": app_state.raw_base_offset + app_state.raw_rx.len() as u64,
+        "rx_bin_offset": app_state.raw_base_offset,
+        "line_errors": app_state.line_errors.describe(),
+        "filter": app_state.filter.describe(),
+    });
+    write(
+        "session.json",
+        serde_json::to_string_pretty(&metadata)?.as_bytes(),
+    )?;
+    Ok(dir)
+}
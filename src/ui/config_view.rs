@@ -0,0 +1,78 @@
+use super::AppState;
+use super::settings::SettingsField;
+use crate::config::UiConfig;
+
+/// Labels of the rows that are not part of the F2 settings panel
+pub const PORT: &str = "Port";
+pub const ALT_LINE_ENDING: &str = "Shift+Enter ending";
+pub const ECHO_MODE: &str = "Echo mode";
+pub const ESC: &str = "Esc key";
+pub const HEX: &str = "Hex view";
+pub const INSTANT_KEYS: &str = "Instant keys";
+pub const MIN_LEVEL: &str = "Min level";
+pub const PASSTHROUGH: &str = "Key passthrough";
+pub const RX_LOG: &str = "RX log";
+pub const TX_LOG: &str = "TX log";
+
+/// Every effective setting as (label, value, where it came from)
+pub fn effective_config(app_state: &AppState, ui_config: &UiConfig) -> Vec<[String; 3]> {
+    let origins = &ui_config.origins;
+    let row = |label: &str, value: String| [label.to_string(), value, origins.describe(label)];
+
+    let mut rows = vec![row(PORT, ui_config.port_name.clone())];
+    rows.extend(
+        SettingsField::ALL
+            .iter()
+            .map(|field| row(field.label(), field.value(ui_config))),
+    );
+    rows.push(row(
+        ALT_LINE_ENDING,
+        ui_config.alt_line_ending.describe().to_string(),
+    ));
+    rows.push(row(
+        ECHO_MODE,
+        format!("{:?}", ui_config.echo_mode).to_lowercase(),
+    ));
+    rows.push(row(
+        ESC,
+        format!("{:?}", ui_config.esc_action).to_lowercase(),
+    ));
+    rows.push(row(
+        HEX,
+        if ui_config.hex { "on" } else { "off" }.to_string(),
+    ));
+    rows.push(row(
+        INSTANT_KEYS,
+        match (
+            ui_config.instant_keys_enabled,
+            ui_config.instant_keys.is_empty(),
+        ) {
+            (_, true) => "none".to_string(),
+            (true, false) => format!("on ({})", ui_config.instant_keys),
+            (false, false) => format!("off ({})", ui_config.instant_keys),
+        },
+    ));
+    rows.push(row(
+        PASSTHROUGH,
+        format!(
+            "{} ({})",
+            if app_state.passthrough { "on" } else { "off" },
+            format!("{:?}", ui_config.passthrough).to_lowercase()
+        ),
+    ));
+    rows.push(row(
+        MIN_LEVEL,
+        app_state
+            .filter
+            .min_level
+            .map_or("all", |l| l.name())
+            .to_string(),
+    ));
+    let path = |p: &Option<std::path::PathBuf>| {
+        p.as_ref()
+            .map_or("off".to_string(), |p| p.display().to_string())
+    };
+    rows.push(row(RX_LOG, path(&ui_config.rx_log_path)));
+    rows.push(row(TX_LOG, path(&ui_config.tx_log_path)));
+    rows
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Lines `--on-exit tail` prints unless told otherwise
+const DEFAULT_TAIL: usize = 40;
+
+/// What to leave in the normal terminal once the TUI closes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExitView {
+    /// The last N output lines
+    Tail(usize),
+    /// The whole scrollback in `$PAGER`
+    Pager,
+}
+
+/// Parse `tail`, `tail:N` or `pager` for `--on-exit`
+pub fn parse_exit_view(s: &str) -> Result<ExitView, String> {
+    match s.split_once(':') {
+        None if s == "tail" => Ok(ExitView::Tail(DEFAULT_TAIL)),
+        None if s == "pager" => Ok(ExitView::Pager),
+        Some(("tail", n)) => n
+            .parse()
+            .map(ExitView::Tail)
+            .map_err(|_| format!("bad line count in '{s}'")),
+        _ => Err(format!("expected tail, tail:N or pager, got '{s}'")),
+    }
+}
+
+/// Pipe `lines` into `$PAGER` (default `less`) and wait for it
+fn page(lines: &[String]) -> Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "more".to_string()
+            } else {
+                "less".to_string()
+            }
+        });
+    let mut words = pager.split_whitespace();
+    let program = words.next().context("PAGER is empty")?;
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start pager '{pager}'"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        for line in lines {
+            // The pager quitting early closes the pipe; that's fine
+            if writeln!(stdin, "{line}").is_err() {
+                break;
+            }
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Show the session's output after the alternate screen is gone
+pub fn show(view: ExitView, lines: &[String]) -> Result<()> {
+    match view {
+        ExitView::Tail(n) => {
+            for line in &lines[lines.len().saturating_sub(n)..] {
+                println!("{line}");
+            }
+            Ok(())
+        }
+        ExitView::Pager if lines.is_empty() => Ok(()),
+        ExitView::Pager => page(lines),
+    }
+}
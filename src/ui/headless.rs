@@ -0,0 +1,136 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use tokio::sync::mpsc;
+
+use super::{
+    AppState, UiMessage, handle_serial, handle_ui_message, init_app_state, session_report,
+    write_stats,
+};
+use crate::config::UiConfig;
+use crate::serial_io::SerialData;
+
+/// One line of `--output json`
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// A complete line received from the device
+    RxLine {
+        text: String,
+        level: Option<&'static str>,
+        tags: Vec<String>,
+        /// The device echoing a line we sent
+        echo: bool,
+    },
+    /// A line transmitted to the device
+    Tx {
+        text: String,
+    },
+    /// Something sermonizer reports itself (power, login, profiler, ...)
+    Note {
+        text: String,
+    },
+    Status {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// One event as a JSON object (a line of `--output json`, a web view message)
+pub fn event_json(time: DateTime<Local>, event: &Event) -> String {
+    let record = Record {
+        time: time.to_rfc3339(),
+        event,
+    };
+    serde_json::to_string(&record).unwrap_or_default()
+}
+
+fn write_events(app_state: &mut AppState) -> Result<()> {
+    let Some(events) = app_state.events.as_mut() else {
+        return Ok(());
+    };
+    if events.is_empty() {
+        return Ok(());
+    }
+    let mut out = std::io::stdout().lock();
+    for (time, event) in events.drain(..) {
+        out.write_all(event_json(time, &event).as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Lines on stdin are sent to the device like `--tx-fifo` lines
+fn spawn_stdin_reader(ui_tx: mpsc::UnboundedSender<UiMessage>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if ui_tx
+                .send(UiMessage::SendLine {
+                    source: "stdin",
+                    line,
+                })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
+
+/// `--output json`: no TUI, every event is written to stdout as one JSON
+/// object per line and stdin lines are transmitted. Ends on Ctrl-C or
+/// when the port goes away.
+pub async fn run_headless(
+    mut ui_rx: mpsc::UnboundedReceiver<UiMessage>,
+    mut serial_rx: mpsc::UnboundedReceiver<SerialData>,
+    port: Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    mut ui_config: UiConfig,
+) -> Result<()> {
+    let mut app_state = init_app_state(&mut ui_config, &port);
+    app_state.events = Some(Vec::new());
+    spawn_stdin_reader(ui_config.ui_tx.clone());
+
+    while ui_config.running.load(Ordering::SeqCst) && !app_state.should_quit {
+        tokio::select! {
+            msg = ui_rx.recv() => {
+                if let Some(msg) = msg {
+                    handle_ui_message(msg, &mut app_state, &port, &mut ui_config).await?;
+                }
+            }
+            data = serial_rx.recv() => {
+                if let Some(data) = data {
+                    let disconnected = matches!(data, SerialData::Disconnected(_));
+                    handle_serial(data, &mut app_state, &port, &ui_config).await?;
+                    if disconnected {
+                        app_state.quit();
+                    }
+                }
+            }
+        }
+        write_events(&mut app_state)?;
+    }
+
+    ui_config.running.store(false, Ordering::SeqCst);
+    write_stats(&mut app_state, &ui_config);
+    for message in session_report(&app_state) {
+        app_state.emit(|| Event::Status { message });
+    }
+    write_events(&mut app_state)
+}
+
+/// Pending `--output json` events with the time they happened
+pub type EventLog = Vec<(DateTime<Local>, Event)>;
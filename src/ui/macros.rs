@@ -0,0 +1,52 @@
+use crossterm::event::KeyEvent;
+
+/// One recorded input event
+#[derive(Clone)]
+pub enum MacroStep {
+    Key(KeyEvent),
+    Paste(String),
+}
+
+/// Ad-hoc keyboard macro: Ctrl+K starts and stops recording, Ctrl+P plays
+/// the last recording back through the normal key handling, so sends and
+/// local actions (filters, F-keys, ...) repeat alike
+#[derive(Default)]
+pub struct MacroRecorder {
+    recording: Option<Vec<MacroStep>>,
+    last: Vec<MacroStep>,
+}
+
+impl MacroRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Start recording, or stop and keep what was recorded; returns a
+    /// status line
+    pub fn toggle(&mut self) -> String {
+        match self.recording.take() {
+            None => {
+                self.recording = Some(Vec::new());
+                "Recording macro... Ctrl+K to stop".to_string()
+            }
+            Some(steps) if steps.is_empty() => {
+                "Macro recording cancelled (nothing recorded)".to_string()
+            }
+            Some(steps) => {
+                let count = steps.len();
+                self.last = steps;
+                format!("Macro recorded: {count} step(s), Ctrl+P to play")
+            }
+        }
+    }
+
+    pub fn record(&mut self, step: MacroStep) {
+        if let Some(steps) = self.recording.as_mut() {
+            steps.push(step);
+        }
+    }
+
+    pub fn steps(&self) -> Vec<MacroStep> {
+        self.last.clone()
+    }
+}
@@ -1,58 +1,154 @@
 pub mod app_state;
+pub mod bundle;
+pub mod config_view;
+pub mod exit_view;
+pub mod headless;
+pub mod macros;
+pub mod pins;
+pub mod popup;
+pub mod port_switcher;
+pub mod reference;
 pub mod rendering;
+pub mod search;
+pub mod settings;
+pub mod stopwatch;
+pub mod tabs;
+pub mod vt100;
 
-pub use app_state::AppState;
+pub use app_state::{AppState, DisplayFilter, OutputEntry};
+use macros::MacroStep;
+use pins::PinnedLines;
+use port_switcher::PortSwitcher;
 pub use rendering::draw_ui;
+use search::run_search;
+use settings::{SettingsField, change_setting};
+pub use tabs::Tab;
+use tabs::{Session, Sessions, TabEvent};
 
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::{Terminal, backend::Backend};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::config::UiConfig;
-use crate::serial_io::{SerialData, write_bytes_async};
-use chrono::Utc;
+use crate::alerts::AlertEvent;
+use crate::auto_login::LoginAction;
+use crate::blocks::BlockTracker;
+use crate::boot_profiler::BootProfiler;
+use crate::bug_report;
+use crate::calc;
+use crate::config::{EchoMode, EscAction, LineEnding, PassthroughMode, UiConfig};
+use crate::config_watch::LiveRules;
+use crate::connect_trace;
+use crate::latency::LatencyTracker;
+use crate::levels::{Level, LevelParser};
+use crate::line_editor::{self, LineEditorDetector};
+use crate::line_errors::LineErrorCounts;
+use crate::logging::write_log_chunk;
+use crate::origins::Origin;
+use crate::port_lock::OnBusy;
+use crate::power::PowerAction;
+use crate::reconnect::{self, Reconnect, Reopened};
+use crate::reset::{self, ResetKind};
+use crate::serial_io::{SerialData, send_break, write_bytes_async};
+use crate::stats_log;
+use crate::tagging::tag_names;
+use crate::template::{parse_template, push_line_ending, run_template};
+use crate::transcript::TranscriptChecker;
+use crate::tx_pipe;
+use chrono::{Local, Utc};
+
+/// Redraw interval while background TX jobs are running
+const TX_QUEUE_REFRESH: Duration = Duration::from_millis(200);
+
+/// Redraw interval while the stopwatch runs
+const STOPWATCH_REFRESH: Duration = Duration::from_millis(100);
+
+/// `--rx-pipe-pane` lines kept
+const PIPE_PANE_LINES: usize = 500;
 
 #[derive(Debug)]
 pub enum UiMessage {
     Quit,
+    /// Short status text shown in the input box title
+    Status(String),
+    /// Locally generated line appended to the output pane
+    Output(String),
+    /// Line from another source (e.g. `--tx-fifo`) to transmit with the
+    /// configured line ending
+    SendLine {
+        source: &'static str,
+        line: String,
+    },
+    /// Driver-reported line error counters changed (Linux only for now)
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    LineErrors(LineErrorCounts),
+    /// A power command finished (error text on failure)
+    PowerDone(PowerAction, Result<String, String>),
+    /// A `{sleep}`/`{wait}` sequence ran to the end or was aborted
+    SequenceDone(Result<(), String>),
+    /// The config file changed; new rules for the current profile
+    RulesReloaded(LiveRules),
+    /// `--reconnect` reopened the port (maybe under a new name)
+    Reconnected(Reopened),
+    /// A serial port appeared or went away (note for the output)
+    PortsChanged(String),
+    /// A line the `--rx-pipe` command printed
+    PipeOutput(String),
+    /// Time for the next `--stats-log` record
+    StatsDue,
+}
+
+/// What a TUI session leaves for the normal terminal
+#[derive(Default)]
+pub struct SessionEnd {
+    /// Transcript, boot profiler and latency summaries
+    pub report: Vec<String>,
+    /// Every output line, when `--on-exit` wants them
+    pub scrollback: Vec<String>,
 }
 
 pub async fn run_ui<B: Backend>(
     terminal: &mut Terminal<B>,
-    mut ui_rx: mpsc::UnboundedReceiver<UiMessage>,
-    mut serial_rx: mpsc::UnboundedReceiver<SerialData>,
-    port: Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
-    ui_config: UiConfig,
-) -> Result<()> {
-    let mut app_state = AppState::new();
-    // No cached timestamp needed with chrono
+    tabs: Vec<Tab>,
+    merged_view: bool,
+) -> Result<SessionEnd> {
+    let (tab_tx, mut tab_rx) = mpsc::unbounded_channel();
+    let several = tabs.len() > 1;
+    let mut sessions = Sessions::new(merged_view && several);
+    for (index, tab) in tabs.into_iter().enumerate() {
+        let Tab {
+            ui_rx,
+            serial_rx,
+            port,
+            mut ui_config,
+        } = tab;
+        tabs::forward(index, ui_rx, serial_rx, tab_tx.clone());
+        if several {
+            tabs::forward_lines(index, ui_config.rx_lines.subscribe(), tab_tx.clone());
+        }
+        let app_state = init_app_state(&mut ui_config, &port);
+        sessions.tabs.push(Session {
+            app_state,
+            ui_config,
+            port,
+            activity: false,
+        });
+    }
+    let running = sessions.active().ui_config.running.clone();
+    let polling = sessions.active().ui_config.polling;
+    set_terminal_title(&sessions.active().ui_config);
+    let mut last_render = Instant::now();
 
-    while ui_config.running.load(Ordering::SeqCst) && !app_state.should_quit {
+    while running.load(Ordering::SeqCst) && !sessions.should_quit() {
         tokio::select! {
-            // UI messages (like quit from Ctrl-C)
-            msg = ui_rx.recv() => {
-                if let Some(msg) = msg {
-                    match msg {
-                        UiMessage::Quit => {
-                            app_state.quit();
-                            break;
-                        }
-                    }
-                }
-            }
-
-            // Serial data
-            data = serial_rx.recv() => {
-                if let Some(data) = data {
-                    match data {
-                        SerialData::Received(line) => {
-                            app_state.add_output(line);
-                        }
-                    }
+            // UI messages (like quit from Ctrl-C) and serial data, per tab
+            event = tab_rx.recv() => {
+                if let Some((index, event)) = event {
+                    handle_tab_event(index, event, &mut sessions).await?;
                 }
             }
 
@@ -61,52 +157,947 @@ pub async fn run_ui<B: Backend>(
                 if event::poll(Duration::from_millis(0)).unwrap_or(false) {
                     event::read()
                 } else {
-                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    tokio::time::sleep(polling.input).await;
                     Err(std::io::Error::new(std::io::ErrorKind::WouldBlock, "no input"))
                 }
             } => {
-                if let Ok(Event::Key(k)) = key_result
-                    && k.kind == KeyEventKind::Press {
-                    handle_key_event(k, &mut app_state, &port, &ui_config).await?;
+                let session = &mut sessions.tabs[sessions.active];
+                match key_result {
+                    Ok(Event::Key(k)) if k.kind == KeyEventKind::Press => {
+                        if sessions.handle_key(&k) {
+                            set_terminal_title(&sessions.active().ui_config);
+                            continue;
+                        }
+                        if sessions.broadcast && broadcast_enter(&k, &mut sessions).await? {
+                            continue;
+                        }
+                        let step = MacroStep::Key(k);
+                        let session = sessions.active();
+                        handle_input(step, &mut session.app_state, &session.port, &mut session.ui_config).await?;
+                    }
+                    Ok(Event::Paste(text)) => {
+                        let step = MacroStep::Paste(text);
+                        handle_input(step, &mut session.app_state, &session.port, &mut session.ui_config).await?;
+                    }
+                    _ => {}
                 }
             }
         }
 
-        // Only render if state changed - major performance optimization
-        if app_state.needs_render {
-            terminal.draw(|f| draw_ui(f, &mut app_state))?;
+        let tab_bar = sessions.tab_bar();
+        let Sessions {
+            tabs,
+            active,
+            merged,
+            merged_view,
+            ..
+        } = &mut sessions;
+        let Session {
+            app_state,
+            ui_config,
+            ..
+        } = &mut tabs[*active];
+
+        // Keep the TX queue widget's progress moving
+        if !ui_config.tx_queue.is_empty() && last_render.elapsed() >= TX_QUEUE_REFRESH {
+            app_state.needs_render = true;
+        }
+
+        // Keep the stopwatch ticking
+        if app_state.stopwatch.is_running() && last_render.elapsed() >= STOPWATCH_REFRESH {
+            app_state.needs_render = true;
+        }
+
+        // Only render if state changed - major performance optimization.
+        // A render held back by `--render-interval` happens on a later pass
+        if (app_state.needs_render || (*merged_view && merged.needs_render))
+            && last_render.elapsed() >= polling.render
+        {
+            last_render = Instant::now();
+            let merged = merged_view.then_some(&mut *merged);
+            terminal.draw(|f| draw_ui(f, app_state, ui_config, &tab_bar, merged))?;
             app_state.mark_rendered();
+            sessions.merged.mark_rendered();
         }
     }
 
-    ui_config.running.store(false, Ordering::SeqCst);
+    running.store(false, Ordering::SeqCst);
+    let mut report = Vec::new();
+    for tab in &mut sessions.tabs {
+        // What the last, partial interval saw
+        write_stats(&mut tab.app_state, &tab.ui_config);
+        let lines = session_report(&tab.app_state);
+        if several && !lines.is_empty() {
+            report.push(format!("{}:", tab.ui_config.port_name));
+        }
+        report.extend(lines);
+    }
+    let Session {
+        app_state,
+        ui_config,
+        ..
+    } = sessions.active();
+    let scrollback = match ui_config.on_exit {
+        Some(_) => app_state
+            .output_lines
+            .iter()
+            .flat_map(OutputEntry::all_lines)
+            .map(str::to_string)
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(SessionEnd { report, scrollback })
+}
+
+/// Enter while broadcasting: the typed line goes to every connected tab's
+/// port, each with its own line ending, echo and logs. Returns false when
+/// the key is for something else (search, popups, passthrough).
+async fn broadcast_enter(
+    key: &crossterm::event::KeyEvent,
+    sessions: &mut Sessions,
+) -> Result<bool> {
+    let session = sessions.active();
+    let app_state = &session.app_state;
+    if key.code != KeyCode::Enter
+        || session.ui_config.read_only
+        || app_state.passthrough
+        || app_state.quit_pending
+        || app_state.config_view
+        || app_state.search_input.is_some()
+        || app_state.settings_panel.is_some()
+        || app_state.port_switcher.is_some()
+        || app_state.popup.is_some()
+        || calc::run(&app_state.input_line).is_some()
+    {
+        return Ok(false);
+    }
+    let line = app_state.input_line.clone();
+    let active = sessions.active;
+    let mut sent = 0;
+    for (index, session) in sessions.tabs.iter_mut().enumerate() {
+        if session.app_state.disconnected {
+            continue;
+        }
+        let ui_config = &session.ui_config;
+        let end = if key.modifiers.contains(KeyModifiers::ALT) {
+            LineEnding::None
+        } else if key.modifiers.contains(KeyModifiers::SHIFT) {
+            ui_config.alt_line_ending
+        } else {
+            ui_config.line_ending
+        };
+        // Whatever was typed in the other tabs stays there
+        let typed = std::mem::replace(&mut session.app_state.input_line, line.clone());
+        handle_enter_key(&mut session.app_state, &session.port, ui_config, end).await?;
+        if index != active {
+            session.app_state.input_line = typed;
+        }
+        sent += 1;
+    }
+    let app_state = &mut sessions.active().app_state;
+    app_state.input_line.clear();
+    app_state.set_status(format!("Sent to {sent} port(s)"));
+    Ok(true)
+}
+
+/// A message, serial data or RX line for one of the tabs
+async fn handle_tab_event(index: usize, event: TabEvent, sessions: &mut Sessions) -> Result<()> {
+    match event {
+        // Hotplug notes concern every tab's port list
+        TabEvent::Ui(UiMessage::PortsChanged(note)) => {
+            for session in &mut sessions.tabs {
+                let msg = UiMessage::PortsChanged(note.clone());
+                handle_ui_message(
+                    msg,
+                    &mut session.app_state,
+                    &session.port,
+                    &mut session.ui_config,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        TabEvent::Ui(msg) => {
+            let retitle = matches!(msg, UiMessage::Reconnected(_)) && index != sessions.active;
+            let session = &mut sessions.tabs[index];
+            handle_ui_message(
+                msg,
+                &mut session.app_state,
+                &session.port,
+                &mut session.ui_config,
+            )
+            .await?;
+            // The terminal title is the shown tab's port
+            if retitle {
+                set_terminal_title(&sessions.active().ui_config);
+            }
+        }
+        TabEvent::Serial(data) => {
+            let session = &mut sessions.tabs[index];
+            handle_serial(
+                data,
+                &mut session.app_state,
+                &session.port,
+                &session.ui_config,
+            )
+            .await?;
+        }
+        TabEvent::Line(line) => {
+            sessions.merge_line(index, line);
+            return Ok(());
+        }
+    }
+    if sessions.tabs[index].app_state.needs_render && sessions.mark_activity(index) {
+        sessions.active().app_state.needs_render = true;
+    }
     Ok(())
 }
 
+/// Move the per-session parts of `ui_config` into a fresh state and start
+/// the startup commands
+fn init_app_state(
+    ui_config: &mut UiConfig,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+) -> AppState {
+    let mut app_state = AppState::new();
+    app_state.filter.min_level = ui_config.min_level;
+    app_state.filter.hide_echo = ui_config.echo_mode == EchoMode::Fold;
+    app_state.transcript = ui_config.transcript.take().map(TranscriptChecker::new);
+    if ui_config.latency {
+        app_state.latency = Some(LatencyTracker::new(ui_config.latency_response.take()));
+    }
+    app_state.auto_login = ui_config.auto_login.take();
+    app_state.passthrough = ui_config.passthrough == PassthroughMode::On;
+    if ui_config.passthrough == PassthroughMode::Auto {
+        app_state.line_editor = Some(LineEditorDetector::default());
+    }
+    app_state.alerts = std::mem::take(&mut ui_config.alerts);
+    app_state.terminal_view = ui_config.terminal_view;
+    app_state.boot_capture = ui_config.boot_capture.take();
+    app_state.stats_log = ui_config.stats_log.take();
+    if let Some(stats) = &app_state.stats_log {
+        tokio::spawn(stats_log::tick(
+            stats.interval,
+            ui_config.running.clone(),
+            ui_config.ui_tx.clone(),
+        ));
+    }
+    app_state.pinned = PinnedLines::new(std::mem::take(&mut ui_config.pins));
+    app_state.blocks = BlockTracker::new(std::mem::take(&mut ui_config.block_rules));
+    #[cfg(feature = "web")]
+    {
+        app_state.web = ui_config.web.take();
+    }
+    #[cfg(feature = "wasm")]
+    {
+        app_state.plugin_actions = std::mem::take(&mut ui_config.plugin_actions);
+    }
+    let startup = std::mem::take(&mut ui_config.startup);
+    if !startup.is_empty() {
+        app_state.add_local_line("-- [startup] sending startup commands --".to_string());
+        app_state.sequences_running += 1;
+        let job = ui_config.tx_queue.start("startup commands", startup.len());
+        tokio::spawn(run_template(
+            startup,
+            job,
+            port.clone(),
+            ui_config.rx_lines.subscribe(),
+            ui_config.tx_log.clone(),
+            ui_config.log_ts,
+            ui_config.ui_tx.clone(),
+        ));
+    }
+    app_state.boot_profiler = BootProfiler::new(std::mem::take(&mut ui_config.milestones));
+    app_state
+}
+
+async fn handle_ui_message(
+    msg: UiMessage,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) -> Result<()> {
+    match msg {
+        UiMessage::Quit => {
+            app_state.quit();
+        }
+        UiMessage::Status(text) => {
+            app_state.set_status(text);
+        }
+        UiMessage::Output(line) => {
+            app_state.add_local_line(line);
+        }
+        UiMessage::SendLine { source, line } => {
+            app_state.add_local_line(format!("[{source}] > {line}"));
+            let end = ui_config.line_ending;
+            let sent = send_line(app_state, port, ui_config, &line, end).await;
+            tx_pipe_failure_as_status(sent, app_state)?;
+        }
+        UiMessage::LineErrors(counts) => {
+            app_state.line_errors = counts;
+            if let Some(stats) = app_state.stats_log.as_mut() {
+                stats.on_line_errors(counts);
+            }
+            let message = format!("Line errors: {}", counts.describe());
+            app_state.emit(|| headless::Event::Error {
+                message: message.clone(),
+            });
+            app_state.set_status(message);
+        }
+        UiMessage::PowerDone(action, result) => {
+            on_power_done(app_state, action, result);
+        }
+        UiMessage::RulesReloaded(rules) => {
+            apply_rules(app_state, ui_config, rules);
+        }
+        UiMessage::PipeOutput(line) => {
+            app_state.pipe_lines.push_back(line);
+            if app_state.pipe_lines.len() > PIPE_PANE_LINES {
+                app_state.pipe_lines.pop_front();
+            }
+            app_state.needs_render = true;
+        }
+        UiMessage::StatsDue => write_stats(app_state, ui_config),
+        UiMessage::PortsChanged(note) => {
+            app_state.add_local_line(note);
+            if let Some(switcher) = app_state.port_switcher.as_mut() {
+                switcher.refresh(None);
+            }
+        }
+        UiMessage::Reconnected(reopened) => {
+            app_state.disconnected = false;
+            app_state.add_local_line(format!("-- reconnected to {} --", reopened.port_name));
+            app_state.set_status(format!("Reconnected to {}", reopened.port_name));
+            app_state
+                .alerts
+                .fire(AlertEvent::Reconnect, &reopened.port_name);
+            ui_config.port_name = reopened.port_name;
+            #[cfg(target_os = "linux")]
+            {
+                ui_config.port_fd = reopened.port_fd;
+                crate::line_errors::watch_line_errors(ui_config);
+            }
+            // The JSON output has no terminal title to update
+            if app_state.events.is_none() {
+                set_terminal_title(ui_config);
+            }
+        }
+        UiMessage::SequenceDone(result) => {
+            app_state.sequences_running = app_state.sequences_running.saturating_sub(1);
+            let text = match result {
+                Ok(()) => "Sequence done".to_string(),
+                Err(e) => {
+                    let message = format!("Sequence aborted: {e}");
+                    app_state.emit(|| headless::Event::Error {
+                        message: message.clone(),
+                    });
+                    message
+                }
+            };
+            app_state.alerts.fire(AlertEvent::SequenceDone, &text);
+            app_state.set_status(text);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_serial(
+    data: SerialData,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &UiConfig,
+) -> Result<()> {
+    match data {
+        SerialData::Received { text, bytes } => {
+            app_state.add_raw(&bytes);
+            if let Some(stats) = app_state.stats_log.as_mut() {
+                stats.on_bytes(bytes.len());
+            }
+            if let Some(note) = connect_trace::first_bytes(&ui_config.port_name, &bytes) {
+                app_state.add_local_line(note);
+            }
+            if !ui_config.hex
+                && let Some(hint) = app_state.line_rate.feed(&bytes)
+            {
+                app_state.set_status(hint);
+            }
+            if !ui_config.hex {
+                app_state.screen.feed(&text);
+            }
+            app_state.add_output(text, ui_config);
+            if let Some(action) = app_state.login_action.take() {
+                let sent = handle_login_action(action, app_state, port, ui_config).await;
+                tx_pipe_failure_as_status(sent, app_state)?;
+            }
+        }
+        SerialData::Disconnected(e) => {
+            app_state.emit(|| headless::Event::Error {
+                message: format!("Port disconnected: {e}"),
+            });
+            app_state.add_local_line(format!("-- port disconnected: {e} --"));
+            app_state.alerts.fire(AlertEvent::Disconnect, &e);
+            if let Some(stats) = app_state.stats_log.as_mut() {
+                stats.on_disconnect();
+            }
+            app_state.set_status(format!("Port disconnected: {e}"));
+        }
+        SerialData::Lost(e) => {
+            app_state.emit(|| headless::Event::Error {
+                message: format!("Port disconnected: {e}; waiting for it to come back"),
+            });
+            app_state.add_local_line(format!(
+                "-- port disconnected: {e}; waiting for it to come back --"
+            ));
+            app_state.alerts.fire(AlertEvent::Disconnect, &e);
+            if let Some(stats) = app_state.stats_log.as_mut() {
+                stats.on_disconnect();
+            }
+            app_state.set_status(format!("Port disconnected: {e}"));
+            app_state.disconnected = true;
+            if let Some(reconnect) = &ui_config.reconnect {
+                let generation = ui_config.port_generation.clone();
+                let spawned_at = generation.current();
+                tokio::spawn(reconnect::reconnect(
+                    reconnect.clone(),
+                    ui_config.port_settings,
+                    port.clone(),
+                    generation,
+                    spawned_at,
+                    ui_config.running.clone(),
+                    ui_config.ui_tx.clone(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Append the `--stats-log` record for the interval just ended
+fn write_stats(app_state: &mut AppState, ui_config: &UiConfig) {
+    let Some(stats) = app_state.stats_log.as_mut() else {
+        return;
+    };
+    if let Err(e) = stats.write(&ui_config.port_name) {
+        app_state.set_status(format!("Stats log: {e}"));
+    }
+}
+
+/// Summaries printed to the normal terminal after the session ends
+fn session_report(app_state: &AppState) -> Vec<String> {
+    let mut report = Vec::new();
+    if let Some(transcript) = &app_state.transcript {
+        report.push(transcript.summary());
+    }
+    report.extend(app_state.boot_profiler.report());
+    if let Some(latency) = &app_state.latency {
+        report.extend(latency.report());
+    }
+    report
+}
+
+/// Ctrl+T: pin or unpin the highlighted line while scrolling; with
+/// auto-scroll on, clear the hand-pinned lines
+fn toggle_pin(app_state: &mut AppState) {
+    if app_state.auto_scroll {
+        let message = match app_state.pinned.clear_manual() {
+            0 => "Scroll up to highlight a line, then Ctrl+T pins it".to_string(),
+            n => format!("Unpinned {n} line(s)"),
+        };
+        app_state.set_status(message);
+        return;
+    }
+    let selected = app_state.list_state.selected().unwrap_or(0);
+    let Some(line) = app_state
+        .visible_lines()
+        .nth(selected)
+        .map(|e| e.text.clone())
+    else {
+        return;
+    };
+    let message = if app_state.pinned.toggle(&line) {
+        format!("Pinned: {line}")
+    } else {
+        format!("Unpinned: {line}")
+    };
+    app_state.set_status(message);
+}
+
+/// Stopwatch events and BREAKs: a line in the output and in each log
+/// being written
+fn record_marker(app_state: &mut AppState, ui_config: &UiConfig, marker: String) {
+    let line = format!("{marker}\n");
+    for log in [&ui_config.rx_log, &ui_config.tx_log].into_iter().flatten() {
+        write_log_chunk(log, line.as_bytes(), ui_config.log_ts);
+    }
+    app_state.add_local_line(marker);
+}
+
+/// Ctrl+X: stop every queued background send and drop whatever the driver
+/// still has buffered for transmission
+async fn cancel_tx(
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &UiConfig,
+) {
+    let cancelled = ui_config.tx_queue.cancel_all();
+    if cancelled == 0 {
+        app_state.set_status("Nothing queued for TX".to_string());
+        return;
+    }
+    let flushed = port
+        .lock()
+        .await
+        .clear(serialport::ClearBuffer::Output)
+        .is_ok();
+    app_state.set_status(format!(
+        "Cancelled {cancelled} TX job(s){}",
+        if flushed {
+            ", output buffer flushed"
+        } else {
+            ""
+        }
+    ));
+}
+
+/// Keys and pastes from the terminal: Ctrl+K and Ctrl+P record and play
+/// macros, everything else is recorded (while recording) and handled. In
+/// passthrough those two go to the device unless Ctrl+] came first.
+async fn handle_input(
+    step: MacroStep,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) -> Result<()> {
+    if let MacroStep::Key(key) = &step
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && (!app_state.passthrough || app_state.passthrough_escape)
+        && matches!(key.code, KeyCode::Char('k' | 'p'))
+    {
+        app_state.passthrough_escape = false;
+        match key.code {
+            KeyCode::Char('k') => {
+                let status = app_state.macros.toggle();
+                app_state.set_status(status);
+                return Ok(());
+            }
+            KeyCode::Char('p') if app_state.macros.is_recording() => {
+                app_state.set_status("Stop recording (Ctrl+K) before playing".to_string());
+                return Ok(());
+            }
+            KeyCode::Char('p') => {
+                let steps = app_state.macros.steps();
+                if steps.is_empty() {
+                    app_state.set_status("No macro recorded (Ctrl+K to record)".to_string());
+                    return Ok(());
+                }
+                let count = steps.len();
+                for step in steps {
+                    dispatch_input(step, app_state, port, ui_config).await?;
+                    if app_state.should_quit {
+                        return Ok(());
+                    }
+                }
+                app_state.set_status(format!("Macro played ({count} step(s))"));
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+    app_state.macros.record(step.clone());
+    dispatch_input(step, app_state, port, ui_config).await
+}
+
+async fn dispatch_input(
+    step: MacroStep,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) -> Result<()> {
+    let result = match step {
+        MacroStep::Key(key) => handle_key_event(key, app_state, port, ui_config).await,
+        MacroStep::Paste(text) => handle_paste(&text, app_state, port, ui_config).await,
+    };
+    tx_pipe_failure_as_status(result, app_state)
+}
+
+/// A `--tx-pipe` filter that failed sent nothing; that is a status line,
+/// not the end of the session
+fn tx_pipe_failure_as_status(result: Result<()>, app_state: &mut AppState) -> Result<()> {
+    match result {
+        Err(e) if e.downcast_ref::<tx_pipe::Failed>().is_some() => {
+            app_state.set_status(e.to_string());
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+const READ_ONLY_STATUS: &str = "Read-only (--read-only): nothing is sent";
+
+/// Keys that write to the port or change its lines: typing and Enter,
+/// passthrough, BREAK, resets, DTR/RTS, dropping the output buffer and
+/// Esc when it sends ESC
+fn sends_to_port(key: &crossterm::event::KeyEvent, ui_config: &UiConfig) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    match key.code {
+        KeyCode::Char(_) if !ctrl && !alt => true,
+        KeyCode::Char('b' | 't' | 'u') if alt => true,
+        KeyCode::Char('x') if ctrl => true,
+        KeyCode::Enter | KeyCode::F(12) => true,
+        KeyCode::Esc => ui_config.esc_action == EscAction::Send,
+        _ => control_line_key(key).is_some(),
+    }
+}
+
 async fn handle_key_event(
     key: crossterm::event::KeyEvent,
     app_state: &mut AppState,
     port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
-    ui_config: &UiConfig,
+    ui_config: &mut UiConfig,
 ) -> Result<()> {
+    let is_quit_key = (key.code == KeyCode::Esc && ui_config.esc_action == EscAction::Quit)
+        || (key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd')));
+    if app_state.quit_pending {
+        app_state.quit_pending = false;
+        if is_quit_key {
+            app_state.quit();
+        } else {
+            app_state.set_status("Quit cancelled".to_string());
+        }
+        return Ok(());
+    }
+    if (!app_state.passthrough || app_state.passthrough_escape)
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && key.code == KeyCode::Char('q')
+    {
+        // Ctrl+Q: force quit, no questions asked
+        app_state.quit();
+        return Ok(());
+    }
+
+    if app_state.config_view
+        && !(key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd')))
+    {
+        if matches!(key.code, KeyCode::Esc | KeyCode::F(10)) {
+            app_state.config_view = false;
+            app_state.needs_render = true;
+        }
+        return Ok(());
+    }
+
+    if let Some(popup) = app_state.popup.as_mut()
+        && !(key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd')))
+    {
+        if !popup.handle_key(&key, KeyCode::F(1)) {
+            app_state.popup = None;
+        }
+        app_state.needs_render = true;
+        return Ok(());
+    }
+
+    if app_state.settings_panel.is_some()
+        && !(key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd')))
+    {
+        handle_settings_key(key, app_state, port, ui_config).await;
+        return Ok(());
+    }
+
+    if app_state.port_switcher.is_some()
+        && !(key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd')))
+    {
+        handle_port_switcher_key(key, app_state, port, ui_config).await;
+        return Ok(());
+    }
+
+    if app_state.search_input.is_some()
+        && !(key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | 'd')))
+    {
+        handle_search_key(key, app_state);
+        return Ok(());
+    }
+
+    // In passthrough every key but F-keys, paging and the Alt shortcuts
+    // goes to the device. Ctrl+] keeps the next key here; twice sends it.
+    let escaped = std::mem::take(&mut app_state.passthrough_escape);
+    let escape_key = line_editor::is_passthrough_escape(&key);
+    if app_state.passthrough && escape_key && !escaped {
+        app_state.passthrough_escape = true;
+        app_state
+            .set_status("Ctrl+]: next key is for sermonizer (Ctrl+] again sends it)".to_string());
+        return Ok(());
+    }
+    let forward = app_state.passthrough && (!escaped || escape_key);
+
+    if ui_config.read_only && sends_to_port(&key, ui_config) {
+        app_state.set_status(READ_ONLY_STATUS.to_string());
+        return Ok(());
+    }
+
+    // `--key` mappings win over the local meaning of F-keys and paging
+    if forward && let Some(bytes) = ui_config.keymap.get(&key) {
+        write_bytes_async(port, bytes).await?;
+        if let Some(w) = &ui_config.tx_log {
+            write_log_chunk(w, bytes, ui_config.log_ts);
+        }
+        return Ok(());
+    }
+
+    // Device-side line editor: keys go out as a terminal would send them,
+    // Ctrl+C and readline's Ctrl keys included
+    if forward
+        && !(key.modifiers.contains(KeyModifiers::ALT)
+            && matches!(
+                key.code,
+                KeyCode::Char('b' | 'd' | 'r' | 's' | 'l' | 'p' | 't' | 'u' | 'v' | '0')
+            ))
+        && let Some(bytes) = line_editor::key_bytes(&key, ui_config.line_ending)
+    {
+        write_bytes_async(port, &bytes).await?;
+        if let Some(w) = &ui_config.tx_log {
+            write_log_chunk(w, &bytes, ui_config.log_ts);
+        }
+        return Ok(());
+    }
+
+    if let Some(field) = control_line_key(&key) {
+        apply_setting(field, true, app_state, port, ui_config).await;
+        return Ok(());
+    }
+
     match key.code {
         KeyCode::Char(c)
             if key.modifiers.contains(KeyModifiers::CONTROL) && (c == 'c' || c == 'd') =>
         {
-            app_state.quit();
+            request_quit(app_state, ui_config);
         }
-        KeyCode::Esc => {
-            app_state.quit();
+        KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            // Ctrl+F opens the search prompt, prefilled with the last query
+            let last = app_state.last_search.as_ref().map(|l| l.query.clone());
+            app_state.search_input = Some(last.unwrap_or_default());
+            app_state.needs_render = true;
+        }
+        KeyCode::F(2) => {
+            app_state.toggle_settings_panel();
+        }
+        #[cfg(feature = "decoders")]
+        KeyCode::F(11) => {
+            cycle_decoder(app_state, ui_config);
         }
+        KeyCode::F(12) => {
+            // A manual choice ends auto-detection
+            app_state.passthrough = !app_state.passthrough;
+            app_state.line_editor = None;
+            ui_config
+                .origins
+                .set(config_view::PASSTHROUGH, Origin::Runtime);
+            app_state.set_status(if app_state.passthrough {
+                "Key passthrough ON: keys go to the device's line editor".to_string()
+            } else {
+                "Key passthrough OFF: lines are edited locally".to_string()
+            });
+        }
+        KeyCode::F(10) => {
+            app_state.config_view = true;
+            app_state.needs_render = true;
+        }
+        KeyCode::F(1) => {
+            app_state.popup = Some(reference::reference_popup(ui_config));
+            app_state.needs_render = true;
+        }
+        KeyCode::Esc => match ui_config.esc_action {
+            EscAction::Quit => request_quit(app_state, ui_config),
+            EscAction::Clear if !app_state.input_line.is_empty() => {
+                app_state.clear_input();
+            }
+            EscAction::Clear => {
+                if app_state.filter.is_active() {
+                    if app_state.filter.min_level.is_some() {
+                        ui_config
+                            .origins
+                            .set(config_view::MIN_LEVEL, Origin::Runtime);
+                    }
+                    let filter = DisplayFilter {
+                        hide_echo: app_state.filter.hide_echo,
+                        ..DisplayFilter::default()
+                    };
+                    app_state.set_filter(filter);
+                    app_state.set_status("Display filter cleared".to_string());
+                }
+            }
+            EscAction::Send => {
+                write_bytes_async(port, b"\x1b").await?;
+                if let Some(w) = &ui_config.tx_log {
+                    write_log_chunk(w, b"\x1b", ui_config.log_ts);
+                }
+            }
+        },
         KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
             // Ctrl+A to re-enable auto-scroll
             app_state.enable_auto_scroll();
         }
+        KeyCode::F(4) => {
+            cycle_tag_filter(app_state, ui_config);
+        }
+        KeyCode::F(5) => {
+            cycle_min_level(app_state);
+            ui_config
+                .origins
+                .set(config_view::MIN_LEVEL, Origin::Runtime);
+        }
+        KeyCode::F(6) => {
+            arm_boot_profiler(app_state, "manually");
+        }
+        KeyCode::F(7) => {
+            start_power_action(app_state, ui_config, PowerAction::Cycle);
+        }
+        KeyCode::F(8) => {
+            start_power_action(app_state, ui_config, PowerAction::Off);
+        }
+        KeyCode::F(9) => {
+            start_power_action(app_state, ui_config, PowerAction::On);
+        }
+        KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            // Ctrl+L logs in (confirms a detected prompt, or retries)
+            match app_state.auto_login.as_mut().map(|a| a.confirm()) {
+                Some(action) => handle_login_action(action, app_state, port, ui_config).await?,
+                None => app_state.set_status("No auto_login configured in the profile".to_string()),
+            }
+        }
+        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            // Ctrl+E: everything needed for a bug ticket in one directory
+            match bundle::export_bundle(app_state, ui_config) {
+                Ok(dir) => app_state.set_status(format!("Session bundle: {}", dir.display())),
+                Err(e) => app_state.set_status(format!("Bundle export failed: {e:#}")),
+            }
+        }
+        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            match write_bug_report(app_state, ui_config) {
+                Ok(path) => app_state.set_status(format!("Bug report: {}", path.display())),
+                Err(e) => app_state.set_status(format!("Bug report failed: {e}")),
+            }
+        }
+        KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            match app_state.calc_result.clone() {
+                Some(value) => value.chars().for_each(|c| app_state.update_input(c)),
+                None => app_state.set_status("No result yet (try :hex 1234)".to_string()),
+            }
+        }
+        KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            cancel_tx(app_state, port, ui_config).await;
+        }
+        KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::ALT) => {
+            let status = match ui_config.tx_queue.toggle_pause() {
+                None => "Nothing queued for TX",
+                Some(true) => "TX jobs paused (Alt+X resumes)",
+                Some(false) => "TX jobs resumed",
+            };
+            app_state.set_status(status.to_string());
+        }
+        KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            toggle_pin(app_state);
+        }
+        KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            let message = app_state.toggle_fold();
+            app_state.set_status(message);
+        }
+        KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            // Ctrl+S to export the (filtered) output
+            match export_visible_lines(app_state) {
+                Ok(path) => app_state.set_status(format!("Exported to {}", path.display())),
+                Err(e) => app_state.set_status(format!("Export failed: {e}")),
+            }
+        }
+        KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => {
+            let duration = ui_config.break_duration;
+            match send_break(port, duration).await {
+                Ok(()) => record_marker(
+                    app_state,
+                    ui_config,
+                    format!("-- [break] {} ms --", duration.as_millis()),
+                ),
+                Err(e) => app_state.set_status(format!("Failed to send BREAK: {e}")),
+            }
+        }
+        KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::ALT) => {
+            app_state.terminal_view = !app_state.terminal_view;
+            app_state.set_status(if app_state.terminal_view {
+                "Terminal view: escape sequences drive the screen (Alt+V for lines)".to_string()
+            } else {
+                "Line view".to_string()
+            });
+        }
+        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) => {
+            app_state.port_switcher = Some(PortSwitcher::new(&ui_config.port_name));
+            app_state.needs_render = true;
+        }
+        KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::ALT) => {
+            trigger_reset(ui_config.reset, app_state, port, ui_config).await;
+        }
+        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::ALT) => {
+            let bootloader = ui_config.reset.and_then(ResetKind::bootloader);
+            trigger_reset(bootloader, app_state, port, ui_config).await;
+        }
+        KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+            let marker = app_state.stopwatch.toggle();
+            record_marker(app_state, ui_config, marker);
+        }
+        KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::ALT) => {
+            match app_state.stopwatch.lap() {
+                Some(marker) => record_marker(app_state, ui_config, marker),
+                None => app_state.set_status("Stopwatch not running (Alt+S starts it)".to_string()),
+            }
+        }
+        KeyCode::Char('0') if key.modifiers.contains(KeyModifiers::ALT) => {
+            let marker = app_state.stopwatch.reset();
+            record_marker(app_state, ui_config, marker);
+        }
+        KeyCode::F(3) => {
+            ui_config.instant_keys_enabled = !ui_config.instant_keys_enabled;
+            ui_config
+                .origins
+                .set(config_view::INSTANT_KEYS, Origin::Runtime);
+            app_state.set_status(if ui_config.instant_keys_enabled {
+                format!("Instant keys ON ({})", ui_config.instant_keys)
+            } else {
+                "Instant keys OFF".to_string()
+            });
+        }
+        KeyCode::Char(c)
+            if ui_config.instant_keys_enabled
+                && app_state.input_line.is_empty()
+                && ui_config.instant_keys.contains(c) =>
+        {
+            // Semi-raw mode: menu digits, y/n answers etc. go out right away
+            let mut buf = [0u8; 4];
+            let bytes = c.encode_utf8(&mut buf).as_bytes();
+            write_bytes_async(port, bytes).await?;
+            if let Some(w) = &ui_config.tx_log {
+                write_log_chunk(w, bytes, ui_config.log_ts);
+            }
+        }
         KeyCode::Char(c) => {
             app_state.update_input(c);
         }
+        KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+            // Alt+Enter: bare payload, no line ending
+            handle_enter_key(app_state, port, ui_config, LineEnding::None).await?;
+        }
+        KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
+            // Shift+Enter: alternate line ending (only reported by terminals
+            // that support keyboard enhancement)
+            let end = ui_config.alt_line_ending;
+            handle_enter_key(app_state, port, ui_config, end).await?;
+        }
         KeyCode::Enter => {
-            handle_enter_key(app_state, port, ui_config).await?;
+            let end = ui_config.line_ending;
+            handle_enter_key(app_state, port, ui_config, end).await?;
         }
         KeyCode::Backspace => {
             app_state.backspace_input();
@@ -134,16 +1125,670 @@ async fn handle_key_event(
     Ok(())
 }
 
+/// Swap in rules from a reloaded config file; they apply to lines received
+/// from now on
+fn apply_rules(app_state: &mut AppState, ui_config: &mut UiConfig, rules: LiveRules) {
+    let mut changed = Vec::new();
+    if let Some(tags) = rules.tags {
+        ui_config.tag_rules = tags;
+        changed.push("tags");
+        // The filtered tag may be gone now
+        if let Some(tag) = &app_state.filter.tag
+            && !tag_names(&ui_config.tag_rules).contains(tag)
+        {
+            let filter = DisplayFilter {
+                tag: None,
+                ..app_state.filter.clone()
+            };
+            app_state.set_filter(filter);
+        }
+    }
+    if let Some(patterns) = rules.level_patterns {
+        ui_config.level_parser = LevelParser::new(patterns);
+        changed.push("level patterns");
+    }
+    if let Some(alerts) = rules.alerts {
+        app_state.alerts.patterns = alerts;
+        changed.push("alerts");
+    }
+    if let Some(folds) = rules.folds {
+        ui_config.fold_rules = folds;
+        changed.push("folds");
+    }
+    if let Some(keys) = rules.keys {
+        ui_config.keymap = keys;
+        changed.push("keys");
+    }
+    let mut status = if changed.is_empty() {
+        "Config reloaded (rules set by flags are kept)".to_string()
+    } else {
+        format!("Config reloaded: {}", changed.join(", "))
+    };
+    if !rules.restart_needed.is_empty() {
+        status.push_str(&format!(
+            "; restart for: {}",
+            rules.restart_needed.join(", ")
+        ));
+    }
+    app_state.set_status(status);
+}
+
+/// Quit, unless a capture or sequence would be cut short; then ask first
+fn request_quit(app_state: &mut AppState, ui_config: &UiConfig) {
+    let mut busy = Vec::new();
+    let files: Vec<_> = [&ui_config.rx_log_path, &ui_config.tx_log_path]
+        .into_iter()
+        .flatten()
+        .map(|p| p.display().to_string())
+        .collect();
+    if !files.is_empty() {
+        busy.push(format!("logging to {}", files.join(", ")));
+    }
+    if app_state.sequences_running > 0 {
+        busy.push("a sequence is running".to_string());
+    }
+    if busy.is_empty() {
+        app_state.quit();
+        return;
+    }
+    app_state.quit_pending = true;
+    app_state.set_status(format!(
+        "Quit? {}. Press Ctrl+C again to quit, any other key to stay (Ctrl+Q forces)",
+        busy.join("; ")
+    ));
+}
+
+/// "sermonizer: <port> @ <baud>" as the terminal (and tmux pane) title, so
+/// several sessions can be told apart
+fn set_terminal_title(ui_config: &UiConfig) {
+    let title = format!(
+        "sermonizer: {} @ {}",
+        ui_config.port_name, ui_config.port_settings.baud
+    );
+    let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::SetTitle(title));
+}
+
+/// Switch to the next decoder: the `--decoder` chain, each registered
+/// decoder on its own, then none
+#[cfg(feature = "decoders")]
+fn cycle_decoder(app_state: &mut AppState, ui_config: &UiConfig) {
+    let mut choices: Vec<String> = ui_config.decoder_spec.iter().cloned().collect();
+    for name in ui_config.decoders.names() {
+        if !choices.iter().any(|c| c == name) {
+            choices.push(name.to_string());
+        }
+    }
+    let Ok(mut active) = ui_config.decoder.lock() else {
+        return;
+    };
+    let next = match active.as_ref() {
+        None => choices.first(),
+        Some(p) => choices
+            .iter()
+            .position(|c| *c == p.spec)
+            .and_then(|i| choices.get(i + 1)),
+    };
+    *active = next.and_then(|spec| ui_config.decoders.pipeline(spec).ok());
+    let status = match active.as_ref() {
+        Some(p) => format!("Decoder: {}", p.spec),
+        None => "Decoder: off".to_string(),
+    };
+    drop(active);
+    app_state.set_status(status);
+}
+
+/// Step the tag filter through: all -> each tag -> all
+fn cycle_tag_filter(app_state: &mut AppState, ui_config: &UiConfig) {
+    let mut names = tag_names(&ui_config.tag_rules);
+    for name in app_state
+        .blocks
+        .names()
+        .into_iter()
+        .chain(app_state.channels.clone())
+    {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        app_state.set_status(
+            "No tag or block rules configured (--tag NAME=REGEX, --block NAME START END)"
+                .to_string(),
+        );
+        return;
+    }
+    let next = match &app_state.filter.tag {
+        None => Some(names[0].clone()),
+        Some(current) => names
+            .iter()
+            .position(|n| n == current)
+            .and_then(|i| names.get(i + 1))
+            .cloned(),
+    };
+    app_state.set_status(match &next {
+        Some(tag) => format!("Showing tag: {tag}"),
+        None => "Showing all lines".to_string(),
+    });
+    let filter = DisplayFilter {
+        tag: next,
+        ..app_state.filter.clone()
+    };
+    app_state.set_filter(filter);
+}
+
+fn arm_boot_profiler(app_state: &mut AppState, how: &str) {
+    if !app_state.boot_profiler.has_milestones() {
+        app_state.set_status("No boot milestones configured (--milestone NAME=REGEX)".to_string());
+        return;
+    }
+    app_state.boot_profiler.arm();
+    app_state.add_local_line(format!("-- [boot] profiler armed {how} --"));
+    app_state.set_status("Boot profiler armed".to_string());
+}
+
+/// Alt+T / Alt+U: run a reset sequence; the boot profiler starts over
+async fn trigger_reset(
+    kind: Option<ResetKind>,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) {
+    let Some(kind) = kind else {
+        app_state.set_status(match ui_config.reset {
+            Some(target) => format!("No bootloader sequence for {}", target.name()),
+            None => "No reset sequence configured (--reset esp32)".to_string(),
+        });
+        return;
+    };
+    if let Err(e) = reset::run(
+        port,
+        kind,
+        ui_config.reset_invert,
+        &mut ui_config.port_settings,
+    )
+    .await
+    {
+        app_state.set_status(format!("Reset failed: {e}"));
+        return;
+    }
+    for field in [SettingsField::Dtr, SettingsField::Rts] {
+        ui_config.origins.set(field.label(), Origin::Runtime);
+    }
+    record_marker(
+        app_state,
+        ui_config,
+        format!("-- [reset] {} --", kind.name()),
+    );
+    if app_state.boot_profiler.has_milestones() {
+        arm_boot_profiler(app_state, "by reset");
+    }
+}
+
+/// Run a power command in the background; the result comes back as
+/// [`UiMessage::PowerDone`]
+fn start_power_action(app_state: &mut AppState, ui_config: &UiConfig, action: PowerAction) {
+    if !ui_config.power.supports(action) {
+        app_state.set_status(format!(
+            "No {} command configured (--{})",
+            action.name(),
+            action.name()
+        ));
+        return;
+    }
+    app_state.add_local_line(format!("-- [power] {} --", action.name()));
+    app_state.set_status(format!("Running {}...", action.name()));
+
+    let power = ui_config.power.clone();
+    let ui_tx = ui_config.ui_tx.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = power.run(action).map_err(|e| format!("{e:#}"));
+        let _ = ui_tx.send(UiMessage::PowerDone(action, result));
+    });
+}
+
+fn on_power_done(app_state: &mut AppState, action: PowerAction, result: Result<String, String>) {
+    match result {
+        Ok(output) => {
+            for line in output.lines().filter(|l| !l.trim().is_empty()) {
+                app_state.add_local_line(format!("-- [power] {line} --"));
+            }
+            app_state.add_local_line(format!("-- [power] {} done --", action.name()));
+            app_state.set_status(format!("{} done", action.name()));
+            // The target boots from here
+            if action != PowerAction::Off && app_state.boot_profiler.has_milestones() {
+                arm_boot_profiler(app_state, &format!("by {}", action.name()));
+            }
+        }
+        Err(e) => {
+            app_state.emit(|| headless::Event::Error {
+                message: format!("{} failed: {e}", action.name()),
+            });
+            app_state.add_local_line(format!("-- [power] {} failed: {e} --", action.name()));
+            app_state.set_status(format!("{} failed: {e}", action.name()));
+        }
+    }
+}
+
+async fn handle_login_action(
+    action: LoginAction,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &UiConfig,
+) -> Result<()> {
+    match action {
+        LoginAction::SendUser(user) => {
+            app_state.add_local_line(format!("-- [login] sending user {user} --"));
+            send_line(app_state, port, ui_config, &user, ui_config.line_ending).await?;
+        }
+        LoginAction::SendPassword(password) => {
+            // Straight to the port: keep it out of the TX log and echo tracking
+            let mut bytes = password.into_bytes();
+            bytes.extend_from_slice(ui_config.line_ending.bytes());
+            write_bytes_async(port, &bytes).await?;
+            app_state.add_local_line("-- [login] password sent --".to_string());
+        }
+        LoginAction::AskConfirm => {
+            let user = app_state.auto_login.as_ref().map_or("", |a| a.user());
+            let status = format!("Login prompt: Ctrl+L to log in as {user}");
+            app_state.set_status(status);
+        }
+        LoginAction::Failed => {
+            app_state.add_local_line(
+                "-- [login] login failed, not retrying (Ctrl+L to try again) --".to_string(),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cycle_min_level(app_state: &mut AppState) {
+    let min_level = Level::cycle_min(app_state.filter.min_level);
+    app_state.set_status(match min_level {
+        Some(level) => format!("Minimum level: {}", level.name()),
+        None => "Showing all levels".to_string(),
+    });
+    let filter = DisplayFilter {
+        min_level,
+        ..app_state.filter.clone()
+    };
+    app_state.set_filter(filter);
+}
+
+/// Write the lines that pass the current filter to a file in the working
+/// directory
+fn export_visible_lines(app_state: &AppState) -> Result<PathBuf> {
+    let mut name = format!("sermonizer-export-{}", Local::now().format("%Y%m%d-%H%M%S"));
+    if let Some(tag) = &app_state.filter.tag {
+        name.push('-');
+        name.push_str(tag);
+    }
+    let path = PathBuf::from(format!("{name}.txt"));
+
+    let mut out = String::new();
+    for line in app_state.visible_lines().flat_map(OutputEntry::all_lines) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    std::fs::write(&path, out)?;
+    Ok(path)
+}
+
+/// Markdown issue report with the environment, adapter, settings and the
+/// last lines of output, secrets redacted
+fn write_bug_report(app_state: &AppState, ui_config: &UiConfig) -> Result<PathBuf> {
+    let settings = config_view::effective_config(app_state, ui_config)
+        .into_iter()
+        .map(|[label, value, origin]| format!("- {label}: {value} ({origin})"))
+        .collect();
+    let start = app_state
+        .output_lines
+        .len()
+        .saturating_sub(bug_report::REPORT_LINES);
+    let output: Vec<String> = app_state.output_lines[start..]
+        .iter()
+        .map(|entry| entry.text.clone())
+        .collect();
+    let report = bug_report::render(
+        &[
+            ("Adapter", ui_config.adapter_info.clone()),
+            ("Settings", settings),
+        ],
+        &output,
+    );
+    let secrets: Vec<&str> = app_state
+        .auto_login
+        .as_ref()
+        .and_then(|a| a.password())
+        .into_iter()
+        .collect();
+
+    let path = PathBuf::from(format!(
+        "sermonizer-report-{}.md",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::write(&path, bug_report::redact(&report, &secrets))?;
+    Ok(path)
+}
+
+/// Pasted text goes into the input line; every complete line in it is sent
+/// as if Enter had been pressed, the remainder stays for editing
+async fn handle_paste(
+    text: &str,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &UiConfig,
+) -> Result<()> {
+    if let Some(input) = app_state.search_input.as_mut() {
+        input.extend(text.chars().filter(|c| !c.is_control()));
+        app_state.needs_render = true;
+        return Ok(());
+    }
+
+    if ui_config.read_only {
+        app_state.set_status(READ_ONLY_STATUS.to_string());
+        return Ok(());
+    }
+
+    if app_state.passthrough {
+        // The device's editor gets the text as if typed, line breaks as Enter
+        let end = match ui_config.line_ending {
+            LineEnding::None => "\r",
+            end => std::str::from_utf8(end.bytes()).unwrap_or("\r"),
+        };
+        let text = text.replace("\r\n", "\n").replace(['\r', '\n'], end);
+        write_bytes_async(port, text.as_bytes()).await?;
+        if let Some(w) = &ui_config.tx_log {
+            write_log_chunk(w, text.as_bytes(), ui_config.log_ts);
+        }
+        return Ok(());
+    }
+
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        for c in line.chars().filter(|c| !c.is_control()) {
+            app_state.update_input(c);
+        }
+        if lines.peek().is_some() {
+            handle_enter_key(app_state, port, ui_config, ui_config.line_ending).await?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_search_key(key: crossterm::event::KeyEvent, app_state: &mut AppState) {
+    let Some(input) = app_state.search_input.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            app_state.search_input = None;
+        }
+        KeyCode::Enter => {
+            let query = input.clone();
+            if !query.is_empty() {
+                let status = run_search(app_state, &query);
+                app_state.set_status(status);
+            }
+        }
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Char(c) => {
+            input.push(c);
+        }
+        _ => return,
+    }
+    app_state.needs_render = true;
+}
+
+async fn handle_settings_key(
+    key: crossterm::event::KeyEvent,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) {
+    let Some(panel) = app_state.settings_panel.as_mut() else {
+        return;
+    };
+    let forward = match key.code {
+        KeyCode::Esc | KeyCode::F(2) => {
+            app_state.toggle_settings_panel();
+            return;
+        }
+        KeyCode::Up => {
+            panel.select_prev();
+            app_state.needs_render = true;
+            return;
+        }
+        KeyCode::Down => {
+            panel.select_next();
+            app_state.needs_render = true;
+            return;
+        }
+        KeyCode::Left => false,
+        KeyCode::Right | KeyCode::Enter | KeyCode::Char(' ') => true,
+        _ => return,
+    };
+
+    let field = panel.field();
+    apply_setting(field, forward, app_state, port, ui_config).await;
+}
+
+async fn handle_port_switcher_key(
+    key: crossterm::event::KeyEvent,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) {
+    let Some(switcher) = app_state.port_switcher.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => app_state.port_switcher = None,
+        KeyCode::Up => switcher.select_prev(),
+        KeyCode::Down => switcher.select_next(),
+        KeyCode::Char('r') => switcher.refresh(None),
+        KeyCode::Enter => {
+            let Some(name) = switcher.selected_port().map(|p| p.port_name.clone()) else {
+                return;
+            };
+            let ports = std::mem::take(&mut switcher.ports);
+            app_state.port_switcher = None;
+            switch_port(&name, &ports, app_state, port, ui_config).await;
+        }
+        _ => return,
+    }
+    app_state.needs_render = true;
+}
+
+/// Move the session to `port_name` with the current settings; the old
+/// port is closed and the scrollback stays, with a marker
+async fn switch_port(
+    port_name: &str,
+    ports: &[serialport::SerialPortInfo],
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) {
+    if port_name == ui_config.port_name && !app_state.disconnected {
+        app_state.set_status(format!("Already on {port_name}"));
+        return;
+    }
+    // Open before closing: on failure the session stays where it was
+    let (new_port, reopened) = match reconnect::open_port(
+        port_name,
+        &ui_config.port_settings,
+        ui_config.open_options,
+        OnBusy::Fail,
+    ) {
+        Ok(opened) => opened,
+        Err(e) => {
+            app_state.set_status(format!("{e:#}"));
+            return;
+        }
+    };
+    // Dropping the old handle closes it. A reconnect still waiting for the
+    // old port must not swap it back in.
+    let mut current = port.lock().await;
+    ui_config.port_generation.bump();
+    *current = new_port;
+    drop(current);
+    let old = std::mem::replace(&mut ui_config.port_name, reopened.port_name);
+    #[cfg(target_os = "linux")]
+    {
+        ui_config.port_fd = reopened.port_fd;
+        crate::line_errors::watch_line_errors(ui_config);
+    }
+    if ui_config.reconnect.is_some() {
+        ui_config.reconnect = Some(Reconnect::new(port_name, ports, ui_config.open_options));
+    }
+    ui_config.adapter_info = bug_report::adapter_info(port_name, ports, &ui_config.quirks);
+    app_state.disconnected = false;
+    record_marker(
+        app_state,
+        ui_config,
+        format!("-- switched port: {old} -> {port_name} --"),
+    );
+    app_state.set_status(format!("Now on {port_name}"));
+    if app_state.events.is_none() {
+        set_terminal_title(ui_config);
+    }
+}
+
+/// Alt+D / Alt+R: the modem control line a key toggles
+fn control_line_key(key: &crossterm::event::KeyEvent) -> Option<SettingsField> {
+    if !key.modifiers.contains(KeyModifiers::ALT) {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('d' | 'D') => Some(SettingsField::Dtr),
+        KeyCode::Char('r' | 'R') => Some(SettingsField::Rts),
+        _ => None,
+    }
+}
+
+/// Change a port setting from the F2 panel or a shortcut and report it
+async fn apply_setting(
+    field: SettingsField,
+    forward: bool,
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &mut UiConfig,
+) {
+    match change_setting(field, forward, ui_config, port).await {
+        Ok(()) => {
+            ui_config.origins.set(field.label(), Origin::Runtime);
+            if field == SettingsField::Baud {
+                app_state.line_rate.reset();
+                set_terminal_title(ui_config);
+            }
+            // Toggling DTR/RTS is how boards get reset from here
+            if matches!(field, SettingsField::Dtr | SettingsField::Rts)
+                && app_state.boot_profiler.has_milestones()
+            {
+                arm_boot_profiler(app_state, "by reset line change");
+            }
+            app_state.set_status(format!(
+                "{} set to {}",
+                field.label(),
+                field.value(ui_config)
+            ))
+        }
+        Err(e) => app_state.set_status(format!("Failed to set {}: {e}", field.label())),
+    }
+}
+
 async fn handle_enter_key(
     app_state: &mut AppState,
     port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
     ui_config: &UiConfig,
+    line_ending: LineEnding,
 ) -> Result<()> {
     let input = app_state.clear_input();
+    if let Some(answer) = calc::run(&input) {
+        match answer {
+            Ok(answer) => {
+                app_state.add_local_line(answer.text);
+                app_state.set_status(format!("{} (Ctrl+Y inserts it)", answer.value));
+                app_state.calc_result = Some(answer.value);
+            }
+            Err(e) => app_state.set_status(format!("{input}: {e}")),
+        }
+        return Ok(());
+    }
+    let (input, suppress_ending) = if ui_config.compat.trailing_backslash_override {
+        split_send_override(&input)
+    } else {
+        (input.as_str(), false)
+    };
+    let line_ending = if suppress_ending {
+        LineEnding::None
+    } else {
+        line_ending
+    };
+    send_line(app_state, port, ui_config, input, line_ending).await
+}
 
-    // Send the complete line to serial port
+/// Transmit `input` followed by `line_ending`, logging it and arming echo
+/// and latency tracking. Lines with `{sleep ..}`/`{wait ..}` directives run
+/// as a background sequence instead.
+async fn send_line(
+    app_state: &mut AppState,
+    port: &Arc<tokio::sync::Mutex<Box<dyn serialport::SerialPort + Send>>>,
+    ui_config: &UiConfig,
+    input: &str,
+    line_ending: LineEnding,
+) -> Result<()> {
+    if ui_config.read_only {
+        app_state.set_status(format!("Not sent (read-only): {input}"));
+        return Ok(());
+    }
+    match parse_template(input) {
+        Ok(Some(mut steps)) => {
+            push_line_ending(&mut steps, line_ending);
+            let job = ui_config.tx_queue.start("sequence", steps.len());
+            tokio::spawn(run_template(
+                steps,
+                job,
+                port.clone(),
+                ui_config.rx_lines.subscribe(),
+                ui_config.tx_log.clone(),
+                ui_config.log_ts,
+                ui_config.ui_tx.clone(),
+            ));
+            app_state.sequences_running += 1;
+            app_state.set_status("Running sequence...".to_string());
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(e) => {
+            app_state.set_status(format!("Bad sequence: {e}"));
+            return Ok(());
+        }
+    }
+
+    if let Some(detector) = app_state.line_editor.as_mut() {
+        detector.on_send(input);
+    }
+
+    // Send the complete line to serial port in one write, so a --tx-pipe
+    // filter sees it whole
+    let end = line_ending.bytes();
+    let line = [input.as_bytes(), end].concat();
+    if !line.is_empty() {
+        write_bytes_async(port, &line).await?;
+    }
     if !input.is_empty() {
-        write_bytes_async(port, input.as_bytes()).await?;
+        app_state.emit(|| headless::Event::Tx {
+            text: input.to_string(),
+        });
+        if ui_config.echo_mode != EchoMode::Show {
+            app_state.expect_echo(input);
+        }
+        if let Some(latency) = app_state.latency.as_mut() {
+            latency.on_send(input);
+        }
         if let Some(w) = &ui_config.tx_log
             && let Ok(mut lw) = w.lock()
         {
@@ -156,21 +1801,29 @@ async fn handle_enter_key(
         }
     }
 
-    // Send line ending
-    let end = ui_config.line_ending.bytes();
-    if !end.is_empty() {
-        write_bytes_async(port, end).await?;
-        if let Some(w) = &ui_config.tx_log
-            && let Ok(mut lw) = w.lock()
-        {
-            use std::io::Write;
-            if ui_config.log_ts && input.is_empty() {
-                let _ = write!(lw, "[{}] ", Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"));
-            }
-            let _ = lw.write_all(end);
-            let _ = lw.flush();
+    if !end.is_empty()
+        && let Some(w) = &ui_config.tx_log
+        && let Ok(mut lw) = w.lock()
+    {
+        use std::io::Write;
+        if ui_config.log_ts && input.is_empty() {
+            let _ = write!(lw, "[{}] ", Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"));
         }
+        let _ = lw.write_all(end);
+        let _ = lw.flush();
     }
 
     Ok(())
 }
+
+/// A trailing `\` suppresses the line ending for this one send; `\\`
+/// escapes it and sends a literal backslash followed by the line ending.
+fn split_send_override(input: &str) -> (&str, bool) {
+    if let Some(rest) = input.strip_suffix("\\\\") {
+        (&input[..rest.len() + 1], false)
+    } else if let Some(rest) = input.strip_suffix('\\') {
+        (rest, true)
+    } else {
+        (input, false)
+    }
+}
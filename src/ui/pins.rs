@@ -0,0 +1,75 @@
+use regex::Regex;
+
+/// Most lines the pinned region shows; the oldest manual pin makes room
+pub const MAX_PINNED: usize = 5;
+
+/// Lines kept visible above the scrolling output: the latest RX line
+/// matching each `--pin` pattern (version banner, current test name, ...)
+/// and lines pinned by hand with Ctrl+T
+#[derive(Default)]
+pub struct PinnedLines {
+    rules: Vec<(Regex, Option<String>)>,
+    manual: Vec<String>,
+}
+
+impl PinnedLines {
+    pub fn new(patterns: Vec<Regex>) -> Self {
+        PinnedLines {
+            rules: patterns.into_iter().map(|p| (p, None)).collect(),
+            manual: Vec::new(),
+        }
+    }
+
+    /// A complete RX line
+    pub fn on_line(&mut self, line: &str) {
+        let mut matched = false;
+        for (pattern, latest) in &mut self.rules {
+            if pattern.is_match(line) {
+                *latest = Some(line.to_string());
+                matched = true;
+            }
+        }
+        if matched {
+            self.trim_manual();
+        }
+    }
+
+    /// Pin `line`, or unpin it if it already is; returns whether it is
+    /// pinned now
+    pub fn toggle(&mut self, line: &str) -> bool {
+        if let Some(pos) = self.manual.iter().position(|l| l == line) {
+            self.manual.remove(pos);
+            return false;
+        }
+        self.manual.push(line.to_string());
+        self.trim_manual();
+        true
+    }
+
+    /// Drop the hand-pinned lines; pattern pins stay. Returns how many
+    /// there were
+    pub fn clear_manual(&mut self) -> usize {
+        std::mem::take(&mut self.manual).len()
+    }
+
+    fn trim_manual(&mut self) {
+        let room = MAX_PINNED.saturating_sub(self.matched().count());
+        if self.manual.len() > room {
+            self.manual.drain(..self.manual.len() - room);
+        }
+    }
+
+    fn matched(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .filter_map(|(_, latest)| latest.as_deref())
+    }
+
+    /// Pattern pins in `--pin` order, then manual pins oldest first
+    pub fn lines(&self) -> Vec<&str> {
+        self.matched()
+            .chain(self.manual.iter().map(String::as_str))
+            .take(MAX_PINNED)
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+/// One page of a [`Popup`]
+pub struct Page {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+/// A read-only overlay of text pages (F1 reference): Tab/←→ switch pages,
+/// ↑↓/PgUp/PgDn scroll, Esc closes. Drawn in the same frame as the menus.
+pub struct Popup {
+    pub title: String,
+    pub pages: Vec<Page>,
+    pub page: usize,
+    pub scroll: usize,
+}
+
+impl Popup {
+    pub fn new(title: impl Into<String>, pages: Vec<Page>) -> Self {
+        Popup {
+            title: title.into(),
+            pages,
+            page: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        self.pages
+            .get(self.page)
+            .map(|p| p.lines.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns false once the popup should close (Esc, or `close_key`
+    /// that opened it)
+    pub fn handle_key(&mut self, key: &KeyEvent, close_key: KeyCode) -> bool {
+        let count = self.pages.len().max(1);
+        let last = self.lines().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => return false,
+            code if code == close_key => return false,
+            KeyCode::Tab | KeyCode::Right => {
+                self.page = (self.page + 1) % count;
+                self.scroll = 0;
+            }
+            KeyCode::BackTab | KeyCode::Left => {
+                self.page = (self.page + count - 1) % count;
+                self.scroll = 0;
+            }
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = (self.scroll + 10).min(last),
+            KeyCode::Home => self.scroll = 0,
+            _ => {}
+        }
+        true
+    }
+}
@@ -0,0 +1,62 @@
+use serialport::SerialPortInfo;
+
+use crate::port_discovery::{describe_port, get_available_ports};
+use crate::quirks::QuirkDb;
+
+/// Alt+P: the ports there are right now, to move the session to another
+pub struct PortSwitcher {
+    pub ports: Vec<SerialPortInfo>,
+    pub selected: usize,
+}
+
+impl PortSwitcher {
+    /// Start on the port in use, if it is listed
+    pub fn new(current: &str) -> Self {
+        let mut switcher = PortSwitcher {
+            ports: Vec::new(),
+            selected: 0,
+        };
+        switcher.refresh(Some(current));
+        switcher
+    }
+
+    /// Enumerate again (ports came or went), keeping the selection on the
+    /// same port where it is still there
+    pub fn refresh(&mut self, keep: Option<&str>) {
+        let keep = keep
+            .map(str::to_string)
+            .or_else(|| self.selected_port().map(|p| p.port_name.clone()));
+        self.ports = get_available_ports().unwrap_or_default();
+        self.selected = keep
+            .and_then(|name| self.ports.iter().position(|p| p.port_name == name))
+            .unwrap_or(0)
+            .min(self.ports.len().saturating_sub(1));
+    }
+
+    pub fn selected_port(&self) -> Option<&SerialPortInfo> {
+        self.ports.get(self.selected)
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.ports.len().saturating_sub(1));
+    }
+
+    /// One line per port: name, USB details and known adapter
+    pub fn lines(&self, current: &str, quirks: &QuirkDb) -> Vec<String> {
+        self.ports
+            .iter()
+            .map(|p| {
+                let marker = if p.port_name == current { '*' } else { ' ' };
+                let mut line = format!("{marker} {}{}", p.port_name, describe_port(p));
+                if let Some(quirk) = quirks.for_port(p) {
+                    line.push_str(&format!(" - {}", quirk.name));
+                }
+                line
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+use super::popup::{Page, Popup};
+use crate::config::UiConfig;
+
+/// Names of the control characters 0x00..=0x1F
+const CONTROL_NAMES: [&str; 32] = [
+    "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "BEL", "BS", "HT", "LF", "VT", "FF", "CR",
+    "SO", "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+    "FS", "GS", "RS", "US",
+];
+
+/// F1: the ASCII table and the active decoder's frame layout
+pub fn reference_popup(ui_config: &UiConfig) -> Popup {
+    Popup::new(
+        "Reference",
+        vec![
+            Page {
+                name: "ASCII".to_string(),
+                lines: ascii_table(),
+            },
+            Page {
+                name: "Decoder".to_string(),
+                lines: decoder_page(ui_config),
+            },
+        ],
+    )
+}
+
+/// dec, hex, character (and Ctrl key for controls), four columns of 32
+fn ascii_table() -> Vec<String> {
+    let cell = |code: u8| {
+        let (name, key) = match code {
+            0..=31 => (
+                CONTROL_NAMES[code as usize].to_string(),
+                format!("^{}", (code + 64) as char),
+            ),
+            32 => ("SP".to_string(), String::new()),
+            127 => ("DEL".to_string(), "^?".to_string()),
+            _ => ((code as char).to_string(), String::new()),
+        };
+        format!("{code:>3} {code:02X} {name:<3} {key:<2}")
+    };
+    let mut lines = vec!["Dec Hx Chr      ".repeat(4).trim_end().to_string()];
+    for row in 0..32u8 {
+        let cells: Vec<String> = (0..4u8).map(|col| cell(col * 32 + row)).collect();
+        lines.push(cells.join("   ").trim_end().to_string());
+    }
+    lines
+}
+
+#[cfg(feature = "decoders")]
+fn decoder_page(ui_config: &UiConfig) -> Vec<String> {
+    let active = ui_config
+        .decoder
+        .lock()
+        .ok()
+        .and_then(|p| p.as_ref().map(|p| p.layout()));
+    match active {
+        Some(layout) => layout,
+        None => {
+            let mut lines = vec!["No decoder active (F11 cycles through them):".to_string()];
+            lines.extend(
+                ui_config
+                    .decoders
+                    .describe()
+                    .iter()
+                    .map(|d| format!("  {d}")),
+            );
+            lines
+        }
+    }
+}
+
+#[cfg(not(feature = "decoders"))]
+fn decoder_page(_ui_config: &UiConfig) -> Vec<String> {
+    vec!["Built without the `decoders` feature".to_string()]
+}
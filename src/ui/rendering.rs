@@ -1,61 +1,657 @@
-use super::app_state::AppState;
+use super::app_state::{AppState, DisplayFilter, OutputEntry};
+use super::config_view::effective_config;
+use super::popup::Popup;
+use super::port_switcher::PortSwitcher;
+use super::settings::{SettingsField, SettingsPanel, on_off};
+use super::tabs::TabBar;
+use crate::config::UiConfig;
+use crate::levels::Level;
+use crate::line_editor::render_edit_line;
+use crate::parity::{MARK_CLOSE, MARK_OPEN};
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    symbols::{self, border},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs},
 };
+use serialport::FlowControl;
+use std::borrow::Cow;
+use std::time::Duration;
 
-pub fn draw_ui(f: &mut Frame, app_state: &mut AppState) {
+/// What the UI draws borders, arrows and markers with
+pub struct Glyphs {
+    border: border::Set,
+    divider: &'static str,
+    up_down: &'static str,
+    left_right: &'static str,
+    echo: &'static str,
+    fold: &'static str,
+    activity: &'static str,
+    stopwatch: &'static str,
+    /// In place of the reader's `⟦ ⟧` around bytes with parity errors
+    marks: Option<(&'static str, &'static str)>,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    border: border::PLAIN,
+    divider: symbols::line::VERTICAL,
+    up_down: "↑↓",
+    left_right: "←→",
+    echo: "» ",
+    fold: "│ ",
+    activity: " •",
+    stopwatch: "⏱ ",
+    marks: None,
+};
+
+/// `--ascii-ui`, for consoles that can't show the above
+const ASCII: Glyphs = Glyphs {
+    border: border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    },
+    divider: "|",
+    up_down: "Up/Down",
+    left_right: "Left/Right",
+    echo: "> ",
+    fold: "| ",
+    activity: " *",
+    stopwatch: "",
+    marks: Some(("[", "]")),
+};
+
+impl Glyphs {
+    fn of(ui_config: &UiConfig) -> &'static Glyphs {
+        if ui_config.ascii_ui { &ASCII } else { &UNICODE }
+    }
+
+    /// A bordered block
+    fn block(&self) -> Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(self.border)
+    }
+}
+
+/// Whether the terminal can be expected to show Unicode: a UTF-8 locale
+/// (the first of `LC_ALL`, `LC_CTYPE`, `LANG` that is set) on anything
+/// but a bare VT or dumb terminal. Windows consoles always can.
+pub fn unicode_terminal() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    let term = std::env::var("TERM").unwrap_or_default();
+    if matches!(
+        term.as_str(),
+        "dumb" | "vt52" | "vt100" | "vt102" | "vt220" | "ansi"
+    ) {
+        return false;
+    }
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// `text` as spans with bytes tagged for parity errors (`⟦41⟧`) highlighted
+fn push_marked<'a>(spans: &mut Vec<Span<'a>>, text: &'a str, style: Style, g: &Glyphs) {
+    let mut rest = text;
+    while let Some(open) = rest.find(MARK_OPEN)
+        && let Some(len) = rest[open..].find(MARK_CLOSE)
+    {
+        let close = open + len + MARK_CLOSE.len_utf8();
+        if open > 0 {
+            spans.push(Span::styled(&rest[..open], style));
+        }
+        let marked: Cow<str> = match g.marks {
+            Some((left, right)) => {
+                let byte = &rest[open + MARK_OPEN.len_utf8()..open + len];
+                Cow::Owned(format!("{left}{byte}{right}"))
+            }
+            None => Cow::Borrowed(&rest[open..close]),
+        };
+        spans.push(Span::styled(
+            marked,
+            Style::default().fg(Color::White).bg(Color::Magenta),
+        ));
+        rest = &rest[close..];
+    }
+    spans.push(Span::styled(rest, style));
+}
+
+/// Port label colors in the merged view, by tab
+const TAB_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::LightRed,
+];
+
+/// The output pane's lines, as the display filter lets them through
+fn output_items<'a>(
+    lines: &'a [OutputEntry],
+    filter: &DisplayFilter,
+    g: &Glyphs,
+) -> Vec<ListItem<'a>> {
+    lines
+        .iter()
+        .filter(|entry| entry.is_shown(filter))
+        .map(|entry| {
+            let text_style = match entry.level {
+                Some(Level::Error) => Style::default().fg(Color::Red),
+                Some(Level::Warn) => Style::default().fg(Color::Yellow),
+                _ => Style::default(),
+            };
+            let mut spans = Vec::new();
+            if let Some((tab, label)) = &entry.source {
+                spans.push(Span::styled(
+                    format!("{label} "),
+                    Style::default().fg(TAB_COLORS[tab % TAB_COLORS.len()]),
+                ));
+            }
+            if entry.echo {
+                spans.push(Span::styled(
+                    format!("{}{}", g.echo, entry.text),
+                    Style::default().fg(Color::DarkGray),
+                ));
+                return ListItem::new(Line::from(spans));
+            }
+            if !entry.tags.is_empty() {
+                spans.push(Span::styled(
+                    format!("[{}] ", entry.tags.join(",")),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            match &entry.expected {
+                Some(expected) => {
+                    spans.push(Span::styled(
+                        entry.text.as_str(),
+                        Style::default().fg(Color::White).bg(Color::Red),
+                    ));
+                    spans.push(Span::styled(
+                        format!("  (expected: {expected})"),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                None => push_marked(&mut spans, &entry.text, text_style, g),
+            }
+            if entry.folded.is_empty() {
+                return ListItem::new(Line::from(spans));
+            }
+            let fold_style = Style::default().fg(Color::DarkGray);
+            if !entry.expanded {
+                spans.push(Span::styled(
+                    format!("  [+{} folded]", entry.folded.len()),
+                    fold_style,
+                ));
+                return ListItem::new(Line::from(spans));
+            }
+            let mut lines = vec![Line::from(spans)];
+            lines.extend(entry.folded.iter().map(|folded| {
+                Line::from(vec![
+                    Span::styled(g.fold, fold_style),
+                    Span::styled(folded.as_str(), text_style),
+                ])
+            }));
+            ListItem::new(lines)
+        })
+        .collect()
+}
+
+pub fn draw_ui(
+    f: &mut Frame,
+    app_state: &mut AppState,
+    ui_config: &UiConfig,
+    tab_bar: &TabBar,
+    merged: Option<&mut AppState>,
+) {
+    let g = Glyphs::of(ui_config);
+    let pinned = app_state.pinned.lines();
+    let pinned_height = if pinned.is_empty() {
+        0
+    } else {
+        pinned.len() as u16 + 2
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(1),    // Output area (takes most space)
             Constraint::Length(3), // Input area (fixed height)
         ])
-        .split(f.area());
-
-    // Serial monitor output - optimize by avoiding allocations where possible
-    let output_items: Vec<ListItem> = app_state
-        .output_lines
-        .iter()
-        .map(|line| ListItem::new(line.as_str()))
-        .collect();
+        .split(draw_tab_bar(f, tab_bar, g));
+    let [pinned_area, output_area] =
+        Layout::vertical([Constraint::Length(pinned_height), Constraint::Min(1)]).areas(chunks[0]);
+    if !pinned.is_empty() {
+        let pinned_list = List::new(pinned.into_iter().map(ListItem::new))
+            .block(g.block().title("Pinned (Ctrl+T pin/unpin)"))
+            .style(Style::default().fg(Color::Cyan));
+        f.render_widget(pinned_list, pinned_area);
+    }
+    let pipe_height = match &ui_config.rx_pipe_pane {
+        Some(_) => (output_area.height / 3).max(3),
+        None => 0,
+    };
+    let [output_area, pipe_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(pipe_height)]).areas(output_area);
+    if let Some(cmd) = &ui_config.rx_pipe_pane {
+        draw_pipe_pane(f, app_state, cmd, pipe_area, g);
+    }
 
-    let title = if app_state.auto_scroll {
-        "Serial Monitor (Auto-scroll ON - ↑↓/PgUp/PgDn to scroll, Ctrl+A to re-enable auto-scroll)"
-    } else {
-        "Serial Monitor (Auto-scroll OFF - ↑↓/PgUp/PgDn to scroll, Ctrl+A to re-enable auto-scroll)"
+    let settings = &ui_config.port_settings;
+    let mut framing = format!("{} {}", settings.baud, settings.framing());
+    if settings.flow_control != FlowControl::None {
+        framing.push_str(&format!(" {}", settings.flow_label()));
+    }
+    framing.push_str(&format!(
+        " DTR {} RTS {}",
+        on_off(settings.dtr),
+        on_off(settings.rts)
+    ));
+    let (pane, auto_scroll) = match &merged {
+        Some(view) => ("Merged view (Alt+M for the tab)", view.auto_scroll),
+        None => ("Serial Monitor", app_state.auto_scroll),
     };
+    let mut title = format!(
+        "{pane} {framing} (Auto-scroll {} - {}/PgUp/PgDn to scroll, Ctrl+A to re-enable auto-scroll)",
+        if auto_scroll { "ON" } else { "OFF" },
+        g.up_down
+    );
+    if merged.is_none() && app_state.filter.is_active() {
+        title.push_str(&format!(" [{}]", app_state.filter.describe()));
+    }
+    if !app_state.stopwatch.is_idle() {
+        title.push_str(&format!(
+            " [{}{}]",
+            g.stopwatch,
+            app_state.stopwatch.describe()
+        ));
+    }
+    if !app_state.line_errors.is_empty() {
+        title.push_str(&format!(
+            " [line errors: {}]",
+            app_state.line_errors.describe()
+        ));
+    }
 
-    let output_list = List::new(output_items)
-        .block(Block::default().borders(Borders::ALL).title(title))
+    let mut title = Line::from(title);
+    if app_state.disconnected {
+        title.spans.insert(
+            0,
+            Span::styled(
+                " DISCONNECTED, waiting for port... ",
+                Style::default().fg(Color::White).bg(Color::Red),
+            ),
+        );
+    }
+
+    // Serial monitor output: the active tab's, or every tab's merged
+    let view = match merged {
+        Some(view) => view,
+        None => &mut *app_state,
+    };
+    let output_list = List::new(output_items(&view.output_lines, &view.filter, g))
+        .block(g.block().title(title))
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().fg(Color::Black).bg(Color::White));
 
     // Handle auto-scrolling vs manual scrolling
-    if app_state.auto_scroll {
+    if view.terminal_view {
+        draw_terminal(f, view, output_area, g);
+    } else if view.auto_scroll {
         // Use the persistent auto-scroll state that stays positioned at bottom
-        f.render_stateful_widget(output_list, chunks[0], &mut app_state.auto_scroll_state);
+        f.render_stateful_widget(output_list, output_area, &mut view.auto_scroll_state);
     } else {
         // Manual scrolling mode - use the user's scroll position
-        f.render_stateful_widget(output_list, chunks[0], &mut app_state.list_state);
+        f.render_stateful_widget(output_list, output_area, &mut view.list_state);
+    }
+
+    if !ui_config.tx_queue.is_empty() {
+        draw_tx_queue(f, output_area, ui_config);
+    }
+
+    // Input line (doubles as the search prompt). In passthrough mode it
+    // shows the device's line editor: its prompt and echo of the keys
+    let (device_line, device_cursor) = render_edit_line(&app_state.partial_line);
+    let (input_text, input_label, input_color) = match &app_state.search_input {
+        Some(query) => (
+            query.as_str(),
+            "Search (text, or x DE AD ?? EF for bytes; Enter find/next, Esc close)",
+            Color::Cyan,
+        ),
+        None if ui_config.read_only => (
+            "",
+            "Read-only (--read-only): nothing is sent to the port",
+            Color::DarkGray,
+        ),
+        None if app_state.passthrough => (
+            device_line.as_str(),
+            "Device line editor (keys go to the device, Ctrl+] for a local key, F12 to edit locally)",
+            Color::Green,
+        ),
+        None => (
+            app_state.input_line.as_str(),
+            "Input (Press Enter to send, Ctrl+C or Esc to exit)",
+            Color::Yellow,
+        ),
+    };
+    let mut input_title = match &app_state.status {
+        Some(status) => format!("{input_label} - {status}"),
+        None => input_label.to_string(),
+    };
+    if app_state.macros.is_recording() {
+        input_title.insert_str(0, "[REC] ");
+    }
+    let input_paragraph = Paragraph::new(input_text)
+        .block(g.block().title(input_title))
+        .style(Style::default().fg(input_color));
+
+    f.render_widget(input_paragraph, chunks[1]);
+
+    // Set cursor position in input field (display width, not bytes, so
+    // accented and wide characters line up)
+    let input_width = if app_state.passthrough && app_state.search_input.is_none() {
+        let before_cursor: String = device_line.chars().take(device_cursor).collect();
+        Span::raw(before_cursor).width() as u16
+    } else {
+        Span::raw(input_text).width() as u16
+    };
+    f.set_cursor_position((chunks[1].x + input_width + 1, chunks[1].y + 1));
+
+    if let Some(panel) = &app_state.settings_panel {
+        draw_settings_panel(f, panel, ui_config);
+    }
+    if let Some(switcher) = &app_state.port_switcher {
+        draw_port_switcher(f, switcher, ui_config);
     }
+    if app_state.config_view {
+        draw_config_view(f, app_state, ui_config);
+    }
+    if let Some(popup) = &app_state.popup {
+        draw_reference(f, popup, g);
+    }
+}
+
+/// One line of port tabs above everything else when there are several;
+/// returns the area left for the session
+fn draw_tab_bar(f: &mut Frame, tab_bar: &TabBar, g: &Glyphs) -> Rect {
+    let tabs = &tab_bar.titles;
+    if tabs.is_empty() {
+        return f.area();
+    }
+    let [bar, rest] = Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(f.area());
+    let bar = if tab_bar.broadcast {
+        const LABEL: &str = " BROADCAST ";
+        let [label, bar] =
+            Layout::horizontal([Constraint::Length(LABEL.len() as u16), Constraint::Min(1)])
+                .areas(bar);
+        let style = Style::default().fg(Color::White).bg(Color::Red);
+        f.render_widget(Paragraph::new(LABEL).style(style), label);
+        bar
+    } else {
+        bar
+    };
+    let titles: Vec<Line> = tabs
+        .iter()
+        .map(|tab| {
+            let style = if tab.disconnected {
+                Style::default().fg(Color::Red)
+            } else if tab.activity {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            let mut label = tab.label.clone();
+            if tab.activity {
+                label.push_str(g.activity);
+            }
+            Line::styled(label, style)
+        })
+        .collect();
+    let selected = tabs.iter().position(|tab| tab.active);
+    let bar_widget = Tabs::new(titles)
+        .select(selected)
+        .divider(g.divider)
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+    f.render_widget(bar_widget, bar);
+    rest
+}
+
+/// The VT100 screen in place of the line list, sized to the pane
+fn draw_terminal(f: &mut Frame, app_state: &mut AppState, area: Rect, g: &Glyphs) {
+    let block = g.block();
+    let inner = block.inner(area);
+    app_state
+        .screen
+        .resize(inner.height as usize, inner.width as usize);
+    let (rows, cols) = app_state.screen.size();
+    let title = format!("Terminal {cols}x{rows} (Alt+V line view)");
+    let screen =
+        Paragraph::new(app_state.screen.lines(app_state.passthrough)).block(block.title(title));
+    f.render_widget(screen, area);
+}
+
+/// Centered rectangle of the given size, clamped to `area`
+/// `--rx-pipe-pane`: the newest lines the pipe command printed
+fn draw_pipe_pane(f: &mut Frame, app_state: &AppState, cmd: &str, area: Rect, g: &Glyphs) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let skip = app_state.pipe_lines.len().saturating_sub(rows);
+    let items = app_state
+        .pipe_lines
+        .iter()
+        .skip(skip)
+        .map(|line| ListItem::new(line.as_str()));
+    let list = List::new(items)
+        .block(g.block().title(format!("RX pipe: {cmd}")))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(list, area);
+}
+
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+fn draw_config_view(f: &mut Frame, app_state: &AppState, ui_config: &UiConfig) {
+    let rows = effective_config(app_state, ui_config);
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|[label, value, origin]| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{label:<20}{value:<28}")),
+                Span::styled(origin.as_str(), Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+
+    let g = Glyphs::of(ui_config);
+    draw_popup(f, g, "Effective configuration (Esc close)", items, 76, None);
+}
 
-    // Input line
-    let input_paragraph = Paragraph::new(app_state.input_line.as_str())
+/// `1:05`, or `1:02:05` past an hour
+fn format_eta(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// `12.3 kB/s`
+fn format_rate(bytes_per_sec: f64) -> String {
+    match bytes_per_sec {
+        r if r >= 1e6 => format!("{:.1} MB/s", r / 1e6),
+        r if r >= 1e3 => format!("{:.1} kB/s", r / 1e3),
+        r => format!("{r:.0} B/s"),
+    }
+}
+
+/// Background sends in the top right corner of the output area: a bar,
+/// the steps done, throughput and time left for each
+fn draw_tx_queue(f: &mut Frame, output_area: Rect, ui_config: &UiConfig) {
+    const BAR: usize = 12;
+    let (full, empty) = if ui_config.ascii_ui {
+        ('#', '-')
+    } else {
+        ('█', '░')
+    };
+    let items: Vec<ListItem> = ui_config
+        .tx_queue
+        .jobs()
+        .iter()
+        .map(|job| {
+            let (done, total) = job.progress();
+            let filled = (done * BAR).checked_div(total).unwrap_or(0).min(BAR);
+            let bar: String = std::iter::repeat_n(full, filled)
+                .chain(std::iter::repeat_n(empty, BAR - filled))
+                .collect();
+            let state = if job.is_paused() {
+                "paused".to_string()
+            } else {
+                match job.eta() {
+                    Some(eta) => format!("ETA {}", format_eta(eta)),
+                    None => String::new(),
+                }
+            };
+            ListItem::new(format!(
+                "{:<16} {bar} {:>9} {:>10} {state}",
+                job.label,
+                format!("{done}/{total}"),
+                format_rate(job.throughput()),
+            ))
+        })
+        .collect();
+    let width = 68.min(output_area.width);
+    let height = (items.len() as u16 + 2).min(output_area.height);
+    let area = Rect {
+        x: output_area.right().saturating_sub(width + 1),
+        y: output_area.y + 1,
+        width,
+        height,
+    };
+    let list = List::new(items)
         .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Input (Press Enter to send, Ctrl+C or Esc to exit)"),
+            Glyphs::of(ui_config)
+                .block()
+                .title("TX queue (Ctrl+X cancel, Alt+X pause/resume)"),
         )
-        .style(Style::default().fg(Color::Yellow));
+        .style(Style::default().fg(Color::Magenta));
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
 
-    f.render_widget(input_paragraph, chunks[1]);
+fn draw_port_switcher(f: &mut Frame, switcher: &PortSwitcher, ui_config: &UiConfig) {
+    let mut items: Vec<ListItem> = switcher
+        .lines(&ui_config.port_name, &ui_config.quirks)
+        .into_iter()
+        .map(ListItem::new)
+        .collect();
+    if items.is_empty() {
+        items.push(ListItem::new("No serial ports found"));
+    }
 
-    // Set cursor position in input field
-    f.set_cursor_position((
-        chunks[1].x + app_state.input_line.len() as u16 + 1,
-        chunks[1].y + 1,
-    ));
+    let g = Glyphs::of(ui_config);
+    draw_popup(
+        f,
+        g,
+        &format!(
+            "Switch port ({} select, Enter switch, r refresh, Esc close)",
+            g.up_down
+        ),
+        items,
+        70,
+        Some(switcher.selected),
+    );
+}
+
+fn draw_settings_panel(f: &mut Frame, panel: &SettingsPanel, ui_config: &UiConfig) {
+    let items: Vec<ListItem> = SettingsField::ALL
+        .iter()
+        .map(|field| ListItem::new(format!("{:<14}{}", field.label(), field.value(ui_config))))
+        .collect();
+
+    let g = Glyphs::of(ui_config);
+    draw_popup(
+        f,
+        g,
+        &format!(
+            "Port settings ({} select, {} change, Esc close)",
+            g.up_down, g.left_right
+        ),
+        items,
+        50,
+        Some(panel.selected),
+    );
+}
+
+/// F1 reference: the current page, scrolled, with the page names in the
+/// title
+fn draw_reference(f: &mut Frame, popup: &Popup, g: &Glyphs) {
+    let pages: Vec<String> = popup
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            if i == popup.page {
+                format!("[{}]", page.name)
+            } else {
+                page.name.clone()
+            }
+        })
+        .collect();
+    let title = format!(
+        "{} {} (Tab page, {} scroll, Esc close)",
+        popup.title,
+        pages.join(" "),
+        g.up_down
+    );
+    let items: Vec<ListItem> = popup
+        .lines()
+        .iter()
+        .skip(popup.scroll)
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+    draw_popup(f, g, &title, items, 72, None);
+}
+
+/// Overlay shared by the menus, the configuration view and the reference:
+/// a bordered list centered over everything, as tall as its items allow,
+/// with the `selected` item highlighted
+fn draw_popup(
+    f: &mut Frame,
+    g: &Glyphs,
+    title: &str,
+    items: Vec<ListItem>,
+    width: u16,
+    selected: Option<usize>,
+) {
+    let height = (items.len() as u16 + 2).min(f.area().height);
+    let area = centered_rect(f.area(), width, height);
+    let list = List::new(items)
+        .block(g.block().title(title))
+        .style(Style::default().fg(Color::White))
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+
+    let mut state = ListState::default().with_selected(selected);
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
 }
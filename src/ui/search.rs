@@ -0,0 +1,153 @@
+use super::app_state::AppState;
+use std::collections::VecDeque;
+
+/// What the user typed at the search prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchQuery {
+    /// Substring of the rendered output lines
+    Text(String),
+    /// Byte pattern over the raw RX stream; `None` is a `??` wildcard
+    Bytes(Vec<Option<u8>>),
+}
+
+/// `x DE AD ?? EF` (or `/x ...`) is a byte pattern, anything else is text
+pub fn parse_query(query: &str) -> Result<SearchQuery, String> {
+    let trimmed = query.strip_prefix('/').unwrap_or(query);
+    let Some(pattern) = trimmed.strip_prefix("x ") else {
+        return Ok(SearchQuery::Text(query.to_string()));
+    };
+
+    let mut bytes = Vec::new();
+    for token in pattern.split_whitespace() {
+        if token == "??" {
+            bytes.push(None);
+            continue;
+        }
+        let byte = u8::from_str_radix(token, 16)
+            .ok()
+            .filter(|_| token.len() == 2)
+            .ok_or_else(|| format!("'{token}' is not a hex byte or ??"))?;
+        bytes.push(Some(byte));
+    }
+    if bytes.is_empty() {
+        return Err("empty byte pattern".to_string());
+    }
+    Ok(SearchQuery::Bytes(bytes))
+}
+
+/// Index of the first match of `pattern` in `haystack` at or after `from`
+pub fn find_bytes(haystack: &VecDeque<u8>, pattern: &[Option<u8>], from: usize) -> Option<usize> {
+    if pattern.len() > haystack.len() {
+        return None;
+    }
+    (from..=haystack.len() - pattern.len()).find(|&start| {
+        pattern
+            .iter()
+            .enumerate()
+            .all(|(i, p)| p.is_none_or(|b| haystack[start + i] == b))
+    })
+}
+
+/// Session offset at the start of a hexdump row (`[ts] 0000001F  ...`)
+pub fn hexdump_row_offset(line: &str) -> Option<u64> {
+    let line = match line.strip_prefix('[') {
+        Some(rest) => rest.split_once("] ")?.1,
+        None => line,
+    };
+    let token = line.get(..8)?;
+    u64::from_str_radix(token, 16).ok()
+}
+
+/// Where the previous search stopped, so pressing Enter again finds the
+/// next match
+#[derive(Debug, Clone)]
+pub struct LastSearch {
+    pub query: String,
+    /// Visible line index (text) or absolute stream offset (bytes)
+    pub position: u64,
+}
+
+/// Run `query` against the output and move the selection to the match.
+/// Returns a status message.
+pub fn run_search(app_state: &mut AppState, query: &str) -> String {
+    let parsed = match parse_query(query) {
+        Ok(q) => q,
+        Err(e) => return format!("Bad search: {e}"),
+    };
+    let previous = app_state
+        .last_search
+        .as_ref()
+        .filter(|last| last.query == query)
+        .map(|last| last.position);
+
+    match parsed {
+        SearchQuery::Text(text) => search_text(app_state, query, &text, previous),
+        SearchQuery::Bytes(pattern) => search_bytes(app_state, query, &pattern, previous),
+    }
+}
+
+/// Search backwards (towards older lines) from the selection, wrapping
+fn search_text(app_state: &mut AppState, query: &str, text: &str, previous: Option<u64>) -> String {
+    let lines: Vec<&str> = app_state.visible_lines().map(|e| e.text.as_str()).collect();
+    if lines.is_empty() {
+        return "No output to search".to_string();
+    }
+    let start = match previous {
+        Some(p) => p as usize,
+        None => app_state.list_state.selected().unwrap_or(lines.len()),
+    };
+
+    let found = (0..lines.len())
+        .map(|i| (start + lines.len() * 2 - 1 - i) % lines.len())
+        .find(|&idx| lines[idx].contains(text));
+
+    match found {
+        Some(idx) => {
+            app_state.select_line(idx);
+            app_state.last_search = Some(LastSearch {
+                query: query.to_string(),
+                position: idx as u64,
+            });
+            format!("Found '{text}' on line {}", idx + 1)
+        }
+        None => format!("'{text}' not found"),
+    }
+}
+
+/// Search forwards through the retained raw RX bytes, wrapping
+fn search_bytes(
+    app_state: &mut AppState,
+    query: &str,
+    pattern: &[Option<u8>],
+    previous: Option<u64>,
+) -> String {
+    let base = app_state.raw_base_offset;
+    let from = previous
+        .map(|p| (p + 1).saturating_sub(base) as usize)
+        .unwrap_or(0);
+    let found = find_bytes(&app_state.raw_rx, pattern, from)
+        .or_else(|| find_bytes(&app_state.raw_rx, pattern, 0));
+
+    let Some(idx) = found else {
+        return "Byte pattern not found in retained RX".to_string();
+    };
+    let offset = base + idx as u64;
+    app_state.last_search = Some(LastSearch {
+        query: query.to_string(),
+        position: offset,
+    });
+
+    // In the hexdump view, jump to the row holding the match
+    let row = app_state
+        .visible_lines()
+        .enumerate()
+        .filter_map(|(i, e)| hexdump_row_offset(&e.text).map(|o| (i, o)))
+        .take_while(|&(_, o)| o <= offset)
+        .last()
+        .map(|(i, _)| i);
+    if let Some(row) = row {
+        app_state.select_line(row);
+    }
+
+    format!("Byte pattern at offset 0x{offset:08X}")
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::config::UiConfig;
+
+/// Parity and mark/space as the settings panel cycles through them; the
+/// last two only where stick parity can be set (Linux)
+const PARITIES: [(Parity, bool); 5] = [
+    (Parity::None, false),
+    (Parity::Odd, false),
+    (Parity::Even, false),
+    (Parity::Odd, true),
+    (Parity::Even, true),
+];
+
+/// Baud rates offered when cycling in the settings panel
+const COMMON_BAUDS: &[u32] = &[
+    300, 1200, 2400, 4800, 9600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600,
+    1_000_000, 2_000_000,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SettingsField {
+    Baud,
+    DataBits,
+    Parity,
+    StopBits,
+    FlowControl,
+    LineEnding,
+    RxLineEnding,
+    Dtr,
+    Rts,
+}
+
+impl SettingsField {
+    pub const ALL: [SettingsField; 9] = [
+        SettingsField::Baud,
+        SettingsField::DataBits,
+        SettingsField::Parity,
+        SettingsField::StopBits,
+        SettingsField::FlowControl,
+        SettingsField::LineEnding,
+        SettingsField::RxLineEnding,
+        SettingsField::Dtr,
+        SettingsField::Rts,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsField::Baud => "Baud rate",
+            SettingsField::DataBits => "Data bits",
+            SettingsField::Parity => "Parity",
+            SettingsField::StopBits => "Stop bits",
+            SettingsField::FlowControl => "Flow control",
+            SettingsField::LineEnding => "TX line ending",
+            SettingsField::RxLineEnding => "RX line ending",
+            SettingsField::Dtr => "DTR",
+            SettingsField::Rts => "RTS",
+        }
+    }
+
+    pub fn value(self, ui_config: &UiConfig) -> String {
+        let s = &ui_config.port_settings;
+        match self {
+            SettingsField::Baud => s.baud.to_string(),
+            SettingsField::DataBits => s.data_bits.to_string(),
+            SettingsField::Parity => s.parity_label(),
+            SettingsField::StopBits => s.stop_bits.to_string(),
+            SettingsField::FlowControl => s.flow_control.to_string(),
+            SettingsField::LineEnding => ui_config.line_ending.describe().to_string(),
+            SettingsField::RxLineEnding => ui_config.rx_line_ending.describe().to_string(),
+            SettingsField::Dtr => on_off(s.dtr).to_string(),
+            SettingsField::Rts => on_off(s.rts).to_string(),
+        }
+    }
+}
+
+pub fn on_off(level: bool) -> &'static str {
+    if level { "on" } else { "off" }
+}
+
+/// Popup state: which row is highlighted
+#[derive(Default)]
+pub struct SettingsPanel {
+    pub selected: usize,
+}
+
+impl SettingsPanel {
+    pub fn field(&self) -> SettingsField {
+        SettingsField::ALL[self.selected]
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(SettingsField::ALL.len() - 1);
+    }
+}
+
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, forward: bool) -> T {
+    let idx = options.iter().position(|o| *o == current).unwrap_or(0);
+    let next = if forward {
+        (idx + 1) % options.len()
+    } else {
+        (idx + options.len() - 1) % options.len()
+    };
+    options[next]
+}
+
+fn cycle_baud(current: u32, forward: bool) -> u32 {
+    if forward {
+        COMMON_BAUDS
+            .iter()
+            .copied()
+            .find(|&b| b > current)
+            .unwrap_or(COMMON_BAUDS[0])
+    } else {
+        COMMON_BAUDS
+            .iter()
+            .rev()
+            .copied()
+            .find(|&b| b < current)
+            .unwrap_or(COMMON_BAUDS[COMMON_BAUDS.len() - 1])
+    }
+}
+
+/// Step `field` to its next/previous value and apply it to the open port
+/// right away. `ui_config` is only updated once the port accepted it.
+pub async fn change_setting(
+    field: SettingsField,
+    forward: bool,
+    ui_config: &mut UiConfig,
+    port: &Arc<Mutex<Box<dyn SerialPort + Send>>>,
+) -> Result<()> {
+    let mut s = ui_config.port_settings;
+    match field {
+        SettingsField::Baud => s.baud = cycle_baud(s.baud, forward),
+        SettingsField::DataBits => {
+            s.data_bits = cycle(
+                &[
+                    DataBits::Five,
+                    DataBits::Six,
+                    DataBits::Seven,
+                    DataBits::Eight,
+                ],
+                s.data_bits,
+                forward,
+            )
+        }
+        SettingsField::Parity => {
+            let options = if cfg!(target_os = "linux") {
+                &PARITIES[..]
+            } else {
+                &PARITIES[..3]
+            };
+            (s.parity, s.stick_parity) = cycle(options, (s.parity, s.stick_parity), forward);
+        }
+        SettingsField::StopBits => {
+            s.stop_bits = cycle(&[StopBits::One, StopBits::Two], s.stop_bits, forward)
+        }
+        SettingsField::FlowControl => {
+            s.flow_control = cycle(
+                &[
+                    FlowControl::None,
+                    FlowControl::Software,
+                    FlowControl::Hardware,
+                ],
+                s.flow_control,
+                forward,
+            )
+        }
+        SettingsField::LineEnding => {
+            // Not a port setting, nothing to apply
+            ui_config.line_ending = if forward {
+                ui_config.line_ending.next()
+            } else {
+                ui_config.line_ending.prev()
+            };
+            return Ok(());
+        }
+        SettingsField::RxLineEnding => {
+            ui_config.rx_line_ending = if forward {
+                ui_config.rx_line_ending.next()
+            } else {
+                ui_config.rx_line_ending.prev()
+            };
+            return Ok(());
+        }
+        SettingsField::Dtr => s.dtr = !s.dtr,
+        SettingsField::Rts => s.rts = !s.rts,
+    }
+
+    {
+        let mut guard = port.lock().await;
+        match field {
+            SettingsField::Baud => guard.set_baud_rate(s.baud)?,
+            SettingsField::DataBits => guard.set_data_bits(s.data_bits)?,
+            SettingsField::Parity => {
+                guard.set_parity(s.parity)?;
+                #[cfg(target_os = "linux")]
+                crate::parity::set_stick_parity(ui_config.port_fd, s.stick_parity)?;
+            }
+            SettingsField::StopBits => guard.set_stop_bits(s.stop_bits)?,
+            SettingsField::FlowControl => guard.set_flow_control(s.flow_control)?,
+            SettingsField::Dtr => guard.write_data_terminal_ready(s.dtr)?,
+            SettingsField::Rts => guard.write_request_to_send(s.rts)?,
+            SettingsField::LineEnding | SettingsField::RxLineEnding => {}
+        }
+    }
+
+    ui_config.port_settings = s;
+    Ok(())
+}
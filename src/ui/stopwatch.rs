@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+/// Manual timing for exploratory testing: Alt+S starts and stops, Alt+L
+/// marks a lap, Alt+0 resets
+#[derive(Default)]
+pub struct Stopwatch {
+    /// When the current run started; `None` while stopped
+    running_since: Option<Instant>,
+    /// Time from earlier runs
+    banked: Duration,
+    laps: u32,
+    /// Elapsed time at the previous lap
+    last_lap: Duration,
+}
+
+/// `m:ss.mmm`
+pub fn format_elapsed(d: Duration) -> String {
+    let millis = d.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+impl Stopwatch {
+    pub fn elapsed(&self) -> Duration {
+        self.banked + self.running_since.map_or(Duration::ZERO, |t| t.elapsed())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    /// Not started since the last reset
+    pub fn is_idle(&self) -> bool {
+        !self.is_running() && self.banked.is_zero()
+    }
+
+    /// Start or stop; returns the marker to record
+    pub fn toggle(&mut self) -> String {
+        match self.running_since.take() {
+            Some(since) => {
+                self.banked += since.elapsed();
+                format!(
+                    "-- [stopwatch] stopped at {} --",
+                    format_elapsed(self.banked)
+                )
+            }
+            None => {
+                self.running_since = Some(Instant::now());
+                if self.banked.is_zero() {
+                    "-- [stopwatch] started --".to_string()
+                } else {
+                    format!(
+                        "-- [stopwatch] resumed at {} --",
+                        format_elapsed(self.banked)
+                    )
+                }
+            }
+        }
+    }
+
+    /// Mark a lap with the total and the split since the previous lap;
+    /// `None` while stopped
+    pub fn lap(&mut self) -> Option<String> {
+        if !self.is_running() {
+            return None;
+        }
+        let elapsed = self.elapsed();
+        self.laps += 1;
+        let split = elapsed - self.last_lap;
+        self.last_lap = elapsed;
+        Some(format!(
+            "-- [lap {}] {} (+{}) --",
+            self.laps,
+            format_elapsed(elapsed),
+            format_elapsed(split)
+        ))
+    }
+
+    /// Back to zero; returns the marker to record
+    pub fn reset(&mut self) -> String {
+        let marker = format!(
+            "-- [stopwatch] reset at {} after {} lap(s) --",
+            format_elapsed(self.elapsed()),
+            self.laps
+        );
+        *self = Stopwatch::default();
+        marker
+    }
+
+    /// Title bar text, e.g. `0:12.345 lap 2`
+    pub fn describe(&self) -> String {
+        let mut text = format_elapsed(self.elapsed());
+        if self.laps > 0 {
+            text.push_str(&format!(" lap {}", self.laps));
+        }
+        if !self.is_running() {
+            text.push_str(" (stopped)");
+        }
+        text
+    }
+}
@@ -0,0 +1,243 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serialport::SerialPort;
+use std::sync::Arc;
+use tokio::sync::{Mutex, broadcast, mpsc};
+
+use super::{AppState, OutputEntry, UiMessage};
+use crate::config::UiConfig;
+use crate::port_discovery::short_name;
+use crate::serial_io::SerialData;
+
+/// A port main has opened: what the UI needs to run a session on it
+pub struct Tab {
+    pub ui_rx: mpsc::UnboundedReceiver<UiMessage>,
+    pub serial_rx: mpsc::UnboundedReceiver<SerialData>,
+    pub port: Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    pub ui_config: UiConfig,
+}
+
+/// A tab's running session: its own scrollback, input line and settings
+pub struct Session {
+    pub app_state: AppState,
+    pub ui_config: UiConfig,
+    pub port: Arc<Mutex<Box<dyn SerialPort + Send>>>,
+    /// Something arrived while another tab was shown
+    pub activity: bool,
+}
+
+/// What a tab's channels deliver, tagged with the tab by [`forward`]
+pub enum TabEvent {
+    Ui(UiMessage),
+    Serial(SerialData),
+    /// A complete RX line, for the merged view
+    Line(String),
+}
+
+/// Feed a tab's channels into the one the UI loop waits on
+pub fn forward(
+    index: usize,
+    mut ui_rx: mpsc::UnboundedReceiver<UiMessage>,
+    mut serial_rx: mpsc::UnboundedReceiver<SerialData>,
+    tx: mpsc::UnboundedSender<(usize, TabEvent)>,
+) {
+    let ui_tx = tx.clone();
+    tokio::spawn(async move {
+        while let Some(msg) = ui_rx.recv().await {
+            if ui_tx.send((index, TabEvent::Ui(msg))).is_err() {
+                break;
+            }
+        }
+    });
+    tokio::spawn(async move {
+        while let Some(data) = serial_rx.recv().await {
+            if tx.send((index, TabEvent::Serial(data))).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Feed a tab's complete RX lines into the UI loop's channel, for the
+/// merged view
+pub fn forward_lines(
+    index: usize,
+    mut rx_lines: broadcast::Receiver<String>,
+    tx: mpsc::UnboundedSender<(usize, TabEvent)>,
+) {
+    tokio::spawn(async move {
+        loop {
+            match rx_lines.recv().await {
+                Ok(line) => {
+                    if tx.send((index, TabEvent::Line(line))).is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// How a tab shows up in the tab bar
+pub struct TabTitle {
+    pub label: String,
+    pub active: bool,
+    pub activity: bool,
+    pub disconnected: bool,
+}
+
+/// The line above the sessions when several ports are open
+pub struct TabBar {
+    pub titles: Vec<TabTitle>,
+    /// Enter sends to every port (Alt+A)
+    pub broadcast: bool,
+}
+
+/// The sessions of a multi-port run (one for a single port) and the one
+/// on screen
+pub struct Sessions {
+    pub tabs: Vec<Session>,
+    pub active: usize,
+    /// RX lines of every tab interleaved as they arrived, each with its
+    /// time and port (kept from the start so Alt+M shows history)
+    pub merged: AppState,
+    /// The output pane shows `merged`; input still goes to the active tab
+    pub merged_view: bool,
+    /// Enter sends the typed line to every connected port (Alt+A)
+    pub broadcast: bool,
+}
+
+impl Sessions {
+    pub fn new(merged_view: bool) -> Self {
+        Sessions {
+            tabs: Vec::new(),
+            active: 0,
+            merged: AppState::new(),
+            merged_view,
+            broadcast: false,
+        }
+    }
+
+    pub fn active(&mut self) -> &mut Session {
+        &mut self.tabs[self.active]
+    }
+
+    /// Ctrl+Left/Right and Alt+1..9 switch tabs, Alt+M toggles the merged
+    /// view (the scroll keys move it while it's shown) and Alt+A broadcast;
+    /// returns whether `key` was one of those
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        if self.tabs.len() < 2 {
+            return false;
+        }
+        if self.merged_view && self.scroll_merged(key) {
+            return true;
+        }
+        let count = self.tabs.len();
+        let target = match key.code {
+            KeyCode::Char('m') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.merged_view = !self.merged_view;
+                self.merged.needs_render = true;
+                self.active().app_state.needs_render = true;
+                return true;
+            }
+            KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.broadcast = !self.broadcast;
+                let status = if self.broadcast {
+                    format!("Broadcast ON: Enter sends to all {count} ports")
+                } else {
+                    "Broadcast OFF".to_string()
+                };
+                self.active().app_state.set_status(status);
+                return true;
+            }
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                (self.active + count - 1) % count
+            }
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                (self.active + 1) % count
+            }
+            KeyCode::Char(c @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                let index = c as usize - '1' as usize;
+                if index >= count {
+                    return false;
+                }
+                index
+            }
+            _ => return false,
+        };
+        self.active = target;
+        let session = self.active();
+        session.activity = false;
+        session.app_state.needs_render = true;
+        true
+    }
+
+    fn scroll_merged(&mut self, key: &KeyEvent) -> bool {
+        let merged = &mut self.merged;
+        match key.code {
+            KeyCode::Up => merged.scroll_up(),
+            KeyCode::Down => merged.scroll_down(),
+            KeyCode::PageUp => merged.scroll_page_up(10),
+            KeyCode::PageDown => merged.scroll_page_down(10),
+            KeyCode::Home => merged.scroll_to_home(),
+            KeyCode::End => merged.scroll_to_bottom(),
+            KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                merged.enable_auto_scroll()
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Add tab `index`'s RX line to the merged view
+    pub fn merge_line(&mut self, index: usize, line: String) {
+        let tab = &self.tabs[index];
+        let level = tab.ui_config.level_parser.parse(&line);
+        let label = match &tab.ui_config.role {
+            Some(role) => role.clone(),
+            None => short_name(&tab.ui_config.port_name),
+        };
+        self.merged
+            .add_entry(OutputEntry::merged(index, &label, line, level));
+    }
+
+    /// Note output in a tab that isn't shown; true the first time, when
+    /// the tab bar needs redrawing
+    pub fn mark_activity(&mut self, index: usize) -> bool {
+        if index == self.active || self.merged_view || self.tabs[index].activity {
+            return false;
+        }
+        self.tabs[index].activity = true;
+        true
+    }
+
+    /// Any tab asked to quit (Ctrl+C, Esc, Ctrl+Q): the whole run ends
+    pub fn should_quit(&self) -> bool {
+        self.tabs.iter().any(|tab| tab.app_state.should_quit)
+    }
+
+    /// No titles for a single port: no tab bar then
+    pub fn tab_bar(&self) -> TabBar {
+        TabBar {
+            titles: self.titles(),
+            broadcast: self.broadcast,
+        }
+    }
+
+    fn titles(&self) -> Vec<TabTitle> {
+        if self.tabs.len() < 2 {
+            return Vec::new();
+        }
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| TabTitle {
+                label: format!("{} {}", i + 1, tab.ui_config.tab_label()),
+                active: i == self.active,
+                activity: tab.activity,
+                disconnected: tab.app_state.disconnected,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,436 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// Size until the first render tells us the pane's
+const DEFAULT_SIZE: (usize, usize) = (24, 80);
+
+/// Longest CSI parameter string kept; anything longer is garbage
+const MAX_PARAMS: usize = 32;
+
+#[derive(Copy, Clone, PartialEq)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+enum Parser {
+    #[default]
+    Ground,
+    Escape,
+    Csi(String),
+    /// OSC and other strings: skipped up to BEL or ESC \
+    Skip,
+}
+
+/// A VT100/ANSI subset for full-screen device UIs (U-Boot menus, curses
+/// style firmware screens): cursor movement and positioning, erase, scroll,
+/// save/restore and SGR colors. Everything else is dropped.
+pub struct TerminalScreen {
+    rows: usize,
+    cols: usize,
+    cells: Vec<Vec<Cell>>,
+    cursor: (usize, usize),
+    saved: (usize, usize),
+    style: Style,
+    parser: Parser,
+}
+
+impl Default for TerminalScreen {
+    fn default() -> Self {
+        let (rows, cols) = DEFAULT_SIZE;
+        TerminalScreen {
+            rows,
+            cols,
+            cells: vec![vec![Cell::default(); cols]; rows],
+            cursor: (0, 0),
+            saved: (0, 0),
+            style: Style::default(),
+            parser: Parser::Ground,
+        }
+    }
+}
+
+/// The 16 ANSI colors; `bright` for 90-97/100-107
+fn ansi_color(n: u16, bright: bool) -> Color {
+    match (n, bright) {
+        (0, false) => Color::Black,
+        (1, false) => Color::Red,
+        (2, false) => Color::Green,
+        (3, false) => Color::Yellow,
+        (4, false) => Color::Blue,
+        (5, false) => Color::Magenta,
+        (6, false) => Color::Cyan,
+        (7, false) => Color::Gray,
+        (0, true) => Color::DarkGray,
+        (1, true) => Color::LightRed,
+        (2, true) => Color::LightGreen,
+        (3, true) => Color::LightYellow,
+        (4, true) => Color::LightBlue,
+        (5, true) => Color::LightMagenta,
+        (6, true) => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+impl TerminalScreen {
+    /// Follow the pane size; content stays anchored top left
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        if (rows, cols) == (self.rows, self.cols) {
+            return;
+        }
+        for row in &mut self.cells {
+            row.resize(cols, Cell::default());
+        }
+        self.cells.resize(rows, vec![Cell::default(); cols]);
+        self.rows = rows;
+        self.cols = cols;
+        self.cursor = self.clamped(self.cursor);
+        self.saved = self.clamped(self.saved);
+    }
+
+    /// `pos` moved onto the screen
+    fn clamped(&self, (row, col): (usize, usize)) -> (usize, usize) {
+        (row.min(self.rows - 1), col.min(self.cols - 1))
+    }
+
+    /// ESC 8 / `CSI u`
+    fn restore_cursor(&mut self) {
+        self.cursor = self.clamped(self.saved);
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn clear(&mut self) {
+        *self = TerminalScreen {
+            rows: self.rows,
+            cols: self.cols,
+            cells: vec![vec![Cell::default(); self.cols]; self.rows],
+            ..Default::default()
+        };
+    }
+
+    pub fn feed(&mut self, text: &str) {
+        for c in text.chars() {
+            self.feed_char(c);
+        }
+    }
+
+    fn feed_char(&mut self, c: char) {
+        match std::mem::take(&mut self.parser) {
+            Parser::Ground => self.ground(c),
+            Parser::Escape => self.escape(c),
+            Parser::Csi(mut params) => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    self.csi(&params, c);
+                } else {
+                    if params.len() < MAX_PARAMS {
+                        params.push(c);
+                    }
+                    self.parser = Parser::Csi(params);
+                }
+            }
+            Parser::Skip => {
+                if c == '\x1b' {
+                    // The `\` of ESC \ lands in Escape and is ignored there
+                    self.parser = Parser::Escape;
+                } else if c != '\x07' {
+                    self.parser = Parser::Skip;
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\x1b' => self.parser = Parser::Escape,
+            '\r' => self.cursor.1 = 0,
+            '\n' | '\x0b' | '\x0c' => self.line_feed(),
+            '\x08' => self.cursor.1 = self.cursor.1.saturating_sub(1),
+            '\t' => self.cursor.1 = ((self.cursor.1 / 8 + 1) * 8).min(self.cols - 1),
+            c if c.is_control() => {}
+            c => self.put(c),
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        match c {
+            '[' => self.parser = Parser::Csi(String::new()),
+            ']' | 'P' | '_' | '^' => self.parser = Parser::Skip,
+            '7' => self.saved = self.cursor,
+            '8' => self.restore_cursor(),
+            'D' => self.line_feed(),
+            'E' => {
+                self.cursor.1 = 0;
+                self.line_feed();
+            }
+            'M' => self.reverse_index(),
+            'c' => self.clear(),
+            _ => {}
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if self.cursor.1 >= self.cols {
+            self.cursor.1 = 0;
+            self.line_feed();
+        }
+        let (row, col) = self.cursor;
+        self.cells[row][col] = Cell {
+            ch: c,
+            style: self.style,
+        };
+        self.cursor.1 += 1;
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.0 + 1 < self.rows {
+            self.cursor.0 += 1;
+        } else {
+            self.cells.remove(0);
+            self.cells.push(vec![Cell::default(); self.cols]);
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+        } else {
+            self.cells.pop();
+            self.cells.insert(0, vec![Cell::default(); self.cols]);
+        }
+    }
+
+    fn erase(&mut self, row: usize, cols: std::ops::Range<usize>) {
+        for cell in &mut self.cells[row][cols] {
+            *cell = Cell::default();
+        }
+    }
+
+    fn csi(&mut self, params: &str, action: char) {
+        // Private modes (`?25l` cursor, `?1049h` alternate screen) are ignored
+        if params.starts_with(['?', '>', '=']) {
+            return;
+        }
+        let args: Vec<u16> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0);
+        let count = |i: usize| (arg(i) as usize).max(1);
+        let (row, col) = self.cursor;
+        let last_row = self.rows - 1;
+        let last_col = self.cols - 1;
+        match action {
+            'A' => self.cursor.0 = row.saturating_sub(count(0)),
+            'B' => self.cursor.0 = (row + count(0)).min(last_row),
+            'C' => self.cursor.1 = (col + count(0)).min(last_col),
+            'D' => self.cursor.1 = col.min(last_col).saturating_sub(count(0)),
+            'G' => self.cursor.1 = (count(0) - 1).min(last_col),
+            'd' => self.cursor.0 = (count(0) - 1).min(last_row),
+            'H' | 'f' => {
+                self.cursor = ((count(0) - 1).min(last_row), (count(1) - 1).min(last_col));
+            }
+            'J' => {
+                let col = col.min(self.cols);
+                match arg(0) {
+                    0 => {
+                        self.erase(row, col..self.cols);
+                        for r in row + 1..self.rows {
+                            self.erase(r, 0..self.cols);
+                        }
+                    }
+                    1 => {
+                        for r in 0..row {
+                            self.erase(r, 0..self.cols);
+                        }
+                        self.erase(row, 0..(col + 1).min(self.cols));
+                    }
+                    _ => {
+                        for r in 0..self.rows {
+                            self.erase(r, 0..self.cols);
+                        }
+                    }
+                }
+            }
+            'K' => {
+                let col = col.min(self.cols);
+                match arg(0) {
+                    0 => self.erase(row, col..self.cols),
+                    1 => self.erase(row, 0..(col + 1).min(self.cols)),
+                    _ => self.erase(row, 0..self.cols),
+                }
+            }
+            'm' => self.sgr(&args),
+            's' => self.saved = self.cursor,
+            'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self, args: &[u16]) {
+        let mut args = args.iter().copied();
+        while let Some(n) = args.next() {
+            self.style = match n {
+                0 => Style::default(),
+                1 => self.style.add_modifier(Modifier::BOLD),
+                2 => self.style.add_modifier(Modifier::DIM),
+                4 => self.style.add_modifier(Modifier::UNDERLINED),
+                7 => self.style.add_modifier(Modifier::REVERSED),
+                22 => self.style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+                24 => self.style.remove_modifier(Modifier::UNDERLINED),
+                27 => self.style.remove_modifier(Modifier::REVERSED),
+                30..=37 => self.style.fg(ansi_color(n - 30, false)),
+                39 => self.style.fg(Color::Reset),
+                40..=47 => self.style.bg(ansi_color(n - 40, false)),
+                49 => self.style.bg(Color::Reset),
+                90..=97 => self.style.fg(ansi_color(n - 90, true)),
+                100..=107 => self.style.bg(ansi_color(n - 100, true)),
+                38 | 48 => {
+                    // 38;5;N (256 colors) or 38;2;R;G;B
+                    let color = match args.next() {
+                        Some(5) => args.next().map(|i| Color::Indexed(i as u8)),
+                        Some(2) => {
+                            let mut rgb = || args.next().unwrap_or(0) as u8;
+                            Some(Color::Rgb(rgb(), rgb(), rgb()))
+                        }
+                        _ => None,
+                    };
+                    match (color, n) {
+                        (Some(color), 38) => self.style.fg(color),
+                        (Some(color), _) => self.style.bg(color),
+                        (None, _) => self.style,
+                    }
+                }
+                _ => self.style,
+            };
+        }
+    }
+
+    /// The screen as styled lines, the cursor cell shown reversed
+    pub fn lines(&self, show_cursor: bool) -> Vec<Line<'static>> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                let mut spans: Vec<Span> = Vec::new();
+                let mut text = String::new();
+                let mut style = None;
+                for (c, cell) in row.iter().enumerate() {
+                    let mut cell_style = cell.style;
+                    if show_cursor && (r, c) == self.cursor {
+                        cell_style = cell_style.add_modifier(Modifier::REVERSED);
+                    }
+                    if style.is_some_and(|s| s != cell_style) {
+                        spans.push(Span::styled(
+                            std::mem::take(&mut text),
+                            style.unwrap_or_default(),
+                        ));
+                    }
+                    style = Some(cell_style);
+                    text.push(cell.ch);
+                }
+                spans.push(Span::styled(text, style.unwrap_or_default()));
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(rows: usize, cols: usize) -> TerminalScreen {
+        let mut screen = TerminalScreen::default();
+        screen.resize(rows, cols);
+        screen
+    }
+
+    fn text(screen: &TerminalScreen) -> Vec<String> {
+        screen
+            .cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|c| c.ch)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prints_and_positions() {
+        let mut s = screen(3, 10);
+        s.feed("ab\r\ncd\x1b[1;5Hx\x1b[3;2Hy");
+        assert_eq!(text(&s), ["ab  x", "cd", " y"]);
+    }
+
+    #[test]
+    fn wraps_and_scrolls() {
+        let mut s = screen(2, 4);
+        s.feed("abcdef\r\ngh");
+        assert_eq!(text(&s), ["ef", "gh"]);
+    }
+
+    #[test]
+    fn positions_are_clamped() {
+        let mut s = screen(3, 5);
+        s.feed("\x1b[99;99H");
+        assert_eq!(s.cursor, (2, 4));
+        s.feed("\x1b[99A");
+        assert_eq!(s.cursor, (0, 4));
+        s.feed("\x1b[99D");
+        assert_eq!(s.cursor, (0, 0));
+    }
+
+    #[test]
+    fn erases() {
+        let mut s = screen(2, 5);
+        s.feed("abcde\r\nfghij\x1b[1;3H\x1b[K");
+        assert_eq!(text(&s), ["ab", "fghij"]);
+        s.feed("\x1b[2J");
+        assert_eq!(text(&s), ["", ""]);
+    }
+
+    #[test]
+    fn restore_after_shrinking_stays_on_screen() {
+        for (save, restore) in [("\x1b7", "\x1b8"), ("\x1b[s", "\x1b[u")] {
+            let mut s = screen(24, 80);
+            s.feed(&format!("\x1b[20;70H{save}"));
+            s.resize(5, 10);
+            s.feed(restore);
+            assert_eq!(s.cursor, (4, 9));
+            s.feed("x\x1b[J\x1b[K");
+            s.resize(2, 2);
+            s.feed(&format!("{restore}z\x1b[1J"));
+            assert_eq!(s.cursor, (1, 2));
+        }
+    }
+
+    #[test]
+    fn restores_the_saved_cursor() {
+        let mut s = screen(5, 10);
+        s.feed("\x1b[2;3H\x1b7\x1b[5;1H\x1b8x");
+        assert_eq!(text(&s)[1], "  x");
+    }
+
+    #[test]
+    fn long_parameters_and_strings_are_dropped() {
+        let mut s = screen(2, 10);
+        let long = "1;".repeat(100);
+        s.feed(&format!("\x1b[{long}mok\x1b]0;title\x07!"));
+        assert_eq!(text(&s)[0], "ok!");
+    }
+}
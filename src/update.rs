@@ -0,0 +1,61 @@
+use anyhow::{Context, Result, bail};
+use std::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::ui::UiMessage;
+
+const CRATES_IO_URL: &str = "https://crates.io/api/v1/crates/sermonizer";
+
+/// Compare `MAJOR.MINOR.PATCH` numerically; pre-release suffixes are ignored
+fn version_tuple(v: &str) -> Vec<u64> {
+    v.split(['.', '-', '+'])
+        .take(3)
+        .map(|p| p.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Latest published version according to crates.io. Uses `curl` so no TLS
+/// stack has to be linked in.
+fn latest_version() -> Result<String> {
+    let output = Command::new("curl")
+        .args(["-fsS", "--max-time", "5", "-A"])
+        .arg(concat!("sermonizer/", env!("CARGO_PKG_VERSION")))
+        .arg(CRATES_IO_URL)
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        bail!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("Unexpected reply from crates.io")?;
+    json["crate"]["max_stable_version"]
+        .as_str()
+        .or_else(|| json["crate"]["max_version"].as_str())
+        .map(str::to_string)
+        .context("No version in crates.io reply")
+}
+
+/// One-line result of the (opt-in) update check
+pub fn check_for_update() -> String {
+    let current = env!("CARGO_PKG_VERSION");
+    match latest_version() {
+        Ok(latest) if version_tuple(&latest) > version_tuple(current) => format!(
+            "Update available: sermonizer {latest} (you have {current}); \
+             `cargo install sermonizer` to upgrade"
+        ),
+        Ok(_) => format!("sermonizer {current} is up to date"),
+        Err(e) => format!("Update check failed: {e:#}"),
+    }
+}
+
+/// The check on every start (`check_updates = true`): runs in the
+/// background and notes the result in the output, so a slow or missing
+/// network doesn't hold up connecting
+pub async fn check_in_background(ui_tx: UnboundedSender<UiMessage>) {
+    if let Ok(result) = tokio::task::spawn_blocking(check_for_update).await {
+        let _ = ui_tx.send(UiMessage::Output(format!("-- {result} --")));
+    }
+}
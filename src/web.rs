@@ -0,0 +1,375 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+/// Events a newly opened page starts with
+const HISTORY: usize = 1000;
+
+/// Pages connected at the same time
+const MAX_CLIENTS: usize = 16;
+
+/// A client that takes longer than this to send its request or to take
+/// events is dropped
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a connected page is checked for close and ping frames
+/// between events
+const POLL: Duration = Duration::from_millis(50);
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>sermonizer</title>
+<style>
+body { margin: 0; background: #111; color: #ddd; font: 14px monospace; }
+#head { position: fixed; top: 0; left: 0; right: 0; padding: 4px 8px; background: #222; color: #888; }
+#out { padding: 32px 8px 8px; white-space: pre-wrap; word-break: break-all; }
+.tx { color: #6cf; } .note { color: #888; } .status { color: #cc6; } .error { color: #f66; }
+.ERROR { color: #f66; } .WARN { color: #fc6; } .DEBUG, .TRACE { color: #888; }
+</style>
+</head>
+<body>
+<div id="head">sermonizer &middot; <span id="state">connecting...</span></div>
+<div id="out"></div>
+<script>
+const out = document.getElementById("out");
+const state = document.getElementById("state");
+function add(cls, text) {
+  const follow = window.innerHeight + window.scrollY >= document.body.scrollHeight - 4;
+  const line = document.createElement("div");
+  line.className = cls;
+  line.textContent = text;
+  out.appendChild(line);
+  while (out.childNodes.length > 5000) out.removeChild(out.firstChild);
+  if (follow) window.scrollTo(0, document.body.scrollHeight);
+}
+function connect() {
+  const ws = new WebSocket((location.protocol === "https:" ? "wss://" : "ws://") + location.host + "/ws");
+  ws.onopen = () => { state.textContent = "live (read-only)"; };
+  ws.onclose = () => { state.textContent = "disconnected, retrying..."; setTimeout(connect, 2000); };
+  ws.onmessage = (msg) => {
+    const e = JSON.parse(msg.data);
+    switch (e.event) {
+      case "rx-line": add(e.level || "rx", e.text); break;
+      case "tx": add("tx", "> " + e.text); break;
+      case "note": add("note", e.text); break;
+      case "status": state.textContent = e.message; break;
+      case "error": add("error", "! " + e.message); break;
+    }
+  };
+}
+connect();
+</script>
+</body>
+</html>
+"#;
+
+struct FeedState {
+    history: VecDeque<String>,
+    live: broadcast::Sender<String>,
+}
+
+/// Console events (JSON, as in `--output json`) for the web view, with
+/// recent history for pages opened mid-session
+#[derive(Clone)]
+pub struct WebFeed {
+    state: Arc<Mutex<FeedState>>,
+}
+
+impl WebFeed {
+    pub fn new() -> Self {
+        WebFeed {
+            state: Arc::new(Mutex::new(FeedState {
+                history: VecDeque::with_capacity(HISTORY),
+                live: broadcast::channel(HISTORY).0,
+            })),
+        }
+    }
+
+    pub fn publish(&self, json: String) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.history.len() == HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(json.clone());
+            let _ = state.live.send(json);
+        }
+    }
+
+    /// Everything so far plus a receiver for what follows, without gaps
+    fn subscribe(&self) -> Option<(Vec<String>, broadcast::Receiver<String>)> {
+        let state = self.state.lock().ok()?;
+        Some((
+            state.history.iter().cloned().collect(),
+            state.live.subscribe(),
+        ))
+    }
+}
+
+/// `--web ADDR`: a bare port listens on localhost only
+pub fn parse_web_addr(s: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = s.parse::<u16>() {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    s.parse()
+        .map_err(|_| format!("expected PORT or HOST:PORT, got '{s}'"))
+}
+
+fn respond(
+    mut stream: TcpStream,
+    status: &str,
+    body: &str,
+    content_type: &str,
+) -> std::io::Result<()> {
+    // The request itself does not matter; read it so the client sees a clean close
+    let mut request = [0u8; 2048];
+    let _ = stream.read(&mut request)?;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+fn serve_page(stream: TcpStream) -> std::io::Result<()> {
+    respond(stream, "200 OK", PAGE, "text/html; charset=utf-8")
+}
+
+/// The host part of a `Host` header, without the port
+fn host_name(host: &str) -> &str {
+    match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => host.split(':').next().unwrap_or(host),
+    }
+}
+
+/// Whether a WebSocket request comes from our own page. Browsers always
+/// send `Origin`, so without this any site open in the browser could read
+/// the console. On a loopback address the host must be a loopback name too,
+/// so a rebound DNS name can't pass as our page either.
+fn origin_allowed(origin: Option<&str>, host: Option<&str>, local: SocketAddr) -> bool {
+    let (Some(origin), Some(host)) = (origin, host) else {
+        return false;
+    };
+    if local.ip().is_loopback() && !matches!(host_name(host), "localhost" | "127.0.0.1" | "::1") {
+        return false;
+    }
+    ["http://", "https://"]
+        .iter()
+        .any(|scheme| origin.eq_ignore_ascii_case(&format!("{scheme}{host}")))
+}
+
+/// Handshake callback refusing requests `origin_allowed` rejects with 403
+struct OriginCheck(SocketAddr);
+
+impl Callback for OriginCheck {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+        if origin_allowed(header("origin"), header("host"), self.0) {
+            return Ok(response);
+        }
+        let mut forbidden = ErrorResponse::new(Some("Forbidden origin".to_string()));
+        *forbidden.status_mut() = StatusCode::FORBIDDEN;
+        Err(forbidden)
+    }
+}
+
+/// Send `json`; false once the page is gone or too slow to take it
+fn send(socket: &mut WebSocket<TcpStream>, json: String) -> bool {
+    socket.send(Message::text(json)).is_ok()
+}
+
+fn serve_socket(stream: TcpStream, feed: &WebFeed, local: SocketAddr) {
+    let Ok(mut socket) = tungstenite::accept_hdr(stream, OriginCheck(local)) else {
+        return;
+    };
+    let Some((history, mut live)) = feed.subscribe() else {
+        return;
+    };
+    for json in history {
+        if !send(&mut socket, json) {
+            return;
+        }
+    }
+    // Reads time out quickly from here on, so the loop can both forward
+    // events and answer what the page sends (pong for ping, the close
+    // handshake)
+    if socket.get_ref().set_read_timeout(Some(POLL)).is_err() {
+        return;
+    }
+    loop {
+        loop {
+            match live.try_recv() {
+                Ok(json) => {
+                    if !send(&mut socket, json) {
+                        return;
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => {}
+                Err(broadcast::error::TryRecvError::Empty) => break,
+                Err(broadcast::error::TryRecvError::Closed) => {
+                    let _ = socket.close(None);
+                    let _ = socket.flush();
+                    return;
+                }
+            }
+        }
+        match socket.read() {
+            // tungstenite queues the pong and the close reply itself
+            Ok(_) => {
+                if socket.flush().is_err() {
+                    return;
+                }
+            }
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// Counts a connected client until dropped
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl ClientSlot {
+    fn take(clients: &Arc<AtomicUsize>) -> Option<Self> {
+        clients
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < MAX_CLIENTS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| ClientSlot(clients.clone()))
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle_connection(stream: TcpStream, feed: WebFeed, local: SocketAddr, _slot: ClientSlot) {
+    if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let mut start = [0u8; 64];
+    let Ok(n) = stream.peek(&mut start) else {
+        return;
+    };
+    if start[..n].starts_with(b"GET /ws") {
+        serve_socket(stream, &feed, local);
+    } else {
+        let _ = serve_page(stream);
+    }
+}
+
+/// Serve the read-only web view in the background: `/` is the page, `/ws`
+/// streams the console events to pages from the same origin. Returns the
+/// address it listens on.
+pub fn spawn_web_view(addr: SocketAddr, feed: WebFeed) -> Result<SocketAddr> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Failed to listen on {addr}"))?;
+    let local = listener.local_addr()?;
+    let clients = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let Some(slot) = ClientSlot::take(&clients) else {
+                // Not waiting for the request: this loop must not stall
+                let mut stream = stream;
+                let _ = stream.set_write_timeout(Some(POLL));
+                let _ = stream.write_all(
+                    b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+                continue;
+            };
+            let feed = feed.clone();
+            std::thread::spawn(move || handle_connection(stream, feed, local, slot));
+        }
+    });
+    Ok(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn same_origin_is_allowed() {
+        let local = addr("127.0.0.1:8080");
+        assert!(origin_allowed(
+            Some("http://127.0.0.1:8080"),
+            Some("127.0.0.1:8080"),
+            local
+        ));
+        assert!(origin_allowed(
+            Some("http://localhost:8080"),
+            Some("localhost:8080"),
+            local
+        ));
+        assert!(origin_allowed(
+            Some("http://[::1]:8080"),
+            Some("[::1]:8080"),
+            addr("[::1]:8080")
+        ));
+        let any = addr("0.0.0.0:8080");
+        assert!(origin_allowed(
+            Some("http://bench-pc:8080"),
+            Some("bench-pc:8080"),
+            any
+        ));
+    }
+
+    #[test]
+    fn other_origins_are_refused() {
+        let local = addr("127.0.0.1:8080");
+        assert!(!origin_allowed(None, Some("127.0.0.1:8080"), local));
+        assert!(!origin_allowed(Some("http://127.0.0.1:8080"), None, local));
+        assert!(!origin_allowed(
+            Some("https://evil.example"),
+            Some("127.0.0.1:8080"),
+            local
+        ));
+        assert!(!origin_allowed(Some("null"), Some("127.0.0.1:8080"), local));
+        assert!(!origin_allowed(
+            Some("http://127.0.0.1:9999"),
+            Some("127.0.0.1:8080"),
+            local
+        ));
+    }
+
+    #[test]
+    fn rebound_names_are_refused_on_loopback() {
+        let local = addr("127.0.0.1:8080");
+        assert!(!origin_allowed(
+            Some("http://evil.example:8080"),
+            Some("evil.example:8080"),
+            local
+        ));
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(parse_web_addr("8080"), Ok(addr("127.0.0.1:8080")));
+        assert_eq!(parse_web_addr("0.0.0.0:80"), Ok(addr("0.0.0.0:80")));
+        assert!(parse_web_addr("nope").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::Result;
+use serialport::SerialPortInfo;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::config::ParityMode;
+use crate::port_discovery::print_ports;
+use crate::profile::{ConfigFile, Profile, save_config};
+use crate::quirks::QuirkDb;
+
+/// Print `question`, read one line and return it trimmed
+/// (or `default` when the answer is empty)
+fn prompt(question: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        print!("{question}: ");
+    } else {
+        print!("{question} [{default}]: ");
+    }
+    let _ = io::stdout().flush();
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let answer = line.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+fn prompt_yes_no(question: &str, default: bool) -> Result<bool> {
+    let answer = prompt(question, if default { "Y/n" } else { "y/N" })?;
+    Ok(match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => default,
+    })
+}
+
+fn ask_port(ports: &[SerialPortInfo]) -> Result<Option<String>> {
+    if ports.is_empty() {
+        println!("No serial ports detected right now.");
+        let answer = prompt("Port path (empty to auto-detect later)", "")?;
+        return Ok((!answer.is_empty()).then_some(answer));
+    }
+
+    // No config file yet, so only the built-in quirks apply
+    print_ports(ports, &QuirkDb::new(Vec::new()));
+    let answer = prompt(
+        &format!(
+            "Port number [1-{}] or path (empty to auto-detect)",
+            ports.len()
+        ),
+        "",
+    )?;
+    if answer.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(match answer.parse::<usize>() {
+        Ok(n) if (1..=ports.len()).contains(&n) => ports[n - 1].port_name.clone(),
+        _ => answer,
+    }))
+}
+
+fn ask_baud() -> Result<u32> {
+    loop {
+        let answer = prompt("Baud rate", "115200")?;
+        match answer.parse::<u32>() {
+            Ok(b) if b > 0 => return Ok(b),
+            _ => println!("Please enter a positive number."),
+        }
+    }
+}
+
+fn ask_parity() -> Result<ParityMode> {
+    loop {
+        let answer = prompt("Parity (none/odd/even)", "none")?;
+        match answer.to_ascii_lowercase().as_str() {
+            "none" | "n" => return Ok(ParityMode::None),
+            "odd" | "o" => return Ok(ParityMode::Odd),
+            "even" | "e" => return Ok(ParityMode::Even),
+            _ => println!("Please answer none, odd or even."),
+        }
+    }
+}
+
+/// First-run wizard: asks for the basics and optionally stores them as a
+/// profile. Runs in cooked mode before the TUI starts.
+pub fn run_wizard(ports: &[SerialPortInfo]) -> Result<Profile> {
+    println!("Welcome to sermonizer! Let's set up your first connection.");
+    println!("(Press Enter to accept the default shown in brackets.)\n");
+
+    let port = ask_port(ports)?;
+    let baud = ask_baud()?;
+    let parity = ask_parity()?;
+
+    let log = prompt("Log received data to file (empty for none)", "")?;
+    let log = (!log.is_empty()).then(|| PathBuf::from(log));
+    let log_ts = log.is_some() && prompt_yes_no("Timestamp log entries?", true)?;
+
+    let profile = Profile {
+        port,
+        baud: Some(baud),
+        parity: Some(parity),
+        log,
+        log_ts,
+        ..Profile::default()
+    };
+
+    if prompt_yes_no("Save these answers as a profile?", true)? {
+        let name = prompt("Profile name", "default")?;
+        let mut config = ConfigFile {
+            default_profile: Some(name.clone()),
+            ..ConfigFile::default()
+        };
+        config.profiles.insert(name.clone(), profile.clone());
+        match save_config(&config) {
+            Ok(path) => println!(
+                "Saved profile '{name}' to {} (used by default from now on)",
+                path.display()
+            ),
+            Err(e) => eprintln!("Warning: {e:#}"),
+        }
+    }
+    println!();
+
+    Ok(profile)
+}